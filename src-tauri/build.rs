@@ -43,6 +43,8 @@ fn check_binaries() {
         "x64"
     } else if cfg!(target_arch = "aarch64") {
         "arm64"
+    } else if cfg!(target_arch = "arm") {
+        "armv7"
     } else {
         "x64"
     };
@@ -107,7 +109,8 @@ fn check_binaries() {
     if !missing.is_empty() {
         eprintln!("\n⚠️  WARNING: Some binaries are missing for current platform!");
         eprintln!("   Missing: {:?}", missing);
-        eprintln!("\n   The app will build, but downloads may fail at runtime.");
+        eprintln!("\n   The app will build, and the startup preflight will download them");
+        eprintln!("   at runtime unless preflight_dependencies is turned off.");
         eprintln!("   To fix: Place the missing binaries in the repository.");
         eprintln!("\n   Download instructions:");
         eprintln!("     - yt-dlp: https://github.com/yt-dlp/yt-dlp/releases/latest");
@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// What kind of failure a download error is, as far as the remedies are concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// HTTP 403 from YouTube's media servers
+    Forbidden,
+    /// "Sign in to confirm you're not a bot"
+    BotCheck,
+    /// yt-dlp could not decipher the player signature, usually fixed by a newer release
+    SignatureExtraction,
+    FormatUnavailable,
+    /// HTTP 429, too many requests
+    RateLimited,
+    AgeRestricted,
+    Network,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Remedy {
+    UpdateYtdlp,
+    SwitchPlayerClient,
+    EnableImpersonation,
+    /// Pace every download, not only large batches
+    SlowDown,
+    EnableCookies,
+    CheckConnection,
+}
+
+impl Remedy {
+    /// Whether the app can apply this remedy itself, the others need the user
+    pub fn auto_applicable(self) -> bool {
+        !matches!(self, Remedy::EnableCookies | Remedy::CheckConnection)
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Remedy::UpdateYtdlp => {
+                "Download the latest yt-dlp, YouTube changes often break older releases"
+            }
+            Remedy::SwitchPlayerClient => {
+                "Switch to the recommended YouTube player clients, which are blocked less often"
+            }
+            Remedy::EnableImpersonation => {
+                "Make yt-dlp impersonate a browser so requests look less like a script"
+            }
+            Remedy::SlowDown => "Pause between requests for every download, not only large playlists",
+            Remedy::EnableCookies => {
+                "Import cookies.txt from a browser signed in to YouTube, so requests are made as that account"
+            }
+            Remedy::CheckConnection => "Check the network connection, proxy or firewall settings",
+        }
+    }
+}
+
+/// Consecutive failures of one kind after which the remedies are recommended, and the
+/// remedies to try, most likely to help first
+struct Rule {
+    code: ErrorCode,
+    threshold: u32,
+    remedies: &'static [Remedy],
+}
+
+const RULES: &[Rule] = &[
+    Rule {
+        code: ErrorCode::Forbidden,
+        threshold: 3,
+        remedies: &[
+            Remedy::SwitchPlayerClient,
+            Remedy::UpdateYtdlp,
+            Remedy::EnableImpersonation,
+            Remedy::EnableCookies,
+        ],
+    },
+    Rule {
+        code: ErrorCode::BotCheck,
+        threshold: 2,
+        remedies: &[
+            Remedy::EnableCookies,
+            Remedy::SwitchPlayerClient,
+            Remedy::EnableImpersonation,
+            Remedy::SlowDown,
+        ],
+    },
+    Rule {
+        code: ErrorCode::SignatureExtraction,
+        threshold: 1,
+        remedies: &[Remedy::UpdateYtdlp, Remedy::SwitchPlayerClient],
+    },
+    Rule {
+        code: ErrorCode::FormatUnavailable,
+        threshold: 3,
+        remedies: &[Remedy::SwitchPlayerClient, Remedy::UpdateYtdlp],
+    },
+    Rule {
+        code: ErrorCode::RateLimited,
+        threshold: 2,
+        remedies: &[Remedy::SlowDown, Remedy::EnableCookies],
+    },
+    Rule {
+        code: ErrorCode::AgeRestricted,
+        threshold: 1,
+        remedies: &[Remedy::EnableCookies],
+    },
+    Rule {
+        code: ErrorCode::Network,
+        threshold: 3,
+        remedies: &[Remedy::CheckConnection],
+    },
+    Rule {
+        code: ErrorCode::Unknown,
+        threshold: 3,
+        remedies: &[Remedy::UpdateYtdlp],
+    },
+];
+
+fn rule_for(code: ErrorCode) -> &'static Rule {
+    RULES
+        .iter()
+        .find(|rule| rule.code == code)
+        .expect("every error code has a rule")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecommendedFix {
+    pub remedy: Remedy,
+    pub description: String,
+    pub auto_applicable: bool,
+    /// Set when the fix was applied automatically, `Some(false)` if applying it failed
+    pub applied: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixAdvice {
+    pub error_code: ErrorCode,
+    /// How many downloads in a row have failed with `error_code`
+    pub consecutive_failures: u32,
+    pub threshold: u32,
+    pub fixes: Vec<RecommendedFix>,
+}
+
+/// Classify a download error from the message yt-dlp left in it
+pub fn classify(error: &str) -> ErrorCode {
+    let error = error.to_lowercase();
+    if error.contains("sign in to confirm your age") || error.contains("age-restricted") {
+        ErrorCode::AgeRestricted
+    } else if error.contains("sign in to confirm you") {
+        ErrorCode::BotCheck
+    } else if error.contains("http error 429") || error.contains("too many requests") {
+        ErrorCode::RateLimited
+    } else if error.contains("http error 403") {
+        ErrorCode::Forbidden
+    } else if error.contains("nsig extraction failed") || error.contains("signature extraction") {
+        ErrorCode::SignatureExtraction
+    } else if error.contains("requested format is not available") {
+        ErrorCode::FormatUnavailable
+    } else if error.contains("unable to download webpage")
+        || error.contains("connection refused")
+        || error.contains("timed out")
+        || error.contains("name or service not known")
+        || error.contains("getaddrinfo failed")
+    {
+        ErrorCode::Network
+    } else {
+        ErrorCode::Unknown
+    }
+}
+
+/// Error code of the current run of failures and its length
+static STREAK: Mutex<Option<(ErrorCode, u32)>> = Mutex::new(None);
+
+/// Track a finished download. Returns the error code once its run of consecutive
+/// failures reaches the rule's threshold, so remedies are offered once per run.
+pub fn record_outcome<T>(result: &Result<T, String>) -> Option<ErrorCode> {
+    let mut streak = STREAK.lock().unwrap();
+    let error = match result {
+        Ok(_) => {
+            *streak = None;
+            return None;
+        }
+        Err(error) => error,
+    };
+    if error.contains(crate::download::CANCELLED_MESSAGE) {
+        return None;
+    }
+
+    let code = classify(error);
+    let count = match *streak {
+        Some((current, count)) if current == code => count + 1,
+        _ => 1,
+    };
+    *streak = Some((code, count));
+    (count == rule_for(code).threshold).then_some(code)
+}
+
+/// Remedies for `error_code`, or for the error currently repeating when it is `None`
+pub fn recommended_fixes(error_code: Option<ErrorCode>) -> Option<FixAdvice> {
+    let streak = *STREAK.lock().unwrap();
+    let error_code = error_code.or(streak.map(|(code, _)| code))?;
+    let rule = rule_for(error_code);
+    let consecutive_failures = streak
+        .filter(|(code, _)| *code == error_code)
+        .map_or(0, |(_, count)| count);
+    Some(FixAdvice {
+        error_code,
+        consecutive_failures,
+        threshold: rule.threshold,
+        fixes: rule
+            .remedies
+            .iter()
+            .map(|&remedy| RecommendedFix {
+                remedy,
+                description: remedy.description().to_string(),
+                auto_applicable: remedy.auto_applicable(),
+                applied: None,
+            })
+            .collect(),
+    })
+}
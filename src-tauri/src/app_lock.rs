@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::BuildHasher;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::api::path::config_dir;
+
+/// Rounds of SHA-256 applied to the salted PIN, so the stored hash is slow to brute force
+const HASH_ROUNDS: usize = 100_000;
+
+const MIN_PIN_LENGTH: usize = 4;
+
+/// Wrong PINs accepted before unlocking is refused for `LOCKOUT`
+const MAX_FAILED_ATTEMPTS: u32 = 5;
+
+const LOCKOUT: Duration = Duration::from_secs(30);
+
+/// Stored next to the preferences rather than in them, so the hash is never sent to
+/// the frontend along with the other settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AppLockConfig {
+    salt: String,
+    pin_hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppLockStatus {
+    /// Whether a PIN has been set
+    pub enabled: bool,
+    /// Whether destructive commands are currently refused
+    pub locked: bool,
+    /// Seconds until another unlock attempt is accepted after too many wrong PINs
+    pub retry_after_seconds: Option<u64>,
+}
+
+/// Unlocked for the rest of this app session. Every start is locked while a PIN is set.
+static UNLOCKED: AtomicBool = AtomicBool::new(false);
+
+static FAILED_ATTEMPTS: Mutex<(u32, Option<Instant>)> = Mutex::new((0, None));
+
+fn get_lock_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("youtube-downloader").join("app_lock.json"))
+}
+
+fn load_config() -> Option<AppLockConfig> {
+    let content = fs::read_to_string(get_lock_path()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn save_config(config: Option<&AppLockConfig>) -> Result<(), String> {
+    let path = get_lock_path().ok_or("Failed to get config directory")?;
+    let Some(config) = config else {
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+        }
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let content = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize app lock: {}", e))?;
+    fs::write(&path, content).map_err(|e| e.to_string())
+}
+
+fn new_salt() -> String {
+    let nanos = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+    let bytes: Vec<u8> = (0..2)
+        .flat_map(|_| RandomState::new().hash_one(nanos).to_le_bytes())
+        .collect();
+    hex::encode(bytes)
+}
+
+fn hash_pin(salt: &str, pin: &str) -> String {
+    let mut digest = Sha256::digest(format!("{}:{}", salt, pin).as_bytes());
+    for _ in 1..HASH_ROUNDS {
+        digest = Sha256::digest(digest);
+    }
+    hex::encode(digest)
+}
+
+fn remaining_lockout() -> Option<Duration> {
+    let (_, locked_out_at) = *FAILED_ATTEMPTS.lock().unwrap();
+    locked_out_at.and_then(|at| LOCKOUT.checked_sub(at.elapsed()))
+}
+
+/// Check a PIN against the stored hash, counting wrong guesses towards the lockout
+fn verify_pin(config: &AppLockConfig, pin: &str) -> Result<(), String> {
+    if let Some(wait) = remaining_lockout() {
+        return Err(format!(
+            "Too many wrong PINs, try again in {} seconds",
+            wait.as_secs() + 1
+        ));
+    }
+
+    let mut attempts = FAILED_ATTEMPTS.lock().unwrap();
+    if hash_pin(&config.salt, pin) == config.pin_hash {
+        *attempts = (0, None);
+        return Ok(());
+    }
+
+    attempts.0 += 1;
+    if attempts.0 >= MAX_FAILED_ATTEMPTS {
+        *attempts = (0, Some(Instant::now()));
+    }
+    Err("Wrong PIN".to_string())
+}
+
+pub fn status() -> AppLockStatus {
+    let enabled = load_config().is_some();
+    AppLockStatus {
+        enabled,
+        locked: enabled && !UNLOCKED.load(Ordering::SeqCst),
+        retry_after_seconds: remaining_lockout().map(|wait| wait.as_secs() + 1),
+    }
+}
+
+/// Refuse a destructive command while the app is locked
+pub fn ensure_unlocked(command: &str) -> Result<(), String> {
+    if status().locked {
+        return Err(format!(
+            "The app is locked, enter the PIN to use {}",
+            command
+        ));
+    }
+    Ok(())
+}
+
+/// Set or change the PIN. Changing an existing PIN requires the current one.
+pub fn set_pin(pin: &str, current_pin: Option<&str>) -> Result<(), String> {
+    if pin.chars().count() < MIN_PIN_LENGTH {
+        return Err(format!(
+            "The PIN must be at least {} characters long",
+            MIN_PIN_LENGTH
+        ));
+    }
+    if let Some(config) = load_config() {
+        verify_pin(&config, current_pin.unwrap_or_default())?;
+    }
+
+    let salt = new_salt();
+    let config = AppLockConfig {
+        pin_hash: hash_pin(&salt, pin),
+        salt,
+    };
+    save_config(Some(&config))?;
+    // Whoever just set the PIN keeps using the app until they lock it
+    UNLOCKED.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Remove the PIN, turning the app lock off
+pub fn remove_pin(pin: &str) -> Result<(), String> {
+    let Some(config) = load_config() else {
+        return Ok(());
+    };
+    verify_pin(&config, pin)?;
+    save_config(None)
+}
+
+pub fn unlock(pin: &str) -> Result<(), String> {
+    let config = load_config().ok_or("No PIN has been set")?;
+    verify_pin(&config, pin)?;
+    UNLOCKED.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+pub fn lock() -> Result<(), String> {
+    if load_config().is_none() {
+        return Err("Set a PIN before locking the app".to_string());
+    }
+    UNLOCKED.store(false, Ordering::SeqCst);
+    Ok(())
+}
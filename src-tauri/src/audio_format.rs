@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Audio format of the downloaded files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioFormat {
+    #[default]
+    Mp3,
+    Opus,
+    M4a,
+    Flac,
+    Wav,
+    /// Vorbis in an Ogg container
+    Ogg,
+}
+
+impl AudioFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Opus => "opus",
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Ogg => "ogg",
+        }
+    }
+
+    /// Name of the format for yt-dlp's `--audio-format`
+    pub fn ytdlp_name(self) -> &'static str {
+        match self {
+            AudioFormat::Ogg => "vorbis",
+            other => other.extension(),
+        }
+    }
+
+    /// ffmpeg encoder producing the format
+    pub fn ffmpeg_codec(self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "libmp3lame",
+            AudioFormat::Opus => "libopus",
+            AudioFormat::M4a => "aac",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Wav => "pcm_s16le",
+            AudioFormat::Ogg => "libvorbis",
+        }
+    }
+
+    /// ffmpeg muxer, for output files whose extension doesn't tell
+    pub fn ffmpeg_muxer(self) -> &'static str {
+        match self {
+            AudioFormat::M4a => "ipod",
+            other => other.extension(),
+        }
+    }
+
+    /// Typical bitrate of the output in kbps, for size estimates. Lossless formats
+    /// ignore the chosen bitrate.
+    pub fn estimated_kbps(self, bitrate: u32) -> u32 {
+        match self {
+            AudioFormat::Wav => 1411,
+            AudioFormat::Flac => 900,
+            _ => bitrate,
+        }
+    }
+
+    /// Lossless formats have no bitrate to choose
+    pub fn is_lossless(self) -> bool {
+        matches!(self, AudioFormat::Flac | AudioFormat::Wav)
+    }
+
+    /// Whether the container can carry cover art, WAV can't
+    pub fn supports_cover_art(self) -> bool {
+        self != AudioFormat::Wav
+    }
+
+    /// Encoder and bitrate arguments for ffmpeg, or a plain copy when `copy` is set
+    pub fn ffmpeg_args(self, bitrate: u32, copy: bool) -> Vec<String> {
+        let mut args = vec!["-codec:a".to_string()];
+        if copy {
+            args.push("copy".to_string());
+        } else {
+            args.push(self.ffmpeg_codec().to_string());
+            if !self.is_lossless() {
+                args.push("-b:a".to_string());
+                args.push(format!("{}k", bitrate));
+            }
+        }
+        args
+    }
+
+    /// Whether audio in the codec yt-dlp reports (`mp3`, `opus`, `mp4a.40.2`, ...) can
+    /// be copied into this format without re-encoding
+    pub fn matches_codec(self, acodec: Option<&str>) -> bool {
+        let Some(acodec) = acodec.map(|c| c.to_ascii_lowercase()) else {
+            return false;
+        };
+        match self {
+            AudioFormat::Mp3 => acodec == "mp3",
+            AudioFormat::Opus => acodec == "opus",
+            AudioFormat::M4a => acodec == "aac" || acodec.starts_with("mp4a"),
+            AudioFormat::Flac => acodec == "flac",
+            AudioFormat::Ogg => acodec == "vorbis",
+            AudioFormat::Wav => false,
+        }
+    }
+}
+
+impl fmt::Display for AudioFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.extension().to_uppercase())
+    }
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+/// Post-processing applied to the audio while it is encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioPreset {
+    /// The audio as it was uploaded
+    #[default]
+    Original,
+    /// Gentle dynamic range compression and a volume boost, so quiet classical music and
+    /// podcasts stay audible in the car or on headphones outdoors
+    NoisyEnvironment,
+}
+
+impl AudioPreset {
+    /// ffmpeg audio filter for the preset, none for the original audio
+    pub fn filter(self) -> Option<&'static str> {
+        match self {
+            AudioPreset::Original => None,
+            // Compress above -20 dB at 3:1, make up 6 dB, boost another 3 dB and limit so the
+            // boost can't clip
+            AudioPreset::NoisyEnvironment => Some(
+                "acompressor=threshold=0.1:ratio=3:attack=20:release=250:makeup=2,\
+                 volume=3dB,alimiter=limit=0.95",
+            ),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::api::path::config_dir;
+
+/// Rotate the audit log once it grows past this size
+const MAX_AUDIT_LOG_SIZE: u64 = 1024 * 1024;
+
+/// Number of rotated audit logs kept next to the active one
+const MAX_ROTATED_LOGS: usize = 3;
+
+static AUDIT_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: String,
+    pub command: String,
+    pub params: serde_json::Value,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+fn get_audit_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("youtube-downloader").join("audit.log"))
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    path.with_extension(format!("log.{}", index))
+}
+
+/// The active audit log followed by the rotated ones that exist
+pub fn log_files() -> Vec<PathBuf> {
+    let Some(path) = get_audit_path() else {
+        return Vec::new();
+    };
+    let mut files = vec![path.clone()];
+    files.extend(
+        (1..=MAX_ROTATED_LOGS)
+            .map(|index| rotated_path(&path, index))
+            .filter(|rotated| rotated.exists()),
+    );
+    files
+}
+
+/// Shift `audit.log` -> `audit.log.1` -> ... dropping the oldest file
+fn rotate(path: &Path) {
+    fs::remove_file(rotated_path(path, MAX_ROTATED_LOGS)).ok();
+    for index in (1..MAX_ROTATED_LOGS).rev() {
+        fs::rename(rotated_path(path, index), rotated_path(path, index + 1)).ok();
+    }
+    fs::rename(path, rotated_path(path, 1)).ok();
+}
+
+/// Append one command invocation and its outcome to the audit log
+pub fn record<T>(command: &str, params: serde_json::Value, result: &Result<T, String>) {
+    let entry = AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        command: command.to_string(),
+        params,
+        success: result.is_ok(),
+        error: result.as_ref().err().cloned(),
+    };
+
+    if let Err(e) = append(&entry) {
+        eprintln!("[audit] Failed to record {}: {}", command, e);
+    }
+}
+
+fn append(entry: &AuditEntry) -> Result<(), String> {
+    let _lock = AUDIT_LOCK.lock().unwrap();
+
+    let path = get_audit_path().ok_or("Failed to get config directory")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    if fs::metadata(&path).map(|m| m.len()).unwrap_or(0) >= MAX_AUDIT_LOG_SIZE {
+        rotate(&path);
+    }
+
+    let line = serde_json::to_string(entry)
+        .map_err(|e| format!("Failed to serialize audit entry: {}", e))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| e.to_string())?;
+    writeln!(file, "{}", line).map_err(|e| e.to_string())
+}
+
+/// Read the most recent audit entries, oldest first, across rotated logs
+pub fn load(limit: usize) -> Vec<AuditEntry> {
+    let _lock = AUDIT_LOCK.lock().unwrap();
+
+    let path = match get_audit_path() {
+        Some(path) => path,
+        None => return Vec::new(),
+    };
+
+    let mut files: Vec<PathBuf> = (1..=MAX_ROTATED_LOGS)
+        .rev()
+        .map(|index| rotated_path(&path, index))
+        .collect();
+    files.push(path);
+
+    let mut entries: Vec<AuditEntry> = files
+        .iter()
+        .filter_map(|file| fs::read_to_string(file).ok())
+        .flat_map(|content| {
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str::<AuditEntry>(line).ok())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    if entries.len() > limit {
+        entries.drain(..entries.len() - limit);
+    }
+    entries
+}
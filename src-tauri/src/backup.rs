@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::channel_rules::{self, ChannelRule};
+use crate::commands::{self, AppPreferences, HistoryData};
+
+/// Version of the backup layout, bumped when a file changes in a way older versions of
+/// the app can't restore
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// Describes the backup, stored next to the files in the archive
+const MANIFEST_NAME: &str = "backup.json";
+
+/// Largest file restored from a backup, far above any real preferences or history
+const MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format_version: u32,
+    pub app_version: String,
+    pub created_at: String,
+    /// Files in the archive besides the manifest
+    pub files: Vec<String>,
+}
+
+/// Settings files that make up a backup under their names in the archive. Downloaded
+/// media, caches, logs, cookies, the app lock PIN and the secrets in the preferences
+/// are left out.
+fn backed_up_files() -> Vec<(&'static str, Option<PathBuf>)> {
+    vec![
+        ("preferences.json", commands::get_preferences_path()),
+        ("history.json", commands::get_history_path()),
+        ("channel_rules.json", channel_rules::get_rules_path()),
+    ]
+}
+
+/// The preferences without the proxy and MQTT passwords and the media server token,
+/// which are stored in plain text and have no place in a backup that may be shared
+fn without_secrets(mut prefs: AppPreferences) -> AppPreferences {
+    if let Some(proxy) = prefs.proxy.as_mut() {
+        proxy.password = None;
+    }
+    if let Some(mqtt) = prefs.mqtt.as_mut() {
+        mqtt.password = None;
+    }
+    if let Some(server) = prefs.media_server.as_mut() {
+        server.token.clear();
+    }
+    prefs
+}
+
+/// Fill the secrets a backup leaves out from the current preferences, so restoring
+/// keeps the passwords and token already set up
+fn with_secrets_of(mut prefs: AppPreferences, current: &AppPreferences) -> AppPreferences {
+    if let (Some(proxy), Some(current)) = (prefs.proxy.as_mut(), current.proxy.as_ref()) {
+        if proxy.password.is_none() {
+            proxy.password.clone_from(&current.password);
+        }
+    }
+    if let (Some(mqtt), Some(current)) = (prefs.mqtt.as_mut(), current.mqtt.as_ref()) {
+        if mqtt.password.is_none() {
+            mqtt.password.clone_from(&current.password);
+        }
+    }
+    if let (Some(server), Some(current)) =
+        (prefs.media_server.as_mut(), current.media_server.as_ref())
+    {
+        if server.token.is_empty() {
+            server.token.clone_from(&current.token);
+        }
+    }
+    prefs
+}
+
+/// Content of a file as it goes into the backup, the preferences without their secrets.
+/// Preferences that can't be read are left out rather than copied as they are.
+fn backed_up_content(name: &str, content: Vec<u8>) -> Option<Vec<u8>> {
+    if name != "preferences.json" {
+        return Some(content);
+    }
+    let prefs = serde_json::from_slice::<AppPreferences>(&content).ok()?;
+    serde_json::to_vec_pretty(&without_secrets(prefs)).ok()
+}
+
+/// Content of a file from the backup as it is restored, the preferences with the
+/// current secrets filled in
+fn restored_content(name: &str, content: Vec<u8>) -> Result<Vec<u8>, String> {
+    if name != "preferences.json" {
+        return Ok(content);
+    }
+    let prefs = serde_json::from_slice::<AppPreferences>(&content)
+        .map_err(|e| format!("The backup's {} is damaged: {}", name, e))?;
+    serde_json::to_vec_pretty(&with_secrets_of(prefs, &AppPreferences::load()))
+        .map_err(|e| format!("Failed to serialize preferences: {}", e))
+}
+
+/// Make sure a file read from a backup is what its name says before it replaces anything
+fn check(name: &str, content: &[u8]) -> Result<(), String> {
+    let parsed = match name {
+        "preferences.json" => serde_json::from_slice::<AppPreferences>(content).map(|_| ()),
+        "history.json" => serde_json::from_slice::<HistoryData>(content).map(|_| ()),
+        "channel_rules.json" => serde_json::from_slice::<Vec<ChannelRule>>(content).map(|_| ()),
+        _ => return Ok(()),
+    };
+    parsed.map_err(|e| format!("The backup's {} is damaged: {}", name, e))
+}
+
+/// Write `content` next to `path` and move it into place, so a failed write leaves the
+/// old file alone
+fn replace(path: &Path, content: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let temp = path.with_extension("restore.tmp");
+    fs::write(&temp, content)
+        .and_then(|_| fs::rename(&temp, path))
+        .map_err(|e| {
+            fs::remove_file(&temp).ok();
+            format!("Failed to write {}: {}", path.display(), e)
+        })
+}
+
+/// Zip the preferences, history and channel rules into `path`
+pub fn create(path: &Path) -> Result<BackupManifest, String> {
+    let files: Vec<(&str, Vec<u8>)> = backed_up_files()
+        .into_iter()
+        .filter_map(|(name, source)| {
+            let content = backed_up_content(name, fs::read(source?).ok()?)?;
+            Some((name, content))
+        })
+        .collect();
+    let manifest = BackupManifest {
+        format_version: BACKUP_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        files: files.iter().map(|(name, _)| name.to_string()).collect(),
+    };
+    let manifest_json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize backup manifest: {}", e))?;
+
+    let temp = path.with_extension("zip.tmp");
+    let written = (|| -> zip::result::ZipResult<()> {
+        let mut zip = zip::ZipWriter::new(fs::File::create(&temp)?);
+        let options = zip::write::FileOptions::default();
+        zip.start_file(MANIFEST_NAME, options)?;
+        zip.write_all(&manifest_json)?;
+        for (name, content) in &files {
+            zip.start_file(*name, options)?;
+            zip.write_all(content)?;
+        }
+        zip.finish()?;
+        Ok(())
+    })();
+    written
+        .map_err(|e| e.to_string())
+        .and_then(|_| fs::rename(&temp, path).map_err(|e| e.to_string()))
+        .map_err(|e| {
+            fs::remove_file(&temp).ok();
+            format!("Failed to write backup {}: {}", path.display(), e)
+        })?;
+    Ok(manifest)
+}
+
+/// Read one file of the archive, refusing anything implausibly large
+fn read_entry(zip: &mut zip::ZipArchive<fs::File>, name: &str) -> Result<Option<Vec<u8>>, String> {
+    let entry = match zip.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {} from the backup: {}", name, e)),
+    };
+    let mut content = Vec::new();
+    entry
+        .take(MAX_FILE_SIZE + 1)
+        .read_to_end(&mut content)
+        .map_err(|e| format!("Failed to read {} from the backup: {}", name, e))?;
+    if content.len() as u64 > MAX_FILE_SIZE {
+        return Err(format!("The backup's {} is too large", name));
+    }
+    Ok(Some(content))
+}
+
+/// Replace the preferences, history and channel rules with those of a backup. Every
+/// file is checked before the first one is written, so a damaged backup changes nothing.
+pub fn restore(path: &Path) -> Result<BackupManifest, String> {
+    let file = fs::File::open(path)
+        .map_err(|e| format!("Failed to open backup {}: {}", path.display(), e))?;
+    let mut zip = zip::ZipArchive::new(file)
+        .map_err(|e| format!("{} is not a backup: {}", path.display(), e))?;
+
+    let manifest: BackupManifest = read_entry(&mut zip, MANIFEST_NAME)?
+        .ok_or_else(|| format!("{} is not a backup, it has no manifest", path.display()))
+        .and_then(|content| {
+            serde_json::from_slice(&content)
+                .map_err(|e| format!("The backup's manifest is damaged: {}", e))
+        })?;
+    if manifest.format_version > BACKUP_FORMAT_VERSION {
+        return Err(format!(
+            "The backup was made by version {} of the app, which is newer than this one ({}). Update the app to restore it.",
+            manifest.app_version,
+            env!("CARGO_PKG_VERSION")
+        ));
+    }
+
+    let mut restored = Vec::new();
+    for (name, target) in backed_up_files() {
+        if !manifest.files.iter().any(|f| f == name) {
+            continue;
+        }
+        let content =
+            read_entry(&mut zip, name)?.ok_or_else(|| format!("The backup is missing {}", name))?;
+        check(name, &content)?;
+        let target = target.ok_or("Failed to get config directory")?;
+        restored.push((target, restored_content(name, content)?));
+    }
+    for (target, content) in &restored {
+        replace(target, content)?;
+    }
+    Ok(manifest)
+}
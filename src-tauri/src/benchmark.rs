@@ -0,0 +1,279 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tauri::api::path::{cache_dir, config_dir};
+use tauri::AppHandle;
+use tokio::process::Command;
+
+use crate::download::{self, MAX_CONCURRENT_DOWNLOADS};
+
+/// Length of the sample, long enough that process start-up doesn't dominate
+const SAMPLE_SECONDS: u32 = 60;
+
+const BITRATES: &[u32] = &[128, 192, 256, 320];
+
+/// Bitrate of the concurrency runs, the app's default
+const CONCURRENCY_BITRATE: u32 = 192;
+
+/// Size of the file written to measure disk throughput
+const DISK_TEST_BYTES: usize = 64 * 1024 * 1024;
+
+/// A concurrency has to beat the previous one by this factor to be recommended
+const MIN_SPEEDUP: f64 = 1.15;
+
+/// Only one benchmark at a time, two would measure each other
+static BENCHMARK_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncodeResult {
+    pub bitrate: u32,
+    pub seconds: f64,
+    /// Seconds of audio encoded per second, 10.0 is ten times real time
+    pub speed: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConcurrencyResult {
+    pub concurrency: usize,
+    /// Combined speed of all encodes running at once
+    pub speed: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    pub ran_at: String,
+    pub sample_seconds: u32,
+    pub cpu_threads: usize,
+    pub encodes: Vec<EncodeResult>,
+    pub concurrency: Vec<ConcurrencyResult>,
+    pub disk_folder: String,
+    pub disk_write_mb_per_second: f64,
+    /// Used as the playlist concurrency while `max_concurrent_downloads` is not set
+    pub recommended_concurrency: usize,
+}
+
+fn get_result_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("youtube-downloader").join("benchmark.json"))
+}
+
+fn get_work_dir() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("youtube-downloader").join("benchmark"))
+}
+
+/// Load the result of the last benchmark, if any
+pub fn load_result() -> Option<BenchmarkResult> {
+    let content = fs::read_to_string(get_result_path()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Playlist concurrency recommended by the last benchmark
+pub fn recommended_concurrency() -> Option<usize> {
+    load_result().map(|r| r.recommended_concurrency.clamp(1, MAX_CONCURRENT_DOWNLOADS))
+}
+
+/// Encode speed the last benchmark measured at the bitrate closest to `bitrate`, in
+/// seconds of audio per second
+pub fn encode_speed(bitrate: u32) -> Option<f64> {
+    load_result()?
+        .encodes
+        .into_iter()
+        .filter(|e| e.speed > 0.0)
+        .min_by_key(|e| e.bitrate.abs_diff(bitrate))
+        .map(|e| e.speed)
+}
+
+fn save_result(result: &BenchmarkResult) -> Result<(), String> {
+    let path = get_result_path().ok_or("Failed to get config directory")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let content = serde_json::to_string_pretty(result)
+        .map_err(|e| format!("Failed to serialize benchmark result: {}", e))?;
+    fs::write(&path, content).map_err(|e| e.to_string())
+}
+
+async fn run_ffmpeg(ffmpeg_cmd: &str, args: &[&str]) -> Result<(), String> {
+    let output = Command::new(ffmpeg_cmd)
+        .arg("-y")
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "ffmpeg failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// The sample converted by the benchmark. It is made by ffmpeg's own test sources
+/// rather than shipped with the app, pink noise under a tone is about as hard to
+/// encode as music.
+async fn ensure_sample(ffmpeg_cmd: &str, work_dir: &Path) -> Result<PathBuf, String> {
+    let sample = work_dir.join(format!("sample-{}s.wav", SAMPLE_SECONDS));
+    if sample.exists() {
+        return Ok(sample);
+    }
+    let partial = work_dir.join("sample.partial.wav");
+    run_ffmpeg(
+        ffmpeg_cmd,
+        &[
+            "-f",
+            "lavfi",
+            "-i",
+            &format!("anoisesrc=d={}:c=pink:a=0.3", SAMPLE_SECONDS),
+            "-f",
+            "lavfi",
+            "-i",
+            &format!("sine=f=440:d={}", SAMPLE_SECONDS),
+            "-filter_complex",
+            "amix=inputs=2",
+            "-ac",
+            "2",
+            "-ar",
+            "44100",
+            &partial.to_string_lossy(),
+        ],
+    )
+    .await?;
+    fs::rename(&partial, &sample).map_err(|e| e.to_string())?;
+    Ok(sample)
+}
+
+/// Convert the sample to MP3 the way downloads are converted, returning the seconds taken
+async fn encode(
+    ffmpeg_cmd: &str,
+    sample: &Path,
+    output: &Path,
+    bitrate: u32,
+) -> Result<f64, String> {
+    let started = Instant::now();
+    let result = run_ffmpeg(
+        ffmpeg_cmd,
+        &[
+            "-i",
+            &sample.to_string_lossy(),
+            "-vn",
+            "-codec:a",
+            "libmp3lame",
+            "-b:a",
+            &format!("{}k", bitrate),
+            &output.to_string_lossy(),
+        ],
+    )
+    .await;
+    fs::remove_file(output).ok();
+    result.map(|()| started.elapsed().as_secs_f64())
+}
+
+/// Write a file to `folder` and flush it to disk, returning the throughput in MB/s
+fn measure_disk_write(folder: &Path) -> Result<f64, String> {
+    let path = folder.join(".youtube-downloader-benchmark.tmp");
+    let chunk = vec![0x5au8; 1024 * 1024];
+    let started = Instant::now();
+    let written = fs::File::create(&path).and_then(|mut file| {
+        for _ in 0..DISK_TEST_BYTES / chunk.len() {
+            file.write_all(&chunk)?;
+        }
+        file.sync_all()
+    });
+    let elapsed = started.elapsed().as_secs_f64();
+    fs::remove_file(&path).ok();
+    written.map_err(|e| format!("Failed to write to {}: {}", folder.display(), e))?;
+    Ok(DISK_TEST_BYTES as f64 / (1024.0 * 1024.0) / elapsed.max(f64::EPSILON))
+}
+
+/// The lowest concurrency after which running more encodes at once stops paying off
+fn recommend(concurrency: &[ConcurrencyResult]) -> usize {
+    let mut recommended = concurrency.first().map_or(1, |c| c.concurrency);
+    let mut speed = concurrency.first().map_or(0.0, |c| c.speed);
+    for result in concurrency.iter().skip(1) {
+        if result.speed < speed * MIN_SPEEDUP {
+            break;
+        }
+        recommended = result.concurrency;
+        speed = result.speed;
+    }
+    recommended
+}
+
+/// Convert the sample at every bitrate and with 1 to `MAX_CONCURRENT_DOWNLOADS` encodes
+/// at once, measure how fast `disk_folder` takes writes and store the result.
+/// Fails while downloads are active, they would skew the numbers.
+pub async fn run(
+    app_handle: &AppHandle,
+    disk_folder: Option<String>,
+) -> Result<BenchmarkResult, String> {
+    let _lock = BENCHMARK_LOCK
+        .try_lock()
+        .map_err(|_| "A benchmark is already running".to_string())?;
+    if download::active_downloads() > 0 {
+        return Err("The benchmark cannot run while downloads are active".to_string());
+    }
+
+    let ffmpeg_cmd = download::ensure_ffmpeg(app_handle).await?;
+    let work_dir = get_work_dir().ok_or("Failed to get cache directory")?;
+    fs::create_dir_all(&work_dir).map_err(|e| e.to_string())?;
+    let sample = ensure_sample(&ffmpeg_cmd, &work_dir).await?;
+    let sample_seconds = SAMPLE_SECONDS as f64;
+
+    let mut encodes = Vec::new();
+    for &bitrate in BITRATES {
+        let output = work_dir.join(format!("encode-{}.mp3", bitrate));
+        let seconds = encode(&ffmpeg_cmd, &sample, &output, bitrate).await?;
+        eprintln!("[benchmark] {}k encoded in {:.2}s", bitrate, seconds);
+        encodes.push(EncodeResult {
+            bitrate,
+            seconds,
+            speed: sample_seconds / seconds.max(f64::EPSILON),
+        });
+    }
+
+    let mut concurrency = Vec::new();
+    for n in 1..=MAX_CONCURRENT_DOWNLOADS {
+        let started = Instant::now();
+        let runs = (0..n).map(|i| {
+            let output = work_dir.join(format!("parallel-{}.mp3", i));
+            let (ffmpeg_cmd, sample) = (&ffmpeg_cmd, &sample);
+            async move { encode(ffmpeg_cmd, sample, &output, CONCURRENCY_BITRATE).await }
+        });
+        for result in futures_util::future::join_all(runs).await {
+            result?;
+        }
+        let seconds = started.elapsed().as_secs_f64();
+        concurrency.push(ConcurrencyResult {
+            concurrency: n,
+            speed: n as f64 * sample_seconds / seconds.max(f64::EPSILON),
+        });
+    }
+
+    let disk_folder = disk_folder
+        .filter(|f| !f.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| work_dir.clone());
+    let disk_dir = disk_folder.clone();
+    let disk_write_mb_per_second =
+        tauri::async_runtime::spawn_blocking(move || measure_disk_write(&disk_dir))
+            .await
+            .map_err(|e| format!("Disk benchmark failed: {}", e))??;
+
+    let result = BenchmarkResult {
+        ran_at: chrono::Utc::now().to_rfc3339(),
+        sample_seconds: SAMPLE_SECONDS,
+        cpu_threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        recommended_concurrency: recommend(&concurrency),
+        encodes,
+        concurrency,
+        disk_folder: disk_folder.to_string_lossy().to_string(),
+        disk_write_mb_per_second,
+    };
+    save_result(&result)?;
+    Ok(result)
+}
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::api::path::config_dir;
+use tauri::{AppHandle, Runtime};
+
+use crate::audio_format::AudioFormat;
+use crate::audio_preset::AudioPreset;
+use crate::download::{self, DownloadOptions, DownloadOverrides};
+use crate::source;
+use crate::tags::TagOverrides;
+
+/// Settings applied to every video of one channel when it is downloaded
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelRule {
+    /// Channel name as yt-dlp reports the uploader, matched ignoring case
+    pub channel: String,
+    #[serde(flatten)]
+    pub tags: TagOverrides,
+    pub audio_format: Option<AudioFormat>,
+    pub audio_preset: Option<AudioPreset>,
+    /// Subfolder of the output folder the channel's downloads go into
+    pub subfolder: Option<String>,
+}
+
+impl ChannelRule {
+    fn matches(&self, channel: &str) -> bool {
+        self.channel.trim().eq_ignore_ascii_case(channel.trim())
+    }
+}
+
+pub(crate) fn get_rules_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("youtube-downloader").join("channel_rules.json"))
+}
+
+pub fn load() -> Vec<ChannelRule> {
+    get_rules_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save(rules: &[ChannelRule]) -> Result<(), String> {
+    let path = get_rules_path().ok_or("Failed to get config directory")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let content = serde_json::to_string_pretty(rules).map_err(|e| e.to_string())?;
+    fs::write(&path, content).map_err(|e| e.to_string())
+}
+
+/// Add, replace or, without a rule, remove the rule for `channel`. Returns every rule.
+pub fn set(channel: &str, rule: Option<ChannelRule>) -> Result<Vec<ChannelRule>, String> {
+    let channel = channel.trim();
+    if channel.is_empty() {
+        return Err("The channel name is empty".to_string());
+    }
+    let mut rules = load();
+    rules.retain(|r| !r.matches(channel));
+    if let Some(mut rule) = rule {
+        rule.channel = channel.to_string();
+        rule.subfolder = rule
+            .subfolder
+            .map(|folder| download::sanitize_filename(&folder))
+            .filter(|folder| !folder.is_empty());
+        rules.push(rule);
+    }
+    save(&rules)?;
+    Ok(rules)
+}
+
+/// Fill what `overrides` leaves unset from the rule for the video's channel, and move
+/// `output_folder` into the rule's subfolder. Playlists, and videos whose channel can't
+/// be looked up, are downloaded as they are.
+pub async fn apply<R: Runtime>(
+    url: &str,
+    output_folder: &mut String,
+    overrides: &mut DownloadOverrides,
+    options: &DownloadOptions,
+    app_handle: &AppHandle<R>,
+) {
+    let rules = load();
+    let single_video = source::parse_source(url).is_ok_and(|s| !s.is_playlist());
+    if rules.is_empty() || !single_video {
+        return;
+    }
+    let uploader = match download::get_video_info(url, None, options, app_handle).await {
+        Ok(info) => info.uploader,
+        Err(e) => {
+            eprintln!(
+                "[channel_rules] Failed to look up the channel of {}: {}",
+                url, e
+            );
+            return;
+        }
+    };
+    let Some(rule) = uploader.and_then(|uploader| rules.into_iter().find(|r| r.matches(&uploader)))
+    else {
+        return;
+    };
+
+    let tags = &mut overrides.tags;
+    tags.artist = tags.artist.take().or(rule.tags.artist);
+    tags.album = tags.album.take().or(rule.tags.album);
+    tags.genre = tags.genre.take().or(rule.tags.genre);
+    overrides.audio_format = overrides.audio_format.or(rule.audio_format);
+    overrides.audio_preset = overrides.audio_preset.or(rule.audio_preset);
+    if let Some(subfolder) = rule.subfolder {
+        *output_folder = Path::new(output_folder.as_str())
+            .join(subfolder)
+            .to_string_lossy()
+            .to_string();
+    }
+}
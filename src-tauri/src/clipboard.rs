@@ -0,0 +1,137 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+
+use crate::events::{self, Event};
+use crate::source::{self, PastedUrl};
+
+/// How often the clipboard is read while the watcher runs
+const POLL_INTERVAL: Duration = Duration::from_millis(1500);
+
+/// Longest time a clipboard tool gets to answer before the read is skipped
+const READ_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Sent as `ytmp3://clipboard/url-detected/v2` when the clipboard changes to text
+/// containing URLs that can be downloaded
+#[derive(Debug, Clone, Serialize)]
+pub struct ClipboardUrls {
+    pub urls: Vec<PastedUrl>,
+}
+
+/// Opt-in watcher offering URLs copied anywhere on the system for download, kept in
+/// tauri State
+#[derive(Default)]
+pub struct ClipboardWatcher {
+    running: Mutex<Option<CancellationToken>>,
+}
+
+impl ClipboardWatcher {
+    /// Start polling the clipboard unless it is already being polled. What is on the
+    /// clipboard at this point is not reported, only what is copied afterwards.
+    pub fn start(&self, app_handle: AppHandle) {
+        let mut running = self.running.lock().unwrap();
+        if running.is_some() {
+            return;
+        }
+        let cancel = CancellationToken::new();
+        *running = Some(cancel.clone());
+        tauri::async_runtime::spawn(async move {
+            let mut last = read_text().await;
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                }
+                let Some(text) = read_text().await else {
+                    continue;
+                };
+                if last.as_deref() == Some(text.as_str()) {
+                    continue;
+                }
+                let urls = source::parse_pasted_urls(&text).urls;
+                if !urls.is_empty() {
+                    events::emit(
+                        &app_handle,
+                        Event::ClipboardUrlDetected,
+                        &ClipboardUrls { urls },
+                    );
+                }
+                last = Some(text);
+            }
+        });
+    }
+
+    pub fn stop(&self) {
+        if let Some(cancel) = self.running.lock().unwrap().take() {
+            cancel.cancel();
+        }
+    }
+}
+
+/// Start or stop the app's watcher to match the preference
+pub fn configure(app_handle: &AppHandle, enabled: bool) {
+    let watcher = app_handle.state::<ClipboardWatcher>();
+    if enabled {
+        watcher.start(app_handle.clone());
+    } else {
+        watcher.stop();
+    }
+}
+
+/// Commands that print the clipboard's text, tried in order. Tauri's own clipboard
+/// support needs a clipboard crate this build doesn't ship, so the tools every desktop
+/// has are used instead.
+fn read_commands() -> Vec<Command> {
+    let tools: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbpaste", &[])]
+    } else if cfg!(windows) {
+        &[(
+            "powershell",
+            &[
+                "-NoProfile",
+                "-NonInteractive",
+                "-Command",
+                "Get-Clipboard -Raw",
+            ],
+        )]
+    } else {
+        &[
+            ("wl-paste", &["--no-newline", "--type", "text"]),
+            ("xclip", &["-selection", "clipboard", "-o"]),
+            ("xsel", &["--clipboard", "--output"]),
+        ]
+    };
+    tools
+        .iter()
+        .map(|(program, args)| {
+            let mut cmd = Command::new(program);
+            cmd.args(*args);
+            #[cfg(windows)]
+            {
+                // Keep a console window from flashing up on every poll
+                const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+                cmd.creation_flags(CREATE_NO_WINDOW);
+            }
+            cmd
+        })
+        .collect()
+}
+
+/// Text on the clipboard, none when it is empty, holds something else or can't be read
+async fn read_text() -> Option<String> {
+    for mut cmd in read_commands() {
+        cmd.stdin(std::process::Stdio::null()).kill_on_drop(true);
+        let Ok(Ok(output)) = tokio::time::timeout(READ_TIMEOUT, cmd.output()).await else {
+            continue;
+        };
+        if output.status.success() {
+            return String::from_utf8(output.stdout)
+                .ok()
+                .filter(|text| !text.trim().is_empty());
+        }
+    }
+    None
+}
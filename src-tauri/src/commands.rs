@@ -1,15 +1,64 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use tauri::api::path::config_dir;
+use tauri::Runtime;
 
+use crate::advisor::{self, ErrorCode, FixAdvice, Remedy};
+use crate::app_lock::{self, AppLockStatus};
+use crate::audio_format::AudioFormat;
+use crate::audio_preset::AudioPreset;
+use crate::audit;
+use crate::backup::{self, BackupManifest};
+use crate::benchmark;
+use crate::channel_rules::{self, ChannelRule};
+use crate::clipboard;
+use crate::compare::{self, ComparisonCandidate, QualityComparison};
+use crate::conversion::LoudnessNormalization;
+use crate::cookies::{self, CookiesStatus};
+use crate::crash;
+use crate::decisions::{self, DecisionKind};
 use crate::deps;
+use crate::deps_fetch;
 use crate::download::{
-    download_playlist_with_progress, download_youtube, is_playlist_url, DownloadResult,
-    PlaylistDownloadResult,
+    self, check_playlist_size, download_playlist_with_progress, download_youtube,
+    matches_sanitized_title, ActiveDownloadGuard, CancelRegistration, DownloadOptions,
+    DownloadOverrides, DownloadResult, ExtractorArgs, FolderOrganization, PlaylistConfirmation,
+    PlaylistDownloadResult, PlaylistEntry, PlaylistEstimate, Politeness, RetryPolicy, Verbosity,
+    VideoInfo, MAX_CONCURRENT_DOWNLOADS, RECOMMENDED_PLAYER_CLIENTS,
 };
+use crate::download_log;
+use crate::events::{self, Event};
+use crate::forecast::QueueForecast;
+use crate::incomplete::{self, IncompleteScan};
+use crate::install_steps::{self, InstallStep};
+use crate::integrity;
+use crate::library::{self, RelocationReport};
+use crate::liner_notes::{self, LinerTrack};
+use crate::lyrics::LyricsSettings;
+use crate::maintenance;
+use crate::manifest::{self, ManifestExport, ManifestFormat, ManifestVerification};
+use crate::media_cache::{self, MediaCacheSettings};
+use crate::media_server::{self, MediaServerSettings};
+use crate::mqtt::{self, MqttSettings};
+use crate::network_sim::{self, NetworkSimulation};
+use crate::preflight::{self, PreflightProgress};
+use crate::proxy::{self, ProxySettings};
+use crate::queue::{BatchResult, DownloadQueue, QueueImport, QueueJob, QueueStateChange};
+use crate::replacement::{self, ReplacementCandidate};
+use crate::sections::SectionedDownload;
+use crate::silence::SilenceTrim;
+use crate::source::{self, ParsedSource};
+use crate::speed_history::{self, SpeedSample};
+use crate::storage::{self, StorageCleanup, StorageLimits, StorageUsage};
+use crate::tracklist::{self, TracklistEntry, TracklistResult};
+use crate::trim::{self, TrimResult, Waveform};
+use crate::version_check::{self, AvailableUpdates};
+use crate::video::{self, VideoContainer};
+use crate::volume;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DownloadHistory {
     pub url: String,
     pub title: Option<String>,
@@ -17,6 +66,27 @@ pub struct DownloadHistory {
     pub bitrate: u32,
     pub timestamp: String,
     pub duration: Option<f64>,
+    /// Versions the download was made with, so failures can be traced to an upgrade
+    pub app_version: Option<String>,
+    pub ytdlp_version: Option<String>,
+    pub ffmpeg_version: Option<String>,
+    /// Entries from before other formats were supported are MP3
+    #[serde(default)]
+    pub audio_format: AudioFormat,
+    /// The file was gone the last time `verify_history_files` looked for it
+    #[serde(default)]
+    pub missing: bool,
+}
+
+/// What `verify_history_files` found
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryVerification {
+    pub checked: usize,
+    pub present: usize,
+    /// Entries whose file is gone, newest first, whether they were pruned or kept
+    pub missing: Vec<DownloadHistory>,
+    /// Entries removed from the history because their file is gone
+    pub pruned: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -31,7 +101,7 @@ impl HistoryData {
         }
     }
 
-    fn load() -> Self {
+    pub(crate) fn load() -> Self {
         if let Some(history_path) = get_history_path() {
             if let Ok(content) = fs::read_to_string(&history_path) {
                 if let Ok(data) = serde_json::from_str::<HistoryData>(&content) {
@@ -42,7 +112,7 @@ impl HistoryData {
         Self::new()
     }
 
-    fn save(&self) -> Result<(), String> {
+    pub(crate) fn save(&self) -> Result<(), String> {
         if let Some(history_path) = get_history_path() {
             if let Some(parent) = history_path.parent() {
                 fs::create_dir_all(parent).map_err(|e| e.to_string())?;
@@ -62,9 +132,106 @@ impl HistoryData {
         }
         self.save()
     }
+
+    /// Point entries whose file is missing at the file yt-dlp actually wrote, for
+    /// downloads recorded under our guessed name. Returns how many were fixed.
+    pub(crate) fn repair_missing_paths(&mut self) -> usize {
+        let mut repaired = 0;
+        for download in &mut self.downloads {
+            let path = Path::new(&download.output_path);
+            if path.exists() {
+                continue;
+            }
+            let (Some(dir), Some(stem)) =
+                (path.parent(), path.file_stem().and_then(|s| s.to_str()))
+            else {
+                continue;
+            };
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+
+            let found = entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .find(|candidate| {
+                    candidate.extension() == path.extension()
+                        && candidate
+                            .file_stem()
+                            .and_then(|s| s.to_str())
+                            .is_some_and(|actual| matches_sanitized_title(actual, stem))
+                });
+            if let Some(found) = found {
+                download.output_path = found.to_string_lossy().to_string();
+                repaired += 1;
+            }
+        }
+        repaired
+    }
+
+    /// Look for the file of every entry, marking the ones that are gone as missing or,
+    /// with `prune`, removing them. Renamed files are found first, like when the
+    /// history is shown.
+    fn verify_files(&mut self, prune: bool) -> HistoryVerification {
+        self.repair_missing_paths();
+        for download in &mut self.downloads {
+            download.missing = !Path::new(&download.output_path).is_file();
+        }
+        let missing: Vec<DownloadHistory> = self
+            .downloads
+            .iter()
+            .rev()
+            .filter(|d| d.missing)
+            .cloned()
+            .collect();
+        let checked = self.downloads.len();
+        if prune {
+            self.downloads.retain(|d| !d.missing);
+        }
+        HistoryVerification {
+            checked,
+            present: checked - missing.len(),
+            pruned: if prune { missing.len() } else { 0 },
+            missing,
+        }
+    }
+
+    /// Write the history to `path`, oldest first, as pretty JSON or as CSV with one
+    /// row per download. Returns how many entries were written.
+    fn export(&self, path: &Path, format: ManifestFormat) -> Result<usize, String> {
+        let content = match format {
+            ManifestFormat::Json => serde_json::to_string_pretty(&self.downloads)
+                .map_err(|e| format!("Failed to serialize history: {}", e))?,
+            ManifestFormat::Csv => {
+                let mut csv =
+                    String::from("title,url,output_path,bitrate,audio_format,duration,timestamp\n");
+                for download in &self.downloads {
+                    let row = [
+                        download.title.clone().unwrap_or_default(),
+                        download.url.clone(),
+                        download.output_path.clone(),
+                        download.bitrate.to_string(),
+                        download.audio_format.extension().to_string(),
+                        download.duration.map(|d| d.to_string()).unwrap_or_default(),
+                        download.timestamp.clone(),
+                    ];
+                    let fields: Vec<String> = row.iter().map(|f| manifest::csv_field(f)).collect();
+                    csv.push_str(&fields.join(","));
+                    csv.push('\n');
+                }
+                csv
+            }
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(self.downloads.len())
+    }
 }
 
-fn get_history_path() -> Option<PathBuf> {
+pub(crate) fn get_history_path() -> Option<PathBuf> {
     config_dir().map(|dir| dir.join("youtube-downloader").join("history.json"))
 }
 
@@ -73,18 +240,233 @@ pub struct AppPreferences {
     pub output_folder: Option<String>,
     pub bitrate: Option<u32>,
     pub last_url: Option<String>,
+    pub extractor_args: Option<ExtractorArgs>,
+    pub impersonate: Option<String>,
+    pub user_agent: Option<String>,
+    pub politeness: Option<Politeness>,
+    /// Also add the login shell's PATH for child processes, not just standard locations
+    pub augment_path_from_shell: Option<bool>,
+    /// Playlists with more items than this need confirmation before downloading
+    pub playlist_confirm_threshold: Option<usize>,
+    pub media_cache: Option<MediaCacheSettings>,
+    pub sectioned_download: Option<SectionedDownload>,
+    /// Romanize non-Latin titles when naming files
+    pub transliterate_titles: Option<bool>,
+    pub ytdlp_verbosity: Option<Verbosity>,
+    /// Check for and download missing or outdated yt-dlp/ffmpeg at startup (default on)
+    pub preflight_dependencies: Option<bool>,
+    /// Download location tried before the official ones, for networks that block them.
+    /// Either a base URL laid out like `binaries/<platform>/<arch>/<binary>` or a URL
+    /// with `{platform}`, `{arch}` and `{binary}` placeholders. Only https:// is accepted,
+    /// and each binary needs its SHA-256 next to it in a `<binary>.sha256` file.
+    pub dependency_mirror: Option<String>,
+    /// Apply the remedies the app can apply itself once an error keeps repeating, unless
+    /// the app is locked
+    pub auto_apply_fixes: Option<bool>,
+    /// Playlist videos downloaded at the same time. Unset uses what the last benchmark
+    /// recommended, or 1 if it never ran.
+    pub max_concurrent_downloads: Option<usize>,
+    /// Retries of playlist videos that failed
+    pub playlist_retry: Option<RetryPolicy>,
+    /// Run fewer playlist videos at once while the CPU or disk is busy (default on)
+    pub adaptive_concurrency: Option<bool>,
+    /// Download speed cap for metered connections, e.g. `500K` or `2M` bytes per second
+    pub max_download_rate: Option<String>,
+    /// Proxy for yt-dlp and for dependency and update downloads
+    pub proxy: Option<ProxySettings>,
+    /// Convert single downloads while they stream in, without a temporary file
+    pub stream_conversion: Option<bool>,
+    /// Pass the imported cookies to yt-dlp (default on once cookies are imported)
+    pub use_cookies: Option<bool>,
+    /// Format downloads are converted to when the caller doesn't pick one (default MP3)
+    pub audio_format: Option<AudioFormat>,
+    /// Write playlist art and album.nfo/metadata.json next to playlist downloads
+    pub folder_artwork: Option<bool>,
+    /// Tag downloads with title, artist and album (default on)
+    pub embed_metadata: Option<bool>,
+    /// Jellyfin or Plex server rescanned after a batch of downloads
+    pub media_server: Option<MediaServerSettings>,
+    /// Embed the video thumbnail as cover art
+    pub embed_thumbnail: Option<bool>,
+    /// MQTT broker the queue status and download events are published to
+    pub mqtt: Option<MqttSettings>,
+    /// Cut sponsor and non-music segments out of downloads with SponsorBlock
+    pub sponsorblock: Option<bool>,
+    /// Leading and trailing silence removed from each track
+    pub silence_trim: Option<SilenceTrim>,
+    /// Also save each chapter of a download as a separate track
+    pub split_chapters: Option<bool>,
+    /// Post-processing preset applied to downloads, the original audio when unset
+    pub audio_preset: Option<AudioPreset>,
+    /// Also send events under their names from before the namespaced channels (default on)
+    pub legacy_events: Option<bool>,
+    /// Accept URLs from any site yt-dlp supports, not just YouTube (default off)
+    pub allow_any_site: Option<bool>,
+    /// Debug throttling and random failures injected into downloads
+    pub network_simulation: Option<NetworkSimulation>,
+    /// Watch the clipboard for copied URLs and offer them for download (default off)
+    pub clipboard_monitor: Option<bool>,
+    /// Disk limits of the logs and yt-dlp's metadata cache
+    pub storage_limits: Option<StorageLimits>,
+    /// Subfolder per playlist or uploader that downloads are sorted into (default none)
+    pub folder_organization: Option<FolderOrganization>,
+    /// Subtitles or captions saved next to each track as `.lrc` lyrics
+    pub lyrics: Option<LyricsSettings>,
+    /// Two-pass loudness normalization to a target LUFS after each download
+    pub loudness_normalization: Option<LoudnessNormalization>,
+    /// Weekly notification summarizing downloads and failures (default off)
+    pub weekly_digest: Option<bool>,
+    /// Send progress only when it reaches a new step or status (default off)
+    pub reduced_updates: Option<bool>,
 }
 
+/// Default for `playlist_confirm_threshold`
+const DEFAULT_PLAYLIST_CONFIRM_THRESHOLD: usize = 50;
+
 impl AppPreferences {
     fn new() -> Self {
         Self {
             output_folder: None,
             bitrate: None,
             last_url: None,
+            extractor_args: None,
+            impersonate: None,
+            user_agent: None,
+            politeness: None,
+            augment_path_from_shell: None,
+            playlist_confirm_threshold: None,
+            media_cache: None,
+            sectioned_download: None,
+            transliterate_titles: None,
+            ytdlp_verbosity: None,
+            preflight_dependencies: None,
+            dependency_mirror: None,
+            auto_apply_fixes: None,
+            max_concurrent_downloads: None,
+            playlist_retry: None,
+            adaptive_concurrency: None,
+            max_download_rate: None,
+            proxy: None,
+            stream_conversion: None,
+            use_cookies: None,
+            audio_format: None,
+            folder_artwork: None,
+            embed_metadata: None,
+            media_server: None,
+            embed_thumbnail: None,
+            mqtt: None,
+            sponsorblock: None,
+            silence_trim: None,
+            split_chapters: None,
+            audio_preset: None,
+            legacy_events: None,
+            allow_any_site: None,
+            network_simulation: None,
+            clipboard_monitor: None,
+            storage_limits: None,
+            folder_organization: None,
+            lyrics: None,
+            loudness_normalization: None,
+            weekly_digest: None,
+            reduced_updates: None,
+        }
+    }
+
+    /// Set up the child process environment according to the saved preferences
+    pub fn bootstrap_environment() {
+        let prefs = Self::load();
+        deps::bootstrap_environment(prefs.augment_path_from_shell.unwrap_or(false));
+    }
+
+    /// Apply the saved settings that are read at runtime: the proxy for the app's own
+    /// HTTP requests, the MQTT broker, whether legacy event names are still sent and
+    /// how often progress is sent
+    pub fn apply_runtime_settings() {
+        let prefs = Self::load();
+        proxy::configure(prefs.proxy);
+        mqtt::configure(prefs.mqtt);
+        events::configure(prefs.legacy_events.unwrap_or(true));
+        source::configure(prefs.allow_any_site.unwrap_or(false));
+        network_sim::configure(prefs.network_simulation.as_ref());
+        download::configure_reduced_updates(prefs.reduced_updates.unwrap_or(false));
+    }
+
+    /// Prepare the dependencies in the background after launch, downloading them if the
+    /// preferences allow it and only extracting the bundled copies otherwise
+    pub fn start_dependency_preflight(app_handle: tauri::AppHandle) {
+        let prefs = Self::load();
+        if prefs.preflight_dependencies.unwrap_or(true) {
+            preflight::start(app_handle, prefs.dependency_mirror);
+        } else {
+            deps::warm_up(app_handle);
         }
     }
 
-    fn load() -> Self {
+    /// Trim the logs and caches that grew past their limits
+    pub fn enforce_storage_limits() -> StorageCleanup {
+        let prefs = Self::load();
+        storage::enforce(
+            &prefs.storage_limits.unwrap_or_default(),
+            prefs.media_cache.as_ref().filter(|c| c.enabled),
+        )
+    }
+
+    /// Start watching the clipboard after launch if the preferences ask for it
+    pub fn start_clipboard_monitor(app_handle: tauri::AppHandle) {
+        if Self::load().clipboard_monitor.unwrap_or(false) {
+            clipboard::configure(&app_handle, true);
+        }
+    }
+
+    /// Build the yt-dlp options used for downloads from the saved preferences
+    fn download_options(&self) -> DownloadOptions {
+        DownloadOptions {
+            extractor_args: self.extractor_args.clone(),
+            impersonate: self.impersonate.clone().filter(|t| !t.trim().is_empty()),
+            user_agent: self.user_agent.clone().filter(|ua| !ua.trim().is_empty()),
+            proxy: self
+                .proxy
+                .as_ref()
+                .filter(|p| p.enabled)
+                .and_then(|p| p.url().ok()),
+            stream_conversion: self.stream_conversion.unwrap_or(false),
+            folder_artwork: self.folder_artwork.unwrap_or(false),
+            embed_metadata: self.embed_metadata.unwrap_or(true),
+            embed_thumbnail: self.embed_thumbnail.unwrap_or(false),
+            sponsorblock: self.sponsorblock.unwrap_or(false),
+            silence_trim: self.silence_trim.clone().filter(|s| s.enabled),
+            split_chapters: self.split_chapters.unwrap_or(false),
+            lyrics: self.lyrics.clone().filter(|l| l.enabled),
+            audio_preset: self.audio_preset.unwrap_or_default(),
+            loudness: self.loudness_normalization.clone().filter(|l| l.enabled),
+            cookies: self
+                .use_cookies
+                .unwrap_or(true)
+                .then(cookies::imported_cookies)
+                .flatten(),
+            max_download_rate: self
+                .max_download_rate
+                .as_deref()
+                .and_then(|rate| download::parse_download_rate(rate).ok()),
+            network_simulation: self.network_simulation.clone().filter(|s| s.enabled),
+            politeness: self.politeness.clone().unwrap_or_default(),
+            media_cache: self.media_cache.clone().filter(|c| c.enabled),
+            sections: self.sectioned_download.clone().filter(|s| s.enabled),
+            transliterate: self.transliterate_titles.unwrap_or(false),
+            verbosity: self.ytdlp_verbosity.unwrap_or_default(),
+            concurrency: self
+                .max_concurrent_downloads
+                .or_else(benchmark::recommended_concurrency)
+                .unwrap_or(1)
+                .clamp(1, MAX_CONCURRENT_DOWNLOADS),
+            adaptive_concurrency: self.adaptive_concurrency.unwrap_or(true),
+            retry: self.playlist_retry.clone().unwrap_or_default(),
+            folder_organization: self.folder_organization.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn load() -> Self {
         if let Some(prefs_path) = get_preferences_path() {
             if let Ok(content) = fs::read_to_string(&prefs_path) {
                 if let Ok(data) = serde_json::from_str::<AppPreferences>(&content) {
@@ -95,6 +477,12 @@ impl AppPreferences {
         Self::new()
     }
 
+    /// Save preferences changed by `command`, refused while the app lock is on
+    fn save_unlocked(&self, command: &str) -> Result<(), String> {
+        app_lock::ensure_unlocked(command)?;
+        self.save()
+    }
+
     fn save(&self) -> Result<(), String> {
         if let Some(prefs_path) = get_preferences_path() {
             if let Some(parent) = prefs_path.parent() {
@@ -108,31 +496,374 @@ impl AppPreferences {
     }
 }
 
-fn get_preferences_path() -> Option<PathBuf> {
+pub(crate) fn get_preferences_path() -> Option<PathBuf> {
     config_dir().map(|dir| dir.join("youtube-downloader").join("preferences.json"))
 }
 
+/// Temporary overrides for the rest of this app session, never written to preferences
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionDefaults {
+    pub bitrate: Option<u32>,
+    /// yt-dlp format selector, e.g. `bestaudio[acodec=opus]/bestaudio/best`
+    pub format: Option<String>,
+    /// Format the audio is converted to, over the saved and the requested one
+    pub audio_format: Option<AudioFormat>,
+}
+
+static SESSION_DEFAULTS: Mutex<SessionDefaults> = Mutex::new(SessionDefaults {
+    bitrate: None,
+    format: None,
+    audio_format: None,
+});
+
+fn session_defaults() -> SessionDefaults {
+    SESSION_DEFAULTS.lock().unwrap().clone()
+}
+
+/// The format downloads are converted to: the session's, then `requested`, then the
+/// saved preference
+fn resolve_audio_format(requested: Option<AudioFormat>) -> AudioFormat {
+    session_defaults()
+        .audio_format
+        .or(requested)
+        .or(AppPreferences::load().audio_format)
+        .unwrap_or_default()
+}
+
+/// Load the download options from preferences, dropping any the current yt-dlp build
+/// cannot honour so the download does not fail on an unknown target
+fn resolve_download_options<R: Runtime>(app_handle: &tauri::AppHandle<R>) -> DownloadOptions {
+    let mut options = AppPreferences::load().download_options();
+    options.format = session_defaults().format;
+
+    if let Some(target) = options.impersonate.clone() {
+        if !deps::supports_impersonate_target(app_handle, &target) {
+            eprintln!(
+                "[download] yt-dlp build does not support impersonating '{}', ignoring",
+                target
+            );
+            options.impersonate = None;
+        }
+    }
+
+    options
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum DownloadResponse {
     Single(DownloadResult),
     Playlist(PlaylistDownloadResult),
+    /// The playlist is larger than the confirmation threshold, nothing was downloaded.
+    /// Call again with `confirmed` set to start the download.
+    ConfirmationRequired(PlaylistConfirmation),
 }
 
+// The arguments are the named fields of the frontend's invoke call, grouping them would
+// change what it sends
+#[allow(clippy::too_many_arguments)]
 #[tauri::command]
 pub async fn download_from_youtube(
     url: String,
-    output_folder: String,
+    mut output_folder: String,
     bitrate: u32,
+    overrides: Option<DownloadOverrides>,
+    confirmed: Option<bool>,
+    download_id: Option<String>,
+    session_note: Option<String>,
     app_handle: tauri::AppHandle,
 ) -> Result<DownloadResponse, String> {
+    let params = serde_json::json!({
+        "url": url,
+        "output_folder": output_folder,
+        "bitrate": bitrate,
+        "overrides": overrides,
+        "confirmed": confirmed,
+        "download_id": download_id,
+        "session_note": session_note,
+    });
+    let mut overrides = overrides.unwrap_or_default();
+    let options = resolve_download_options(&app_handle);
+    channel_rules::apply(
+        &url,
+        &mut output_folder,
+        &mut overrides,
+        &options,
+        &app_handle,
+    )
+    .await;
+    // Journaled so a download cut short by a crash can be resumed on the next start.
+    // Queue jobs need no journal, the queue requeues them itself.
+    let journal_id = incomplete::begin(&url, &output_folder, bitrate, &overrides);
+    let mut result = start_download(
+        url.clone(),
+        output_folder,
+        bitrate,
+        overrides,
+        confirmed.unwrap_or(false),
+        download_id,
+        app_handle,
+    )
+    .await;
+    incomplete::finish(&journal_id);
+    if let (Some(note), Ok(DownloadResponse::Playlist(playlist))) = (&session_note, &mut result) {
+        let tracks: Vec<LinerTrack> = playlist
+            .downloaded_videos
+            .iter()
+            .map(|video| LinerTrack {
+                title: video
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| file_title(&video.output_path)),
+                source: url.clone(),
+                duration: video.duration,
+            })
+            .collect();
+        playlist.liner_notes_path = write_liner_notes(&playlist.output_folder, note, &tracks);
+    }
+    if matches!(&result, Ok(DownloadResponse::Playlist(p)) if !p.downloaded_videos.is_empty()) {
+        tauri::async_runtime::spawn(refresh_media_server());
+    }
+    audit::record("download_from_youtube", params, &result);
+    result
+}
+
+/// The file name of a track without its extension, for tracks that have no title
+fn file_title(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Write the liner notes of a finished session. A failure is only logged, the
+/// downloads themselves went through.
+fn write_liner_notes(folder: &str, note: &str, tracks: &[LinerTrack]) -> Option<String> {
+    if tracks.is_empty() {
+        return None;
+    }
+    match liner_notes::write(Path::new(folder), note, tracks) {
+        Ok(path) => Some(path.to_string_lossy().to_string()),
+        Err(e) => {
+            eprintln!("[liner_notes] {}", e);
+            None
+        }
+    }
+}
+
+/// Rescan the media server's library after a batch of downloads, when one is configured
+pub(crate) async fn refresh_media_server() {
+    let Some(settings) = AppPreferences::load().media_server.filter(|s| s.enabled) else {
+        return;
+    };
+    match media_server::refresh(&settings).await {
+        Ok(()) => eprintln!("[media_server] Requested a library refresh"),
+        Err(e) => eprintln!("[media_server] Library refresh failed: {}", e),
+    }
+}
+
+/// Run one download to completion, cancellable through `cancel_download` by
+/// `download_id` or, without one, by its URL. Shared by `download_from_youtube` and
+/// the download queue. What `overrides` leaves unset comes from the saved preferences.
+pub(crate) async fn start_download<R: Runtime>(
+    url: String,
+    output_folder: String,
+    bitrate: u32,
+    overrides: DownloadOverrides,
+    confirmed: bool,
+    download_id: Option<String>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<DownloadResponse, String> {
+    let _active = ActiveDownloadGuard::new();
+    let cancel = CancelRegistration::new(download_id.as_deref().unwrap_or(&url));
+    let result = run_download(
+        url,
+        output_folder,
+        bitrate,
+        overrides,
+        confirmed,
+        cancel.token.clone(),
+        app_handle.clone(),
+    )
+    .await;
+    if let Some(error_code) = advisor::record_outcome(&result) {
+        offer_fixes(&app_handle, error_code).await;
+    }
+    result
+}
+
+/// Tell the frontend which remedies to try for an error that keeps repeating, applying
+/// the ones the app can apply itself when the preferences allow it
+async fn offer_fixes<R: Runtime>(app_handle: &tauri::AppHandle<R>, error_code: ErrorCode) {
+    let Some(mut advice) = advisor::recommended_fixes(Some(error_code)) else {
+        return;
+    };
+    // Fixes change settings or replace yt-dlp, which a locked app only offers
+    let auto_apply = AppPreferences::load().auto_apply_fixes.unwrap_or(false)
+        && app_lock::ensure_unlocked("auto_apply_fixes").is_ok();
+    if auto_apply {
+        for fix in advice.fixes.iter_mut().filter(|fix| fix.auto_applicable) {
+            let applied = apply_remedy(app_handle, fix.remedy).await;
+            if let Err(e) = &applied {
+                eprintln!("[advisor] Failed to apply {:?}: {}", fix.remedy, e);
+            }
+            fix.applied = Some(applied.is_ok());
+        }
+    }
+    events::emit(app_handle, Event::RecommendedFixes, &advice);
+}
+
+async fn apply_remedy<R: Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    remedy: Remedy,
+) -> Result<(), String> {
+    let mut prefs = AppPreferences::load();
+    match remedy {
+        Remedy::UpdateYtdlp => {
+            return deps_fetch::fetch_binary(
+                app_handle,
+                "yt-dlp",
+                prefs.dependency_mirror.as_deref(),
+                |_, _, _| {},
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("{:#}", e));
+        }
+        Remedy::SwitchPlayerClient => {
+            let mut extractor_args = prefs.extractor_args.take().unwrap_or_default();
+            extractor_args.player_clients = RECOMMENDED_PLAYER_CLIENTS.to_vec();
+            prefs.extractor_args = Some(extractor_args);
+        }
+        Remedy::EnableImpersonation => {
+            let handle = app_handle.clone();
+            let targets = tauri::async_runtime::spawn_blocking(move || {
+                deps::get_impersonate_targets(&handle)
+            })
+            .await
+            .unwrap_or_default();
+            let target = targets
+                .iter()
+                .find(|t| t.to_lowercase().starts_with("chrome"))
+                .or(targets.first())
+                .ok_or("The installed yt-dlp build does not support impersonation")?;
+            prefs.impersonate = Some(target.clone());
+        }
+        Remedy::SlowDown => {
+            let mut politeness = prefs.politeness.take().unwrap_or_default();
+            politeness.enabled = true;
+            politeness.min_batch_size = 1;
+            prefs.politeness = Some(politeness);
+        }
+        Remedy::EnableCookies if cookies::imported_cookies().is_some() => {
+            prefs.use_cookies = Some(true);
+        }
+        Remedy::EnableCookies | Remedy::CheckConnection => {
+            return Err(format!("{:?} has to be done by the user", remedy));
+        }
+    }
+    prefs.save()
+}
+
+/// App, yt-dlp and ffmpeg versions recorded with each history entry
+struct ToolVersions {
+    app: Option<String>,
+    ytdlp: Option<String>,
+    ffmpeg: Option<String>,
+}
+
+impl ToolVersions {
+    async fn detect<R: Runtime>(app_handle: &tauri::AppHandle<R>) -> Self {
+        let handle = app_handle.clone();
+        let (ytdlp, ffmpeg) = tauri::async_runtime::spawn_blocking(move || {
+            (
+                deps::installed_version(&handle, "yt-dlp"),
+                deps::installed_version(&handle, "ffmpeg"),
+            )
+        })
+        .await
+        .unwrap_or_default();
+        Self {
+            app: Some(app_handle.package_info().version.to_string()),
+            ytdlp,
+            ffmpeg,
+        }
+    }
+}
+
+async fn run_download<R: Runtime>(
+    url: String,
+    output_folder: String,
+    bitrate: u32,
+    overrides: DownloadOverrides,
+    confirmed: bool,
+    cancel: tokio_util::sync::CancellationToken,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<DownloadResponse, String> {
+    let parsed_source = source::parse_source(&url)?;
+    volume::check_output_folder(&output_folder)?;
+    let mut options = resolve_download_options(&app_handle);
+    options.cancel = cancel;
+    if let Some(sponsorblock) = overrides.sponsorblock {
+        options.sponsorblock = sponsorblock;
+    }
+    if let Some(split_chapters) = overrides.split_chapters {
+        options.split_chapters = split_chapters;
+    }
+    if let Some(audio_preset) = overrides.audio_preset {
+        options.audio_preset = audio_preset;
+    }
+    options.playlist_selection = overrides.selection;
+    options.clip = overrides.clip.resolve()?;
+    options.tag_overrides = overrides.tags;
+    if options.clip.is_some() && parsed_source.is_playlist() {
+        return Err("A time range can only be downloaded from a single video".to_string());
+    }
+    let audio_format = resolve_audio_format(overrides.audio_format);
+    let bitrate = session_defaults().bitrate.unwrap_or(bitrate);
+
     // Check if URL is a playlist
-    if is_playlist_url(&url) {
-        let result =
-            download_playlist_with_progress(&url, &output_folder, bitrate, app_handle.clone())
-                .await?;
+    if parsed_source.is_playlist() {
+        if !confirmed {
+            let threshold = AppPreferences::load()
+                .playlist_confirm_threshold
+                .unwrap_or(DEFAULT_PLAYLIST_CONFIRM_THRESHOLD);
+            if let Some(confirmation) =
+                check_playlist_size(&url, bitrate, threshold, &options, &app_handle).await?
+            {
+                let choice = decisions::request(
+                    &app_handle,
+                    DecisionKind::PlaylistSize,
+                    format!(
+                        "This playlist has {} items, more than the {} that need confirming. Download all of them?",
+                        confirmation.item_count, confirmation.threshold
+                    ),
+                    &["download", "cancel"],
+                    "cancel",
+                    serde_json::to_value(&confirmation).unwrap_or_default(),
+                )
+                .await;
+                match choice.as_deref() {
+                    Some("download") => {}
+                    Some(_) => return Err(download::CANCELLED_MESSAGE.to_string()),
+                    // Nobody answered, fall back to asking through the response
+                    None => return Ok(DownloadResponse::ConfirmationRequired(confirmation)),
+                }
+            }
+        }
+
+        let result = download_playlist_with_progress(
+            &url,
+            &output_folder,
+            bitrate,
+            audio_format,
+            &options,
+            app_handle.clone(),
+        )
+        .await?;
 
         // Save each video to history
+        let versions = ToolVersions::detect(&app_handle).await;
         let mut history = HistoryData::load();
         for video in &result.downloaded_videos {
             let download = DownloadHistory {
@@ -142,26 +873,64 @@ pub async fn download_from_youtube(
                 bitrate,
                 timestamp: chrono::Utc::now().to_rfc3339(),
                 duration: video.duration,
+                app_version: versions.app.clone(),
+                ytdlp_version: versions.ytdlp.clone(),
+                ffmpeg_version: versions.ffmpeg.clone(),
+                audio_format,
+                missing: false,
             };
             history.add(download).ok();
         }
 
         // Send notification
         let app_name = app_handle.package_info().name.clone();
+        let (title, body) = if result.cancelled {
+            (
+                "Playlist Download Cancelled",
+                format!(
+                    "Cancelled after downloading {} of {} videos",
+                    result.downloaded_videos.len(),
+                    result.total_videos
+                ),
+            )
+        } else if !result.failed_videos.is_empty() {
+            (
+                "Playlist Download Finished With Errors",
+                format!(
+                    "Downloaded {} videos, {} failed",
+                    result.downloaded_videos.len(),
+                    result.failed_videos.len()
+                ),
+            )
+        } else {
+            (
+                "Playlist Download Complete",
+                format!(
+                    "Successfully downloaded {} videos from playlist",
+                    result.downloaded_videos.len()
+                ),
+            )
+        };
         tauri::api::notification::Notification::new(&app_name)
-            .title("Playlist Download Complete")
-            .body(&format!(
-                "Successfully downloaded {} videos from playlist",
-                result.downloaded_videos.len()
-            ))
+            .title(title)
+            .body(&body)
             .show()
             .ok();
 
         Ok(DownloadResponse::Playlist(result))
     } else {
-        let result = download_youtube(&url, &output_folder, bitrate, &app_handle).await?;
+        let result = download_youtube(
+            &url,
+            &output_folder,
+            bitrate,
+            audio_format,
+            &options,
+            &app_handle,
+        )
+        .await?;
 
         // Save to history
+        let versions = ToolVersions::detect(&app_handle).await;
         let mut history = HistoryData::load();
         let download = DownloadHistory {
             url: url.clone(),
@@ -170,6 +939,11 @@ pub async fn download_from_youtube(
             bitrate,
             timestamp: chrono::Utc::now().to_rfc3339(),
             duration: result.duration,
+            app_version: versions.app,
+            ytdlp_version: versions.ytdlp,
+            ffmpeg_version: versions.ffmpeg,
+            audio_format,
+            missing: false,
         };
         history.add(download).ok();
 
@@ -177,7 +951,10 @@ pub async fn download_from_youtube(
         let app_name = app_handle.package_info().name.clone();
         tauri::api::notification::Notification::new(&app_name)
             .title("Download Complete")
-            .body("Successfully downloaded and converted to MP3")
+            .body(format!(
+                "Successfully downloaded and converted to {}",
+                audio_format
+            ))
             .show()
             .ok();
 
@@ -185,16 +962,306 @@ pub async fn download_from_youtube(
     }
 }
 
+/// Download the same track at 2 or 3 bitrates into a temporary area so they can be
+/// compared by ear. Keep one with `keep_compared_quality`, or drop them all with
+/// `discard_quality_comparison`.
 #[tauri::command]
-pub async fn get_download_history() -> Result<Vec<DownloadHistory>, String> {
-    let history = HistoryData::load();
-    Ok(history.downloads)
+pub async fn start_quality_comparison(
+    url: String,
+    bitrates: Vec<u32>,
+    audio_format: Option<AudioFormat>,
+    app_handle: tauri::AppHandle,
+) -> Result<QualityComparison, String> {
+    let params = serde_json::json!({
+        "url": url,
+        "bitrates": bitrates,
+        "audio_format": audio_format,
+    });
+    let result = run_quality_comparison(&url, &bitrates, audio_format, &app_handle).await;
+    audit::record("start_quality_comparison", params, &result);
+    result
+}
+
+async fn run_quality_comparison(
+    url: &str,
+    bitrates: &[u32],
+    audio_format: Option<AudioFormat>,
+    app_handle: &tauri::AppHandle,
+) -> Result<QualityComparison, String> {
+    if source::parse_source(url)?.is_playlist() {
+        return Err("Only a single video can be compared".to_string());
+    }
+    let _active = ActiveDownloadGuard::new();
+    let cancel = CancelRegistration::new(url);
+    let mut options = resolve_download_options(app_handle);
+    options.cancel = cancel.token.clone();
+    let audio_format = audio_format
+        .or(AppPreferences::load().audio_format)
+        .unwrap_or_default();
+    compare::start(url, bitrates, audio_format, &options, app_handle).await
+}
+
+/// Find the tracks of a tracklist pasted by the user or copied from a video
+/// description, for reviewing them before `download_tracklist`
+#[tauri::command]
+pub async fn parse_tracklist(text: String) -> Result<Vec<TracklistEntry>, String> {
+    Ok(tracklist::parse(&text))
+}
+
+/// Download a single video once and split it at the tracklist's timestamps into
+/// separately named and tagged tracks, each added to the history. Cancellable through
+/// `cancel_download` by `download_id` or, without one, by its URL.
+#[tauri::command]
+pub async fn download_tracklist(
+    url: String,
+    tracklist: Vec<TracklistEntry>,
+    output_folder: String,
+    bitrate: u32,
+    download_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<TracklistResult, String> {
+    let params = serde_json::json!({
+        "url": url,
+        "tracklist": tracklist,
+        "output_folder": output_folder,
+        "bitrate": bitrate,
+        "download_id": download_id,
+    });
+    let result = run_tracklist_download(
+        &url,
+        &tracklist,
+        &output_folder,
+        bitrate,
+        download_id.as_deref(),
+        &app_handle,
+    )
+    .await;
+    audit::record("download_tracklist", params, &result);
+    result
+}
+
+async fn run_tracklist_download(
+    url: &str,
+    tracklist: &[TracklistEntry],
+    output_folder: &str,
+    bitrate: u32,
+    download_id: Option<&str>,
+    app_handle: &tauri::AppHandle,
+) -> Result<TracklistResult, String> {
+    if source::parse_source(url)?.is_playlist() {
+        return Err("A tracklist can only be split from a single video".to_string());
+    }
+    volume::check_output_folder(output_folder)?;
+    let _active = ActiveDownloadGuard::new();
+    let cancel = CancelRegistration::new(download_id.unwrap_or(url));
+    let mut options = resolve_download_options(app_handle);
+    options.cancel = cancel.token.clone();
+    let audio_format = resolve_audio_format(None);
+    let bitrate = session_defaults().bitrate.unwrap_or(bitrate);
+    let result = tracklist::download(
+        url,
+        tracklist,
+        output_folder,
+        bitrate,
+        audio_format,
+        &options,
+        app_handle,
+    )
+    .await?;
+
+    let versions = ToolVersions::detect(app_handle).await;
+    let mut history = HistoryData::load();
+    for track in &result.tracks {
+        let download = DownloadHistory {
+            url: url.to_string(),
+            title: Some(track.title.clone()),
+            output_path: track.output_path.clone(),
+            bitrate,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            duration: Some(track.end - track.start),
+            app_version: versions.app.clone(),
+            ytdlp_version: versions.ytdlp.clone(),
+            ffmpeg_version: versions.ffmpeg.clone(),
+            audio_format,
+            missing: false,
+        };
+        history.add(download).ok();
+    }
+    Ok(result)
+}
+
+/// Move the compared copy at `bitrate` into `output_folder`, add it to the history
+/// and delete the other copies. With `make_default`, `bitrate` becomes the default
+/// bitrate.
+#[tauri::command]
+pub async fn keep_compared_quality(
+    id: String,
+    bitrate: u32,
+    output_folder: String,
+    make_default: Option<bool>,
+    app_handle: tauri::AppHandle,
+) -> Result<ComparisonCandidate, String> {
+    let params = serde_json::json!({
+        "id": id,
+        "bitrate": bitrate,
+        "output_folder": output_folder,
+        "make_default": make_default,
+    });
+    let result = keep_quality(&id, bitrate, &output_folder, make_default, &app_handle).await;
+    audit::record("keep_compared_quality", params, &result);
+    result
+}
+
+async fn keep_quality(
+    id: &str,
+    bitrate: u32,
+    output_folder: &str,
+    make_default: Option<bool>,
+    app_handle: &tauri::AppHandle,
+) -> Result<ComparisonCandidate, String> {
+    volume::check_output_folder(output_folder)?;
+    let (comparison, kept) = compare::keep(id, bitrate, output_folder)?;
+
+    let versions = ToolVersions::detect(app_handle).await;
+    let mut history = HistoryData::load();
+    let download = DownloadHistory {
+        url: comparison.url,
+        title: comparison.title,
+        output_path: kept.path.clone(),
+        bitrate,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        duration: comparison.duration,
+        app_version: versions.app,
+        ytdlp_version: versions.ytdlp,
+        ffmpeg_version: versions.ffmpeg,
+        audio_format: comparison.audio_format,
+        missing: false,
+    };
+    history.add(download).ok();
+
+    if make_default.unwrap_or(false) {
+        let mut prefs = AppPreferences::load();
+        prefs.bitrate = Some(bitrate);
+        prefs.save_unlocked("keep_compared_quality")?;
+    }
+    Ok(kept)
+}
+
+/// Delete every copy of a quality comparison without keeping any
+#[tauri::command]
+pub async fn discard_quality_comparison(id: String) -> Result<(), String> {
+    compare::discard(&id)
+}
+
+/// Downloads that were still running when the app last quit or crashed, and the partial
+/// files in their output folders and the default one
+#[tauri::command]
+pub async fn get_incomplete_downloads() -> Result<IncompleteScan, String> {
+    let default_folder = AppPreferences::load().output_folder;
+    tauri::async_runtime::spawn_blocking(move || incomplete::scan(default_folder.as_deref()))
+        .await
+        .map_err(|e| format!("Scan task failed: {}", e))
+}
+
+/// Add the downloads that never finished to the queue. yt-dlp continues from their
+/// partial files instead of starting over.
+#[tauri::command]
+pub async fn resume_incomplete_downloads(
+    queue: tauri::State<'_, DownloadQueue>,
+) -> Result<Vec<QueueJob>, String> {
+    let result = Ok(incomplete::take_all()
+        .into_iter()
+        .map(|d| queue.enqueue(d.url, d.output_folder, d.bitrate, d.overrides))
+        .collect());
+    audit::record(
+        "resume_incomplete_downloads",
+        serde_json::json!({}),
+        &result,
+    );
+    result
+}
+
+/// Split a pasted blob of text into the YouTube URLs it contains, ready for queuing
+#[tauri::command]
+pub async fn parse_pasted_urls(text: String) -> Result<source::PasteParseReport, String> {
+    Ok(source::parse_pasted_urls(&text))
+}
+
+/// One page of the download history, newest first
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryPage {
+    pub downloads: Vec<DownloadHistory>,
+    /// Entries in the whole history
+    pub total: usize,
+    pub offset: usize,
+}
+
+/// Up to `limit` history entries, skipping the `offset` most recent ones. Without a
+/// `limit` everything from `offset` on is returned.
+#[tauri::command]
+pub async fn get_download_history(
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<HistoryPage, String> {
+    let offset = offset.unwrap_or(0);
+    let mut history = HistoryData::load();
+    // Loading the first page is when the history is shown, later pages were checked then
+    if offset == 0 && history.repair_missing_paths() > 0 {
+        history.save().ok();
+    }
+    let total = history.downloads.len();
+    let downloads = history
+        .downloads
+        .into_iter()
+        .rev()
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        .collect();
+    Ok(HistoryPage {
+        downloads,
+        total,
+        offset,
+    })
+}
+
+/// Save the download history to `path` for keeping records outside the app. The
+/// format follows the file extension unless `format` is given.
+#[tauri::command]
+pub async fn export_history(path: String, format: Option<ManifestFormat>) -> Result<usize, String> {
+    let params = serde_json::json!({ "path": path, "format": format });
+    let output = PathBuf::from(&path);
+    let format = format.unwrap_or_else(|| ManifestFormat::of(&output));
+    let result = HistoryData::load().export(&output, format);
+    audit::record("export_history", params, &result);
+    result
+}
+
+/// Check which history entries still have their file on disk and mark the others as
+/// missing, or remove them with `prune`
+#[tauri::command]
+pub async fn verify_history_files(prune: Option<bool>) -> Result<HistoryVerification, String> {
+    let prune = prune.unwrap_or(false);
+    let params = serde_json::json!({ "prune": prune });
+    let result = if prune {
+        app_lock::ensure_unlocked("verify_history_files")
+    } else {
+        Ok(())
+    }
+    .and_then(|()| {
+        let mut history = HistoryData::load();
+        let verification = history.verify_files(prune);
+        history.save()?;
+        Ok(verification)
+    });
+    audit::record("verify_history_files", params, &result);
+    result
 }
 
 #[tauri::command]
 pub async fn clear_history() -> Result<(), String> {
-    let history = HistoryData::new();
-    history.save()
+    let result = app_lock::ensure_unlocked("clear_history").and_then(|_| HistoryData::new().save());
+    audit::record("clear_history", serde_json::json!({}), &result);
+    result
 }
 
 #[tauri::command]
@@ -202,8 +1269,23 @@ pub async fn check_deps(app_handle: tauri::AppHandle) -> Result<deps::DepsCheckR
     Ok(deps::check_deps(&app_handle))
 }
 
+/// Diagnose system yt-dlp installs and PATH differences between the app and a terminal
+#[tauri::command]
+pub async fn check_ytdlp_environment(
+    app_handle: tauri::AppHandle,
+) -> Result<deps::EnvironmentReport, String> {
+    Ok(deps::check_environment(&app_handle))
+}
+
 #[tauri::command]
 pub async fn clear_extracted_binaries(app_handle: tauri::AppHandle) -> Result<(), String> {
+    let result = app_lock::ensure_unlocked("clear_extracted_binaries")
+        .and_then(|_| remove_extracted_binaries(&app_handle));
+    audit::record("clear_extracted_binaries", serde_json::json!({}), &result);
+    result
+}
+
+fn remove_extracted_binaries(app_handle: &tauri::AppHandle) -> Result<(), String> {
     use std::fs;
 
     let app_data_dir = app_handle
@@ -229,15 +1311,33 @@ pub async fn clear_extracted_binaries(app_handle: tauri::AppHandle) -> Result<()
         }
     }
 
+    deps::reset_impersonate_targets();
+
     Ok(())
 }
 
+/// Force a fresh extraction of the bundled binaries, ignoring any cached hashes
+#[tauri::command]
+pub async fn reextract_binaries(app_handle: tauri::AppHandle) -> Result<(), String> {
+    let result = ["yt-dlp", "ffmpeg"].iter().try_for_each(|binary| {
+        deps::reextract_binary(&app_handle, binary)
+            .map(|_| ())
+            .map_err(|e| format!("Failed to re-extract {}: {}", binary, e))
+    });
+    deps::reset_impersonate_targets();
+    audit::record("reextract_binaries", serde_json::json!({}), &result);
+    result
+}
+
 /// Save the output folder path to preferences
 #[tauri::command]
 pub async fn save_output_folder(output_folder: String) -> Result<(), String> {
+    let params = serde_json::json!({ "output_folder": output_folder });
     let mut prefs = AppPreferences::load();
     prefs.output_folder = Some(output_folder);
-    prefs.save()
+    let result = prefs.save_unlocked("save_output_folder");
+    audit::record("save_output_folder", params, &result);
+    result
 }
 
 /// Get the saved output folder path from preferences
@@ -254,7 +1354,17 @@ pub async fn save_preferences(
     bitrate: Option<u32>,
     last_url: Option<String>,
 ) -> Result<(), String> {
+    let params = serde_json::json!({
+        "output_folder": output_folder,
+        "bitrate": bitrate,
+        "last_url": last_url,
+    });
     let mut prefs = AppPreferences::load();
+    // The last URL is saved on every download, so only the settings need the app unlocked
+    let changes_settings = output_folder
+        .as_ref()
+        .is_some_and(|folder| prefs.output_folder.as_ref() != Some(folder))
+        || bitrate.is_some_and(|br| prefs.bitrate != Some(br));
     if let Some(folder) = output_folder {
         prefs.output_folder = Some(folder);
     }
@@ -264,7 +1374,13 @@ pub async fn save_preferences(
     if let Some(url) = last_url {
         prefs.last_url = Some(url);
     }
-    prefs.save()
+    let result = if changes_settings {
+        prefs.save_unlocked("save_preferences")
+    } else {
+        prefs.save()
+    };
+    audit::record("save_preferences", params, &result);
+    result
 }
 
 /// Get all saved preferences
@@ -272,3 +1388,1533 @@ pub async fn save_preferences(
 pub async fn get_preferences() -> Result<AppPreferences, String> {
     Ok(AppPreferences::load())
 }
+
+/// Save the YouTube extractor arguments (player clients, formats preference)
+#[tauri::command]
+pub async fn save_extractor_args(extractor_args: Option<ExtractorArgs>) -> Result<(), String> {
+    let params = serde_json::json!({ "extractor_args": extractor_args });
+    let mut prefs = AppPreferences::load();
+    prefs.extractor_args = extractor_args;
+    let result = prefs.save_unlocked("save_extractor_args");
+    audit::record("save_extractor_args", params, &result);
+    result
+}
+
+/// Switch to the recommended YouTube player clients, the usual fix when downloads
+/// start failing with 403s or bot checks. Returns the extractor arguments now in use.
+#[tauri::command]
+pub async fn apply_recommended_workaround() -> Result<ExtractorArgs, String> {
+    let mut prefs = AppPreferences::load();
+    let mut extractor_args = prefs.extractor_args.take().unwrap_or_default();
+    extractor_args.player_clients = RECOMMENDED_PLAYER_CLIENTS.to_vec();
+    prefs.extractor_args = Some(extractor_args.clone());
+    let result = prefs
+        .save_unlocked("apply_recommended_workaround")
+        .map(|_| extractor_args);
+    audit::record(
+        "apply_recommended_workaround",
+        serde_json::json!({}),
+        &result,
+    );
+    result
+}
+
+/// Save the HTTP client options used to get past bot detection
+#[tauri::command]
+pub async fn save_http_client_options(
+    impersonate: Option<String>,
+    user_agent: Option<String>,
+) -> Result<(), String> {
+    let params = serde_json::json!({ "impersonate": impersonate, "user_agent": user_agent });
+    let mut prefs = AppPreferences::load();
+    prefs.impersonate = impersonate;
+    prefs.user_agent = user_agent;
+    let result = prefs.save_unlocked("save_http_client_options");
+    audit::record("save_http_client_options", params, &result);
+    result
+}
+
+/// List the impersonation targets available in the managed yt-dlp build
+#[tauri::command]
+pub async fn get_impersonate_targets(app_handle: tauri::AppHandle) -> Result<Vec<String>, String> {
+    Ok(deps::get_impersonate_targets(&app_handle))
+}
+
+/// Save the request pacing used for large playlist and channel downloads
+#[tauri::command]
+pub async fn save_politeness(politeness: Option<Politeness>) -> Result<(), String> {
+    let params = serde_json::json!({ "politeness": politeness });
+    let mut prefs = AppPreferences::load();
+    prefs.politeness = politeness;
+    let result = prefs.save_unlocked("save_politeness");
+    audit::record("save_politeness", params, &result);
+    result
+}
+
+/// Get the most recent audit trail entries (default 200), oldest first
+#[tauri::command]
+pub async fn get_audit_trail(limit: Option<usize>) -> Result<Vec<audit::AuditEntry>, String> {
+    Ok(audit::load(limit.unwrap_or(200)))
+}
+
+/// Get the most recent crash report so the user can attach it to an issue
+#[tauri::command]
+pub async fn get_last_crash_report() -> Result<Option<crash::CrashReport>, String> {
+    Ok(crash::load_last_report())
+}
+
+/// Delete stored crash reports once the user has dealt with them
+#[tauri::command]
+pub async fn clear_crash_reports() -> Result<(), String> {
+    let result =
+        app_lock::ensure_unlocked("clear_crash_reports").and_then(|_| crash::clear_reports());
+    audit::record("clear_crash_reports", serde_json::json!({}), &result);
+    result
+}
+
+/// Opt in or out of reading the login shell profile to extend PATH for child processes
+#[tauri::command]
+pub async fn save_path_augmentation(use_shell_profile: bool) -> Result<(), String> {
+    let params = serde_json::json!({ "use_shell_profile": use_shell_profile });
+    let mut prefs = AppPreferences::load();
+    prefs.augment_path_from_shell = Some(use_shell_profile);
+    let result = prefs.save_unlocked("save_path_augmentation");
+    if result.is_ok() {
+        deps::bootstrap_environment(use_shell_profile);
+    }
+    audit::record("save_path_augmentation", params, &result);
+    result
+}
+
+/// Save how many playlist items can be downloaded without asking first.
+/// `None` restores the default of 50.
+#[tauri::command]
+pub async fn save_playlist_confirm_threshold(threshold: Option<usize>) -> Result<(), String> {
+    let params = serde_json::json!({ "threshold": threshold });
+    let mut prefs = AppPreferences::load();
+    prefs.playlist_confirm_threshold = threshold;
+    let result = prefs.save_unlocked("save_playlist_confirm_threshold");
+    audit::record("save_playlist_confirm_threshold", params, &result);
+    result
+}
+
+/// Apply temporary bitrate/format overrides to every download until the app restarts.
+/// Pass empty defaults to go back to the saved preferences.
+#[tauri::command]
+pub async fn set_session_defaults(defaults: SessionDefaults) -> Result<(), String> {
+    let params = serde_json::json!({ "defaults": defaults });
+    *SESSION_DEFAULTS.lock().unwrap() = defaults;
+    let result = Ok(());
+    audit::record("set_session_defaults", params, &result);
+    result
+}
+
+/// Get the overrides currently applied for this session
+#[tauri::command]
+pub async fn get_session_defaults() -> Result<SessionDefaults, String> {
+    Ok(session_defaults())
+}
+
+/// Run the maintenance tasks right away instead of waiting for the idle scheduler
+#[tauri::command]
+pub async fn run_maintenance_now(
+    app_handle: tauri::AppHandle,
+) -> Result<maintenance::MaintenanceReport, String> {
+    let result = tauri::async_runtime::spawn_blocking(move || maintenance::run(&app_handle, false))
+        .await
+        .map_err(|e| format!("Maintenance task failed: {}", e))
+        .and_then(|r| r);
+    audit::record("run_maintenance_now", serde_json::json!({}), &result);
+    result
+}
+
+/// Get the report of the last maintenance run, scheduled or manual
+#[tauri::command]
+pub async fn get_maintenance_status() -> Result<Option<maintenance::MaintenanceReport>, String> {
+    Ok(maintenance::load_last_report())
+}
+
+/// Save the media cache settings, trimming the cache right away if the limit shrank
+#[tauri::command]
+pub async fn save_media_cache_settings(media_cache: MediaCacheSettings) -> Result<(), String> {
+    let params = serde_json::json!({ "media_cache": media_cache });
+    let mut prefs = AppPreferences::load();
+    prefs.media_cache = Some(media_cache.clone());
+    let result = prefs.save_unlocked("save_media_cache_settings");
+    if result.is_ok() {
+        media_cache::evict(&media_cache);
+    }
+    audit::record("save_media_cache_settings", params, &result);
+    result
+}
+
+/// Delete all cached source audio, returning the number of bytes freed
+#[tauri::command]
+pub async fn clear_media_cache() -> Result<u64, String> {
+    let result = app_lock::ensure_unlocked("clear_media_cache").and_then(|_| media_cache::clear());
+    audit::record("clear_media_cache", serde_json::json!({}), &result);
+    result
+}
+
+/// Save the settings for splitting very long videos into separately downloaded sections
+#[tauri::command]
+pub async fn save_sectioned_download(
+    sectioned_download: Option<SectionedDownload>,
+) -> Result<(), String> {
+    let params = serde_json::json!({ "sectioned_download": sectioned_download });
+    let mut prefs = AppPreferences::load();
+    prefs.sectioned_download = sectioned_download;
+    let result = prefs.save_unlocked("save_sectioned_download");
+    audit::record("save_sectioned_download", params, &result);
+    result
+}
+
+/// Turn romanized filenames for non-Latin titles on or off
+#[tauri::command]
+pub async fn save_transliteration(enabled: bool) -> Result<(), String> {
+    let params = serde_json::json!({ "enabled": enabled });
+    let mut prefs = AppPreferences::load();
+    prefs.transliterate_titles = Some(enabled);
+    let result = prefs.save_unlocked("save_transliteration");
+    audit::record("save_transliteration", params, &result);
+    result
+}
+
+/// Save how much output yt-dlp prints. Failed downloads are retried verbosely regardless.
+#[tauri::command]
+pub async fn save_ytdlp_verbosity(verbosity: Option<Verbosity>) -> Result<(), String> {
+    let params = serde_json::json!({ "verbosity": verbosity });
+    let mut prefs = AppPreferences::load();
+    prefs.ytdlp_verbosity = verbosity;
+    let result = prefs.save_unlocked("save_ytdlp_verbosity");
+    audit::record("save_ytdlp_verbosity", params, &result);
+    result
+}
+
+/// Get the captured yt-dlp output of the download attempts for a video
+#[tauri::command]
+pub async fn get_download_log(video_id: String) -> Result<Option<String>, String> {
+    Ok(download_log::load(&video_id))
+}
+
+/// Get the recent download speed samples of a video, one per second, for a sparkline
+#[tauri::command]
+pub async fn get_speed_history(id: String) -> Result<Vec<SpeedSample>, String> {
+    Ok(speed_history::get(&id))
+}
+
+/// Move the whole library to another folder (e.g. a new drive), pointing the history
+/// and the output folder preference at the new location
+#[tauri::command]
+pub async fn relocate_library(
+    old_root: String,
+    new_root: String,
+) -> Result<RelocationReport, String> {
+    let params = serde_json::json!({ "old_root": old_root, "new_root": new_root });
+    if let Err(e) = app_lock::ensure_unlocked("relocate_library") {
+        let result = Err(e);
+        audit::record("relocate_library", params, &result);
+        return result;
+    }
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let old_root = PathBuf::from(old_root);
+        let new_root = PathBuf::from(new_root);
+        let (moved, mut report) = library::relocate(&old_root, &new_root)?;
+
+        let moved: std::collections::HashMap<PathBuf, PathBuf> = moved.into_iter().collect();
+        let mut history = HistoryData::load();
+        for download in &mut history.downloads {
+            if let Some(dest) = moved.get(Path::new(&download.output_path)) {
+                download.output_path = dest.to_string_lossy().to_string();
+                report.history_entries_updated += 1;
+            }
+        }
+        history.save()?;
+
+        let mut prefs = AppPreferences::load();
+        if let Some(relative) = prefs
+            .output_folder
+            .as_deref()
+            .and_then(|folder| Path::new(folder).strip_prefix(&old_root).ok())
+        {
+            prefs.output_folder = Some(new_root.join(relative).to_string_lossy().to_string());
+            prefs.save()?;
+        }
+
+        Ok(report)
+    })
+    .await
+    .map_err(|e| format!("Relocation task failed: {}", e))
+    .and_then(|r| r);
+    audit::record("relocate_library", params, &result);
+    result
+}
+
+/// Get whether a PIN is set and whether destructive commands are currently refused
+#[tauri::command]
+pub async fn get_app_lock_status() -> Result<AppLockStatus, String> {
+    Ok(app_lock::status())
+}
+
+/// Set or change the PIN that guards clearing history, deleting files and changing
+/// settings. Changing an existing PIN requires the current one.
+#[tauri::command]
+pub async fn set_app_lock_pin(pin: String, current_pin: Option<String>) -> Result<(), String> {
+    let result = app_lock::set_pin(&pin, current_pin.as_deref());
+    audit::record("set_app_lock_pin", serde_json::json!({}), &result);
+    result
+}
+
+/// Turn the app lock off
+#[tauri::command]
+pub async fn remove_app_lock_pin(pin: String) -> Result<(), String> {
+    let result = app_lock::remove_pin(&pin);
+    audit::record("remove_app_lock_pin", serde_json::json!({}), &result);
+    result
+}
+
+/// Allow destructive commands until the app is locked again or restarted
+#[tauri::command]
+pub async fn unlock_app(pin: String) -> Result<(), String> {
+    let result = app_lock::unlock(&pin);
+    audit::record("unlock_app", serde_json::json!({}), &result);
+    result
+}
+
+/// Lock the app again so others can download but not change or delete anything
+#[tauri::command]
+pub async fn lock_app() -> Result<(), String> {
+    let result = app_lock::lock();
+    audit::record("lock_app", serde_json::json!({}), &result);
+    result
+}
+
+/// Turn the startup check and download of missing or outdated dependencies on or off
+#[tauri::command]
+pub async fn save_dependency_preflight(enabled: bool) -> Result<(), String> {
+    let params = serde_json::json!({ "enabled": enabled });
+    let mut prefs = AppPreferences::load();
+    prefs.preflight_dependencies = Some(enabled);
+    let result = prefs.save_unlocked("save_dependency_preflight");
+    audit::record("save_dependency_preflight", params, &result);
+    result
+}
+
+/// Save the custom download location tried before the official yt-dlp/ffmpeg mirrors
+#[tauri::command]
+pub async fn save_dependency_mirror(mirror: Option<String>) -> Result<(), String> {
+    let params = serde_json::json!({ "mirror": mirror });
+    let mut prefs = AppPreferences::load();
+    prefs.dependency_mirror = mirror.filter(|m| !m.trim().is_empty());
+    // The binaries are run once downloaded, so they must not come over plain http
+    let result = if prefs
+        .dependency_mirror
+        .as_deref()
+        .is_some_and(|m| !m.trim_start().starts_with("https://"))
+    {
+        Err("The mirror must be an https:// URL".to_string())
+    } else {
+        prefs.save_unlocked("save_dependency_mirror")
+    };
+    audit::record("save_dependency_mirror", params, &result);
+    result
+}
+
+/// Run the dependency preflight now and return the outcome for each binary. Progress
+/// is emitted as `preflight-progress` events while it runs.
+#[tauri::command]
+pub async fn run_dependency_preflight(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<PreflightProgress>, String> {
+    let mirror = AppPreferences::load().dependency_mirror;
+    let result = if preflight::run(app_handle, mirror).await {
+        Ok(preflight::status())
+    } else {
+        Err("The dependency preflight is already running".to_string())
+    };
+    audit::record("run_dependency_preflight", serde_json::json!({}), &result);
+    result
+}
+
+/// Get the last reported preflight progress of each binary
+#[tauri::command]
+pub async fn get_preflight_status() -> Result<Vec<PreflightProgress>, String> {
+    Ok(preflight::status())
+}
+
+/// Install yt-dlp or ffmpeg from a binary or release archive on disk, for networks where
+/// the dependency downloads are blocked. Returns the path of the installed binary.
+#[tauri::command]
+pub async fn install_dependency_from_file(
+    app_handle: tauri::AppHandle,
+    path: String,
+    binary: Option<String>,
+) -> Result<String, String> {
+    let params = serde_json::json!({ "path": path, "binary": binary });
+    let result = match app_lock::ensure_unlocked("install_dependency_from_file") {
+        Ok(()) => tauri::async_runtime::spawn_blocking(move || {
+            deps_fetch::install_from_file(&app_handle, Path::new(&path), binary.as_deref())
+                .map(|installed| installed.to_string_lossy().to_string())
+                .map_err(|e| format!("{:#}", e))
+        })
+        .await
+        .map_err(|e| format!("Install task failed: {}", e))
+        .and_then(|r| r),
+        Err(e) => Err(e),
+    };
+    audit::record("install_dependency_from_file", params, &result);
+    result
+}
+
+/// Use a yt-dlp or ffmpeg (`kind`) downloaded or installed by hand as the managed copy.
+/// Without a `path` a file dialog asks for it. Returns the installed path, or `None`
+/// when the dialog was closed without picking a file.
+#[tauri::command]
+pub async fn designate_binary(
+    app_handle: tauri::AppHandle,
+    path: Option<String>,
+    kind: String,
+) -> Result<Option<String>, String> {
+    let params = serde_json::json!({ "path": path, "kind": kind });
+    let result = match app_lock::ensure_unlocked("designate_binary") {
+        Ok(()) => tauri::async_runtime::spawn_blocking(move || {
+            let picked = path.map(PathBuf::from).or_else(|| {
+                tauri::api::dialog::blocking::FileDialogBuilder::new()
+                    .set_title(&format!("Choose the {} executable", kind))
+                    .pick_file()
+            });
+            let Some(picked) = picked else {
+                return Ok(None);
+            };
+            deps_fetch::designate_binary(&app_handle, &picked, &kind)
+                .map(|installed| Some(installed.to_string_lossy().to_string()))
+                .map_err(|e| format!("{:#}", e))
+        })
+        .await
+        .map_err(|e| format!("Install task failed: {}", e))
+        .and_then(|r| r),
+        Err(e) => Err(e),
+    };
+    audit::record("designate_binary", params, &result);
+    result
+}
+
+/// Answer a `user-decision-required` event with one of the choices it offered
+#[tauri::command]
+pub async fn submit_decision(token: String, choice: String) -> Result<(), String> {
+    decisions::submit(&token, &choice)
+}
+
+/// Steps for installing yt-dlp and/or ffmpeg by hand, each with a command to copy or a
+/// page to open. Defaults to both dependencies on the current platform.
+#[tauri::command]
+pub async fn get_installation_instructions(
+    binary: Option<String>,
+    platform: Option<String>,
+) -> Result<Vec<InstallStep>, String> {
+    install_steps::for_platform(binary.as_deref(), platform.as_deref())
+}
+
+/// Check the managed yt-dlp and ffmpeg against their install manifest for tampering,
+/// truncation or version drift. With `repair`, failing binaries are re-extracted from
+/// the bundle or downloaded again.
+#[tauri::command]
+pub async fn verify_dependencies(
+    app_handle: tauri::AppHandle,
+    repair: Option<bool>,
+) -> Result<integrity::IntegrityReport, String> {
+    let repair = repair.unwrap_or(false);
+    let mirror = AppPreferences::load().dependency_mirror;
+    let report = integrity::verify(&app_handle, repair, mirror.as_deref()).await;
+    let result = Ok(report);
+    audit::record(
+        "verify_dependencies",
+        serde_json::json!({ "repair": repair }),
+        &result,
+    );
+    result
+}
+
+/// Delete yt-dlp's cache (player signatures and the like) and temp files, returning the
+/// number of bytes freed. Useful when downloads fail on a stale cached signature.
+#[tauri::command]
+pub async fn clear_ytdlp_cache() -> Result<u64, String> {
+    let result = app_lock::ensure_unlocked("clear_ytdlp_cache")
+        .and_then(|_| deps::clear_ytdlp_cache().map_err(|e| format!("{:#}", e)));
+    audit::record("clear_ytdlp_cache", serde_json::json!({}), &result);
+    result
+}
+
+/// Stop a running download, killing yt-dlp and removing its partial files. `download_id`
+/// is the ID passed to `download_from_youtube`, or its URL when none was given; without
+/// it every running download is cancelled. Returns how many downloads were cancelled.
+#[tauri::command]
+pub async fn cancel_download(download_id: Option<String>) -> Result<usize, String> {
+    let params = serde_json::json!({ "download_id": download_id });
+    let result = Ok(download::cancel_downloads(download_id.as_deref()));
+    audit::record("cancel_download", params, &result);
+    result
+}
+
+/// Latest yt-dlp and app releases on GitHub compared with the installed versions. The
+/// answer is cached and GitHub is asked at most once a day per repository, unless
+/// `force` is set.
+#[tauri::command]
+pub async fn get_available_updates(
+    app_handle: tauri::AppHandle,
+    force: Option<bool>,
+) -> Result<AvailableUpdates, String> {
+    Ok(version_check::available_updates(&app_handle, force.unwrap_or(false)).await)
+}
+
+/// Remedies for `error_code`, or for the error that is currently repeating when none is
+/// given. Returns `None` when no download has failed since the last success.
+#[tauri::command]
+pub async fn get_recommended_fixes(
+    error_code: Option<ErrorCode>,
+) -> Result<Option<FixAdvice>, String> {
+    Ok(advisor::recommended_fixes(error_code))
+}
+
+/// Apply one of the remedies returned by `get_recommended_fixes`
+#[tauri::command]
+pub async fn apply_recommended_fix(
+    remedy: Remedy,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let params = serde_json::json!({ "remedy": remedy });
+    let result = match app_lock::ensure_unlocked("apply_recommended_fix") {
+        Ok(()) => apply_remedy(&app_handle, remedy).await,
+        Err(e) => Err(e),
+    };
+    audit::record("apply_recommended_fix", params, &result);
+    result
+}
+
+/// Choose whether remedies are applied automatically when an error keeps repeating
+#[tauri::command]
+pub async fn save_auto_apply_fixes(enabled: Option<bool>) -> Result<(), String> {
+    let params = serde_json::json!({ "enabled": enabled });
+    let mut prefs = AppPreferences::load();
+    prefs.auto_apply_fixes = enabled;
+    let result = prefs.save_unlocked("save_auto_apply_fixes");
+    audit::record("save_auto_apply_fixes", params, &result);
+    result
+}
+
+/// Add a download to the persistent queue. Jobs run one after another in the background
+/// and report through `queue-job-updated` and `queue-job-progress` events.
+#[tauri::command]
+pub async fn enqueue_download(
+    url: String,
+    mut output_folder: String,
+    bitrate: u32,
+    overrides: Option<DownloadOverrides>,
+    queue: tauri::State<'_, DownloadQueue>,
+    app_handle: tauri::AppHandle,
+) -> Result<QueueJob, String> {
+    let params = serde_json::json!({
+        "url": url,
+        "output_folder": output_folder,
+        "bitrate": bitrate,
+        "overrides": overrides,
+    });
+    let options = resolve_download_options(&app_handle);
+    let result = match volume::check_output_folder(&output_folder) {
+        Ok(()) => download::check_url(&url, &options, &app_handle).await,
+        Err(e) => Err(e),
+    };
+    let mut overrides = overrides.unwrap_or_default();
+    if result.is_ok() {
+        channel_rules::apply(
+            &url,
+            &mut output_folder,
+            &mut overrides,
+            &options,
+            &app_handle,
+        )
+        .await;
+    }
+    let result = result.map(|_| queue.enqueue(url, output_folder, bitrate, overrides));
+    audit::record("enqueue_download", params, &result);
+    result
+}
+
+/// Save the jobs still to run, and the failed ones, with their settings to `path` as
+/// JSON. Returns how many were saved.
+#[tauri::command]
+pub async fn export_queue(
+    path: String,
+    queue: tauri::State<'_, DownloadQueue>,
+) -> Result<usize, String> {
+    let params = serde_json::json!({ "path": path });
+    let result = queue.export(Path::new(&path));
+    audit::record("export_queue", params, &result);
+    result
+}
+
+/// Queue the jobs saved by `export_queue`, optionally all into `output_folder`, skipping
+/// invalid ones and those already waiting in the queue
+#[tauri::command]
+pub async fn import_queue(
+    path: String,
+    output_folder: Option<String>,
+    queue: tauri::State<'_, DownloadQueue>,
+) -> Result<QueueImport, String> {
+    let params = serde_json::json!({ "path": path, "output_folder": output_folder });
+    let result = queue.import(Path::new(&path), output_folder.as_deref());
+    audit::record("import_queue", params, &result);
+    result
+}
+
+/// When the queue is expected to finish, per job and overall, from how long the
+/// finished jobs took
+#[tauri::command]
+pub async fn get_queue_forecast(
+    queue: tauri::State<'_, DownloadQueue>,
+) -> Result<QueueForecast, String> {
+    Ok(queue.forecast())
+}
+
+/// Get every job in the queue, oldest first
+#[tauri::command]
+pub async fn get_queue(queue: tauri::State<'_, DownloadQueue>) -> Result<Vec<QueueJob>, String> {
+    Ok(queue.jobs())
+}
+
+/// Remove a job from the queue, cancelling it if it is running
+#[tauri::command]
+pub async fn remove_from_queue(
+    job_id: String,
+    queue: tauri::State<'_, DownloadQueue>,
+) -> Result<QueueJob, String> {
+    let params = serde_json::json!({ "job_id": job_id });
+    let result = queue
+        .remove(&job_id)
+        .ok_or_else(|| format!("No job {} in the queue", job_id));
+    audit::record("remove_from_queue", params, &result);
+    result
+}
+
+/// Run a queue-wide control and tell the UI through `Event::QueueStateChanged`
+fn apply_queue_control(
+    action: &str,
+    control: impl FnOnce(&DownloadQueue) -> QueueStateChange,
+    queue: &DownloadQueue,
+    app_handle: &tauri::AppHandle,
+) -> Result<QueueStateChange, String> {
+    let change = control(queue);
+    events::emit(app_handle, Event::QueueStateChanged, &change);
+    let result = Ok(change);
+    audit::record(action, serde_json::json!({}), &result);
+    result
+}
+
+/// Pause every queued job and interrupt the running one
+#[tauri::command]
+pub async fn pause_all(
+    queue: tauri::State<'_, DownloadQueue>,
+    app_handle: tauri::AppHandle,
+) -> Result<QueueStateChange, String> {
+    apply_queue_control("pause_all", DownloadQueue::pause_all, &queue, &app_handle)
+}
+
+/// Queue the paused jobs again
+#[tauri::command]
+pub async fn resume_all(
+    queue: tauri::State<'_, DownloadQueue>,
+    app_handle: tauri::AppHandle,
+) -> Result<QueueStateChange, String> {
+    apply_queue_control("resume_all", DownloadQueue::resume_all, &queue, &app_handle)
+}
+
+/// Cancel every job that hasn't finished
+#[tauri::command]
+pub async fn cancel_all(
+    queue: tauri::State<'_, DownloadQueue>,
+    app_handle: tauri::AppHandle,
+) -> Result<QueueStateChange, String> {
+    apply_queue_control("cancel_all", DownloadQueue::cancel_all, &queue, &app_handle)
+}
+
+/// Remove the done, failed and cancelled jobs from the queue
+#[tauri::command]
+pub async fn clear_completed(
+    queue: tauri::State<'_, DownloadQueue>,
+    app_handle: tauri::AppHandle,
+) -> Result<QueueStateChange, String> {
+    apply_queue_control(
+        "clear_completed",
+        DownloadQueue::clear_completed,
+        &queue,
+        &app_handle,
+    )
+}
+
+/// Save how many playlist videos are downloaded at the same time, 1 to 4
+#[tauri::command]
+pub async fn save_max_concurrent_downloads(
+    max_concurrent_downloads: Option<usize>,
+) -> Result<(), String> {
+    let params = serde_json::json!({ "max_concurrent_downloads": max_concurrent_downloads });
+    let result = match max_concurrent_downloads {
+        Some(n) if !(1..=MAX_CONCURRENT_DOWNLOADS).contains(&n) => Err(format!(
+            "Concurrent downloads must be between 1 and {}",
+            MAX_CONCURRENT_DOWNLOADS
+        )),
+        _ => {
+            let mut prefs = AppPreferences::load();
+            prefs.max_concurrent_downloads = max_concurrent_downloads;
+            prefs.save_unlocked("save_max_concurrent_downloads")
+        }
+    };
+    audit::record("save_max_concurrent_downloads", params, &result);
+    result
+}
+
+/// Save how often failed playlist videos are retried, `None` restores the defaults
+#[tauri::command]
+pub async fn save_retry_policy(retry_policy: Option<RetryPolicy>) -> Result<(), String> {
+    let params = serde_json::json!({ "retry_policy": retry_policy });
+    let result = match &retry_policy {
+        Some(policy) if policy.max_retries > 10 => {
+            Err("At most 10 retries are allowed".to_string())
+        }
+        Some(policy)
+            if !(policy.initial_delay_seconds >= 0.0
+                && policy.max_delay_seconds >= policy.initial_delay_seconds) =>
+        {
+            Err("The maximum delay must be at least the initial delay".to_string())
+        }
+        _ => {
+            let mut prefs = AppPreferences::load();
+            prefs.playlist_retry = retry_policy;
+            prefs.save_unlocked("save_retry_policy")
+        }
+    };
+    audit::record("save_retry_policy", params, &result);
+    result
+}
+
+/// Save the download speed cap, `None` or an empty string removes it
+#[tauri::command]
+pub async fn save_max_download_rate(max_download_rate: Option<String>) -> Result<(), String> {
+    let params = serde_json::json!({ "max_download_rate": max_download_rate });
+    let result = max_download_rate
+        .filter(|rate| !rate.trim().is_empty())
+        .map(|rate| download::parse_download_rate(&rate))
+        .transpose()
+        .and_then(|rate| {
+            let mut prefs = AppPreferences::load();
+            prefs.max_download_rate = rate;
+            prefs.save_unlocked("save_max_download_rate")
+        });
+    audit::record("save_max_download_rate", params, &result);
+    result
+}
+
+/// Save the proxy settings, `None` goes back to connecting directly
+#[tauri::command]
+pub async fn save_proxy_settings(proxy: Option<ProxySettings>) -> Result<(), String> {
+    let params = serde_json::json!({ "proxy": proxy.as_ref().map(ProxySettings::redacted) });
+    let result = proxy
+        .as_ref()
+        .map_or(Ok(()), ProxySettings::validate)
+        .and_then(|()| {
+            let mut prefs = AppPreferences::load();
+            prefs.proxy = proxy.clone();
+            prefs.save_unlocked("save_proxy_settings")
+        });
+    if result.is_ok() {
+        proxy::configure(proxy);
+    }
+    audit::record("save_proxy_settings", params, &result);
+    result
+}
+
+/// Save whether single downloads are piped into ffmpeg instead of converted from a file
+#[tauri::command]
+pub async fn save_stream_conversion(enabled: bool) -> Result<(), String> {
+    let mut prefs = AppPreferences::load();
+    prefs.stream_conversion = Some(enabled);
+    let result = prefs.save_unlocked("save_stream_conversion");
+    audit::record(
+        "save_stream_conversion",
+        serde_json::json!({ "enabled": enabled }),
+        &result,
+    );
+    result
+}
+
+/// Import a cookies.txt exported from a browser signed in to YouTube
+#[tauri::command]
+pub async fn import_cookies(path: String) -> Result<CookiesStatus, String> {
+    let params = serde_json::json!({ "path": path });
+    let result = app_lock::ensure_unlocked("import_cookies").and_then(|_| {
+        let enabled = AppPreferences::load().use_cookies.unwrap_or(true);
+        cookies::import(Path::new(&path), enabled)
+    });
+    audit::record("import_cookies", params, &result);
+    result
+}
+
+/// Delete the imported cookies
+#[tauri::command]
+pub async fn remove_cookies() -> Result<(), String> {
+    let result = app_lock::ensure_unlocked("remove_cookies").and_then(|_| cookies::remove());
+    audit::record("remove_cookies", serde_json::json!({}), &result);
+    result
+}
+
+/// Whether cookies are imported and used for downloads
+#[tauri::command]
+pub async fn get_cookies_status() -> Result<CookiesStatus, String> {
+    Ok(cookies::status(
+        AppPreferences::load().use_cookies.unwrap_or(true),
+    ))
+}
+
+/// Save whether downloads use the imported cookies
+#[tauri::command]
+pub async fn save_use_cookies(enabled: bool) -> Result<(), String> {
+    let mut prefs = AppPreferences::load();
+    prefs.use_cookies = Some(enabled);
+    let result = prefs.save_unlocked("save_use_cookies");
+    audit::record(
+        "save_use_cookies",
+        serde_json::json!({ "enabled": enabled }),
+        &result,
+    );
+    result
+}
+
+/// Save whether playlist concurrency follows the system load
+#[tauri::command]
+pub async fn save_adaptive_concurrency(enabled: bool) -> Result<(), String> {
+    let mut prefs = AppPreferences::load();
+    prefs.adaptive_concurrency = Some(enabled);
+    let result = prefs.save_unlocked("save_adaptive_concurrency");
+    audit::record(
+        "save_adaptive_concurrency",
+        serde_json::json!({ "enabled": enabled }),
+        &result,
+    );
+    result
+}
+
+/// Measure conversion speed at several bitrates and concurrencies plus the write speed of
+/// `output_folder`. The result sets the playlist concurrency unless one was saved.
+#[tauri::command]
+pub async fn run_benchmark(
+    output_folder: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<benchmark::BenchmarkResult, String> {
+    let params = serde_json::json!({ "output_folder": output_folder });
+    let result = benchmark::run(&app_handle, output_folder).await;
+    audit::record("run_benchmark", params, &result);
+    result
+}
+
+/// Get the result of the last benchmark
+#[tauri::command]
+pub async fn get_benchmark_result() -> Result<Option<benchmark::BenchmarkResult>, String> {
+    Ok(benchmark::load_result())
+}
+
+/// Save the format downloads are converted to when none is picked for the download
+#[tauri::command]
+pub async fn save_audio_format(audio_format: AudioFormat) -> Result<(), String> {
+    let mut prefs = AppPreferences::load();
+    prefs.audio_format = Some(audio_format);
+    let result = prefs.save_unlocked("save_audio_format");
+    audit::record(
+        "save_audio_format",
+        serde_json::json!({ "audio_format": audio_format }),
+        &result,
+    );
+    result
+}
+
+/// Download the full video, merged into MP4 unless another `container` is picked.
+/// Progress is reported through `video-download-progress` events, and the download is
+/// cancellable through `cancel_download` like audio downloads.
+#[tauri::command]
+pub async fn download_video(
+    url: String,
+    output_folder: String,
+    container: Option<VideoContainer>,
+    download_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<DownloadResult, String> {
+    let params = serde_json::json!({
+        "url": url,
+        "output_folder": output_folder,
+        "container": container,
+        "download_id": download_id,
+    });
+    let _active = ActiveDownloadGuard::new();
+    let cancel = CancelRegistration::new(download_id.as_deref().unwrap_or(&url));
+    let mut options = resolve_download_options(&app_handle);
+    options.cancel = cancel.token.clone();
+    let result = match volume::check_output_folder(&output_folder) {
+        Ok(()) => {
+            video::download_video(
+                &url,
+                &output_folder,
+                container.unwrap_or_default(),
+                &options,
+                &app_handle,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+
+    if result.is_ok() {
+        let app_name = app_handle.package_info().name.clone();
+        tauri::api::notification::Notification::new(&app_name)
+            .title("Download Complete")
+            .body("Successfully downloaded the video")
+            .show()
+            .ok();
+    }
+    audit::record("download_video", params, &result);
+    result
+}
+
+/// Save whether playlist downloads get folder.jpg, album.nfo and metadata.json
+#[tauri::command]
+pub async fn save_folder_artwork(enabled: bool) -> Result<(), String> {
+    let mut prefs = AppPreferences::load();
+    prefs.folder_artwork = Some(enabled);
+    let result = prefs.save_unlocked("save_folder_artwork");
+    audit::record(
+        "save_folder_artwork",
+        serde_json::json!({ "enabled": enabled }),
+        &result,
+    );
+    result
+}
+
+/// Save whether downloads are tagged with title, artist and album
+#[tauri::command]
+pub async fn save_embed_metadata(enabled: bool) -> Result<(), String> {
+    let mut prefs = AppPreferences::load();
+    prefs.embed_metadata = Some(enabled);
+    let result = prefs.save_unlocked("save_embed_metadata");
+    audit::record(
+        "save_embed_metadata",
+        serde_json::json!({ "enabled": enabled }),
+        &result,
+    );
+    result
+}
+
+/// Save the Jellyfin or Plex server to refresh after a batch of downloads
+#[tauri::command]
+pub async fn save_media_server(media_server: Option<MediaServerSettings>) -> Result<(), String> {
+    let params = serde_json::json!({
+        "media_server": media_server.as_ref().map(MediaServerSettings::redacted),
+    });
+    let result = media_server
+        .as_ref()
+        .map_or(Ok(()), MediaServerSettings::validate)
+        .and_then(|()| {
+            let mut prefs = AppPreferences::load();
+            prefs.media_server = media_server;
+            prefs.save_unlocked("save_media_server")
+        });
+    audit::record("save_media_server", params, &result);
+    result
+}
+
+/// Refresh the media server's library now, with `media_server` or the saved settings,
+/// so the settings can be tried out before saving them
+#[tauri::command]
+pub async fn refresh_media_library(
+    media_server: Option<MediaServerSettings>,
+) -> Result<(), String> {
+    let params = serde_json::json!({
+        "media_server": media_server.as_ref().map(MediaServerSettings::redacted),
+    });
+    let result = match media_server.or(AppPreferences::load().media_server) {
+        Some(settings) if settings.enabled => media_server::refresh(&settings).await,
+        _ => Err("No media server is configured".to_string()),
+    };
+    audit::record("refresh_media_library", params, &result);
+    result
+}
+
+/// Save whether the video thumbnail is embedded as cover art
+#[tauri::command]
+pub async fn save_embed_thumbnail(enabled: bool) -> Result<(), String> {
+    let mut prefs = AppPreferences::load();
+    prefs.embed_thumbnail = Some(enabled);
+    let result = prefs.save_unlocked("save_embed_thumbnail");
+    audit::record(
+        "save_embed_thumbnail",
+        serde_json::json!({ "enabled": enabled }),
+        &result,
+    );
+    result
+}
+
+/// Save the MQTT broker the queue status and download events are published to
+#[tauri::command]
+pub async fn save_mqtt_settings(mqtt: Option<MqttSettings>) -> Result<(), String> {
+    let params = serde_json::json!({ "mqtt": mqtt.as_ref().map(MqttSettings::redacted) });
+    let result = mqtt
+        .as_ref()
+        .map_or(Ok(()), MqttSettings::validate)
+        .and_then(|()| {
+            let mut prefs = AppPreferences::load();
+            prefs.mqtt = mqtt.clone();
+            prefs.save_unlocked("save_mqtt_settings")
+        });
+    if result.is_ok() {
+        mqtt::configure(mqtt);
+    }
+    audit::record("save_mqtt_settings", params, &result);
+    result
+}
+
+/// Publish a test event with `mqtt`, so the settings can be tried out before saving them
+#[tauri::command]
+pub async fn test_mqtt_settings(mqtt: MqttSettings) -> Result<(), String> {
+    let params = serde_json::json!({ "mqtt": mqtt.redacted() });
+    let result = match mqtt.validate() {
+        Ok(()) => mqtt::publish(&mqtt, &mqtt.event_topic, br#"{"event":"test"}"#, false).await,
+        Err(e) => Err(e),
+    };
+    audit::record("test_mqtt_settings", params, &result);
+    result
+}
+
+/// Save whether downloads cut sponsor and non-music segments out with SponsorBlock
+#[tauri::command]
+pub async fn save_sponsorblock(enabled: bool) -> Result<(), String> {
+    let mut prefs = AppPreferences::load();
+    prefs.sponsorblock = Some(enabled);
+    let result = prefs.save_unlocked("save_sponsorblock");
+    audit::record(
+        "save_sponsorblock",
+        serde_json::json!({ "enabled": enabled }),
+        &result,
+    );
+    result
+}
+
+/// Peak levels of a downloaded file for drawing its waveform in the trim editor
+#[tauri::command]
+pub async fn get_waveform(
+    path: String,
+    samples: usize,
+    app_handle: tauri::AppHandle,
+) -> Result<Waveform, String> {
+    let ffmpeg_cmd = download::ensure_ffmpeg(&app_handle).await?;
+    trim::waveform(&ffmpeg_cmd, Path::new(&path), samples).await
+}
+
+/// Save the part of a downloaded file between `start` and `end` seconds to `output`
+#[tauri::command]
+pub async fn trim_file(
+    path: String,
+    start: f64,
+    end: f64,
+    output: String,
+    app_handle: tauri::AppHandle,
+) -> Result<TrimResult, String> {
+    let params = serde_json::json!({
+        "path": path,
+        "start": start,
+        "end": end,
+        "output": output,
+    });
+    let result = match download::ensure_ffmpeg(&app_handle).await {
+        Ok(ffmpeg_cmd) => {
+            trim::trim(
+                &ffmpeg_cmd,
+                Path::new(&path),
+                start,
+                end,
+                &[],
+                Path::new(&output),
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    audit::record("trim_file", params, &result);
+    result
+}
+
+/// Save the leading and trailing silence removal settings
+#[tauri::command]
+pub async fn save_silence_trim(silence_trim: Option<SilenceTrim>) -> Result<(), String> {
+    let params = serde_json::json!({ "silence_trim": silence_trim });
+    let result = silence_trim
+        .as_ref()
+        .map_or(Ok(()), SilenceTrim::validate)
+        .and_then(|()| {
+            let mut prefs = AppPreferences::load();
+            prefs.silence_trim = silence_trim;
+            prefs.save_unlocked("save_silence_trim")
+        });
+    audit::record("save_silence_trim", params, &result);
+    result
+}
+
+/// Save whether downloads are also split into one track per chapter
+#[tauri::command]
+pub async fn save_split_chapters(enabled: bool) -> Result<(), String> {
+    let mut prefs = AppPreferences::load();
+    prefs.split_chapters = Some(enabled);
+    let result = prefs.save_unlocked("save_split_chapters");
+    audit::record(
+        "save_split_chapters",
+        serde_json::json!({ "enabled": enabled }),
+        &result,
+    );
+    result
+}
+
+/// Save the post-processing preset applied to downloads
+#[tauri::command]
+pub async fn save_audio_preset(audio_preset: AudioPreset) -> Result<(), String> {
+    let mut prefs = AppPreferences::load();
+    prefs.audio_preset = Some(audio_preset);
+    let result = prefs.save_unlocked("save_audio_preset");
+    audit::record(
+        "save_audio_preset",
+        serde_json::json!({ "audio_preset": audio_preset }),
+        &result,
+    );
+    result
+}
+
+/// Estimate the size and time of downloading a playlist, before the user commits to it
+#[tauri::command]
+pub async fn estimate_playlist(
+    url: String,
+    bitrate: u32,
+    format: Option<AudioFormat>,
+    app_handle: tauri::AppHandle,
+) -> Result<PlaylistEstimate, String> {
+    let mut options = resolve_download_options(&app_handle);
+    options.audio_format = resolve_audio_format(format);
+    let bitrate = session_defaults().bitrate.unwrap_or(bitrate);
+    download::estimate_playlist(&url, bitrate, &options, &app_handle).await
+}
+
+/// List the videos of a playlist without downloading them, so items can be deselected
+/// before the download starts
+#[tauri::command]
+pub async fn get_playlist_info(
+    url: String,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<PlaylistEntry>, String> {
+    let options = resolve_download_options(&app_handle);
+    download::get_playlist_info(&url, &options, &app_handle).await
+}
+
+/// Save whether events are also sent under their legacy names, for older UIs
+#[tauri::command]
+pub async fn save_legacy_events(enabled: bool) -> Result<(), String> {
+    let mut prefs = AppPreferences::load();
+    prefs.legacy_events = Some(enabled);
+    let result = prefs.save_unlocked("save_legacy_events");
+    if result.is_ok() {
+        events::configure(enabled);
+    }
+    audit::record(
+        "save_legacy_events",
+        serde_json::json!({ "enabled": enabled }),
+        &result,
+    );
+    result
+}
+
+/// Save whether progress is only sent when it reaches a new step (a tenth of the way)
+/// or its status changes, instead of several times a second
+#[tauri::command]
+pub async fn save_reduced_updates(enabled: bool) -> Result<(), String> {
+    let mut prefs = AppPreferences::load();
+    prefs.reduced_updates = Some(enabled);
+    let result = prefs.save_unlocked("save_reduced_updates");
+    if result.is_ok() {
+        download::configure_reduced_updates(enabled);
+    }
+    audit::record(
+        "save_reduced_updates",
+        serde_json::json!({ "enabled": enabled }),
+        &result,
+    );
+    result
+}
+
+/// Get the per-channel rules applied to downloads as they are started or queued
+#[tauri::command]
+pub async fn get_channel_rules() -> Result<Vec<ChannelRule>, String> {
+    Ok(channel_rules::load())
+}
+
+/// Add or replace the rule for the videos of `channel`, or remove it when `rule` is
+/// none. Returns every rule.
+#[tauri::command]
+pub async fn set_channel_rule(
+    channel: String,
+    rule: Option<ChannelRule>,
+) -> Result<Vec<ChannelRule>, String> {
+    let params = serde_json::json!({ "channel": channel, "rule": rule });
+    let result = app_lock::ensure_unlocked("set_channel_rule")
+        .and_then(|()| channel_rules::set(&channel, rule));
+    audit::record("set_channel_rule", params, &result);
+    result
+}
+
+/// Look up a single video's title, length, uploader, thumbnail, formats and estimated
+/// size at `bitrate`, so the download can be confirmed before it starts
+#[tauri::command]
+pub async fn get_video_info(
+    url: String,
+    bitrate: u32,
+    app_handle: tauri::AppHandle,
+) -> Result<VideoInfo, String> {
+    let options = resolve_download_options(&app_handle);
+    let bitrate = session_defaults().bitrate.unwrap_or(bitrate);
+    download::get_video_info(&url, Some(bitrate), &options, &app_handle).await
+}
+
+/// Save whether URLs from sites other than YouTube are accepted. yt-dlp probes each
+/// such URL before it is downloaded.
+#[tauri::command]
+pub async fn save_allow_any_site(enabled: bool) -> Result<(), String> {
+    let mut prefs = AppPreferences::load();
+    prefs.allow_any_site = Some(enabled);
+    let result = prefs.save_unlocked("save_allow_any_site");
+    if result.is_ok() {
+        source::configure(enabled);
+    }
+    audit::record(
+        "save_allow_any_site",
+        serde_json::json!({ "enabled": enabled }),
+        &result,
+    );
+    result
+}
+
+/// Queue several single URLs at once, such as a pasted list of links, and wait for all
+/// of them. The combined progress is sent as `ytmp3://batch/progress/v2` and each URL
+/// gets its own result, failed and rejected ones included.
+#[tauri::command]
+pub async fn download_multiple(
+    urls: Vec<String>,
+    output_folder: String,
+    bitrate: u32,
+    overrides: Option<DownloadOverrides>,
+    session_note: Option<String>,
+    queue: tauri::State<'_, DownloadQueue>,
+    app_handle: tauri::AppHandle,
+) -> Result<BatchResult, String> {
+    let params = serde_json::json!({
+        "urls": urls,
+        "output_folder": output_folder,
+        "bitrate": bitrate,
+        "overrides": overrides,
+        "session_note": session_note,
+    });
+    let checked = if urls.is_empty() {
+        Err("No URLs to download".to_string())
+    } else {
+        volume::check_output_folder(&output_folder)
+    };
+    if let Err(e) = checked {
+        let result = Err(e);
+        audit::record("download_multiple", params, &result);
+        return result;
+    }
+
+    let options = resolve_download_options(&app_handle);
+    let overrides = overrides.unwrap_or_default();
+    let mut entries = Vec::with_capacity(urls.len());
+    for url in urls {
+        let job = match download::check_url(&url, &options, &app_handle).await {
+            Ok(_) => {
+                let (mut folder, mut overrides) = (output_folder.clone(), overrides.clone());
+                channel_rules::apply(&url, &mut folder, &mut overrides, &options, &app_handle)
+                    .await;
+                Ok(queue.enqueue(url.clone(), folder, bitrate, overrides))
+            }
+            Err(e) => Err(e),
+        };
+        entries.push((url, job));
+    }
+    let mut batch = queue.run_batch(&app_handle, entries).await;
+    if let Some(note) = session_note {
+        let history = HistoryData::load();
+        let tracks: Vec<LinerTrack> = batch
+            .items
+            .iter()
+            .filter_map(|item| Some((item, item.output_paths.first()?)))
+            .map(|(item, path)| {
+                let entry = history.downloads.iter().find(|d| &d.output_path == path);
+                LinerTrack {
+                    title: entry
+                        .and_then(|d| d.title.clone())
+                        .unwrap_or_else(|| file_title(path)),
+                    source: item.url.clone(),
+                    duration: entry.and_then(|d| d.duration),
+                }
+            })
+            .collect();
+        batch.liner_notes_path = write_liner_notes(&output_folder, &note, &tracks);
+    }
+    let result = Ok(batch);
+    audit::record("download_multiple", params, &result);
+    result
+}
+
+/// Save the simulated slow network used to test retries and progress, `None` turns it
+/// off. Saving starts the simulated failures over from the seed.
+#[tauri::command]
+pub async fn save_network_simulation(
+    network_simulation: Option<NetworkSimulation>,
+) -> Result<(), String> {
+    let params = serde_json::json!({ "network_simulation": network_simulation });
+    let result = network_simulation
+        .as_ref()
+        .map_or(Ok(()), NetworkSimulation::validate)
+        .and_then(|_| {
+            let mut prefs = AppPreferences::load();
+            prefs.network_simulation = network_simulation.clone();
+            prefs.save_unlocked("save_network_simulation")
+        });
+    if result.is_ok() {
+        network_sim::configure(network_simulation.as_ref());
+    }
+    audit::record("save_network_simulation", params, &result);
+    result
+}
+
+/// Save whether the clipboard is watched for URLs, starting or stopping the watcher
+#[tauri::command]
+pub async fn save_clipboard_monitor(
+    enabled: bool,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let mut prefs = AppPreferences::load();
+    prefs.clipboard_monitor = Some(enabled);
+    let result = prefs.save_unlocked("save_clipboard_monitor");
+    if result.is_ok() {
+        clipboard::configure(&app_handle, enabled);
+    }
+    audit::record(
+        "save_clipboard_monitor",
+        serde_json::json!({ "enabled": enabled }),
+        &result,
+    );
+    result
+}
+
+/// Save whether a summary of the week's downloads is sent as a notification every
+/// seven days
+#[tauri::command]
+pub async fn save_weekly_digest(enabled: bool) -> Result<(), String> {
+    let mut prefs = AppPreferences::load();
+    prefs.weekly_digest = Some(enabled);
+    let result = prefs.save_unlocked("save_weekly_digest");
+    audit::record(
+        "save_weekly_digest",
+        serde_json::json!({ "enabled": enabled }),
+        &result,
+    );
+    result
+}
+
+/// Save the disk limits of the logs and caches, trimming them right away
+#[tauri::command]
+pub async fn save_storage_limits(storage_limits: StorageLimits) -> Result<StorageCleanup, String> {
+    let params = serde_json::json!({ "storage_limits": storage_limits });
+    let result = storage_limits.validate().and_then(|_| {
+        let mut prefs = AppPreferences::load();
+        prefs.storage_limits = Some(storage_limits);
+        prefs.save_unlocked("save_storage_limits")?;
+        Ok(AppPreferences::enforce_storage_limits())
+    });
+    audit::record("save_storage_limits", params, &result);
+    result
+}
+
+/// Break down how much disk the logs, caches and binaries managed by the app use
+#[tauri::command]
+pub async fn get_storage_usage(app_handle: tauri::AppHandle) -> Result<StorageUsage, String> {
+    let prefs = AppPreferences::load();
+    Ok(storage::usage(
+        &app_handle,
+        &prefs.storage_limits.unwrap_or_default(),
+        prefs.media_cache.as_ref().filter(|c| c.enabled),
+    ))
+}
+
+/// Save the preferences, download history and channel rules into a zip archive at
+/// `path`, for restoring after reinstalling
+#[tauri::command]
+pub async fn create_backup(path: String) -> Result<BackupManifest, String> {
+    let result = backup::create(Path::new(&path));
+    audit::record(
+        "create_backup",
+        serde_json::json!({ "path": path }),
+        &result,
+    );
+    result
+}
+
+/// Replace the preferences, download history and channel rules with those of a backup
+/// made by `create_backup`, applying the restored settings right away
+#[tauri::command]
+pub async fn restore_backup(
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<BackupManifest, String> {
+    let result =
+        app_lock::ensure_unlocked("restore_backup").and_then(|_| backup::restore(Path::new(&path)));
+    if result.is_ok() {
+        AppPreferences::apply_runtime_settings();
+        clipboard::configure(
+            &app_handle,
+            AppPreferences::load().clipboard_monitor.unwrap_or(false),
+        );
+    }
+    audit::record(
+        "restore_backup",
+        serde_json::json!({ "path": path }),
+        &result,
+    );
+    result
+}
+
+/// Save whether downloads are sorted into a subfolder per playlist or uploader
+#[tauri::command]
+pub async fn save_folder_organization(
+    folder_organization: Option<FolderOrganization>,
+) -> Result<(), String> {
+    let params = serde_json::json!({ "folder_organization": folder_organization });
+    let mut prefs = AppPreferences::load();
+    prefs.folder_organization = folder_organization;
+    let result = prefs.save_unlocked("save_folder_organization");
+    audit::record("save_folder_organization", params, &result);
+    result
+}
+
+/// Save whether subtitles are downloaded next to each track as lyrics
+#[tauri::command]
+pub async fn save_lyrics(lyrics: Option<LyricsSettings>) -> Result<(), String> {
+    let params = serde_json::json!({ "lyrics": lyrics });
+    let result = lyrics
+        .as_ref()
+        .map_or(Ok(()), LyricsSettings::validate)
+        .and_then(|()| {
+            let mut prefs = AppPreferences::load();
+            prefs.lyrics = lyrics;
+            prefs.save_unlocked("save_lyrics")
+        });
+    audit::record("save_lyrics", params, &result);
+    result
+}
+
+/// Search YouTube for videos that could replace the history entry of video `id` after
+/// it was removed, matching its title and length
+#[tauri::command]
+pub async fn find_replacement(
+    id: String,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<ReplacementCandidate>, String> {
+    let history = HistoryData::load();
+    let entry = history
+        .downloads
+        .iter()
+        .find(|entry| match source::parse_source(&entry.url) {
+            Ok(ParsedSource::Video { video_id }) => video_id == id,
+            Ok(ParsedSource::Playlist { video_id, .. }) => video_id.as_deref() == Some(&id),
+            _ => false,
+        })
+        .ok_or_else(|| format!("Video {} is not in the download history", id))?;
+    // Entries from before titles were saved still have the title in the filename
+    let title = entry
+        .title
+        .clone()
+        .or_else(|| {
+            Path::new(&entry.output_path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+        })
+        .filter(|title| !title.trim().is_empty())
+        .ok_or_else(|| format!("The history entry of video {} has no title", id))?;
+    let options = resolve_download_options(&app_handle);
+    replacement::find(&id, &title, entry.duration, &options, &app_handle).await
+}
+
+/// Save the loudness normalization run over each downloaded track
+#[tauri::command]
+pub async fn save_loudness_normalization(
+    loudness_normalization: Option<LoudnessNormalization>,
+) -> Result<(), String> {
+    let params = serde_json::json!({ "loudness_normalization": loudness_normalization });
+    let result = loudness_normalization
+        .as_ref()
+        .map_or(Ok(()), LoudnessNormalization::validate)
+        .and_then(|()| {
+            let mut prefs = AppPreferences::load();
+            prefs.loudness_normalization = loudness_normalization;
+            prefs.save_unlocked("save_loudness_normalization")
+        });
+    audit::record("save_loudness_normalization", params, &result);
+    result
+}
+
+/// Write a manifest of every file in the library at `root` to `path`, with its size,
+/// SHA-256, tags and source URL, for checking backups and spotting bit-rot later
+#[tauri::command]
+pub async fn export_library_manifest(
+    root: String,
+    path: String,
+    format: Option<ManifestFormat>,
+    app_handle: tauri::AppHandle,
+) -> Result<ManifestExport, String> {
+    let params = serde_json::json!({ "root": root, "path": path, "format": format });
+    let sources = HistoryData::load()
+        .downloads
+        .into_iter()
+        .map(|download| (PathBuf::from(download.output_path), download.url))
+        .collect();
+    let result = match download::ensure_ffmpeg(&app_handle).await {
+        Ok(ffmpeg_cmd) => {
+            manifest::export(
+                &ffmpeg_cmd,
+                Path::new(&root),
+                Path::new(&path),
+                format.unwrap_or_default(),
+                sources,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    audit::record("export_library_manifest", params, &result);
+    result
+}
+
+/// Re-hash the library at `root` and report files that are missing, changed or not in
+/// a manifest written by `export_library_manifest`
+#[tauri::command]
+pub async fn verify_manifest(
+    manifest_path: String,
+    root: String,
+) -> Result<ManifestVerification, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        manifest::verify(Path::new(&manifest_path), Path::new(&root))
+    })
+    .await
+    .map_err(|e| format!("Verification task failed: {}", e))
+    .and_then(|r| r)
+}
@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::api::path::cache_dir;
+use tauri::{AppHandle, Runtime};
+
+use crate::audio_format::AudioFormat;
+use crate::download::{self, DownloadOptions};
+use crate::library;
+use crate::queue;
+
+/// Two bitrates are enough for an A/B test, more than three is hard to tell apart
+const MIN_BITRATES: usize = 2;
+const MAX_BITRATES: usize = 3;
+
+/// One copy of the track, waiting in the comparison folder to be previewed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComparisonCandidate {
+    pub bitrate: u32,
+    pub path: String,
+    pub file_size: Option<u64>,
+}
+
+/// The same track downloaded at several bitrates, until one of them is kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityComparison {
+    pub id: String,
+    pub url: String,
+    pub title: Option<String>,
+    pub duration: Option<f64>,
+    pub audio_format: AudioFormat,
+    pub candidates: Vec<ComparisonCandidate>,
+}
+
+static COMPARISONS: Mutex<Option<HashMap<String, QualityComparison>>> = Mutex::new(None);
+
+fn get_compare_root() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("youtube-downloader").join("compare"))
+}
+
+/// Sorted, deduplicated bitrates, or an error when there are too few or too many
+fn comparison_bitrates(bitrates: &[u32]) -> Result<Vec<u32>, String> {
+    let mut bitrates = bitrates.to_vec();
+    bitrates.sort_unstable();
+    bitrates.dedup();
+    if !(MIN_BITRATES..=MAX_BITRATES).contains(&bitrates.len()) {
+        return Err(format!(
+            "Pick {} or {} different bitrates to compare",
+            MIN_BITRATES, MAX_BITRATES
+        ));
+    }
+    Ok(bitrates)
+}
+
+/// Remove comparison folders left behind by an earlier run of the app
+fn remove_abandoned(root: &Path) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    let comparisons = COMPARISONS.lock().unwrap();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let id = entry.file_name().to_string_lossy().to_string();
+        if !comparisons.as_ref().is_some_and(|c| c.contains_key(&id)) {
+            fs::remove_dir_all(entry.path()).ok();
+        }
+    }
+}
+
+/// Download `url` once per bitrate into its own folder of the comparison area, ready
+/// to be previewed. Nothing is added to the history until one copy is kept.
+pub async fn start<R: Runtime>(
+    url: &str,
+    bitrates: &[u32],
+    audio_format: AudioFormat,
+    options: &DownloadOptions,
+    app_handle: &AppHandle<R>,
+) -> Result<QualityComparison, String> {
+    if audio_format.is_lossless() {
+        return Err(format!(
+            "{} is lossless, there are no bitrates to compare",
+            audio_format
+        ));
+    }
+    let bitrates = comparison_bitrates(bitrates)?;
+    let root = get_compare_root().ok_or("Failed to get cache directory")?;
+    remove_abandoned(&root);
+
+    let id = queue::new_id("compare");
+    let dir = root.join(&id);
+    let mut comparison = QualityComparison {
+        id: id.clone(),
+        url: url.to_string(),
+        title: None,
+        duration: None,
+        audio_format,
+        candidates: Vec::new(),
+    };
+    for bitrate in bitrates {
+        let folder = dir.join(format!("{}k", bitrate));
+        let result = download::download_youtube(
+            url,
+            &folder.to_string_lossy(),
+            bitrate,
+            audio_format,
+            options,
+            app_handle,
+        )
+        .await;
+        let result = match result {
+            Ok(result) => result,
+            Err(e) => {
+                fs::remove_dir_all(&dir).ok();
+                return Err(e);
+            }
+        };
+        comparison.title = comparison.title.or(result.title);
+        comparison.duration = comparison.duration.or(result.duration);
+        comparison.candidates.push(ComparisonCandidate {
+            bitrate,
+            path: result.output_path,
+            file_size: result.file_size,
+        });
+    }
+
+    COMPARISONS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(id, comparison.clone());
+    Ok(comparison)
+}
+
+/// Move the copy at `bitrate` into `output_folder` and delete the others. Returns the
+/// comparison and where the kept file ended up.
+pub fn keep(
+    id: &str,
+    bitrate: u32,
+    output_folder: &str,
+) -> Result<(QualityComparison, ComparisonCandidate), String> {
+    let comparison = COMPARISONS
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|c| c.get(id).cloned())
+        .ok_or_else(|| format!("No quality comparison with id {}", id))?;
+    let candidate = comparison
+        .candidates
+        .iter()
+        .find(|c| c.bitrate == bitrate)
+        .ok_or_else(|| format!("The comparison has no copy at {} kbps", bitrate))?;
+
+    let from = Path::new(&candidate.path);
+    let file_name = from.file_name().ok_or("The compared file has no name")?;
+    let to = Path::new(output_folder).join(file_name);
+    if to.exists() {
+        return Err(format!("{} already exists", to.display()));
+    }
+    library::move_file(from, &to).map_err(|e| format!("Failed to keep {}: {}", to.display(), e))?;
+
+    discard(id)?;
+    let kept = ComparisonCandidate {
+        bitrate,
+        path: to.to_string_lossy().to_string(),
+        file_size: candidate.file_size,
+    };
+    Ok((comparison, kept))
+}
+
+/// Delete every copy of a comparison
+pub fn discard(id: &str) -> Result<(), String> {
+    let removed = COMPARISONS
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|c| c.remove(id));
+    if removed.is_none() {
+        return Err(format!("No quality comparison with id {}", id));
+    }
+    if let Some(root) = get_compare_root() {
+        fs::remove_dir_all(root.join(id)).ok();
+    }
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, Runtime};
+use tokio_util::sync::CancellationToken;
+
+use crate::events::{self, Event};
+use crate::proxy;
+
+/// Times one download is resumed after its connection dropped, before it fails
+const MAX_RECONNECTS: u32 = 3;
+
+/// Longest wait for the network to come back before the download fails
+const MAX_WAIT: Duration = Duration::from_secs(10 * 60);
+
+/// How often the network is checked while a download waits for it
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Answers with an empty 204, the cheapest request that proves YouTube is reachable
+const CHECK_URL: &str = "https://www.youtube.com/generate_204";
+
+/// Sent as `ytmp3://download/waiting-for-network/v2` when a download stops for a dropped
+/// connection, and again once it resumes or gives up
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkWait {
+    pub url: String,
+    /// Still waiting, false once the wait is over
+    pub waiting: bool,
+    /// The network came back and the download is resumed
+    pub resumed: bool,
+    /// Reconnect number for this download, starting at 1
+    pub attempt: u32,
+    pub max_attempts: u32,
+}
+
+/// yt-dlp errors that mean the connection went away rather than the video or format
+/// being unavailable
+pub fn is_connection_lost(error: &str) -> bool {
+    let error = error.to_lowercase();
+    [
+        "connection reset",
+        "connection aborted",
+        "connection refused",
+        "network is unreachable",
+        "no route to host",
+        "timed out",
+        "temporary failure in name resolution",
+        "name or service not known",
+        "getaddrinfo failed",
+        "nodename nor servname provided",
+        "remote end closed connection",
+        "incompleteread",
+        "connection broken",
+    ]
+    .iter()
+    .any(|pattern| error.contains(pattern))
+}
+
+/// Whether YouTube can be reached right now, through the configured proxy if there is one
+pub async fn is_online() -> bool {
+    match proxy::http_client(Duration::from_secs(5), Some(Duration::from_secs(10))) {
+        Ok(client) => client.head(CHECK_URL).send().await.is_ok(),
+        // SOCKS proxies only work for yt-dlp, a direct connection is the best guess
+        Err(_) => tokio::time::timeout(
+            Duration::from_secs(5),
+            tokio::net::TcpStream::connect(("www.youtube.com", 443)),
+        )
+        .await
+        .is_ok_and(|connected| connected.is_ok()),
+    }
+}
+
+/// Check the network every few seconds until it is back. False when it stayed down for
+/// `MAX_WAIT` or the download was cancelled.
+async fn wait_until_online(cancel: &CancellationToken) -> bool {
+    let deadline = tokio::time::Instant::now() + MAX_WAIT;
+    while tokio::time::Instant::now() < deadline {
+        if is_online().await {
+            return true;
+        }
+        tokio::select! {
+            _ = cancel.cancelled() => return false,
+            _ = tokio::time::sleep(CHECK_INTERVAL) => {}
+        }
+    }
+    false
+}
+
+/// Reconnect budget of one download, resuming it when yt-dlp died from a dropped
+/// connection. Partial files are kept by yt-dlp, so a resumed run with `--continue`
+/// picks up where the last one stopped.
+pub struct Reconnect<'a, R: Runtime> {
+    app_handle: &'a AppHandle<R>,
+    url: &'a str,
+    cancel: &'a CancellationToken,
+    attempts: u32,
+}
+
+impl<'a, R: Runtime> Reconnect<'a, R> {
+    pub fn new(app_handle: &'a AppHandle<R>, url: &'a str, cancel: &'a CancellationToken) -> Self {
+        Self {
+            app_handle,
+            url,
+            cancel,
+            attempts: 0,
+        }
+    }
+
+    fn emit(&self, waiting: bool, resumed: bool) {
+        events::emit(
+            self.app_handle,
+            Event::DownloadWaitingForNetwork,
+            &NetworkWait {
+                url: self.url.to_string(),
+                waiting,
+                resumed,
+                attempt: self.attempts,
+                max_attempts: MAX_RECONNECTS,
+            },
+        );
+    }
+
+    /// After a failed run with `error`, wait for the network if the connection dropped
+    /// and the budget isn't used up. Returns whether the download should be resumed.
+    pub async fn after_failure(&mut self, error: &str) -> bool {
+        if self.attempts >= MAX_RECONNECTS
+            || self.cancel.is_cancelled()
+            || !is_connection_lost(error)
+        {
+            return false;
+        }
+        self.attempts += 1;
+        eprintln!(
+            "[connectivity] Connection lost downloading {}, waiting for the network ({}/{})",
+            self.url, self.attempts, MAX_RECONNECTS
+        );
+        self.emit(true, false);
+        let online = wait_until_online(self.cancel).await;
+        self.emit(false, online);
+        if online {
+            eprintln!("[connectivity] Network is back, resuming {}", self.url);
+        }
+        online
+    }
+}
@@ -2,13 +2,19 @@ use serde::{Deserialize, Serialize};
 use std::path::Path;
 use tokio::process::Command;
 
+use crate::audio_format::AudioFormat;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConversionResult {
     pub output_path: String,
     pub duration: Option<f64>,
     pub file_size: Option<u64>,
+    /// The input already was MP3 and its audio was copied without re-encoding
+    pub lossless_copy: bool,
 }
 
+// Standalone conversion of a local file, not offered as a command
+#[allow(dead_code)]
 pub async fn convert_file(
     input_path: &str,
     output_folder: &str,
@@ -27,26 +33,32 @@ pub async fn convert_file(
     let output_path = Path::new(output_folder).join(format!("{}.mp3", stem));
 
     // Check if ffmpeg is available
-    let ffmpeg_check = Command::new("ffmpeg")
-        .arg("-version")
-        .output()
-        .await;
+    let ffmpeg_check = Command::new("ffmpeg").arg("-version").output().await;
 
     if ffmpeg_check.is_err() {
-        return Err("FFmpeg is not installed. Please install FFmpeg to use this application.".to_string());
+        return Err(
+            "FFmpeg is not installed. Please install FFmpeg to use this application.".to_string(),
+        );
     }
 
+    // An MP3 input only needs its audio copied into the new file
+    let lossless_copy = get_audio_codec(input_path).await.as_deref() == Some("mp3");
+
     // Build ffmpeg command
-    let output = Command::new("ffmpeg")
-        .arg("-i")
-        .arg(input_path)
-        .arg("-vn") // No video
-        .arg("-acodec")
-        .arg("libmp3lame")
-        .arg("-ab")
-        .arg(format!("{}k", bitrate))
-        .arg("-ar")
-        .arg("44100")
+    let mut command = Command::new("ffmpeg");
+    command.arg("-i").arg(input_path).arg("-vn"); // No video
+    if lossless_copy {
+        command.arg("-acodec").arg("copy");
+    } else {
+        command
+            .arg("-acodec")
+            .arg("libmp3lame")
+            .arg("-ab")
+            .arg(format!("{}k", bitrate))
+            .arg("-ar")
+            .arg("44100");
+    }
+    let output = command
         .arg("-y") // Overwrite output file
         .arg(output_path.to_str().unwrap())
         .output()
@@ -59,9 +71,7 @@ pub async fn convert_file(
     }
 
     // Get file size
-    let file_size = std::fs::metadata(&output_path)
-        .ok()
-        .map(|m| m.len());
+    let file_size = std::fs::metadata(&output_path).ok().map(|m| m.len());
 
     // Try to get duration (optional)
     let duration = get_duration(input_path).await.ok();
@@ -70,9 +80,34 @@ pub async fn convert_file(
         output_path: output_path.to_string_lossy().to_string(),
         duration,
         file_size,
+        lossless_copy,
     })
 }
 
+/// Codec of the first audio stream, such as `mp3` or `opus`
+async fn get_audio_codec(input_path: &str) -> Option<String> {
+    let output = Command::new("ffprobe")
+        .arg("-v")
+        .arg("error")
+        .arg("-select_streams")
+        .arg("a:0")
+        .arg("-show_entries")
+        .arg("stream=codec_name")
+        .arg("-of")
+        .arg("default=noprint_wrappers=1:nokey=1")
+        .arg(input_path)
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let codec = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .to_lowercase();
+    (!codec.is_empty()).then_some(codec)
+}
+
 async fn get_duration(input_path: &str) -> Result<f64, String> {
     let output = Command::new("ffprobe")
         .arg("-v")
@@ -97,3 +132,187 @@ async fn get_duration(input_path: &str) -> Result<f64, String> {
         .map_err(|_| "Failed to parse duration".to_string())
 }
 
+/// Two-pass EBU R128 loudness normalization with ffmpeg's `loudnorm`, run over each
+/// downloaded track so videos mastered at very different volumes play back alike
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoudnessNormalization {
+    pub enabled: bool,
+    /// Integrated loudness the track is brought to, in LUFS
+    pub target_lufs: f64,
+    /// Highest true peak allowed, in dBTP
+    pub true_peak_db: f64,
+    /// Loudness range allowed, in LU
+    pub loudness_range: f64,
+}
+
+impl Default for LoudnessNormalization {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target_lufs: -14.0,
+            true_peak_db: -1.0,
+            loudness_range: 11.0,
+        }
+    }
+}
+
+/// Values the first `loudnorm` pass measured, passed back to the second one
+#[derive(Debug, Deserialize)]
+struct LoudnessMeasurement {
+    input_i: String,
+    input_tp: String,
+    input_lra: String,
+    input_thresh: String,
+    target_offset: String,
+}
+
+impl LoudnessNormalization {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(-70.0..=-5.0).contains(&self.target_lufs) {
+            return Err(format!(
+                "The target loudness must be between -70 and -5 LUFS, got {}",
+                self.target_lufs
+            ));
+        }
+        if !(-9.0..=0.0).contains(&self.true_peak_db) {
+            return Err(format!(
+                "The true peak must be between -9 and 0 dBTP, got {}",
+                self.true_peak_db
+            ));
+        }
+        if !(1.0..=50.0).contains(&self.loudness_range) {
+            return Err(format!(
+                "The loudness range must be between 1 and 50 LU, got {}",
+                self.loudness_range
+            ));
+        }
+        Ok(())
+    }
+
+    fn targets(&self) -> String {
+        format!(
+            "loudnorm=I={}:TP={}:LRA={}",
+            self.target_lufs, self.true_peak_db, self.loudness_range
+        )
+    }
+}
+
+/// ffmpeg muxer writing `audio_format`, for output files whose extension doesn't say
+fn muxer(audio_format: AudioFormat) -> &'static str {
+    match audio_format {
+        AudioFormat::Mp3 => "mp3",
+        AudioFormat::Opus => "opus",
+        AudioFormat::M4a => "ipod",
+        AudioFormat::Flac => "flac",
+        AudioFormat::Ogg => "ogg",
+        AudioFormat::Wav => "wav",
+    }
+}
+
+/// The JSON block `loudnorm` prints at the end of its output
+fn parse_measurement(stderr: &str) -> Option<LoudnessMeasurement> {
+    let start = stderr.rfind('{')?;
+    let end = stderr[start..].find('}')? + start;
+    serde_json::from_str(&stderr[start..=end]).ok()
+}
+
+/// Sample rate of the input as ffmpeg reports it (`44100 Hz`), `loudnorm` otherwise
+/// upsamples to 192 kHz
+fn sample_rate(stderr: &str) -> Option<u32> {
+    let before = &stderr[..stderr.find(" Hz")?];
+    before
+        .rsplit(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Normalize the loudness of the track at `path` in place, keeping its tags and cover
+/// art. The track is re-encoded at `bitrate`.
+pub async fn normalize_loudness(
+    ffmpeg_cmd: &str,
+    path: &Path,
+    audio_format: AudioFormat,
+    bitrate: u32,
+    settings: &LoudnessNormalization,
+) -> Result<(), String> {
+    let measured = Command::new(ffmpeg_cmd)
+        .arg("-hide_banner")
+        .arg("-nostats")
+        .arg("-i")
+        .arg(path)
+        .arg("-vn")
+        .arg("-af")
+        .arg(format!("{}:print_format=json", settings.targets()))
+        .arg("-f")
+        .arg("null")
+        .arg("-")
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    let stderr = String::from_utf8_lossy(&measured.stderr);
+    if !measured.status.success() {
+        return Err(format!("Failed to measure the loudness: {}", stderr.trim()));
+    }
+    let measurement =
+        parse_measurement(&stderr).ok_or("ffmpeg did not report the measured loudness")?;
+    let rate = match audio_format {
+        AudioFormat::Opus => 48000,
+        _ => sample_rate(&stderr).unwrap_or(44100),
+    };
+
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or("Invalid track filename")?;
+    // Not named like a track, a parallel playlist item looking for its file would
+    // take it
+    let temp = path.with_file_name(format!("{}.loudnorm.tmp", stem));
+    let filter = format!(
+        "{}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true",
+        settings.targets(),
+        measurement.input_i,
+        measurement.input_tp,
+        measurement.input_lra,
+        measurement.input_thresh,
+        measurement.target_offset
+    );
+    let normalized = Command::new(ffmpeg_cmd)
+        .arg("-y")
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-i")
+        .arg(path)
+        .arg("-map")
+        .arg("0:a")
+        .arg("-map")
+        .arg("0:v?")
+        .arg("-c:v")
+        .arg("copy")
+        .arg("-map_metadata")
+        .arg("0")
+        .arg("-af")
+        .arg(filter)
+        .arg("-ar")
+        .arg(rate.to_string())
+        .args(audio_format.ffmpeg_args(bitrate, false))
+        .arg("-f")
+        .arg(muxer(audio_format))
+        .arg(&temp)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    if !normalized.status.success() {
+        std::fs::remove_file(&temp).ok();
+        return Err(format!(
+            "Failed to normalize the loudness: {}",
+            String::from_utf8_lossy(&normalized.stderr).trim()
+        ));
+    }
+    std::fs::rename(&temp, path).map_err(|e| {
+        std::fs::remove_file(&temp).ok();
+        format!("Failed to replace {}: {}", path.display(), e)
+    })
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::api::path::config_dir;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CookiesStatus {
+    pub imported: bool,
+    /// Last write of the file, yt-dlp saves refreshed cookies back after each download
+    pub updated_at: Option<String>,
+    pub cookie_count: usize,
+    /// Cookies for youtube.com and google.com, the ones that sign yt-dlp in
+    pub youtube_cookie_count: usize,
+    /// Whether downloads pass the cookies to yt-dlp
+    pub enabled: bool,
+}
+
+/// The imported cookies.txt, readable by the current user only
+pub fn get_cookies_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("youtube-downloader").join("cookies.txt"))
+}
+
+/// The imported cookies file, if there is one
+pub fn imported_cookies() -> Option<PathBuf> {
+    get_cookies_path().filter(|path| path.is_file())
+}
+
+/// Domains of the cookies in a Netscape cookies.txt, failing when it isn't one
+fn cookie_domains(content: &str) -> Result<Vec<&str>, String> {
+    let mut domains = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        // HttpOnly cookies are written as comments with this prefix
+        let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 7 {
+            return Err(format!(
+                "Line {} is not a cookie, export the cookies in the Netscape cookies.txt format",
+                number + 1
+            ));
+        }
+        domains.push(fields[0]);
+    }
+    if domains.is_empty() {
+        return Err("The file contains no cookies".to_string());
+    }
+    Ok(domains)
+}
+
+fn is_youtube_domain(domain: &str) -> bool {
+    let domain = domain.trim_start_matches('.');
+    ["youtube.com", "google.com"]
+        .iter()
+        .any(|d| domain == *d || domain.ends_with(&format!(".{}", d)))
+}
+
+/// Write the cookies so only the current user can read them
+fn write_private(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    // An existing file keeps its mode, so tighten it as well
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(content.as_bytes())?;
+    file.sync_all()
+}
+
+/// Copy a cookies.txt exported from a browser into the app config folder
+pub fn import(source: &Path, enabled: bool) -> Result<CookiesStatus, String> {
+    let content = fs::read_to_string(source)
+        .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+    let domains = cookie_domains(&content)?;
+    if !domains.iter().any(|d| is_youtube_domain(d)) {
+        return Err(
+            "The file has no YouTube cookies, export them while signed in to YouTube".to_string(),
+        );
+    }
+
+    let path = get_cookies_path().ok_or("Failed to get config directory")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    write_private(&path, &content).map_err(|e| format!("Failed to save the cookies: {}", e))?;
+    Ok(status(enabled))
+}
+
+/// Delete the imported cookies
+pub fn remove() -> Result<(), String> {
+    match imported_cookies() {
+        Some(path) => {
+            fs::remove_file(path).map_err(|e| format!("Failed to delete the cookies: {}", e))
+        }
+        None => Ok(()),
+    }
+}
+
+pub fn status(enabled: bool) -> CookiesStatus {
+    let path = imported_cookies();
+    let updated_at = path
+        .as_ref()
+        .and_then(|p| fs::metadata(p).ok())
+        .and_then(|m| m.modified().ok())
+        .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339());
+    let content = path.and_then(|p| fs::read_to_string(p).ok());
+    let domains = content
+        .as_deref()
+        .and_then(|c| cookie_domains(c).ok())
+        .unwrap_or_default();
+    CookiesStatus {
+        imported: content.is_some(),
+        updated_at,
+        cookie_count: domains.len(),
+        youtube_cookie_count: domains.iter().filter(|d| is_youtube_domain(d)).count(),
+        enabled,
+    }
+}
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::backtrace::Backtrace;
+use std::fs;
+use std::panic::PanicHookInfo;
+use std::path::PathBuf;
+use tauri::api::path::config_dir;
+
+/// Number of crash reports kept on disk, oldest are removed first
+const MAX_CRASH_REPORTS: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub timestamp: String,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub thread: Option<String>,
+    pub message: String,
+    pub location: Option<String>,
+    pub backtrace: String,
+}
+
+pub(crate) fn get_crash_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("youtube-downloader").join("crashes"))
+}
+
+/// Install a panic hook that writes a crash report with a backtrace before the
+/// default hook runs, so the next launch can offer it for a bug report
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        let report = build_report(info);
+        if let Err(e) = write_report(&report) {
+            eprintln!("[crash] Failed to write crash report: {}", e);
+        }
+        default_hook(info);
+    }));
+}
+
+fn build_report(info: &PanicHookInfo) -> CrashReport {
+    let message = if let Some(s) = info.payload().downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = info.payload().downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "Unknown panic payload".to_string()
+    };
+
+    CrashReport {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        thread: std::thread::current().name().map(|n| n.to_string()),
+        message,
+        location: info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+        backtrace: Backtrace::force_capture().to_string(),
+    }
+}
+
+fn write_report(report: &CrashReport) -> Result<(), String> {
+    let crash_dir = get_crash_dir().ok_or("Failed to get config directory")?;
+    fs::create_dir_all(&crash_dir).map_err(|e| e.to_string())?;
+
+    let file_name = format!(
+        "crash-{}.json",
+        chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ")
+    );
+    let content = serde_json::to_string_pretty(report)
+        .map_err(|e| format!("Failed to serialize crash report: {}", e))?;
+    fs::write(crash_dir.join(file_name), content).map_err(|e| e.to_string())?;
+
+    let mut reports = list_report_files();
+    while reports.len() > MAX_CRASH_REPORTS {
+        fs::remove_file(reports.remove(0)).ok();
+    }
+
+    Ok(())
+}
+
+/// Crash report files sorted oldest first (the names embed the timestamp)
+fn list_report_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = get_crash_dir()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("json"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+/// Load the most recent crash report, if the app has crashed before
+pub fn load_last_report() -> Option<CrashReport> {
+    let path = list_report_files().pop()?;
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Remove all stored crash reports
+pub fn clear_reports() -> Result<(), String> {
+    for path in list_report_files() {
+        fs::remove_file(&path)
+            .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, GlobalWindowEvent, Manager, Runtime, WindowEvent};
+use tokio::sync::oneshot;
+
+use crate::download;
+use crate::events::{self, Event};
+use crate::queue;
+
+/// How long a decision waits for an answer before the default choice applies, so a UI
+/// that doesn't handle decisions only delays things
+const DECISION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// What the backend needs the user to decide
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionKind {
+    /// The output file of a download already exists
+    Overwrite,
+    /// A playlist has more items than the confirmation threshold
+    PlaylistSize,
+    /// The window is being closed while downloads are running
+    ExitWithActiveDownloads,
+}
+
+/// Sent as a `ytmp3://user-decision-required/v2` event, answered with `submit_decision`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionRequest {
+    pub token: String,
+    pub kind: DecisionKind,
+    pub message: String,
+    pub choices: Vec<String>,
+    /// Applied when no answer arrives within `timeout_seconds`
+    pub default_choice: String,
+    pub timeout_seconds: u64,
+    /// Kind-specific context, such as the existing file or the playlist estimate
+    pub details: serde_json::Value,
+}
+
+struct PendingDecision {
+    choices: Vec<String>,
+    answer: oneshot::Sender<String>,
+}
+
+static PENDING: Mutex<Option<HashMap<String, PendingDecision>>> = Mutex::new(None);
+
+/// Whether the exit question is already on screen, so closing again doesn't ask twice
+static EXIT_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Ask the UI to pick one of `choices` and wait for the answer. Returns `None` when
+/// nothing answered in time, leaving the caller to apply `default_choice`.
+pub async fn request<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    kind: DecisionKind,
+    message: String,
+    choices: &[&str],
+    default_choice: &str,
+    details: serde_json::Value,
+) -> Option<String> {
+    let token = queue::new_id("decision");
+    let (answer, receiver) = oneshot::channel();
+    let choices: Vec<String> = choices.iter().map(|c| c.to_string()).collect();
+    PENDING
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(
+            token.clone(),
+            PendingDecision {
+                choices: choices.clone(),
+                answer,
+            },
+        );
+
+    let request = DecisionRequest {
+        token: token.clone(),
+        kind,
+        message,
+        choices,
+        default_choice: default_choice.to_string(),
+        timeout_seconds: DECISION_TIMEOUT.as_secs(),
+        details,
+    };
+    events::emit(app_handle, Event::UserDecisionRequired, &request);
+
+    let choice = tokio::time::timeout(DECISION_TIMEOUT, receiver)
+        .await
+        .ok()
+        .and_then(|answer| answer.ok());
+    if let Some(pending) = PENDING.lock().unwrap().as_mut() {
+        pending.remove(&token);
+    }
+    choice
+}
+
+/// Answer a pending decision with one of the choices it offered
+pub fn submit(token: &str, choice: &str) -> Result<(), String> {
+    let mut pending = PENDING.lock().unwrap();
+    let decision = pending
+        .as_mut()
+        .and_then(|pending| pending.remove(token))
+        .ok_or("No decision is waiting for this token, it may have timed out")?;
+    if !decision.choices.iter().any(|c| c == choice) {
+        let expected = decision.choices.join(", ");
+        pending
+            .get_or_insert_with(HashMap::new)
+            .insert(token.to_string(), decision);
+        return Err(format!(
+            "Invalid choice '{}', expected one of: {}",
+            choice, expected
+        ));
+    }
+    decision
+        .answer
+        .send(choice.to_string())
+        .map_err(|_| "The decision is no longer waiting for an answer".to_string())
+}
+
+/// Keep the window open while downloads run and ask whether to quit anyway. Quitting
+/// cancels the downloads first, so their partial files are cleaned up.
+pub fn handle_window_event<R: Runtime>(event: &GlobalWindowEvent<R>) {
+    let WindowEvent::CloseRequested { api, .. } = event.event() else {
+        return;
+    };
+    let active = download::active_downloads();
+    if active == 0 {
+        return;
+    }
+    api.prevent_close();
+    if EXIT_PENDING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let app_handle = event.window().app_handle();
+    tauri::async_runtime::spawn(async move {
+        let choice = request(
+            &app_handle,
+            DecisionKind::ExitWithActiveDownloads,
+            format!(
+                "{} download{} still running. Quit and cancel {}?",
+                active,
+                if active == 1 { " is" } else { "s are" },
+                if active == 1 { "it" } else { "them" }
+            ),
+            &["quit", "keep_running"],
+            "quit",
+            serde_json::json!({ "active_downloads": active }),
+        )
+        .await;
+        EXIT_PENDING.store(false, Ordering::SeqCst);
+        if choice.as_deref() != Some("keep_running") {
+            download::cancel_downloads(None);
+            // Give the cancelled downloads a moment to stop and clean up after themselves
+            for _ in 0..50 {
+                if download::active_downloads() == 0 {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            app_handle.exit(0);
+        }
+    });
+}
@@ -1,12 +1,15 @@
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
-use tauri::AppHandle;
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Runtime};
 
 static EXTRACTION_LOCK: Mutex<()> = Mutex::new(());
 
-fn get_platform() -> &'static str {
+pub(crate) fn get_platform() -> &'static str {
     if cfg!(target_os = "windows") {
         "windows"
     } else if cfg!(target_os = "macos") {
@@ -16,17 +19,19 @@ fn get_platform() -> &'static str {
     }
 }
 
-fn get_arch() -> &'static str {
+pub(crate) fn get_arch() -> &'static str {
     if cfg!(target_arch = "x86_64") {
         "x64"
     } else if cfg!(target_arch = "aarch64") {
         "arm64"
+    } else if cfg!(target_arch = "arm") {
+        "armv7"
     } else {
         "x64"
     }
 }
 
-fn get_binary_name(binary: &str) -> String {
+pub(crate) fn get_binary_name(binary: &str) -> String {
     if cfg!(target_os = "windows") {
         format!("{}.exe", binary)
     } else {
@@ -34,7 +39,10 @@ fn get_binary_name(binary: &str) -> String {
     }
 }
 
-fn get_bundled_binary_path(app_handle: &AppHandle, binary: &str) -> Result<PathBuf> {
+pub(crate) fn get_bundled_binary_path<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    binary: &str,
+) -> Result<PathBuf> {
     let platform = get_platform();
     let arch = get_arch();
     let binary_name = get_binary_name(binary);
@@ -65,7 +73,7 @@ fn get_bundled_binary_path(app_handle: &AppHandle, binary: &str) -> Result<PathB
     }
 }
 
-fn get_app_bin_dir(app_handle: &AppHandle) -> Result<PathBuf> {
+pub(crate) fn get_app_bin_dir<R: Runtime>(app_handle: &AppHandle<R>) -> Result<PathBuf> {
     let app_data_dir = app_handle
         .path_resolver()
         .app_data_dir()
@@ -75,12 +83,185 @@ fn get_app_bin_dir(app_handle: &AppHandle) -> Result<PathBuf> {
     Ok(bin_dir)
 }
 
-fn get_extracted_binary_path(app_handle: &AppHandle, binary: &str) -> Result<PathBuf> {
+fn get_extracted_binary_path<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    binary: &str,
+) -> Result<PathBuf> {
     let bin_dir = get_app_bin_dir(app_handle)?;
     let binary_name = get_binary_name(binary);
     Ok(bin_dir.join(&binary_name))
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FileFingerprint {
+    modified_ms: u128,
+    size: u64,
+}
+
+/// Fingerprint of an extracted binary that was last verified to run, together with
+/// the bundled source it came from so an app update invalidates it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct KnownGoodBinary {
+    path: PathBuf,
+    fingerprint: FileFingerprint,
+    source: Option<FileFingerprint>,
+}
+
+fn get_known_good_path<R: Runtime>(app_handle: &AppHandle<R>) -> Result<PathBuf> {
+    Ok(get_app_bin_dir(app_handle)?.join("known-good.json"))
+}
+
+fn load_known_good<R: Runtime>(app_handle: &AppHandle<R>) -> HashMap<String, KnownGoodBinary> {
+    get_known_good_path(app_handle)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_known_good<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    known_good: &HashMap<String, KnownGoodBinary>,
+) {
+    let saved = get_known_good_path(app_handle).and_then(|known_good_path| {
+        let content = serde_json::to_string_pretty(known_good)?;
+        fs::write(known_good_path, content)?;
+        Ok(())
+    });
+    if let Err(e) = saved {
+        eprintln!("[deps] Failed to save known-good binary cache: {}", e);
+    }
+}
+
+fn fingerprint(path: &Path) -> Option<FileFingerprint> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified_ms = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_millis();
+    Some(FileFingerprint {
+        modified_ms,
+        size: metadata.len(),
+    })
+}
+
+/// Remember that the binary at `path` runs, keyed by its mtime and size
+fn record_known_good<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    binary: &str,
+    path: &Path,
+    source: Option<&Path>,
+) {
+    let Some(fingerprint) = fingerprint(path) else {
+        return;
+    };
+    let mut known_good = load_known_good(app_handle);
+    known_good.insert(
+        binary.to_string(),
+        KnownGoodBinary {
+            path: path.to_path_buf(),
+            fingerprint,
+            source: source.and_then(self::fingerprint),
+        },
+    );
+    save_known_good(app_handle, &known_good);
+}
+
+fn forget_known_good<R: Runtime>(app_handle: &AppHandle<R>, binary: &str) {
+    let mut known_good = load_known_good(app_handle);
+    if known_good.remove(binary).is_some() {
+        save_known_good(app_handle, &known_good);
+    }
+}
+
+/// Check whether `path` and its bundled source still match the last verified fingerprints
+fn is_known_good<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    binary: &str,
+    path: &Path,
+    source: Option<&Path>,
+) -> bool {
+    let known_good = load_known_good(app_handle);
+    let Some(known) = known_good.get(binary) else {
+        return false;
+    };
+    known.path == path
+        && fingerprint(path).as_ref() == Some(&known.fingerprint)
+        && source.and_then(fingerprint) == known.source
+}
+
+/// Remove leftovers of interrupted extractions and known-good entries that no longer
+/// match the file on disk. Returns the number of entries and files removed.
+pub fn prune_caches<R: Runtime>(app_handle: &AppHandle<R>) -> Result<usize> {
+    let _lock = EXTRACTION_LOCK.lock().unwrap();
+    let bin_dir = get_app_bin_dir(app_handle)?;
+    let mut removed = 0;
+
+    if let Ok(entries) = fs::read_dir(&bin_dir) {
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.extension().and_then(|s| s.to_str()) == Some("tmp")
+                && fs::remove_file(&path).is_ok()
+            {
+                eprintln!("[deps] Removed stale temp file {}", path.display());
+                removed += 1;
+            }
+        }
+    }
+
+    let mut known_good = load_known_good(app_handle);
+    let before = known_good.len();
+    known_good.retain(|_, known| fingerprint(&known.path).as_ref() == Some(&known.fingerprint));
+    if known_good.len() != before {
+        removed += before - known_good.len();
+        save_known_good(app_handle, &known_good);
+    }
+
+    Ok(removed)
+}
+
+/// SHA-256 of a file's contents as a hex string
+pub(crate) fn hash_file(path: &Path) -> Result<String> {
+    use sha2::{Digest, Sha256};
+    use std::io::Read;
+
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("Failed to open {} for hashing", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Hash of the bundled source, stored next to the extracted binary
+fn get_source_hash_path(extracted_path: &Path) -> PathBuf {
+    let mut file_name = extracted_path
+        .file_name()
+        .unwrap_or_default()
+        .to_os_string();
+    file_name.push(".sha256");
+    extracted_path.with_file_name(file_name)
+}
+
+/// Check whether the extracted binary is a byte-for-byte copy of the bundled source,
+/// using the stored source hash. Returns `None` when no hash was stored yet.
+fn matches_source_hash(extracted_path: &Path, bundled_path: &Path) -> Option<bool> {
+    let stored = fs::read_to_string(get_source_hash_path(extracted_path)).ok()?;
+    let stored = stored.trim();
+    let source_hash = hash_file(bundled_path).ok()?;
+    if stored != source_hash {
+        return Some(false);
+    }
+    Some(hash_file(extracted_path).ok()? == source_hash)
+}
+
 fn copy_binary_atomic(source: &Path, dest: &Path) -> Result<()> {
     let parent = dest
         .parent()
@@ -109,12 +290,40 @@ fn copy_binary_atomic(source: &Path, dest: &Path) -> Result<()> {
     Ok(())
 }
 
-fn extract_binary(app_handle: &AppHandle, binary: &str) -> Result<PathBuf> {
+fn extract_binary<R: Runtime>(app_handle: &AppHandle<R>, binary: &str) -> Result<PathBuf> {
     let _lock = EXTRACTION_LOCK.lock().unwrap();
 
     let extracted_path = get_extracted_binary_path(app_handle, binary)?;
     eprintln!("[deps] Extracted binary path: {:?}", extracted_path);
 
+    let source_path = get_bundled_binary_path(app_handle, binary)
+        .ok()
+        .filter(|p| p.exists());
+
+    if extracted_path.exists()
+        && is_known_good(app_handle, binary, &extracted_path, source_path.as_deref())
+    {
+        eprintln!("[deps] Extracted binary matches last known good copy, skipping verification");
+        return Ok(extracted_path);
+    }
+
+    if let Some(ref source) = source_path {
+        match matches_source_hash(&extracted_path, source) {
+            Some(true) => {
+                eprintln!(
+                    "[deps] Extracted binary matches bundled source hash, skipping verification"
+                );
+                record_known_good(app_handle, binary, &extracted_path, Some(source));
+                return Ok(extracted_path);
+            }
+            Some(false) => {
+                eprintln!("[deps] Extracted binary differs from bundled source, re-extracting...");
+                fs::remove_file(&extracted_path).ok();
+            }
+            None => {}
+        }
+    }
+
     if extracted_path.exists() {
         eprintln!("[deps] Extracted binary already exists, verifying...");
 
@@ -137,6 +346,7 @@ fn extract_binary(app_handle: &AppHandle, binary: &str) -> Result<PathBuf> {
 
             if result.is_ok() && result.as_ref().unwrap().status.success() {
                 eprintln!("[deps] Extracted binary is valid, using existing copy");
+                record_known_good(app_handle, binary, &extracted_path, source_path.as_deref());
                 return Ok(extracted_path);
             }
 
@@ -175,14 +385,317 @@ fn extract_binary(app_handle: &AppHandle, binary: &str) -> Result<PathBuf> {
     copy_binary_atomic(&bundled_path, &extracted_path)
         .context(format!("Failed to extract binary: {}", binary))?;
 
+    match hash_file(&bundled_path) {
+        Ok(source_hash) => {
+            fs::write(get_source_hash_path(&extracted_path), source_hash).ok();
+        }
+        Err(e) => eprintln!("[deps] Failed to hash bundled binary: {}", e),
+    }
+
     eprintln!("[deps] Successfully extracted binary: {}", binary);
+    record_known_good(app_handle, binary, &extracted_path, Some(&bundled_path));
+    record_manifest(
+        app_handle,
+        binary,
+        &extracted_path,
+        BinaryOrigin::Bundle,
+        &bundled_path.to_string_lossy(),
+        binary_version(&extracted_path, binary),
+    );
     Ok(extracted_path)
 }
 
-pub fn get_bundled_binary(app_handle: &AppHandle, binary: &str) -> Result<PathBuf> {
+/// Remove the extracted copy of `binary` and its cached hashes, then extract it again
+pub fn reextract_binary<R: Runtime>(app_handle: &AppHandle<R>, binary: &str) -> Result<PathBuf> {
+    {
+        let _lock = EXTRACTION_LOCK.lock().unwrap();
+        let extracted_path = get_extracted_binary_path(app_handle, binary)?;
+        fs::remove_file(get_source_hash_path(&extracted_path)).ok();
+        if extracted_path.exists() {
+            fs::remove_file(&extracted_path)
+                .with_context(|| format!("Failed to remove {}", extracted_path.display()))?;
+        }
+        forget_known_good(app_handle, binary);
+    }
     extract_binary(app_handle, binary)
 }
 
+/// Whether the app bundle ships a usable copy of `binary` for this platform, rather
+/// than nothing or an empty placeholder
+pub(crate) fn has_bundled_binary<R: Runtime>(app_handle: &AppHandle<R>, binary: &str) -> bool {
+    get_bundled_binary_path(app_handle, binary)
+        .ok()
+        .and_then(|path| fs::metadata(path).ok())
+        .is_some_and(|metadata| metadata.len() > 0)
+}
+
+/// Path of the managed copy of `binary`, whether extracted from the bundle or downloaded
+pub(crate) fn managed_binary_path<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    binary: &str,
+) -> Result<PathBuf> {
+    get_extracted_binary_path(app_handle, binary)
+}
+
+/// Where a managed binary came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryOrigin {
+    Bundle,
+    Download,
+    /// Installed from a file the user picked
+    File,
+    /// Copied from a yt-dlp/ffmpeg the user pointed at, e.g. one installed by a package
+    /// manager
+    Designated,
+}
+
+/// What a managed binary looked like when it was installed, to detect later changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ManifestEntry {
+    pub sha256: String,
+    pub size: u64,
+    pub version: Option<String>,
+    pub origin: BinaryOrigin,
+    /// Bundled path, download URL or picked file the binary was installed from
+    pub source: String,
+    pub installed_at: String,
+}
+
+fn get_manifest_path<R: Runtime>(app_handle: &AppHandle<R>) -> Result<PathBuf> {
+    Ok(get_app_bin_dir(app_handle)?.join("manifest.json"))
+}
+
+pub(crate) fn load_manifest<R: Runtime>(
+    app_handle: &AppHandle<R>,
+) -> HashMap<String, ManifestEntry> {
+    get_manifest_path(app_handle)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn record_manifest<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    binary: &str,
+    path: &Path,
+    origin: BinaryOrigin,
+    source: &str,
+    version: Option<String>,
+) {
+    let recorded = (|| -> Result<()> {
+        let entry = ManifestEntry {
+            sha256: hash_file(path)?,
+            size: fs::metadata(path)?.len(),
+            version,
+            origin,
+            source: source.to_string(),
+            installed_at: chrono::Utc::now().to_rfc3339(),
+        };
+        let mut manifest = load_manifest(app_handle);
+        manifest.insert(binary.to_string(), entry);
+        fs::write(
+            get_manifest_path(app_handle)?,
+            serde_json::to_string_pretty(&manifest)?,
+        )?;
+        Ok(())
+    })();
+    if let Err(e) = recorded {
+        eprintln!("[deps] Failed to record {} in the manifest: {}", binary, e);
+    }
+}
+
+/// First line of the binary's version output, `None` if it does not run
+pub(crate) fn binary_version(path: &Path, binary: &str) -> Option<String> {
+    let output = std::process::Command::new(path)
+        .arg(if binary == "ffmpeg" {
+            "-version"
+        } else {
+            "--version"
+        })
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+}
+
+/// Version number of the managed `binary`, e.g. `2024.08.06` for yt-dlp or `6.1.1` for
+/// ffmpeg. Taken from the manifest when it has one, so no binary has to be started.
+pub(crate) fn installed_version<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    binary: &str,
+) -> Option<String> {
+    let line = match load_manifest(app_handle)
+        .remove(binary)
+        .and_then(|entry| entry.version)
+    {
+        Some(line) => line,
+        None => binary_version(&get_bundled_binary(app_handle, binary).ok()?, binary)?,
+    };
+    let line = line.strip_prefix("ffmpeg version").unwrap_or(&line);
+    line.split_whitespace().next().map(|v| v.to_string())
+}
+
+/// Install a binary obtained outside the bundle as the managed copy, after checking
+/// that it runs and identifies itself as `binary`. `origin_source` is the URL or file
+/// it came from.
+pub(crate) fn install_binary<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    binary: &str,
+    source: &Path,
+    origin: BinaryOrigin,
+    origin_source: &str,
+) -> Result<PathBuf> {
+    let _lock = EXTRACTION_LOCK.lock().unwrap();
+    let extracted_path = get_extracted_binary_path(app_handle, binary)?;
+
+    let staged = extracted_path.with_file_name(format!("{}.new.tmp", get_binary_name(binary)));
+    copy_binary_atomic(source, &staged)?;
+    let Some(version) = binary_version(&staged, binary) else {
+        fs::remove_file(&staged).ok();
+        anyhow::bail!("{} does not run on this system", source.display());
+    };
+    // Guard against picking the wrong file, e.g. ffmpeg installed as yt-dlp
+    let identified = if binary == "ffmpeg" {
+        version.starts_with("ffmpeg version")
+    } else {
+        version.starts_with(|c: char| c.is_ascii_digit())
+    };
+    if !identified {
+        fs::remove_file(&staged).ok();
+        anyhow::bail!("{} is not a {} binary", source.display(), binary);
+    }
+
+    // Not a copy of the bundle, so there is no source hash to compare against
+    fs::remove_file(get_source_hash_path(&extracted_path)).ok();
+    fs::rename(&staged, &extracted_path)
+        .with_context(|| format!("Failed to install {}", extracted_path.display()))?;
+    record_known_good(app_handle, binary, &extracted_path, None);
+    record_manifest(
+        app_handle,
+        binary,
+        &extracted_path,
+        origin,
+        origin_source,
+        Some(version),
+    );
+    if binary == "yt-dlp" {
+        reset_impersonate_targets();
+    }
+
+    eprintln!("[deps] Installed {} at {:?}", binary, extracted_path);
+    Ok(extracted_path)
+}
+
+pub fn get_bundled_binary<R: Runtime>(app_handle: &AppHandle<R>, binary: &str) -> Result<PathBuf> {
+    #[cfg(test)]
+    if let Some(path) = TEST_BINARY.lock().unwrap().clone() {
+        return Ok(path);
+    }
+    extract_binary(app_handle, binary)
+}
+
+/// Stand-in used for every binary by the integration tests, instead of the bundle
+#[cfg(test)]
+static TEST_BINARY: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+#[cfg(test)]
+pub(crate) fn use_test_binary(path: PathBuf) {
+    *TEST_BINARY.lock().unwrap() = Some(path);
+}
+
+/// Extract and verify the bundled binaries in the background after launch, so app
+/// startup never waits on it and the first download finds them ready
+pub fn warm_up<R: Runtime>(app_handle: AppHandle<R>) {
+    tauri::async_runtime::spawn_blocking(move || {
+        for binary in ["yt-dlp", "ffmpeg"] {
+            if let Err(e) = get_bundled_binary(&app_handle, binary) {
+                eprintln!("[deps] Warm-up failed for {}: {}", binary, e);
+            }
+        }
+        eprintln!("[deps] Warm-up complete");
+    });
+}
+
+/// Impersonation targets supported by the extracted yt-dlp build, probed once per session
+static IMPERSONATE_TARGETS: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// List the `--impersonate` targets the managed yt-dlp build can actually use.
+/// Only builds shipping curl_cffi report available targets; others return an empty list.
+pub fn get_impersonate_targets<R: Runtime>(app_handle: &AppHandle<R>) -> Vec<String> {
+    if let Some(targets) = IMPERSONATE_TARGETS.lock().unwrap().as_ref() {
+        return targets.clone();
+    }
+
+    let targets = match get_bundled_binary(app_handle, "yt-dlp") {
+        Ok(path) => match std::process::Command::new(&path)
+            .arg("--list-impersonate-targets")
+            .output()
+        {
+            Ok(output) if output.status.success() => {
+                parse_impersonate_targets(&String::from_utf8_lossy(&output.stdout))
+            }
+            Ok(_) => {
+                eprintln!("[deps] yt-dlp does not support --list-impersonate-targets");
+                Vec::new()
+            }
+            Err(e) => {
+                eprintln!("[deps] Failed to list impersonate targets: {}", e);
+                Vec::new()
+            }
+        },
+        Err(e) => {
+            eprintln!("[deps] ERROR: Failed to extract yt-dlp: {}", e);
+            Vec::new()
+        }
+    };
+
+    *IMPERSONATE_TARGETS.lock().unwrap() = Some(targets.clone());
+    targets
+}
+
+/// Forget the probed impersonation targets, e.g. after the extracted binaries are removed
+pub fn reset_impersonate_targets() {
+    *IMPERSONATE_TARGETS.lock().unwrap() = None;
+}
+
+/// Check whether the managed yt-dlp build can impersonate the given target
+pub fn supports_impersonate_target<R: Runtime>(app_handle: &AppHandle<R>, target: &str) -> bool {
+    let client = target.split(':').next().unwrap_or(target).to_lowercase();
+    get_impersonate_targets(app_handle)
+        .iter()
+        .any(|t| *t == client || client.starts_with(&format!("{}-", t)))
+}
+
+fn parse_impersonate_targets(output: &str) -> Vec<String> {
+    let mut targets: Vec<String> = Vec::new();
+
+    for line in output.lines() {
+        let line = line.trim();
+        if line.is_empty()
+            || line.starts_with('[')
+            || line.starts_with('-')
+            || line.starts_with("Client")
+            || line.contains("(unavailable)")
+        {
+            continue;
+        }
+
+        if let Some(client) = line.split_whitespace().next() {
+            let client = client.to_lowercase();
+            if !targets.contains(&client) {
+                targets.push(client);
+            }
+        }
+    }
+
+    targets
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct DepsCheckResult {
     pub ytdlp_path: Option<String>,
@@ -191,9 +704,10 @@ pub struct DepsCheckResult {
     pub ffmpeg_version: Option<String>,
     pub ytdlp_error: Option<String>,
     pub ffmpeg_error: Option<String>,
+    pub ytdlp_impersonate_targets: Vec<String>,
 }
 
-pub fn check_deps(app_handle: &AppHandle) -> DepsCheckResult {
+pub fn check_deps<R: Runtime>(app_handle: &AppHandle<R>) -> DepsCheckResult {
     eprintln!("[deps] Checking dependencies...");
     let mut result = DepsCheckResult {
         ytdlp_path: None,
@@ -202,6 +716,7 @@ pub fn check_deps(app_handle: &AppHandle) -> DepsCheckResult {
         ffmpeg_version: None,
         ytdlp_error: None,
         ffmpeg_error: None,
+        ytdlp_impersonate_targets: Vec::new(),
     };
 
     eprintln!("[deps] Checking yt-dlp...");
@@ -231,6 +746,10 @@ pub fn check_deps(app_handle: &AppHandle) -> DepsCheckResult {
         }
     }
 
+    if result.ytdlp_version.is_some() {
+        result.ytdlp_impersonate_targets = get_impersonate_targets(app_handle);
+    }
+
     eprintln!("[deps] Checking ffmpeg...");
     match get_bundled_binary(app_handle, "ffmpeg") {
         Ok(path) => {
@@ -261,3 +780,353 @@ pub fn check_deps(app_handle: &AppHandle) -> DepsCheckResult {
     eprintln!("[deps] Dependency check complete");
     result
 }
+
+/// Why a yt-dlp found on the system PATH failed to run
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpawnErrorKind {
+    NotFound,
+    PermissionDenied,
+    /// The script's interpreter (usually Python) is missing
+    BadInterpreter,
+    ExecFormat,
+    /// Python starts but the yt-dlp package is missing or broken
+    MissingPythonModule,
+    NonZeroExit,
+    Other,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct SystemYtdlpReport {
+    pub path: String,
+    /// The file is a Python script/entry-point shim rather than a standalone binary
+    pub is_python_shim: bool,
+    pub version: Option<String>,
+    pub error_kind: Option<SpawnErrorKind>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct EnvironmentReport {
+    pub system_ytdlp: Option<SystemYtdlpReport>,
+    pub gui_path: Vec<String>,
+    pub login_shell_path: Option<Vec<String>>,
+    /// Directories on the login shell PATH that the GUI-launched app doesn't see
+    pub missing_from_gui_path: Vec<String>,
+    pub managed_ytdlp_path: Option<String>,
+    /// Whether downloads use the managed binary instead of the system one
+    pub prefers_managed: bool,
+    pub summary: String,
+}
+
+fn split_path(path: &str) -> Vec<String> {
+    std::env::split_paths(path)
+        .map(|p| p.to_string_lossy().to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+/// PATH handed to child processes, built once by `bootstrap_environment`
+static CHILD_PATH: Mutex<Option<std::ffi::OsString>> = Mutex::new(None);
+
+/// Directories where package managers commonly install yt-dlp/ffmpeg but which
+/// GUI-launched apps usually don't have on PATH
+fn standard_bin_dirs() -> Vec<String> {
+    let mut dirs: Vec<String> = Vec::new();
+    if cfg!(target_os = "macos") {
+        dirs.extend(["/opt/homebrew/bin", "/opt/local/bin"].map(String::from));
+    }
+    if cfg!(target_os = "linux") {
+        dirs.extend(["/home/linuxbrew/.linuxbrew/bin", "/snap/bin"].map(String::from));
+    }
+    if cfg!(unix) {
+        dirs.extend(["/usr/local/bin", "/usr/bin", "/bin"].map(String::from));
+        if let Some(home) = tauri::api::path::home_dir() {
+            dirs.push(
+                home.join(".local")
+                    .join("bin")
+                    .to_string_lossy()
+                    .to_string(),
+            );
+        }
+    }
+    dirs
+}
+
+/// Build the PATH used for child processes: the app's own PATH plus standard install
+/// locations, and optionally the directories from the user's login shell profile
+pub fn bootstrap_environment(include_shell_profile: bool) {
+    let mut dirs = split_path(&std::env::var("PATH").unwrap_or_default());
+
+    let mut extra = standard_bin_dirs();
+    if include_shell_profile {
+        extra.extend(get_login_shell_path().unwrap_or_default());
+    }
+    for dir in extra {
+        if !dirs.contains(&dir) && Path::new(&dir).is_dir() {
+            dirs.push(dir);
+        }
+    }
+
+    match std::env::join_paths(&dirs) {
+        Ok(path) => {
+            eprintln!("[deps] Child process PATH: {}", path.to_string_lossy());
+            *CHILD_PATH.lock().unwrap() = Some(path);
+        }
+        Err(e) => eprintln!("[deps] Failed to build child process PATH: {}", e),
+    }
+}
+
+/// PATH for spawned yt-dlp/ffmpeg processes, bootstrapped on first use if needed
+pub fn child_path() -> std::ffi::OsString {
+    if CHILD_PATH.lock().unwrap().is_none() {
+        bootstrap_environment(false);
+    }
+    CHILD_PATH
+        .lock()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| std::env::var_os("PATH").unwrap_or_default())
+}
+
+/// Variables passed through to yt-dlp as they are, everything else is dropped
+const PASSTHROUGH_ENV: &[&str] = &[
+    // Windows needs these to start Python and resolve executables
+    "SYSTEMROOT",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+    "NUMBER_OF_PROCESSORS",
+    "PROCESSOR_ARCHITECTURE",
+    "USER",
+    "USERNAME",
+    "LOGNAME",
+    "TZ",
+];
+
+/// App-managed home, temp and cache directories for yt-dlp
+fn get_ytdlp_state_dir() -> Option<PathBuf> {
+    tauri::api::path::cache_dir().map(|dir| dir.join("youtube-downloader").join("yt-dlp"))
+}
+
+/// Directory passed to yt-dlp as `--cache-dir`
+pub fn ytdlp_cache_dir() -> Option<PathBuf> {
+    get_ytdlp_state_dir().map(|dir| dir.join("cache"))
+}
+
+/// Environment for spawned yt-dlp processes. Proxies, config locations and other shell
+/// settings of the user are left out, and home and temp point at app-managed folders,
+/// so downloads behave the same however the app was started.
+pub fn isolated_env() -> Vec<(String, std::ffi::OsString)> {
+    let mut env: Vec<(String, std::ffi::OsString)> = PASSTHROUGH_ENV
+        .iter()
+        .filter_map(|name| Some((name.to_string(), std::env::var_os(name)?)))
+        .collect();
+    env.push(("PATH".to_string(), child_path()));
+
+    let Some(state_dir) = get_ytdlp_state_dir() else {
+        return env;
+    };
+    let (home, tmp) = (state_dir.join("home"), state_dir.join("tmp"));
+    for dir in [&home, &tmp] {
+        if let Err(e) = fs::create_dir_all(dir) {
+            eprintln!("[deps] Failed to create {}: {}", dir.display(), e);
+        }
+    }
+    for name in [
+        "HOME",
+        "USERPROFILE",
+        "APPDATA",
+        "LOCALAPPDATA",
+        "XDG_CONFIG_HOME",
+        "XDG_CACHE_HOME",
+    ] {
+        env.push((name.to_string(), home.clone().into_os_string()));
+    }
+    for name in ["TMPDIR", "TEMP", "TMP"] {
+        env.push((name.to_string(), tmp.clone().into_os_string()));
+    }
+    env
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .map(|path| {
+                    if path.is_dir() {
+                        dir_size(&path)
+                    } else {
+                        fs::metadata(&path).map(|m| m.len()).unwrap_or(0)
+                    }
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Delete yt-dlp's cache and temp files, returning the number of bytes freed
+pub fn clear_ytdlp_cache() -> Result<u64> {
+    let Some(state_dir) = get_ytdlp_state_dir() else {
+        return Ok(0);
+    };
+    let mut freed = 0;
+    for dir in [state_dir.join("cache"), state_dir.join("tmp")] {
+        if !dir.exists() {
+            continue;
+        }
+        freed += dir_size(&dir);
+        fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+    }
+    Ok(freed)
+}
+
+/// PATH as seen by the user's login shell, which is what a terminal session gets
+fn get_login_shell_path() -> Option<Vec<String>> {
+    if cfg!(target_os = "windows") {
+        return None;
+    }
+
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+    let output = std::process::Command::new(shell)
+        .args(["-l", "-c", "printf %s \"$PATH\""])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+    Some(split_path(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+fn find_in_dirs(dirs: &[String], binary: &str) -> Option<PathBuf> {
+    let binary_name = get_binary_name(binary);
+    dirs.iter()
+        .map(|dir| Path::new(dir).join(&binary_name))
+        .find(|candidate| candidate.is_file())
+}
+
+fn is_python_shim(path: &Path) -> bool {
+    use std::io::Read;
+
+    let mut head = [0u8; 256];
+    let read = fs::File::open(path)
+        .and_then(|mut f| f.read(&mut head))
+        .unwrap_or(0);
+    let head = String::from_utf8_lossy(&head[..read]);
+    head.starts_with("#!") && head.lines().next().unwrap_or("").contains("python")
+}
+
+fn classify_spawn_error(e: &std::io::Error, path: &Path) -> SpawnErrorKind {
+    match e.kind() {
+        std::io::ErrorKind::PermissionDenied => SpawnErrorKind::PermissionDenied,
+        // ENOENT on a file that exists means its #! interpreter is missing
+        std::io::ErrorKind::NotFound if path.exists() => SpawnErrorKind::BadInterpreter,
+        std::io::ErrorKind::NotFound => SpawnErrorKind::NotFound,
+        _ if e.raw_os_error() == Some(8) => SpawnErrorKind::ExecFormat,
+        _ => SpawnErrorKind::Other,
+    }
+}
+
+fn probe_system_ytdlp(path: &Path) -> SystemYtdlpReport {
+    let mut report = SystemYtdlpReport {
+        path: path.to_string_lossy().to_string(),
+        is_python_shim: is_python_shim(path),
+        version: None,
+        error_kind: None,
+        error: None,
+    };
+
+    match std::process::Command::new(path).arg("--version").output() {
+        Ok(output) if output.status.success() => {
+            report.version = Some(String::from_utf8_lossy(&output.stdout).trim().to_string());
+        }
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            report.error_kind = Some(
+                if stderr.contains("ModuleNotFoundError") || stderr.contains("ImportError") {
+                    SpawnErrorKind::MissingPythonModule
+                } else {
+                    SpawnErrorKind::NonZeroExit
+                },
+            );
+            report.error = Some(stderr.lines().last().unwrap_or("").to_string());
+        }
+        Err(e) => {
+            report.error_kind = Some(classify_spawn_error(&e, path));
+            report.error = Some(e.to_string());
+        }
+    }
+
+    report
+}
+
+/// Diagnose "works in terminal, fails in app" problems by comparing the GUI PATH with
+/// the login shell PATH and checking whether a system yt-dlp actually runs
+pub fn check_environment<R: Runtime>(app_handle: &AppHandle<R>) -> EnvironmentReport {
+    let gui_path = split_path(&child_path().to_string_lossy());
+    let login_shell_path = get_login_shell_path();
+
+    let missing_from_gui_path: Vec<String> = login_shell_path
+        .as_ref()
+        .map(|shell_path| {
+            shell_path
+                .iter()
+                .filter(|dir| !gui_path.contains(dir))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut search_dirs = gui_path.clone();
+    search_dirs.extend(missing_from_gui_path.iter().cloned());
+    let system_ytdlp = find_in_dirs(&search_dirs, "yt-dlp").map(|p| probe_system_ytdlp(&p));
+
+    let managed_ytdlp_path = get_bundled_binary(app_handle, "yt-dlp")
+        .ok()
+        .map(|p| p.to_string_lossy().to_string());
+    let prefers_managed = managed_ytdlp_path.is_some();
+
+    let summary =
+        match &system_ytdlp {
+            Some(report) if report.error_kind.is_some() => format!(
+                "The system yt-dlp at {} is broken ({:?}){}. {}",
+                report.path,
+                report.error_kind.as_ref().unwrap(),
+                if report.is_python_shim {
+                    ", it is a Python shim whose interpreter or package is not usable from the app"
+                } else {
+                    ""
+                },
+                if prefers_managed {
+                    "The app uses its managed yt-dlp instead."
+                } else {
+                    "Reinstall yt-dlp as a standalone binary or restore the bundled one."
+                }
+            ),
+            Some(report) => format!(
+            "System yt-dlp at {} works ({}). The app uses its managed copy for consistent results.",
+            report.path,
+            report.version.as_deref().unwrap_or("unknown version")
+        ),
+            None if !missing_from_gui_path.is_empty() => format!(
+            "No system yt-dlp found. The app's PATH is missing {} director{} from your shell PATH.",
+            missing_from_gui_path.len(),
+            if missing_from_gui_path.len() == 1 { "y" } else { "ies" }
+        ),
+            None => "No system yt-dlp found; the app uses its managed copy.".to_string(),
+        };
+
+    EnvironmentReport {
+        system_ytdlp,
+        gui_path,
+        login_shell_path,
+        missing_from_gui_path,
+        managed_ytdlp_path,
+        prefers_managed,
+        summary,
+    }
+}
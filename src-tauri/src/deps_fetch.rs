@@ -0,0 +1,527 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Runtime};
+
+use crate::deps::{self, BinaryOrigin};
+use crate::proxy;
+
+/// How the downloaded file holds the binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArchiveKind {
+    /// The download is the binary itself
+    Plain,
+    Zip,
+    TarXz,
+}
+
+/// Where the SHA-256 of a download is published. Downloads without one are never used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Checksum {
+    /// A file in the same release listing `<hash>  <file name>` for each of its files,
+    /// e.g. yt-dlp's `SHA2-256SUMS`
+    List(&'static str),
+    /// A file holding the hash, at the download's URL with `.sha256` added
+    Sidecar,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct DependencySource {
+    pub url: String,
+    pub archive: ArchiveKind,
+    pub checksum: Checksum,
+}
+
+impl DependencySource {
+    fn checksum_url(&self) -> String {
+        match self.checksum {
+            Checksum::List(name) => match self.url.rsplit_once('/') {
+                Some((release, _)) => format!("{}/{}", release, name),
+                None => name.to_string(),
+            },
+            Checksum::Sidecar => format!("{}.sha256", self.url),
+        }
+    }
+
+    /// Name of the downloaded file, as the checksum list has it
+    fn file_name(&self) -> &str {
+        self.url.rsplit('/').next().unwrap_or(&self.url)
+    }
+}
+
+/// Archive kind of a download, going by the file name in its URL or path
+fn archive_kind_for(url: &str) -> ArchiveKind {
+    let path = url.split(['?', '#']).next().unwrap_or(url).to_lowercase();
+    if path.ends_with(".zip") || path.ends_with("/zip") {
+        ArchiveKind::Zip
+    } else if path.ends_with(".tar.xz") {
+        ArchiveKind::TarXz
+    } else {
+        ArchiveKind::Plain
+    }
+}
+
+/// yt-dlp publishes the hashes of a release's files in one list
+const YTDLP_SUMS: Checksum = Checksum::List("SHA2-256SUMS");
+
+/// The GitHub FFmpeg-Builds releases (BtbN's and yt-dlp's fork) do too
+const FFMPEG_BUILDS_SUMS: Checksum = Checksum::List("checksums.sha256");
+
+/// Official and fallback download locations of `binary` for a platform with where their
+/// checksums are, most preferred first. johnvansickle.com (only MD5 sums) and
+/// evermeet.cx (only GPG signatures) publish no SHA-256, so their builds aren't used.
+fn upstream_urls(binary: &str, platform: &str, arch: &str) -> &'static [(&'static str, Checksum)] {
+    match (binary, platform, arch) {
+        ("yt-dlp", "windows", "arm64") => &[
+            ("https://github.com/yt-dlp/yt-dlp/releases/latest/download/yt-dlp_arm64.exe", YTDLP_SUMS),
+            ("https://github.com/yt-dlp/yt-dlp-nightly-builds/releases/latest/download/yt-dlp_arm64.exe", YTDLP_SUMS),
+        ],
+        ("yt-dlp", "windows", _) => &[
+            ("https://github.com/yt-dlp/yt-dlp/releases/latest/download/yt-dlp.exe", YTDLP_SUMS),
+            ("https://github.com/yt-dlp/yt-dlp-nightly-builds/releases/latest/download/yt-dlp.exe", YTDLP_SUMS),
+        ],
+        ("yt-dlp", "macos", _) => &[
+            ("https://github.com/yt-dlp/yt-dlp/releases/latest/download/yt-dlp_macos", YTDLP_SUMS),
+            ("https://github.com/yt-dlp/yt-dlp-nightly-builds/releases/latest/download/yt-dlp_macos", YTDLP_SUMS),
+        ],
+        ("yt-dlp", "linux", "arm64") => &[
+            ("https://github.com/yt-dlp/yt-dlp/releases/latest/download/yt-dlp_linux_aarch64", YTDLP_SUMS),
+            ("https://github.com/yt-dlp/yt-dlp-nightly-builds/releases/latest/download/yt-dlp_linux_aarch64", YTDLP_SUMS),
+        ],
+        ("yt-dlp", "linux", "armv7") => &[
+            ("https://github.com/yt-dlp/yt-dlp/releases/latest/download/yt-dlp_linux_armv7l", YTDLP_SUMS),
+            ("https://github.com/yt-dlp/yt-dlp-nightly-builds/releases/latest/download/yt-dlp_linux_armv7l", YTDLP_SUMS),
+        ],
+        ("yt-dlp", "linux", _) => &[
+            ("https://github.com/yt-dlp/yt-dlp/releases/latest/download/yt-dlp_linux", YTDLP_SUMS),
+            ("https://github.com/yt-dlp/yt-dlp-nightly-builds/releases/latest/download/yt-dlp_linux", YTDLP_SUMS),
+        ],
+        // gyan.dev only builds for x64
+        ("ffmpeg", "windows", "arm64") => &[
+            ("https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/ffmpeg-master-latest-winarm64-gpl.zip", FFMPEG_BUILDS_SUMS),
+            ("https://github.com/yt-dlp/FFmpeg-Builds/releases/download/latest/ffmpeg-master-latest-winarm64-gpl.zip", FFMPEG_BUILDS_SUMS),
+        ],
+        ("ffmpeg", "windows", _) => &[
+            ("https://www.gyan.dev/ffmpeg/builds/ffmpeg-release-essentials.zip", Checksum::Sidecar),
+            ("https://github.com/yt-dlp/FFmpeg-Builds/releases/download/latest/ffmpeg-master-latest-win64-gpl.zip", FFMPEG_BUILDS_SUMS),
+            ("https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/ffmpeg-master-latest-win64-gpl.zip", FFMPEG_BUILDS_SUMS),
+        ],
+        ("ffmpeg", "macos", "arm64") => &[
+            ("https://ffmpeg.martin-riedl.de/redirect/latest/macos/arm64/release/ffmpeg.zip", Checksum::Sidecar),
+        ],
+        ("ffmpeg", "macos", _) => &[
+            ("https://ffmpeg.martin-riedl.de/redirect/latest/macos/amd64/release/ffmpeg.zip", Checksum::Sidecar),
+        ],
+        ("ffmpeg", "linux", "arm64") => &[
+            ("https://github.com/yt-dlp/FFmpeg-Builds/releases/download/latest/ffmpeg-master-latest-linuxarm64-gpl.tar.xz", FFMPEG_BUILDS_SUMS),
+            ("https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/ffmpeg-master-latest-linuxarm64-gpl.tar.xz", FFMPEG_BUILDS_SUMS),
+        ],
+        // No 32-bit ARM build has a SHA-256, so ffmpeg has to be installed from a file there
+        ("ffmpeg", "linux", "armv7") => &[],
+        ("ffmpeg", "linux", _) => &[
+            ("https://github.com/yt-dlp/FFmpeg-Builds/releases/download/latest/ffmpeg-master-latest-linux64-gpl.tar.xz", FFMPEG_BUILDS_SUMS),
+            ("https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/ffmpeg-master-latest-linux64-gpl.tar.xz", FFMPEG_BUILDS_SUMS),
+        ],
+        _ => &[],
+    }
+}
+
+/// Expand a user mirror into the URL of `binary`. The mirror may use the `{platform}`,
+/// `{arch}` and `{binary}` placeholders; a plain base URL gets the same
+/// `<platform>/<arch>/<binary>` layout as the bundled `binaries` folder. Each binary
+/// needs its SHA-256 next to it, in a file named after it with `.sha256` added.
+fn custom_mirror_url(mirror: &str, binary: &str, platform: &str, arch: &str) -> String {
+    let binary_name = deps::get_binary_name(binary);
+    if mirror.contains('{') {
+        mirror
+            .replace("{platform}", platform)
+            .replace("{arch}", arch)
+            .replace("{binary}", &binary_name)
+    } else {
+        format!(
+            "{}/{}/{}/{}",
+            mirror.trim_end_matches('/'),
+            platform,
+            arch,
+            binary_name
+        )
+    }
+}
+
+/// Where to download `binary` for the current platform, in the order to try them.
+/// A custom mirror from the preferences comes first, unless it isn't https (saved
+/// before plain http mirrors were refused).
+pub(crate) fn sources_for(binary: &str, custom_mirror: Option<&str>) -> Vec<DependencySource> {
+    let (platform, arch) = (deps::get_platform(), deps::get_arch());
+    custom_mirror
+        .map(str::trim)
+        .filter(|mirror| mirror.starts_with("https://"))
+        .map(|mirror| {
+            (
+                custom_mirror_url(mirror, binary, platform, arch),
+                Checksum::Sidecar,
+            )
+        })
+        .into_iter()
+        .chain(
+            upstream_urls(binary, platform, arch)
+                .iter()
+                .map(|(url, checksum)| (url.to_string(), *checksum)),
+        )
+        .map(|(url, checksum)| DependencySource {
+            archive: archive_kind_for(&url),
+            url,
+            checksum,
+        })
+        .collect()
+}
+
+/// The SHA-256 `checksums` gives for `file_name`, lowercased. A list is searched for
+/// the file's line, a sidecar file holds only the one hash.
+fn expected_hash(checksums: &str, checksum: Checksum, file_name: &str) -> Option<String> {
+    checksums
+        .lines()
+        .find_map(|line| {
+            let mut fields = line.split_whitespace();
+            let hash = fields.next()?;
+            match checksum {
+                // `sha256sum` marks files hashed in binary mode with `*`
+                Checksum::List(_) => {
+                    (fields.next()?.trim_start_matches('*') == file_name).then_some(hash)
+                }
+                Checksum::Sidecar => Some(hash),
+            }
+        })
+        .filter(|hash| hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(str::to_lowercase)
+}
+
+/// Fetch the published SHA-256 of a source's download
+async fn fetch_expected_hash(source: &DependencySource) -> Result<String> {
+    let url = source.checksum_url();
+    let client =
+        proxy::http_client(std::time::Duration::from_secs(20), None).map_err(anyhow::Error::msg)?;
+    let checksums = client
+        .get(&url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("Failed to download the checksum {}", url))?
+        .text()
+        .await
+        .with_context(|| format!("Failed to read the checksum {}", url))?;
+    expected_hash(&checksums, source.checksum, source.file_name())
+        .with_context(|| format!("{} has no SHA-256 for {}", url, source.file_name()))
+}
+
+/// Download `url` to `dest`, reporting `(downloaded, total)` bytes as chunks arrive
+async fn download_file(
+    url: &str,
+    dest: &Path,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<()> {
+    let client =
+        proxy::http_client(std::time::Duration::from_secs(20), None).map_err(anyhow::Error::msg)?;
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("Failed to download {}", url))?;
+
+    let total = response.content_length();
+    let mut file =
+        fs::File::create(dest).with_context(|| format!("Failed to create {}", dest.display()))?;
+    let mut downloaded = 0u64;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .with_context(|| format!("Download of {} was interrupted", url))?
+    {
+        file.write_all(&chunk)?;
+        downloaded += chunk.len() as u64;
+        on_progress(downloaded, total);
+    }
+    file.flush()?;
+
+    if total.is_some_and(|total| downloaded < total) {
+        anyhow::bail!("Download of {} ended early", url);
+    }
+    Ok(())
+}
+
+/// Find the file called `name` anywhere below `dir`
+fn find_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    for path in fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+    {
+        if path.is_dir() {
+            if let Some(found) = find_file(&path, name) {
+                return Some(found);
+            }
+        } else if path.file_name().and_then(|n| n.to_str()) == Some(name) {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Unpack the binary called `binary_name` from an archive into `work_dir`
+pub(crate) fn unpack(
+    archive: &Path,
+    kind: ArchiveKind,
+    binary_name: &str,
+    work_dir: &Path,
+) -> Result<PathBuf> {
+    match kind {
+        ArchiveKind::Plain => Ok(archive.to_path_buf()),
+        ArchiveKind::Zip => {
+            let file = fs::File::open(archive)?;
+            let mut zip = zip::ZipArchive::new(file).context("Not a valid zip archive")?;
+            for index in 0..zip.len() {
+                let mut entry = zip.by_index(index)?;
+                let is_binary = Path::new(entry.name()).file_name().and_then(|n| n.to_str())
+                    == Some(binary_name);
+                if entry.is_file() && is_binary {
+                    let dest = work_dir.join(binary_name);
+                    let mut out = fs::File::create(&dest)?;
+                    std::io::copy(&mut entry, &mut out)?;
+                    return Ok(dest);
+                }
+            }
+            anyhow::bail!("{} was not found in the archive", binary_name)
+        }
+        ArchiveKind::TarXz => {
+            // There is no xz decoder among our dependencies, but every Linux system has tar
+            let status = std::process::Command::new("tar")
+                .arg("-xJf")
+                .arg(archive)
+                .arg("-C")
+                .arg(work_dir)
+                .status()
+                .context("Failed to run tar")?;
+            if !status.success() {
+                anyhow::bail!("Failed to unpack {}", archive.display());
+            }
+            find_file(work_dir, binary_name)
+                .with_context(|| format!("{} was not found in the archive", binary_name))
+        }
+    }
+}
+
+/// Download the current release of `binary` and install it as the managed copy, trying
+/// each source in turn until one works. A download is checked against its published
+/// SHA-256 before anything in it is run, and a source without one fails. `on_progress`
+/// also gets the URL being tried.
+pub async fn fetch_binary<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    binary: &str,
+    custom_mirror: Option<&str>,
+    mut on_progress: impl FnMut(&str, u64, Option<u64>),
+) -> Result<PathBuf> {
+    let sources = sources_for(binary, custom_mirror);
+    if sources.is_empty() {
+        anyhow::bail!("No {} download is available for this platform", binary);
+    }
+    let bin_dir = deps::managed_binary_path(app_handle, binary)?
+        .parent()
+        .context("Managed binary has no parent directory")?
+        .to_path_buf();
+    let work_dir = bin_dir.join(format!("{}-download.tmp", binary));
+
+    let mut errors = Vec::new();
+    for source in sources {
+        fs::remove_dir_all(&work_dir).ok();
+        fs::create_dir_all(&work_dir)
+            .with_context(|| format!("Failed to create {}", work_dir.display()))?;
+
+        let result = async {
+            let expected = fetch_expected_hash(&source).await?;
+            let archive = work_dir.join("download");
+            eprintln!("[deps] Downloading {} from {}", binary, source.url);
+            download_file(&source.url, &archive, |downloaded, total| {
+                on_progress(&source.url, downloaded, total)
+            })
+            .await?;
+            let actual = deps::hash_file(&archive)?;
+            if actual != expected {
+                anyhow::bail!(
+                    "Checksum mismatch: expected SHA-256 {}, the download has {}",
+                    expected,
+                    actual
+                );
+            }
+
+            let binary_name = deps::get_binary_name(binary);
+            let unpacked = unpack(&archive, source.archive, &binary_name, &work_dir)?;
+            let app_handle = app_handle.clone();
+            let binary = binary.to_string();
+            let url = source.url.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                deps::install_binary(
+                    &app_handle,
+                    &binary,
+                    &unpacked,
+                    BinaryOrigin::Download,
+                    &url,
+                )
+            })
+            .await
+            .context("Install task failed")?
+        }
+        .await;
+
+        fs::remove_dir_all(&work_dir).ok();
+        match result {
+            Ok(path) => return Ok(path),
+            Err(e) => {
+                eprintln!("[deps] {} source failed: {:#}", binary, e);
+                errors.push(format!("{}: {:#}", source.url, e));
+            }
+        }
+    }
+
+    anyhow::bail!(
+        "Every {} download source failed:\n{}",
+        binary,
+        errors.join("\n")
+    )
+}
+
+/// Which dependency a user-provided file holds, going by its name
+fn binary_for_file(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?.to_lowercase();
+    if name.contains("ffmpeg") {
+        Some("ffmpeg")
+    } else if name.contains("yt-dlp") || name.contains("yt_dlp") {
+        Some("yt-dlp")
+    } else {
+        None
+    }
+}
+
+/// Install a yt-dlp/ffmpeg binary or release archive the user downloaded themselves.
+/// `binary` can be left out when the file name says which one it is.
+pub fn install_from_file<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    path: &Path,
+    binary: Option<&str>,
+) -> Result<PathBuf> {
+    if !path.is_file() {
+        anyhow::bail!("{} is not a file", path.display());
+    }
+    let binary = match binary {
+        Some(binary @ ("yt-dlp" | "ffmpeg")) => binary,
+        Some(other) => anyhow::bail!("Unknown dependency '{}'", other),
+        None => binary_for_file(path).with_context(|| {
+            format!(
+                "Cannot tell whether {} is yt-dlp or ffmpeg, please choose one",
+                path.display()
+            )
+        })?,
+    };
+
+    let bin_dir = deps::managed_binary_path(app_handle, binary)?
+        .parent()
+        .context("Managed binary has no parent directory")?
+        .to_path_buf();
+    let work_dir = bin_dir.join(format!("{}-import.tmp", binary));
+    fs::remove_dir_all(&work_dir).ok();
+    fs::create_dir_all(&work_dir)
+        .with_context(|| format!("Failed to create {}", work_dir.display()))?;
+
+    let kind = archive_kind_for(&path.to_string_lossy());
+    let result =
+        unpack(path, kind, &deps::get_binary_name(binary), &work_dir).and_then(|unpacked| {
+            deps::install_binary(
+                app_handle,
+                binary,
+                &unpacked,
+                BinaryOrigin::File,
+                &path.to_string_lossy(),
+            )
+        });
+
+    fs::remove_dir_all(&work_dir).ok();
+    result
+}
+
+/// Make a yt-dlp/ffmpeg executable the user pointed at the managed copy. It is checked
+/// to run and identify as `binary`, copied into the bin directory and recorded with its
+/// version and original location.
+pub fn designate_binary<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    path: &Path,
+    binary: &str,
+) -> Result<PathBuf> {
+    if !matches!(binary, "yt-dlp" | "ffmpeg") {
+        anyhow::bail!("Unknown dependency '{}'", binary);
+    }
+    if !path.is_file() {
+        anyhow::bail!("{} is not a file", path.display());
+    }
+    // Package managers install symlinks (Homebrew into its Cellar), copy what they point at
+    let path =
+        fs::canonicalize(path).with_context(|| format!("Failed to resolve {}", path.display()))?;
+    if deps::managed_binary_path(app_handle, binary).is_ok_and(|managed| managed == path) {
+        anyhow::bail!("{} already is the managed {}", path.display(), binary);
+    }
+    deps::install_binary(
+        app_handle,
+        binary,
+        &path,
+        BinaryOrigin::Designated,
+        &path.to_string_lossy(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expected_hash, Checksum};
+
+    const HASH: &str = "0A1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8f9";
+
+    #[test]
+    fn finds_the_file_in_a_checksum_list() {
+        let list = format!(
+            "{}  yt-dlp.exe\n{}  yt-dlp_linux\n{} *yt-dlp_macos\n",
+            "f".repeat(64),
+            HASH,
+            "e".repeat(64)
+        );
+        let sums = Checksum::List("SHA2-256SUMS");
+        assert_eq!(
+            expected_hash(&list, sums, "yt-dlp_linux"),
+            Some(HASH.to_lowercase())
+        );
+        assert_eq!(
+            expected_hash(&list, sums, "yt-dlp_macos"),
+            Some("e".repeat(64))
+        );
+        assert_eq!(expected_hash(&list, sums, "yt-dlp_linux_armv7l"), None);
+    }
+
+    #[test]
+    fn reads_a_sidecar_checksum() {
+        assert_eq!(
+            expected_hash(&format!("{}\n", HASH), Checksum::Sidecar, "ffmpeg.zip"),
+            Some(HASH.to_lowercase())
+        );
+        assert_eq!(
+            expected_hash(
+                &format!("{}  ffmpeg-7.1.zip", HASH),
+                Checksum::Sidecar,
+                "ffmpeg.zip"
+            ),
+            Some(HASH.to_lowercase())
+        );
+        // An error page served in place of the checksum is not a hash
+        assert_eq!(
+            expected_hash("<html>Not found</html>", Checksum::Sidecar, "ffmpeg.zip"),
+            None
+        );
+    }
+}
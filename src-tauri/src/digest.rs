@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::api::path::config_dir;
+use tauri::{AppHandle, Manager};
+
+use crate::commands::{AppPreferences, HistoryData};
+use crate::queue::{DownloadQueue, JobStatus, QueueJob};
+
+/// Days covered by a digest and between two digests
+const DIGEST_DAYS: i64 = 7;
+
+/// What happened over the last week, sent as a notification when it is due
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeeklyDigest {
+    pub period_start: String,
+    pub period_end: String,
+    pub tracks_downloaded: usize,
+    /// Combined length of the tracks that report one, in seconds
+    pub total_duration: f64,
+    /// Queue jobs that failed during the week and are still in the queue, to retry or remove
+    pub failed_jobs: usize,
+}
+
+/// When the last digest went out, kept apart from the preferences
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DigestState {
+    last_sent_at: Option<String>,
+}
+
+fn get_state_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("youtube-downloader").join("digest.json"))
+}
+
+fn load_state() -> DigestState {
+    get_state_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(state: &DigestState) -> Result<(), String> {
+    let path = get_state_path().ok_or("Failed to get config directory")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let content = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+    fs::write(&path, content).map_err(|e| e.to_string())
+}
+
+fn within(timestamp: Option<&str>, since: chrono::DateTime<chrono::Utc>) -> bool {
+    timestamp
+        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+        .is_some_and(|t| t >= since)
+}
+
+/// Summarize the week up to now from the history and the queue
+pub fn build(jobs: &[QueueJob]) -> WeeklyDigest {
+    let now = chrono::Utc::now();
+    let since = now - chrono::Duration::days(DIGEST_DAYS);
+    let history = HistoryData::load();
+    let recent: Vec<_> = history
+        .downloads
+        .iter()
+        .filter(|d| within(Some(d.timestamp.as_str()), since))
+        .collect();
+    WeeklyDigest {
+        period_start: since.to_rfc3339(),
+        period_end: now.to_rfc3339(),
+        tracks_downloaded: recent.len(),
+        total_duration: recent.iter().filter_map(|d| d.duration).sum(),
+        failed_jobs: jobs
+            .iter()
+            .filter(|j| j.status == JobStatus::Failed && within(j.finished_at.as_deref(), since))
+            .count(),
+    }
+}
+
+/// Notification text for a digest, none for a week with nothing to report
+fn notification_body(digest: &WeeklyDigest) -> Option<String> {
+    if digest.tracks_downloaded == 0 && digest.failed_jobs == 0 {
+        return None;
+    }
+    let hours = digest.total_duration / 3600.0;
+    let mut body = format!(
+        "{} track{} downloaded ({:.1} hours of audio)",
+        digest.tracks_downloaded,
+        if digest.tracks_downloaded == 1 {
+            ""
+        } else {
+            "s"
+        },
+        hours
+    );
+    if digest.failed_jobs > 0 {
+        body.push_str(&format!(
+            ", {} failed download{} need{} attention in the queue",
+            digest.failed_jobs,
+            if digest.failed_jobs == 1 { "" } else { "s" },
+            if digest.failed_jobs == 1 { "s" } else { "" }
+        ));
+    }
+    Some(body)
+}
+
+fn is_due(state: &DigestState) -> bool {
+    let since = chrono::Utc::now() - chrono::Duration::days(DIGEST_DAYS);
+    !within(state.last_sent_at.as_deref(), since)
+}
+
+/// Send the weekly digest when it is turned on and a week has passed since the last
+/// one. Called by the maintenance scheduler.
+pub fn send_if_due(app_handle: &AppHandle) {
+    if !AppPreferences::load().weekly_digest.unwrap_or(false) {
+        return;
+    }
+    let mut state = load_state();
+    if !is_due(&state) {
+        return;
+    }
+    // The first check after turning it on only starts the week
+    if state.last_sent_at.is_some() {
+        let digest = build(&app_handle.state::<DownloadQueue>().jobs());
+        if let Some(body) = notification_body(&digest) {
+            let app_name = app_handle.package_info().name.clone();
+            tauri::api::notification::Notification::new(&app_name)
+                .title("Your Week in Downloads")
+                .body(body)
+                .show()
+                .ok();
+        }
+    }
+    state.last_sent_at = Some(chrono::Utc::now().to_rfc3339());
+    if let Err(e) = save_state(&state) {
+        eprintln!("[digest] Failed to save digest state: {}", e);
+    }
+}
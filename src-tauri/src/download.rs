@@ -1,11 +1,336 @@
+use crate::app_lock;
+use crate::audio_format::AudioFormat;
+use crate::audio_preset::AudioPreset;
+use crate::benchmark;
+use crate::connectivity::Reconnect;
+use crate::conversion::{self, LoudnessNormalization};
+use crate::decisions::{self, DecisionKind};
 use crate::deps;
+use crate::download_log;
+use crate::events::{self, Event};
+use crate::folder_art::{self, CollectionTrack};
+use crate::lyrics::{self, LyricsSettings};
+use crate::media_cache::{self, MediaCacheSettings};
+use crate::network_sim::NetworkSimulation;
+use crate::queue::DownloadQueue;
+use crate::sections::{self, SectionedDownload};
+use crate::silence::SilenceTrim;
+use crate::source::{self, ParsedSource};
+use crate::speed_history;
+use crate::streaming;
+use crate::system_load::AdaptiveLimit;
+use crate::tags::{self, TagOverrides};
+use crate::tracklist::{self, DetectedTracklist, TracklistSource};
+use crate::transliterate::transliterate;
+use futures_util::stream::{self, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use tauri::{AppHandle, Manager};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Runtime};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+
+/// Upper bound for `DownloadOptions::concurrency`, more mostly gets a client throttled
+pub const MAX_CONCURRENT_DOWNLOADS: usize = 4;
+
+/// Number of downloads currently running, used to keep background work out of the way
+static ACTIVE_DOWNLOADS: AtomicUsize = AtomicUsize::new(0);
+
+tokio::task_local! {
+    /// ID of the queue job the current download belongs to, see `emit_progress`
+    pub(crate) static PROGRESS_JOB: String;
+}
+
+/// Discrete steps the overall progress is divided into, for announcing "step 4 of 10"
+/// rather than a percentage that changes every moment
+const PROGRESS_STEPS: u32 = 10;
+
+/// Only send progress when its step or status changes, see `configure_reduced_updates`
+static REDUCED_UPDATES: AtomicBool = AtomicBool::new(false);
+
+/// Step and status last sent for each download while updates are reduced, keyed by
+/// queue job ID (empty for direct downloads)
+static LAST_SENT_STEP: Mutex<Option<HashMap<String, (u32, String)>>> = Mutex::new(None);
+
+/// Send far fewer progress events, for screen readers and users sensitive to motion
+pub fn configure_reduced_updates(enabled: bool) {
+    REDUCED_UPDATES.store(enabled, Ordering::SeqCst);
+}
+
+/// Progress as sent to the frontend, with its discrete step
+#[derive(Debug, Serialize)]
+pub(crate) struct SteppedProgress<'a> {
+    #[serde(flatten)]
+    pub(crate) progress: &'a DownloadProgress,
+    pub(crate) step: u32,
+    pub(crate) total_steps: u32,
+}
+
+/// Whether a progress update should be sent. With reduced updates, only a new step or
+/// status is, the in-between percentages and transfer figures are dropped.
+fn should_send(key: &str, step: u32, status: &str) -> bool {
+    if !REDUCED_UPDATES.load(Ordering::SeqCst) {
+        return true;
+    }
+    let mut last = LAST_SENT_STEP.lock().unwrap();
+    let last = last.get_or_insert_with(HashMap::new);
+    if last
+        .get(key)
+        .is_some_and(|(s, st)| *s == step && st == status)
+    {
+        return false;
+    }
+    if step >= PROGRESS_STEPS {
+        last.remove(key);
+    } else {
+        last.insert(key.to_string(), (step, status.to_string()));
+    }
+    true
+}
+
+/// Emit the download progress, and the queue job progress tagged with the job ID when
+/// the download is running as a queue job
+fn emit_progress<R: Runtime>(app_handle: &AppHandle<R>, progress: DownloadProgress) {
+    let job_id = PROGRESS_JOB.try_with(|id| id.clone()).ok();
+    if let (Some(job_id), Some(queue)) = (&job_id, app_handle.try_state::<DownloadQueue>()) {
+        queue.report_progress(job_id, progress.overall_progress);
+    }
+
+    let step = ((progress.overall_progress.clamp(0.0, 100.0) / 100.0) * PROGRESS_STEPS as f64)
+        .floor() as u32;
+    if !should_send(
+        job_id.as_deref().unwrap_or_default(),
+        step,
+        &progress.status,
+    ) {
+        return;
+    }
+    let progress = SteppedProgress {
+        progress: &progress,
+        step,
+        total_steps: PROGRESS_STEPS,
+    };
+    if let Some(job_id) = job_id {
+        events::emit(
+            app_handle,
+            Event::QueueJobProgress,
+            &serde_json::json!({ "job_id": job_id, "progress": &progress }),
+        );
+    }
+    events::emit(app_handle, Event::DownloadProgress, &progress);
+}
+
+pub fn active_downloads() -> usize {
+    ACTIVE_DOWNLOADS.load(Ordering::SeqCst)
+}
+
+/// Counts as an active download for as long as it is alive
+pub struct ActiveDownloadGuard;
+
+impl ActiveDownloadGuard {
+    pub fn new() -> Self {
+        ACTIVE_DOWNLOADS.fetch_add(1, Ordering::SeqCst);
+        ActiveDownloadGuard
+    }
+}
+
+impl Drop for ActiveDownloadGuard {
+    fn drop(&mut self) {
+        ACTIVE_DOWNLOADS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Error returned by a download that was stopped with `cancel_download`
+pub const CANCELLED_MESSAGE: &str = "Download cancelled";
+
+/// Cancellation tokens of the running downloads by download ID, each with a
+/// registration number so a finished download only removes its own entry
+static CANCEL_TOKENS: Mutex<Option<HashMap<String, (u64, CancellationToken)>>> = Mutex::new(None);
+
+static NEXT_CANCEL_REGISTRATION: AtomicUsize = AtomicUsize::new(0);
+
+/// Makes a download cancellable under an ID for as long as it is alive
+pub struct CancelRegistration {
+    id: String,
+    registration: u64,
+    pub token: CancellationToken,
+}
+
+impl CancelRegistration {
+    pub fn new(id: &str) -> Self {
+        let registration = NEXT_CANCEL_REGISTRATION.fetch_add(1, Ordering::SeqCst) as u64;
+        let token = CancellationToken::new();
+        CANCEL_TOKENS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .insert(id.to_string(), (registration, token.clone()));
+        Self {
+            id: id.to_string(),
+            registration,
+            token,
+        }
+    }
+}
+
+impl Drop for CancelRegistration {
+    fn drop(&mut self) {
+        if let Some(tokens) = CANCEL_TOKENS.lock().unwrap().as_mut() {
+            if tokens
+                .get(&self.id)
+                .is_some_and(|(registration, _)| *registration == self.registration)
+            {
+                tokens.remove(&self.id);
+            }
+        }
+    }
+}
+
+/// Cancel the download registered under `id`, or every running download when `id` is
+/// `None`. Returns how many downloads were cancelled.
+pub fn cancel_downloads(id: Option<&str>) -> usize {
+    let tokens = CANCEL_TOKENS.lock().unwrap();
+    let Some(tokens) = tokens.as_ref() else {
+        return 0;
+    };
+    let mut cancelled = 0;
+    for (key, (_, token)) in tokens {
+        if id.is_none_or(|id| id == key) && !token.is_cancelled() {
+            token.cancel();
+            cancelled += 1;
+        }
+    }
+    cancelled
+}
+
+/// Run `cmd` to completion like `output()`, killing the process if the download is
+/// cancelled in the meantime
+pub(crate) async fn run_output(
+    cmd: &mut Command,
+    cancel: &CancellationToken,
+) -> std::io::Result<std::process::Output> {
+    let child = cmd
+        .kill_on_drop(true)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+    tokio::select! {
+        output = child.wait_with_output() => output,
+        _ = cancel.cancelled() => Err(std::io::Error::new(
+            std::io::ErrorKind::Interrupted,
+            CANCELLED_MESSAGE,
+        )),
+    }
+}
+
+/// Whether a file is something yt-dlp leaves behind while a download is in progress
+pub(crate) fn is_partial_file(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    matches!(ext, "part" | "ytdl") || name.contains(".part-Frag") || name.contains(".temp.")
+}
+
+/// SponsorBlock categories cut out of music downloads
+const SPONSORBLOCK_CATEGORIES: &str = "music_offtopic,sponsor";
+
+/// Image extensions of the thumbnails yt-dlp writes before embedding them
+const THUMBNAIL_EXTENSIONS: [&str; 4] = ["jpg", "webp", "png", "jpeg"];
+
+/// Delete the thumbnail images yt-dlp leaves next to `output_path` when embedding fails
+/// or is interrupted, they share the audio file's name
+fn remove_thumbnail_files(output_path: &Path) {
+    for ext in THUMBNAIL_EXTENSIONS {
+        let thumbnail = output_path.with_extension(ext);
+        if thumbnail.is_file() && fs::remove_file(&thumbnail).is_ok() {
+            eprintln!(
+                "[download] Removed leftover thumbnail {}",
+                thumbnail.display()
+            );
+        }
+    }
+}
+
+/// Whether the file is named `<name>.<anything>` for one of `names`
+fn is_named_after(path: &Path, names: &[String]) -> bool {
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    names.iter().filter(|name| !name.is_empty()).any(|name| {
+        file_name
+            .strip_prefix(name.as_str())
+            .is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+/// Delete the partial files in `dir` that were written after `since` and are named
+/// after one of `names`, so other downloads writing to the same folder keep theirs
+pub(crate) fn remove_partial_files(dir: &Path, names: &[String], since: std::time::SystemTime) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        let recent = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| modified >= since);
+        if recent && is_partial_file(&path) && is_named_after(&path, names) {
+            match fs::remove_file(&path) {
+                Ok(()) => eprintln!("[download] Removed partial file {}", path.display()),
+                Err(e) => eprintln!("[download] Failed to remove {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+/// Where a download writes and the names its files start with: the file stem, the
+/// title yt-dlp names the file after when it isn't given one, and the video ID the
+/// media cache uses. Filled in as they become known.
+#[derive(Debug, Default)]
+struct DownloadFiles {
+    folder: String,
+    names: Vec<String>,
+}
+
+impl DownloadFiles {
+    fn add_names(&mut self, stem: Option<&str>, title: Option<&str>, id: Option<&str>) {
+        self.names
+            .extend([stem, title, id].into_iter().flatten().map(str::to_string));
+    }
+}
+
+/// Clean up the partial files of a cancelled download and tell the frontend it stopped
+fn finish_cancelled<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    files: &DownloadFiles,
+    since: std::time::SystemTime,
+) {
+    remove_partial_files(Path::new(&files.folder), &files.names, since);
+    if let Some(cache_dir) = media_cache::get_cache_dir() {
+        remove_partial_files(&cache_dir, &files.names, since);
+    }
+    let progress = DownloadProgress {
+        overall_progress: 0.0,
+        current_song: None,
+        total_songs: None,
+        song_progress: 0.0,
+        status: "Cancelled".to_string(),
+        current_title: None,
+        estimated_size: None,
+        transfer: TransferStats::default(),
+    };
+    emit_progress(app_handle, progress);
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DownloadResult {
@@ -13,6 +338,19 @@ pub struct DownloadResult {
     pub title: Option<String>,
     pub duration: Option<f64>,
     pub file_size: Option<u64>,
+    /// The yt-dlp format ID that was actually downloaded, if known
+    pub format_id: Option<String>,
+    /// The source audio already was MP3 and was copied into the file without
+    /// re-encoding, so it keeps the source bitrate
+    #[serde(default)]
+    pub lossless_copy: bool,
+    /// One file per chapter when the video was split by its chapters, in chapter order.
+    /// The whole track at `output_path` is kept next to them.
+    #[serde(default)]
+    pub chapter_paths: Vec<String>,
+    /// Subtitles saved next to the track as lyrics, one per language
+    #[serde(default)]
+    pub lyrics_paths: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,6 +358,50 @@ pub struct PlaylistDownloadResult {
     pub output_folder: String,
     pub total_videos: usize,
     pub downloaded_videos: Vec<DownloadResult>,
+    /// Videos that still failed after retrying, in playlist order
+    pub failed_videos: Vec<FailedVideo>,
+    /// The download was cancelled part way, `downloaded_videos` holds what finished
+    pub cancelled: bool,
+    /// Liner notes written for the session, when it was given a note
+    #[serde(default)]
+    pub liner_notes_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedVideo {
+    pub url: String,
+    pub title: Option<String>,
+    pub error: String,
+    /// How many times the download was tried
+    pub attempts: u32,
+}
+
+/// Retries of a failed playlist item, waiting `initial_delay_seconds` before the first
+/// retry and doubling the wait for every further one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_delay_seconds: f64,
+    pub max_delay_seconds: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            initial_delay_seconds: 5.0,
+            max_delay_seconds: 60.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before the retry that follows attempt number `attempt` (1-based)
+    fn delay_for(&self, attempt: u32) -> std::time::Duration {
+        let delay = self.initial_delay_seconds * 2f64.powi(attempt.saturating_sub(1) as i32);
+        std::time::Duration::from_secs_f64(delay.min(self.max_delay_seconds).max(0.0))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -30,29 +412,755 @@ pub struct DownloadProgress {
     pub song_progress: f64,
     pub status: String,
     pub current_title: Option<String>,
+    /// Projected size of the current item's output file in bytes
+    pub estimated_size: Option<u64>,
+    #[serde(flatten)]
+    pub transfer: TransferStats,
+}
+
+/// Transfer figures of the item being downloaded, as reported by yt-dlp
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransferStats {
+    pub downloaded_bytes: Option<u64>,
+    /// Exact size, or yt-dlp's estimate for fragmented downloads
+    pub total_bytes: Option<u64>,
+    /// Bytes per second
+    pub speed: Option<f64>,
+    /// Seconds left
+    pub eta: Option<u64>,
+}
+
+/// `--progress-template` printing each progress update as one line of JSON, read back
+/// by `ProgressReport::parse`
+pub(crate) const PROGRESS_TEMPLATE: &str = "download:[progress] %(progress)j";
+
+/// yt-dlp's progress hook dictionary, as printed by `PROGRESS_TEMPLATE`. Byte counts
+/// are floats for some downloaders.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ProgressReport {
+    /// `downloading`, `finished` or `error`
+    pub status: String,
+    pub downloaded_bytes: Option<f64>,
+    pub total_bytes: Option<f64>,
+    pub total_bytes_estimate: Option<f64>,
+    pub speed: Option<f64>,
+    pub eta: Option<f64>,
+}
+
+impl ProgressReport {
+    pub(crate) fn parse(line: &str) -> Option<Self> {
+        serde_json::from_str(line.strip_prefix("[progress] ")?).ok()
+    }
+
+    fn total_bytes(&self) -> Option<f64> {
+        self.total_bytes
+            .or(self.total_bytes_estimate)
+            .filter(|&total| total > 0.0)
+    }
+
+    /// Percentage of the current file downloaded, `None` while the size is unknown
+    pub(crate) fn percent(&self) -> Option<f64> {
+        if self.status == "finished" {
+            return Some(100.0);
+        }
+        let fraction = self.downloaded_bytes? / self.total_bytes()?;
+        Some((fraction * 100.0).clamp(0.0, 100.0))
+    }
+
+    pub(crate) fn transfer(&self) -> TransferStats {
+        let bytes = |value: Option<f64>| value.filter(|v| *v >= 0.0).map(|v| v.round() as u64);
+        TransferStats {
+            downloaded_bytes: bytes(self.downloaded_bytes),
+            total_bytes: bytes(self.total_bytes()),
+            speed: self.speed.filter(|speed| *speed >= 0.0),
+            eta: bytes(self.eta),
+        }
+    }
+}
+
+/// YouTube player clients yt-dlp can impersonate when extracting formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayerClient {
+    Default,
+    Web,
+    WebSafari,
+    Mweb,
+    Android,
+    Ios,
+    Tv,
+}
+
+impl PlayerClient {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PlayerClient::Default => "default",
+            PlayerClient::Web => "web",
+            PlayerClient::WebSafari => "web_safari",
+            PlayerClient::Mweb => "mweb",
+            PlayerClient::Android => "android",
+            PlayerClient::Ios => "ios",
+            PlayerClient::Tv => "tv",
+        }
+    }
+}
+
+/// Player clients to switch to when YouTube starts rejecting the default ones
+pub const RECOMMENDED_PLAYER_CLIENTS: [PlayerClient; 3] =
+    [PlayerClient::Tv, PlayerClient::Ios, PlayerClient::Mweb];
+
+/// Structured form of yt-dlp's `--extractor-args "youtube:..."`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExtractorArgs {
+    pub player_clients: Vec<PlayerClient>,
+    /// Value for the `formats` extractor argument, e.g. `missing_pot` or `incomplete`
+    pub formats: Option<String>,
+}
+
+impl ExtractorArgs {
+    /// Render as the value passed to `--extractor-args`, if anything is configured
+    fn to_arg(&self) -> Option<String> {
+        let mut parts = Vec::new();
+
+        if !self.player_clients.is_empty() {
+            let clients: Vec<&str> = self.player_clients.iter().map(|c| c.as_str()).collect();
+            parts.push(format!("player_client={}", clients.join(",")));
+        }
+
+        if let Some(formats) = self.formats.as_deref().filter(|f| !f.trim().is_empty()) {
+            parts.push(format!("formats={}", formats.trim()));
+        }
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(format!("youtube:{}", parts.join(";")))
+        }
+    }
+}
+
+/// Request pacing passed to yt-dlp so large batches don't trigger 429 throttling
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Politeness {
+    pub enabled: bool,
+    /// Playlists with at least this many items are paced
+    pub min_batch_size: usize,
+    /// Seconds to sleep between requests during extraction (`--sleep-requests`)
+    pub sleep_requests: f64,
+    /// Minimum seconds to sleep before each download (`--sleep-interval`)
+    pub sleep_interval: f64,
+    /// Upper bound of the randomized sleep before each download (`--max-sleep-interval`)
+    pub max_sleep_interval: f64,
+}
+
+impl Default for Politeness {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_batch_size: 25,
+            sleep_requests: 0.75,
+            sleep_interval: 2.0,
+            max_sleep_interval: 6.0,
+        }
+    }
+}
+
+/// How much yt-dlp prints (`--quiet` / `--verbose`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verbosity {
+    Quiet,
+    #[default]
+    Normal,
+    Verbose,
+}
+
+/// Subfolder of the output folder downloads are sorted into, named after yt-dlp's
+/// `playlist_title` or `uploader` field
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderOrganization {
+    #[default]
+    None,
+    /// `<output>/<playlist_title>/`, single videos stay in the output folder
+    Playlist,
+    /// `<output>/<uploader>/`, playlists go into the folder of the channel owning them
+    Uploader,
+}
+
+impl FolderOrganization {
+    /// Create and return the folder downloads go into, the output folder itself when
+    /// the field it is named after is missing
+    fn resolve(
+        self,
+        output_folder: &str,
+        playlist_title: Option<&str>,
+        uploader: Option<&str>,
+    ) -> Result<String, String> {
+        let name = match self {
+            Self::None => None,
+            Self::Playlist => playlist_title,
+            Self::Uploader => uploader,
+        }
+        .map(sanitize_filename)
+        .filter(|name| !name.trim().is_empty());
+        let Some(name) = name else {
+            return Ok(output_folder.to_string());
+        };
+        let folder = Path::new(output_folder).join(name.trim());
+        std::fs::create_dir_all(&folder)
+            .map_err(|e| format!("Failed to create folder {}: {}", folder.display(), e))?;
+        Ok(folder.to_string_lossy().to_string())
+    }
+}
+
+/// Playlist items to download, by their position in the playlist starting at 1.
+/// Everything is downloaded when nothing is set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlaylistSelection {
+    /// Positions and ranges like yt-dlp's `--playlist-items`, e.g. `1,3,10-25,40-`
+    pub playlist_items: Option<String>,
+    pub playlist_start: Option<usize>,
+    /// Also limits a channel download to its latest uploads
+    pub playlist_end: Option<usize>,
+    /// Only videos uploaded on or after this day, `YYYYMMDD` or `YYYY-MM-DD`. Meant
+    /// for channels, whose listing carries approximate upload dates.
+    pub uploaded_after: Option<String>,
+}
+
+impl PlaylistSelection {
+    /// Whether every item is selected
+    fn is_all(&self) -> bool {
+        self.playlist_start.is_none()
+            && self.playlist_end.is_none()
+            && self.uploaded_after.is_none()
+            && self
+                .playlist_items
+                .as_deref()
+                .is_none_or(|items| items.trim().is_empty())
+    }
+
+    /// `uploaded_after` as `YYYYMMDD`, the form yt-dlp reports upload dates in
+    fn uploaded_after(&self) -> Result<Option<String>, String> {
+        let Some(date) = self.uploaded_after.as_deref().map(str::trim) else {
+            return Ok(None);
+        };
+        if date.is_empty() {
+            return Ok(None);
+        }
+        let compact = date.replace('-', "");
+        if chrono::NaiveDate::parse_from_str(&compact, "%Y%m%d").is_err() {
+            return Err(format!(
+                "Invalid upload date '{}', expected YYYY-MM-DD",
+                date
+            ));
+        }
+        Ok(Some(compact))
+    }
+
+    /// Inclusive position ranges the selection allows, the start and end bounds
+    /// applied to every range
+    pub fn ranges(&self) -> Result<Vec<(usize, usize)>, String> {
+        let start = self.playlist_start.unwrap_or(1).max(1);
+        let end = self.playlist_end.unwrap_or(usize::MAX);
+        if start > end {
+            return Err(format!(
+                "The playlist start {} comes after the end {}",
+                start, end
+            ));
+        }
+        let items = self.playlist_items.as_deref().unwrap_or("").trim();
+        if items.is_empty() {
+            return Ok(vec![(start, end)]);
+        }
+
+        let position = |value: &str, item: &str| {
+            value
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|&p| p > 0)
+                .ok_or_else(|| format!("Invalid playlist item '{}'", item.trim()))
+        };
+        let mut ranges = Vec::new();
+        for item in items.split(',').filter(|item| !item.trim().is_empty()) {
+            let (first, last) = match item.split_once('-') {
+                Some((first, last)) => (
+                    position(first, item)?,
+                    if last.trim().is_empty() {
+                        usize::MAX
+                    } else {
+                        position(last, item)?
+                    },
+                ),
+                None => {
+                    let p = position(item, item)?;
+                    (p, p)
+                }
+            };
+            if first > last {
+                return Err(format!("Invalid playlist item range '{}'", item.trim()));
+            }
+            if first.max(start) <= last.min(end) {
+                ranges.push((first.max(start), last.min(end)));
+            }
+        }
+        Ok(ranges)
+    }
+}
+
+/// Part of a single video to download, with times as `HH:MM:SS`, `MM:SS` or seconds.
+/// Either end may be left out to start at the beginning or run to the end.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipRange {
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
 }
 
-pub async fn ensure_ytdlp(app_handle: &AppHandle) -> Result<String, String> {
+impl ClipRange {
+    /// The range in seconds, `None` when the whole video is downloaded
+    pub fn resolve(&self) -> Result<Option<Clip>, String> {
+        let time = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(parse_timestamp)
+                .transpose()
+        };
+        let (start, end) = (time(&self.start_time)?, time(&self.end_time)?);
+        if start.is_none() && end.is_none() {
+            return Ok(None);
+        }
+        let start = start.unwrap_or(0.0);
+        if end.is_some_and(|end| end <= start) {
+            return Err("The clip end time must come after its start time".to_string());
+        }
+        Ok(Some(Clip { start, end }))
+    }
+}
+
+/// A resolved `ClipRange`, passed to yt-dlp as `--download-sections`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clip {
+    pub start: f64,
+    /// Runs to the end of the video when unset
+    pub end: Option<f64>,
+}
+
+impl Clip {
+    fn section_arg(&self) -> String {
+        match self.end {
+            Some(end) => format!("*{}-{}", self.start, end),
+            None => format!("*{}-inf", self.start),
+        }
+    }
+
+    /// Filename suffix telling the clip apart from the whole video, like `(1m30s-4m05s)`
+    fn label(&self) -> String {
+        let end = self
+            .end
+            .map(clock_label)
+            .unwrap_or_else(|| "end".to_string());
+        format!("({}-{})", clock_label(self.start), end)
+    }
+
+    /// Length of the clip in a video that is `duration` seconds long
+    fn length(&self, duration: Option<f64>) -> Option<f64> {
+        let end = match (self.end, duration) {
+            (Some(end), Some(duration)) => end.min(duration),
+            (Some(end), None) => end,
+            (None, duration) => duration?,
+        };
+        Some((end - self.start).max(0.0))
+    }
+}
+
+/// Seconds in a `HH:MM:SS`, `MM:SS` or plain seconds time, fractions allowed in the
+/// seconds
+fn parse_timestamp(value: &str) -> Result<f64, String> {
+    let invalid = || {
+        format!(
+            "Invalid time '{}', expected HH:MM:SS, MM:SS or seconds",
+            value
+        )
+    };
+    let parts: Vec<&str> = value.split(':').collect();
+    if parts.len() > 3 {
+        return Err(invalid());
+    }
+    let (seconds, whole) = parts.split_last().ok_or_else(invalid)?;
+    let seconds: f64 = seconds
+        .parse()
+        .ok()
+        .filter(|s: &f64| s.is_finite() && *s >= 0.0)
+        .filter(|&s| whole.is_empty() || s < 60.0)
+        .ok_or_else(invalid)?;
+    let mut total = 0.0;
+    for (index, part) in whole.iter().enumerate() {
+        let value: u32 = part.parse().map_err(|_| invalid())?;
+        // Minutes after hours stay below an hour
+        if index > 0 && value >= 60 {
+            return Err(invalid());
+        }
+        total = total * 60.0 + value as f64;
+    }
+    Ok(total * 60.0 + seconds)
+}
+
+/// `1h02m03s`, `2m03s` or `45s`, safe to use in filenames
+fn clock_label(seconds: f64) -> String {
+    let seconds = seconds.floor() as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}h{:02}m{:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m{:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Settings picked for a single download over the saved preferences
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadOverrides {
+    pub audio_format: Option<AudioFormat>,
+    /// Cut sponsor and non-music segments out with SponsorBlock
+    pub sponsorblock: Option<bool>,
+    /// Also save each chapter as a separate track
+    pub split_chapters: Option<bool>,
+    pub audio_preset: Option<AudioPreset>,
+    /// Playlist items to download, all of them when unset
+    #[serde(flatten)]
+    pub selection: PlaylistSelection,
+    /// Part of a single video to download, all of it when unset
+    #[serde(flatten)]
+    pub clip: ClipRange,
+    /// Tags written over the ones found in the video info, set by a channel rule
+    pub tags: TagOverrides,
+}
+
+/// Extra yt-dlp settings applied to every invocation of a download
+#[derive(Debug, Clone, Default)]
+pub struct DownloadOptions {
+    pub extractor_args: Option<ExtractorArgs>,
+    /// Browser to impersonate via `--impersonate`; needs a curl_cffi-enabled yt-dlp build
+    pub impersonate: Option<String>,
+    pub user_agent: Option<String>,
+    /// cookies.txt passed to `--cookies`, for age-restricted and members-only videos
+    pub cookies: Option<PathBuf>,
+    /// Proxy URL passed to `--proxy`
+    pub proxy: Option<String>,
+    /// Pipe single downloads from yt-dlp straight into ffmpeg instead of converting
+    /// a downloaded file
+    pub stream_conversion: bool,
+    /// Download speed cap passed to `--limit-rate`, e.g. `500K` or `2M` bytes per second
+    pub max_download_rate: Option<String>,
+    /// Throttling and failures injected into yt-dlp runs for testing, when enabled
+    pub network_simulation: Option<NetworkSimulation>,
+    pub politeness: Politeness,
+    /// yt-dlp format selector used instead of `bestaudio/best`
+    pub format: Option<String>,
+    /// Download into the media cache and transcode from there, when enabled
+    pub media_cache: Option<MediaCacheSettings>,
+    /// Split very long videos into separately downloaded sections
+    pub sections: Option<SectionedDownload>,
+    /// Romanize non-Latin titles in filenames, keeping the original title in the tags
+    pub transliterate: bool,
+    /// Write title, artist and album tags into the downloaded files
+    pub embed_metadata: bool,
+    /// Tags written over the ones found in the video info, even with tagging off
+    pub tag_overrides: TagOverrides,
+    /// Embed the video thumbnail as cover art. Only yt-dlp's own conversion embeds it,
+    /// so streamed downloads fall back to a file and cached ones have no art.
+    pub embed_thumbnail: bool,
+    /// Cut sponsor and non-music segments out with SponsorBlock
+    pub sponsorblock: bool,
+    /// Also save each chapter as a separate track, into a folder named after the video
+    pub split_chapters: bool,
+    /// Save subtitles next to each track as lyrics, set only when enabled
+    pub lyrics: Option<LyricsSettings>,
+    /// Remove leading and trailing silence while encoding, set only when enabled
+    pub silence_trim: Option<SilenceTrim>,
+    /// Compression and volume post-processing applied while encoding
+    pub audio_preset: AudioPreset,
+    /// Two-pass loudness normalization of each finished track, set only when enabled
+    pub loudness: Option<LoudnessNormalization>,
+    /// Playlist items that are fetched, the others are left out before anything downloads
+    pub playlist_selection: PlaylistSelection,
+    /// Part of a single video that is downloaded, all of it when unset
+    pub clip: Option<Clip>,
+    pub verbosity: Verbosity,
+    /// Playlist items downloaded at the same time, 1 to `MAX_CONCURRENT_DOWNLOADS`
+    pub concurrency: usize,
+    /// Scale the playlist items running at once between 1 and `concurrency` with the
+    /// CPU and I/O load
+    pub adaptive_concurrency: bool,
+    pub retry: RetryPolicy,
+    /// Write folder.jpg, album.nfo and metadata.json into the folder of a playlist download
+    pub folder_artwork: bool,
+    /// Format the audio is converted to
+    pub audio_format: AudioFormat,
+    /// Subfolder of the output folder each download goes into
+    pub folder_organization: FolderOrganization,
+    /// Whether `politeness` applies, decided once the batch size is known
+    pub(crate) paced: bool,
+    /// Cancelled by `cancel_download`
+    pub(crate) cancel: CancellationToken,
+}
+
+impl DownloadOptions {
+    /// Options for a batch of `batch_size` items, pacing requests for large batches
+    fn for_batch(&self, batch_size: usize) -> Self {
+        let mut options = self.clone();
+        options.paced = self.politeness.enabled && batch_size >= self.politeness.min_batch_size;
+        options
+    }
+
+    /// The same options with verbose output, for retrying something that failed
+    fn escalated(&self, verbose: bool) -> Self {
+        let mut options = self.clone();
+        if verbose {
+            options.verbosity = Verbosity::Verbose;
+        }
+        options
+    }
+
+    /// Whether tags are written at all. A romanized filename needs the original title
+    /// kept in the tags either way.
+    fn writes_tags(&self) -> bool {
+        self.embed_metadata || self.transliterate || !self.tag_overrides.is_empty()
+    }
+
+    /// Tags to write into the files of a video, none when tagging is off
+    pub(crate) fn tags_for(&self, video_info: &serde_json::Value) -> Vec<(&'static str, String)> {
+        if self.writes_tags() {
+            let mut tags = tags::from_video_info(video_info);
+            self.tag_overrides.apply(&mut tags);
+            tags
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Whether the download needs yt-dlp's own post-processing, which the media cache,
+    /// streaming and sections all bypass. Clips are cut by yt-dlp while downloading.
+    fn needs_ytdlp_postprocessing(&self) -> bool {
+        self.sponsorblock || self.split_chapters || self.lyrics.is_some() || self.clip.is_some()
+    }
+
+    /// Whether downloads go through the media cache
+    fn uses_media_cache(&self) -> bool {
+        self.media_cache.is_some() && !self.needs_ytdlp_postprocessing()
+    }
+
+    /// ffmpeg audio filter applied while encoding, if any. Silence is trimmed before
+    /// the preset boosts what is left.
+    pub(crate) fn audio_filter(&self) -> Option<String> {
+        let filters: Vec<String> = self
+            .silence_trim
+            .as_ref()
+            .map(SilenceTrim::filter)
+            .into_iter()
+            .chain(self.audio_preset.filter().map(str::to_string))
+            .collect();
+        (!filters.is_empty()).then(|| filters.join(","))
+    }
+
+    /// ffmpeg arguments encoding to `audio_format` through the audio filter. Filtered
+    /// audio has to be re-encoded, so `copy` only applies without a filter.
+    pub(crate) fn encoder_args(&self, bitrate: u32, copy: bool) -> Vec<String> {
+        let filter = self.audio_filter();
+        let mut args = Vec::new();
+        if let Some(ref filter) = filter {
+            args.push("-af".to_string());
+            args.push(filter.clone());
+        }
+        args.extend(
+            self.audio_format
+                .ffmpeg_args(bitrate, copy && filter.is_none()),
+        );
+        args
+    }
+
+    /// Whether audio in `acodec` ends up copied into the output file without re-encoding
+    fn copies_audio(&self, acodec: Option<&str>) -> bool {
+        self.audio_filter().is_none() && self.audio_format.matches_codec(acodec)
+    }
+
+    /// Whether yt-dlp is asked to embed the thumbnail into the output file
+    fn embeds_thumbnail(&self) -> bool {
+        self.embed_thumbnail && self.audio_format.supports_cover_art()
+    }
+
+    pub(crate) fn format_selector(&self) -> &str {
+        self.format
+            .as_deref()
+            .filter(|f| !f.trim().is_empty())
+            .unwrap_or("bestaudio/best")
+    }
+}
+
+/// Normalize a download rate such as `500k` or `1.5 M` to the form `--limit-rate` takes
+pub fn parse_download_rate(rate: &str) -> Result<String, String> {
+    let rate = rate.trim().replace(' ', "");
+    let (number, suffix) = match rate.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&rate[..i], c.to_ascii_uppercase().to_string()),
+        _ => (rate.as_str(), String::new()),
+    };
+    let valid_suffix = matches!(suffix.as_str(), "" | "K" | "M" | "G");
+    match number.parse::<f64>() {
+        Ok(value) if valid_suffix && value > 0.0 && value.is_finite() => {
+            Ok(format!("{}{}", number, suffix))
+        }
+        _ => Err(format!(
+            "Invalid download rate '{}', use bytes per second with an optional K, M or G suffix such as 500K",
+            rate
+        )),
+    }
+}
+
+/// Create a yt-dlp command with the download options already applied
+pub(crate) fn ytdlp_command(ytdlp_cmd: &str, options: &DownloadOptions) -> Command {
+    let mut cmd = Command::new(ytdlp_cmd);
+    cmd.env_clear()
+        .envs(deps::isolated_env())
+        // Keep progress output in the C locale so numbers use `.` decimals and
+        // English units, while still emitting UTF-8 titles
+        .env("LC_ALL", "C")
+        .env("PYTHONIOENCODING", "utf-8");
+    // A yt-dlp config file of the user's would change formats and output names
+    cmd.arg("--ignore-config");
+    // Pick up the .part file of a download the app was interrupted in
+    cmd.arg("--continue");
+    if let Some(cache_dir) = deps::ytdlp_cache_dir() {
+        cmd.arg("--cache-dir").arg(cache_dir);
+    }
+    if let Some(arg) = options.extractor_args.as_ref().and_then(|a| a.to_arg()) {
+        cmd.arg("--extractor-args").arg(arg);
+    }
+    if let Some(ref target) = options.impersonate {
+        cmd.arg("--impersonate").arg(target);
+    }
+    if let Some(ref user_agent) = options.user_agent {
+        cmd.arg("--user-agent").arg(user_agent);
+    }
+    if let Some(ref cookies) = options.cookies {
+        cmd.arg("--cookies").arg(cookies);
+    }
+    if let Some(ref proxy) = options.proxy {
+        cmd.arg("--proxy").arg(proxy);
+    }
+    if let Some(ref rate) = options.max_download_rate {
+        cmd.arg("--limit-rate").arg(rate);
+    }
+    match options.verbosity {
+        // Keep the progress lines the playlist progress reporting relies on
+        Verbosity::Quiet => {
+            cmd.arg("--quiet").arg("--progress");
+        }
+        Verbosity::Verbose => {
+            cmd.arg("--verbose");
+        }
+        Verbosity::Normal => {}
+    }
+    if options.paced {
+        let politeness = &options.politeness;
+        cmd.arg("--sleep-requests")
+            .arg(politeness.sleep_requests.to_string())
+            .arg("--sleep-interval")
+            .arg(politeness.sleep_interval.to_string())
+            .arg("--max-sleep-interval")
+            .arg(
+                politeness
+                    .max_sleep_interval
+                    .max(politeness.sleep_interval)
+                    .to_string(),
+            );
+    }
+    if let Some(ref simulation) = options.network_simulation {
+        simulation.apply(&mut cmd);
+    }
+    cmd
+}
+
+pub async fn ensure_ytdlp<R: Runtime>(app_handle: &AppHandle<R>) -> Result<String, String> {
     deps::get_bundled_binary(app_handle, "yt-dlp")
         .map(|p| p.to_string_lossy().to_string())
         .map_err(|e| format!("Failed to get bundled yt-dlp: {}", e))
 }
 
-pub async fn ensure_ffmpeg(app_handle: &AppHandle) -> Result<String, String> {
+pub async fn ensure_ffmpeg<R: Runtime>(app_handle: &AppHandle<R>) -> Result<String, String> {
     deps::get_bundled_binary(app_handle, "ffmpeg")
         .map(|p| p.to_string_lossy().to_string())
         .map_err(|e| format!("Failed to get bundled ffmpeg: {}", e))
 }
 
-pub async fn download_youtube(
+/// URLs whose last download failed this session, retried with verbose output
+static FAILED_URLS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn previously_failed(url: &str) -> bool {
+    FAILED_URLS.lock().unwrap().iter().any(|u| u == url)
+}
+
+fn record_outcome(url: &str, success: bool) {
+    let mut failed = FAILED_URLS.lock().unwrap();
+    failed.retain(|u| u != url);
+    if !success {
+        failed.push(url.to_string());
+    }
+}
+
+pub async fn download_youtube<R: Runtime>(
     url: &str,
     output_folder: &str,
     bitrate: u32,
-    app_handle: &AppHandle,
+    audio_format: AudioFormat,
+    options: &DownloadOptions,
+    app_handle: &AppHandle<R>,
 ) -> Result<DownloadResult, String> {
-    if !is_youtube_url(url) {
-        return Err("Invalid YouTube URL. Please provide a valid YouTube video URL.".to_string());
+    let started = std::time::SystemTime::now();
+    let mut options = options.escalated(previously_failed(url));
+    options.audio_format = audio_format;
+    // The folder organization can move the download into a subfolder, which is where a
+    // cancelled download leaves its partial files
+    let mut files = DownloadFiles {
+        folder: output_folder.to_string(),
+        names: Vec::new(),
+    };
+    let result = download_single(
+        url,
+        output_folder,
+        bitrate,
+        &options,
+        app_handle,
+        &mut files,
+    )
+    .await;
+    if options.cancel.is_cancelled() {
+        finish_cancelled(app_handle, &files, started);
+        return Err(CANCELLED_MESSAGE.to_string());
     }
+    record_outcome(url, result.is_ok());
+    result
+}
+
+/// Download one video into `output_folder`, or the subfolder the folder organization
+/// picks for it, which is set in `files` as soon as it is known along with the names
+/// of its files
+async fn download_single<R: Runtime>(
+    url: &str,
+    output_folder: &str,
+    bitrate: u32,
+    options: &DownloadOptions,
+    app_handle: &AppHandle<R>,
+    files: &mut DownloadFiles,
+) -> Result<DownloadResult, String> {
+    source::parse_source(url)?;
 
     let ytdlp_cmd = match ensure_ytdlp(app_handle).await {
         Ok(cmd) => cmd,
@@ -60,6 +1168,7 @@ pub async fn download_youtube(
             return Err(format!("Failed to get bundled yt-dlp: {}", e));
         }
     };
+    probe_site(&ytdlp_cmd, url, options).await?;
 
     let ffmpeg_cmd = match ensure_ffmpeg(app_handle).await {
         Ok(cmd) => cmd,
@@ -72,11 +1181,14 @@ pub async fn download_youtube(
         .parent()
         .ok_or("Failed to get ffmpeg directory")?;
 
-    let info_output = Command::new(&ytdlp_cmd)
+    let mut info_cmd = ytdlp_command(&ytdlp_cmd, options);
+    info_cmd
         .arg("--dump-json")
         .arg("--no-playlist")
-        .arg(url)
-        .output()
+        .arg("-f")
+        .arg(options.format_selector())
+        .arg(url);
+    let info_output = run_output(&mut info_cmd, &options.cancel)
         .await
         .map_err(|e| {
             format!(
@@ -117,80 +1229,363 @@ pub async fn download_youtube(
         })?;
 
     let title = video_info["title"].as_str().map(|s| sanitize_filename(s));
+    let mut file_stem = video_info["title"].as_str().map(|s| match options.clip {
+        Some(ref clip) => format!("{} {}", file_stem_for(s, options), clip.label()),
+        None => file_stem_for(s, options),
+    });
+
+    let duration = match options.clip {
+        Some(ref clip) => clip.length(video_info["duration"].as_f64()),
+        None => video_info["duration"].as_f64(),
+    };
+    if let (Some(clip), Some(total)) = (options.clip, video_info["duration"].as_f64()) {
+        if clip.start >= total {
+            return Err(format!(
+                "The clip starts at {} but the video is only {} long",
+                clock_label(clip.start),
+                clock_label(total)
+            ));
+        }
+    }
 
-    let duration = video_info["duration"].as_f64();
+    let uploader = ["uploader", "channel"]
+        .iter()
+        .filter_map(|field| video_info[*field].as_str())
+        .find(|s| !s.is_empty());
+    let output_folder = &options
+        .folder_organization
+        .resolve(output_folder, None, uploader)?;
+    files.folder.clone_from(output_folder);
 
     // Determine the expected output path
-    let output_path = if let Some(ref t) = title {
-        Path::new(output_folder).join(format!("{}.mp3", t))
+    let extension = options.audio_format.extension();
+    let mut output_path = if let Some(ref stem) = file_stem {
+        Path::new(output_folder).join(format!("{}.{}", stem, extension))
     } else {
         // Fallback: use video ID or default name
         let video_id = video_info["id"].as_str().unwrap_or("video");
-        Path::new(output_folder).join(format!("{}.mp3", video_id))
+        Path::new(output_folder).join(format!("{}.{}", video_id, extension))
     };
 
-    // Check if file already exists before downloading
+    // Ask what to do when the file already exists, skipping the download by default
+    let mut renamed = false;
     if output_path.exists() {
-        // File already exists, skip download and return existing file info
+        let choice = decisions::request(
+            app_handle,
+            DecisionKind::Overwrite,
+            format!("{} already exists. Overwrite it?", output_path.display()),
+            &["overwrite", "keep_both", "skip"],
+            "skip",
+            serde_json::json!({
+                "path": output_path.to_string_lossy(),
+                "title": title,
+            }),
+        )
+        .await;
+        match choice.as_deref() {
+            Some("overwrite") => {
+                app_lock::ensure_unlocked("overwrite")?;
+                std::fs::remove_file(&output_path)
+                    .map_err(|e| format!("Failed to replace {}: {}", output_path.display(), e))?;
+            }
+            Some("keep_both") => {
+                let stem = output_path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let (free_stem, free_path) = (2..)
+                    .map(|n| {
+                        let candidate = format!("{} ({})", stem, n);
+                        let path =
+                            Path::new(output_folder).join(format!("{}.{}", candidate, extension));
+                        (candidate, path)
+                    })
+                    .find(|(_, path)| !path.exists())
+                    .expect("unbounded range always finds a free name");
+                file_stem = Some(free_stem);
+                output_path = free_path;
+                renamed = true;
+            }
+            _ => {
+                let file_size = std::fs::metadata(&output_path).ok().map(|m| m.len());
+
+                return Ok(DownloadResult {
+                    output_path: output_path.to_string_lossy().to_string(),
+                    title,
+                    duration,
+                    file_size,
+                    format_id: None,
+                    lossless_copy: false,
+                    chapter_paths: Vec::new(),
+                    lyrics_paths: Vec::new(),
+                });
+            }
+        }
+    }
+
+    files.add_names(
+        file_stem.as_deref(),
+        video_info["title"].as_str(),
+        video_info["id"].as_str(),
+    );
+
+    // Re-encode from the media cache instead of downloading again, if possible
+    let cache_key = video_info["id"]
+        .as_str()
+        .filter(|_| options.uses_media_cache());
+    if let Some(cached) = cache_key.and_then(media_cache::lookup) {
+        let lossless_copy = media_cache::transcode(
+            &ffmpeg_cmd,
+            &cached,
+            &output_path,
+            bitrate,
+            options,
+            &options.tags_for(&video_info),
+        )
+        .await?;
         let file_size = std::fs::metadata(&output_path).ok().map(|m| m.len());
 
-        return Ok(DownloadResult {
-            output_path: output_path.to_string_lossy().to_string(),
-            title,
-            duration,
-            file_size,
-        });
+        return Ok(normalized(
+            &ffmpeg_cmd,
+            bitrate,
+            options,
+            DownloadResult {
+                output_path: output_path.to_string_lossy().to_string(),
+                title,
+                duration,
+                file_size,
+                format_id: None,
+                lossless_copy,
+                chapter_paths: Vec::new(),
+                lyrics_paths: Vec::new(),
+            },
+        )
+        .await);
     }
 
-    let output_path_buf = Path::new(output_folder);
-    let output_template = output_path_buf.join("%(title)s.%(ext)s");
-    let output_template_str = output_template.to_string_lossy().to_string();
+    // Sections are joined locally, where yt-dlp can't cut or split them
+    if !options.needs_ytdlp_postprocessing()
+        && options
+            .sections
+            .as_ref()
+            .is_some_and(|s| s.applies_to(duration))
+    {
+        sections::download_in_sections(
+            &ytdlp_cmd,
+            &ffmpeg_cmd,
+            url,
+            &video_info,
+            &output_path,
+            bitrate,
+            options,
+        )
+        .await
+        .map_err(with_workaround_hint)?;
+        let file_size = std::fs::metadata(&output_path).ok().map(|m| m.len());
 
-    let download_output = Command::new(&ytdlp_cmd)
-        .arg("-x")
-        .arg("--audio-format")
-        .arg("mp3")
-        .arg("--audio-quality")
-        .arg(format!("{}K", bitrate))
-        .arg("--ffmpeg-location")
-        .arg(ffmpeg_dir)
-        .arg("-o")
-        .arg(&output_template_str)
-        .arg("--no-playlist")
-        .arg(url)
-        .output()
+        return Ok(normalized(
+            &ffmpeg_cmd,
+            bitrate,
+            options,
+            DownloadResult {
+                output_path: output_path.to_string_lossy().to_string(),
+                title,
+                duration,
+                file_size,
+                format_id: video_info["format_id"].as_str().map(|s| s.to_string()),
+                lossless_copy: false,
+                chapter_paths: Vec::new(),
+                lyrics_paths: Vec::new(),
+            },
+        )
+        .await);
+    }
+
+    // Piping into ffmpeg leaves no place for yt-dlp to embed the thumbnail, cut segments
+    // or split chapters
+    if options.stream_conversion
+        && cache_key.is_none()
+        && !options.embeds_thumbnail()
+        && !options.needs_ytdlp_postprocessing()
+    {
+        let lossless_copy = options.copies_audio(downloaded_acodec(&video_info, None));
+        match streaming::download_streamed(
+            &ytdlp_cmd,
+            &ffmpeg_cmd,
+            url,
+            &video_info,
+            &output_path,
+            (!lossless_copy).then_some(bitrate),
+            options,
+        )
         .await
-        .map_err(|e| format!("Download failed: {}", e))?;
+        {
+            Ok(()) => {
+                let file_size = std::fs::metadata(&output_path).ok().map(|m| m.len());
+                return Ok(normalized(
+                    &ffmpeg_cmd,
+                    bitrate,
+                    options,
+                    DownloadResult {
+                        output_path: output_path.to_string_lossy().to_string(),
+                        title,
+                        duration,
+                        file_size,
+                        format_id: video_info["format_id"].as_str().map(|s| s.to_string()),
+                        lossless_copy,
+                        chapter_paths: Vec::new(),
+                        lyrics_paths: Vec::new(),
+                    },
+                )
+                .await);
+            }
+            Err(e) if options.cancel.is_cancelled() => return Err(e),
+            Err(e) => {
+                eprintln!(
+                    "[download] Streaming conversion failed, downloading to a file instead: {}",
+                    e
+                );
+                download_log::append(
+                    video_info["id"].as_str().unwrap_or_default(),
+                    "streamed",
+                    &e,
+                );
+            }
+        }
+    }
 
-    if !download_output.status.success() {
-        let error = String::from_utf8_lossy(&download_output.stderr);
-        return Err(format!("Download failed: {}", error));
+    let output_template_str = output_template(
+        Path::new(output_folder),
+        file_stem
+            .as_deref()
+            .filter(|_| options.transliterate || options.clip.is_some() || renamed),
+    );
+
+    // Walk down the format ladder until one of them downloads successfully
+    let mut used_format: Option<Option<String>> = None;
+    let mut reported_path: Option<PathBuf> = None;
+    let mut last_error = String::new();
+    let video_id = video_info["id"].as_str().unwrap_or_default();
+    let mut attempt_options = options.clone();
+
+    for format_id in format_attempts(&video_info) {
+        let mut cmd = ytdlp_command(&ytdlp_cmd, &attempt_options);
+        add_output_args(
+            &mut cmd,
+            cache_key.is_some(),
+            &output_template_str,
+            bitrate,
+            ffmpeg_dir,
+            options,
+        )?;
+        cmd.arg("--no-playlist");
+        if cache_key.is_none() {
+            // Report the final path, yt-dlp's sanitization differs from ours
+            cmd.arg("--print")
+                .arg("after_move:filepath")
+                .arg("--no-simulate");
+        }
+        if let Some(ref id) = format_id {
+            cmd.arg("-f").arg(id);
+        }
+
+        cmd.arg(url);
+        let attempt = attempt_label(&format_id, &attempt_options);
+        let mut reconnect = Reconnect::new(app_handle, url, &options.cancel);
+        let (download_output, error) = loop {
+            let output = run_output(&mut cmd, &options.cancel)
+                .await
+                .map_err(|e| format!("Download failed: {}", e))?;
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            download_log::append(video_id, &attempt, &error);
+            if output.status.success() || !reconnect.after_failure(&error).await {
+                break (output, error);
+            }
+        };
+
+        if download_output.status.success() {
+            used_format = Some(format_id);
+            reported_path = printed_filepath(&download_output.stdout);
+            break;
+        }
+
+        if !is_format_error(&error) {
+            return Err(with_workaround_hint(format!("Download failed: {}", error)));
+        }
+
+        eprintln!(
+            "[download] Format {} failed, trying next format...",
+            format_id.as_deref().unwrap_or("default")
+        );
+        last_error = error;
+        attempt_options.verbosity = Verbosity::Verbose;
+    }
+
+    let format_id = match used_format {
+        Some(format_id) => format_id,
+        None => {
+            return Err(with_workaround_hint(format!(
+                "Download failed: {}",
+                last_error
+            )))
+        }
+    };
+
+    // yt-dlp copies a source already in the output format instead of converting it
+    let lossless_copy = match cache_key {
+        Some(_) => {
+            transcode_cached(&ffmpeg_cmd, &video_info, &output_path, bitrate, options).await?
+        }
+        None => options.copies_audio(downloaded_acodec(&video_info, format_id.as_deref())),
+    };
+
+    // Trust the path yt-dlp reported over the guessed one
+    let output_path = match reported_path {
+        Some(path) if path.exists() => {
+            if path != output_path {
+                eprintln!(
+                    "[download] yt-dlp saved to {} instead of {}",
+                    path.display(),
+                    output_path.display()
+                );
+            }
+            path
+        }
+        _ => output_path,
+    };
+
+    if options.embeds_thumbnail() {
+        remove_thumbnail_files(&output_path);
     }
 
     // Get file size
     let file_size = std::fs::metadata(&output_path).ok().map(|m| m.len());
 
-    Ok(DownloadResult {
-        output_path: output_path.to_string_lossy().to_string(),
-        title,
-        duration,
-        file_size,
-    })
+    Ok(normalized(
+        &ffmpeg_cmd,
+        bitrate,
+        options,
+        DownloadResult {
+            output_path: output_path.to_string_lossy().to_string(),
+            title,
+            duration,
+            file_size,
+            format_id,
+            lossless_copy,
+            chapter_paths: chapter_files(&output_path, options),
+            lyrics_paths: lyrics_files(&output_path, options),
+        },
+    )
+    .await)
 }
 
-pub async fn download_playlist(
+pub async fn download_playlist<R: Runtime>(
     url: &str,
     output_folder: &str,
     bitrate: u32,
-    app_handle: &AppHandle,
+    app_handle: &AppHandle<R>,
 ) -> Result<PlaylistDownloadResult, String> {
-    if !is_youtube_url(url) {
-        return Err(
-            "Invalid YouTube URL. Please provide a valid YouTube playlist URL.".to_string(),
-        );
-    }
-
-    if !is_playlist_url(url) {
+    if !source::parse_source(url)?.is_playlist() {
         return Err("URL does not appear to be a playlist URL.".to_string());
     }
 
@@ -351,6 +1746,10 @@ pub async fn download_playlist(
                         title: file_name,
                         duration: None, // We don't parse duration for playlist items
                         file_size,
+                        format_id: None,
+                        lossless_copy: false,
+                        chapter_paths: Vec::new(),
+                        lyrics_paths: Vec::new(),
                     });
                 }
             }
@@ -361,11 +1760,14 @@ pub async fn download_playlist(
         output_folder: output_folder.to_string(),
         total_videos,
         downloaded_videos,
+        failed_videos: Vec::new(),
+        cancelled: false,
+        liner_notes_path: None,
     })
 }
 
 /// Helper function to parse yt-dlp progress lines and emit progress events
-fn process_progress_line(
+fn process_progress_line<R: Runtime>(
     line: &str,
     current_song: &mut usize,
     song_progress: &mut f64,
@@ -373,68 +1775,43 @@ fn process_progress_line(
     current_title: &mut Option<String>,
     completed_songs: &mut usize,
     total_videos: usize,
-    app_handle: &AppHandle,
+    app_handle: &AppHandle<R>,
 ) {
     // Pattern 1: [download] XX.X% of YYY at ZZZ ETA MM:SS
     // Pattern 2: [download] 100% of YYY
     // Pattern 3: [download]   XX.X% of ... (with spaces)
     if line.contains("[download]") {
         // Try to extract percentage - look for number followed by %
-        // Use regex-like approach: find % and work backwards
-        if let Some(percent_pos) = line.find('%') {
-            // Find the start of the number
-            let mut num_start = percent_pos;
-            let mut found_digit = false;
-
-            // Work backwards to find the start of the number
-            while num_start > 0 {
-                let ch = line.chars().nth(num_start - 1).unwrap_or(' ');
-                if ch.is_ascii_digit() || ch == '.' {
-                    found_digit = true;
-                    num_start -= 1;
-                } else if found_digit {
-                    // We've found the start of the number
-                    break;
-                } else {
-                    num_start -= 1;
-                }
-            }
+        if let Some(new_progress) = parse_progress_percent(line) {
+            // Only update if progress actually changed (avoid spam)
+            if (new_progress - *song_progress).abs() > 0.1 || *song_progress == 0.0 {
+                *song_progress = new_progress;
+                *status = "Downloading...".to_string();
 
-            if found_digit && num_start < percent_pos {
-                let percent_str = &line[num_start..percent_pos].trim();
-                if let Ok(percent) = percent_str.parse::<f64>() {
-                    let new_progress = percent.min(100.0).max(0.0);
-
-                    // Only update if progress actually changed (avoid spam)
-                    if (new_progress - *song_progress).abs() > 0.1 || *song_progress == 0.0 {
-                        *song_progress = new_progress;
-                        *status = "Downloading...".to_string();
-
-                        let overall_progress = if total_videos > 0 {
-                            ((*completed_songs as f64 + *song_progress / 100.0)
-                                / total_videos as f64)
-                                * 100.0
-                        } else {
-                            *song_progress
-                        };
+                let overall_progress = if total_videos > 0 {
+                    ((*completed_songs as f64 + *song_progress / 100.0) / total_videos as f64)
+                        * 100.0
+                } else {
+                    *song_progress
+                };
 
-                        let progress = DownloadProgress {
-                            overall_progress,
-                            current_song: Some(*current_song + 1),
-                            total_songs: Some(total_videos),
-                            song_progress: *song_progress,
-                            status: status.clone(),
-                            current_title: current_title.clone(),
-                        };
-                        app_handle.emit_all("download-progress", progress).ok();
-                    }
-                }
+                let progress = DownloadProgress {
+                    overall_progress,
+                    current_song: Some(*current_song + 1),
+                    total_songs: Some(total_videos),
+                    song_progress: *song_progress,
+                    status: status.clone(),
+                    current_title: current_title.clone(),
+                    estimated_size: None,
+                    transfer: TransferStats::default(),
+                };
+                emit_progress(app_handle, progress);
             }
+        }
 
-            // Check if download is complete (100%)
-            if line.contains("100%") || (*song_progress >= 99.9 && line.contains("[download]")) {
-                *song_progress = 100.0;
-            }
+        // Check if download is complete (100%)
+        if line.contains("100%") || (*song_progress >= 99.9 && line.contains('%')) {
+            *song_progress = 100.0;
         }
     }
     // Pattern: [ExtractAudio] Destination: ...
@@ -455,8 +1832,10 @@ fn process_progress_line(
             song_progress: 90.0,
             status: status.clone(),
             current_title: current_title.clone(),
+            estimated_size: None,
+            transfer: TransferStats::default(),
         };
-        app_handle.emit_all("download-progress", progress).ok();
+        emit_progress(app_handle, progress);
     }
     // Pattern: [Merger] Merging formats into ...
     else if line.contains("[Merger]") || line.contains("Merging formats") {
@@ -476,8 +1855,10 @@ fn process_progress_line(
             song_progress: 95.0,
             status: status.clone(),
             current_title: current_title.clone(),
+            estimated_size: None,
+            transfer: TransferStats::default(),
         };
-        app_handle.emit_all("download-progress", progress).ok();
+        emit_progress(app_handle, progress);
     }
     // Pattern: [youtube] Extracting URL: ... or [youtube] Video ID: ...
     // This indicates a new video is starting
@@ -517,8 +1898,10 @@ fn process_progress_line(
                     song_progress: 0.0,
                     status: status.clone(),
                     current_title: None,
+                    estimated_size: None,
+                    transfer: TransferStats::default(),
                 };
-                app_handle.emit_all("download-progress", progress).ok();
+                emit_progress(app_handle, progress);
             }
         }
     }
@@ -546,8 +1929,10 @@ fn process_progress_line(
                 song_progress: 100.0,
                 status: status.clone(),
                 current_title: current_title.clone(),
+                estimated_size: None,
+                transfer: TransferStats::default(),
             };
-            app_handle.emit_all("download-progress", progress).ok();
+            emit_progress(app_handle, progress);
         }
     }
     // Pattern: Downloading playlist: ... or [playlist] ...
@@ -576,8 +1961,10 @@ fn process_progress_line(
                         song_progress: 0.0,
                         status: status.clone(),
                         current_title: current_title.clone(),
+                        estimated_size: None,
+                        transfer: TransferStats::default(),
                     };
-                    app_handle.emit_all("download-progress", progress).ok();
+                    emit_progress(app_handle, progress);
                 }
             }
         }
@@ -608,8 +1995,10 @@ fn process_progress_line(
                         song_progress: *song_progress,
                         status: status.clone(),
                         current_title: current_title.clone(),
+                        estimated_size: None,
+                        transfer: TransferStats::default(),
                     };
-                    app_handle.emit_all("download-progress", progress).ok();
+                    emit_progress(app_handle, progress);
                 }
             }
         }
@@ -630,45 +2019,237 @@ fn process_progress_line(
     }
 }
 
-pub async fn download_playlist_with_progress(
-    url: &str,
-    output_folder: &str,
-    bitrate: u32,
-    app_handle: AppHandle,
-) -> Result<PlaylistDownloadResult, String> {
-    if !is_youtube_url(url) {
-        return Err(
-            "Invalid YouTube URL. Please provide a valid YouTube playlist URL.".to_string(),
-        );
-    }
-
-    if !is_playlist_url(url) {
-        return Err("URL does not appear to be a playlist URL.".to_string());
-    }
+/// Progress bookkeeping for a single playlist item, shared by every format attempt
+struct ItemProgressContext<'a, R: Runtime> {
+    run: &'a PlaylistRun<'a, R>,
+    index: usize,
+    current_title: Option<String>,
+    /// Key for the speed history of this item
+    video_id: Option<String>,
+    estimated_size: Option<u64>,
+    cancel: CancellationToken,
+}
 
-    let ytdlp_cmd = match ensure_ytdlp(&app_handle).await {
-        Ok(cmd) => cmd,
-        Err(e) => {
-            return Err(format!("Failed to get bundled yt-dlp: {}", e));
+/// Send each line of a child process stream to `tx` until the stream closes
+pub(crate) fn forward_lines<R>(stream: R, tx: tokio::sync::mpsc::UnboundedSender<String>)
+where
+    R: tokio::io::AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stream).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if tx.send(line).is_err() {
+                break;
+            }
         }
-    };
+    });
+}
 
-    let ffmpeg_cmd = match ensure_ffmpeg(&app_handle).await {
-        Ok(cmd) => cmd,
-        Err(e) => {
-            return Err(format!("Failed to get bundled ffmpeg: {}", e));
+/// Run one yt-dlp download for a playlist item, emitting progress events as it goes.
+/// Returns whether the process succeeded along with its captured stderr output. A
+/// cancelled download kills the process and counts as a failure.
+async fn run_item_download<R: Runtime>(
+    cmd: &mut Command,
+    ctx: &ItemProgressContext<'_, R>,
+) -> Result<(bool, String), String> {
+    let index = ctx.index;
+    let total_videos = ctx.run.total_videos;
+    let current_song_num = index + 1;
+
+    let mut child = cmd
+        .kill_on_drop(true)
+        .stderr(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            format!(
+                "Failed to start download for video {}: {}",
+                current_song_num, e
+            )
+        })?;
+
+    // yt-dlp prints progress to stdout and errors to stderr, read both as they arrive
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    forward_lines(
+        child.stdout.take().ok_or("Failed to capture stdout")?,
+        tx.clone(),
+    );
+    forward_lines(child.stderr.take().ok_or("Failed to capture stderr")?, tx);
+    let mut song_progress = 0.0;
+    let mut stderr_output = String::new();
+
+    // Parse progress for this single video
+    loop {
+        let line = tokio::select! {
+            line = rx.recv() => line,
+            _ = ctx.cancel.cancelled() => {
+                child.kill().await.ok();
+                return Ok((false, stderr_output));
+            }
+        };
+        match line {
+            None => break, // Both streams closed
+            Some(line) => {
+                let line = line.trim();
+                let report = ProgressReport::parse(line);
+                // The JSON progress lines would drown out the errors in the download log
+                if report.is_none() {
+                    stderr_output.push_str(line);
+                    stderr_output.push('\n');
+                }
+                if !line.is_empty() {
+                    // Structured progress from PROGRESS_TEMPLATE, or a plain
+                    // `[download] XX.X%` line from a yt-dlp that ignored it
+                    if report.is_some() || line.contains("[download]") {
+                        let (percent, transfer) = match report {
+                            Some(ref report) => (report.percent(), report.transfer()),
+                            None => (parse_progress_percent(line), TransferStats::default()),
+                        };
+                        let speed = transfer.speed.or_else(|| speed_history::parse_speed(line));
+                        if let (Some(id), Some(speed)) = (ctx.video_id.as_deref(), speed) {
+                            speed_history::record(id, speed);
+                        }
+                        if let Some(new_progress) = percent {
+                            // Only update if progress changed significantly
+                            if (new_progress - song_progress).abs() > 0.5 || song_progress == 0.0 {
+                                song_progress = new_progress;
+
+                                let overall_progress =
+                                    ctx.run.overall_progress(index, song_progress);
+
+                                let progress = DownloadProgress {
+                                    overall_progress,
+                                    current_song: Some(current_song_num),
+                                    total_songs: Some(total_videos),
+                                    song_progress,
+                                    status: if song_progress >= 95.0 {
+                                        format!("Converting to {}...", ctx.run.options.audio_format)
+                                    } else {
+                                        "Downloading...".to_string()
+                                    },
+                                    current_title: ctx.current_title.clone(),
+                                    estimated_size: ctx.estimated_size,
+                                    transfer,
+                                };
+                                emit_progress(ctx.run.app_handle, progress);
+                            }
+                        }
+                    }
+                    // Check for conversion status
+                    else if line.contains("[ExtractAudio]") || line.contains("[Merger]") {
+                        song_progress = 95.0;
+                        let overall_progress = ctx.run.overall_progress(index, 95.0);
+
+                        let progress = DownloadProgress {
+                            overall_progress,
+                            current_song: Some(current_song_num),
+                            total_songs: Some(total_videos),
+                            song_progress: 95.0,
+                            status: format!("Converting to {}...", ctx.run.options.audio_format),
+                            current_title: ctx.current_title.clone(),
+                            estimated_size: ctx.estimated_size,
+                            transfer: TransferStats::default(),
+                        };
+                        emit_progress(ctx.run.app_handle, progress);
+                    }
+                }
+            }
         }
-    };
+    }
 
-    let ffmpeg_dir = Path::new(&ffmpeg_cmd)
-        .parent()
-        .ok_or("Failed to get ffmpeg directory")?;
+    // Wait for process to complete
+    let status_result = child
+        .wait()
+        .await
+        .map_err(|e| format!("Failed to wait for process: {}", e))?;
 
-    let info_output = Command::new(&ytdlp_cmd)
-        .arg("--dump-json")
-        .arg("--flat-playlist")
-        .arg(url)
-        .output()
+    Ok((status_result.success(), stderr_output))
+}
+
+/// A playlist item as listed by `--flat-playlist`, before anything is downloaded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    /// Position in the playlist starting at 1, as used by `PlaylistSelection`
+    pub position: usize,
+    pub url: String,
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub duration: Option<f64>,
+    pub uploader: Option<String>,
+    /// `YYYYMMDD`, approximate for channel uploads
+    pub upload_date: Option<String>,
+    pub playlist_title: Option<String>,
+    /// Channel owning the playlist, which may differ from the item's uploader
+    pub playlist_uploader: Option<String>,
+}
+
+impl PlaylistEntry {
+    fn from_flat_entry(position: usize, url: String, entry: &serde_json::Value) -> Self {
+        Self {
+            position,
+            url,
+            id: entry
+                .get("id")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string()),
+            title: entry
+                .get("title")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string()),
+            duration: entry.get("duration").and_then(|v| v.as_f64()),
+            uploader: ["uploader", "channel"]
+                .iter()
+                .filter_map(|field| entry.get(*field).and_then(|v| v.as_str()))
+                .find(|s| !s.is_empty())
+                .map(|s| s.to_string()),
+            upload_date: entry
+                .get("upload_date")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string()),
+            playlist_title: entry
+                .get("playlist_title")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string()),
+            playlist_uploader: ["playlist_uploader", "playlist_channel"]
+                .iter()
+                .filter_map(|field| entry.get(*field).and_then(|v| v.as_str()))
+                .find(|s| !s.is_empty())
+                .map(|s| s.to_string()),
+        }
+    }
+}
+
+/// List the unique videos of a playlist without downloading anything
+async fn fetch_playlist_entries(
+    ytdlp_cmd: &str,
+    url: &str,
+    options: &DownloadOptions,
+) -> Result<Vec<PlaylistEntry>, String> {
+    if let Some(name) = source::account_playlist(url) {
+        if options.cookies.is_none() {
+            return Err(format!(
+                "{} is private to your account. Import cookies.txt from a browser signed in to YouTube and turn on cookies to download it.",
+                name
+            ));
+        }
+    }
+    let selection = &options.playlist_selection;
+    let ranges = selection.ranges()?;
+    let uploaded_after = selection.uploaded_after()?;
+    let mut cmd = ytdlp_command(ytdlp_cmd, options);
+    cmd.arg("--dump-json").arg("--flat-playlist");
+    // Only list as far as the selection reaches, channels can have thousands of uploads
+    if let Some(end) = ranges.iter().map(|&(_, last)| last).max() {
+        if end != usize::MAX {
+            cmd.arg("--playlist-end").arg(end.to_string());
+        }
+    }
+    if uploaded_after.is_some() {
+        cmd.arg("--extractor-args")
+            .arg("youtubetab:approximate_date");
+    }
+    cmd.arg(source::listing_url(url));
+    let info_output = run_output(&mut cmd, &options.cancel)
         .await
         .map_err(|e| {
             format!(
@@ -703,7 +2284,8 @@ pub async fn download_playlist_with_progress(
         .collect();
 
     let mut seen_ids = HashSet::new();
-    let mut video_urls = Vec::new();
+    let mut playlist_entries = Vec::new();
+    let mut position = 0;
 
     for entry in &entries {
         let entry_type = entry.get("_type").and_then(|v| v.as_str());
@@ -711,279 +2293,769 @@ pub async fn download_playlist_with_progress(
         if entry_type == Some("playlist") || entry_type == Some("channel") {
             continue;
         }
+        // Positions count every item, so they match the playlist as shown on YouTube
+        position += 1;
+        if !ranges
+            .iter()
+            .any(|&(first, last)| (first..=last).contains(&position))
+        {
+            continue;
+        }
+        // Items without a date are kept, nothing says they are too old
+        let upload_date = entry.get("upload_date").and_then(|v| v.as_str());
+        if let (Some(after), Some(date)) = (&uploaded_after, upload_date) {
+            if date < after.as_str() {
+                continue;
+            }
+        }
 
         if let Some(id) = entry.get("id").and_then(|v| v.as_str()) {
             if !id.is_empty() && seen_ids.insert(id.to_string()) {
                 let video_url = format!("https://www.youtube.com/watch?v={}", id);
-                video_urls.push(video_url);
+                playlist_entries.push(PlaylistEntry::from_flat_entry(position, video_url, entry));
             }
         } else if let Some(url) = entry.get("url").and_then(|v| v.as_str()) {
             if url.contains("watch?v=") {
                 if let Some(video_id) = url.split("v=").nth(1).and_then(|s| s.split('&').next()) {
                     if !video_id.is_empty() && seen_ids.insert(video_id.to_string()) {
-                        video_urls.push(url.to_string());
+                        playlist_entries.push(PlaylistEntry::from_flat_entry(
+                            position,
+                            url.to_string(),
+                            entry,
+                        ));
                     }
                 }
             }
         }
     }
 
-    let total_videos = video_urls.len();
+    if playlist_entries.is_empty() && position > 0 && !options.playlist_selection.is_all() {
+        return Err(format!(
+            "None of the playlist's {} items are in the selected range",
+            position
+        ));
+    }
+    Ok(playlist_entries)
+}
 
-    if total_videos == 0 {
-        return Err("Playlist appears to be empty or could not be accessed.".to_string());
+/// A format yt-dlp can download a video in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoFormat {
+    pub format_id: String,
+    pub ext: Option<String>,
+    /// `none` for video-only formats
+    pub acodec: Option<String>,
+    /// `none` for audio-only formats
+    pub vcodec: Option<String>,
+    /// Audio bitrate in kbps
+    pub abr: Option<f64>,
+    /// Exact or approximate size in bytes
+    pub filesize: Option<u64>,
+    pub format_note: Option<String>,
+}
+
+/// What a single video download would fetch, for confirming it before it starts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoInfo {
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub duration: Option<f64>,
+    pub uploader: Option<String>,
+    pub thumbnail: Option<String>,
+    pub view_count: Option<u64>,
+    /// Format the download would use with the current format selector
+    pub format_id: Option<String>,
+    pub formats: Vec<VideoFormat>,
+    /// Timestamped tracklist found in the description or pinned comment, for offering
+    /// to split the video into tracks
+    pub tracklist: Option<DetectedTracklist>,
+    /// Projected size of the converted audio at the requested bitrate, in bytes
+    pub estimated_size: Option<u64>,
+}
+
+impl VideoInfo {
+    fn from_video_info(video_info: &serde_json::Value, bitrate: Option<u32>) -> Self {
+        let text = |value: &serde_json::Value, field: &str| {
+            value[field]
+                .as_str()
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+        };
+        let formats = video_info["formats"]
+            .as_array()
+            .map(|formats| {
+                formats
+                    .iter()
+                    .filter_map(|format| {
+                        Some(VideoFormat {
+                            format_id: text(format, "format_id")?,
+                            ext: text(format, "ext"),
+                            acodec: text(format, "acodec"),
+                            vcodec: text(format, "vcodec"),
+                            abr: format["abr"].as_f64(),
+                            filesize: format["filesize"]
+                                .as_u64()
+                                .or_else(|| format["filesize_approx"].as_u64()),
+                            format_note: text(format, "format_note"),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            id: text(video_info, "id"),
+            title: text(video_info, "title"),
+            duration: video_info["duration"].as_f64(),
+            uploader: text(video_info, "uploader").or_else(|| text(video_info, "channel")),
+            thumbnail: text(video_info, "thumbnail"),
+            view_count: video_info["view_count"].as_u64(),
+            format_id: text(video_info, "format_id"),
+            formats,
+            tracklist: video_info["description"].as_str().and_then(|description| {
+                tracklist::detect(
+                    description,
+                    TracklistSource::Description,
+                    video_info["duration"].as_f64(),
+                )
+            }),
+            estimated_size: bitrate.and_then(|bitrate| estimate_output_size(video_info, bitrate)),
+        }
     }
+}
 
-    // Capture existing files before download
-    let mut existing_files: HashSet<String> = if let Ok(entries) = std::fs::read_dir(output_folder)
-    {
-        entries
-            .filter_map(|e| e.ok())
-            .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("mp3"))
-            .filter_map(|e| e.path().to_string_lossy().to_string().into())
-            .collect()
-    } else {
-        HashSet::new()
+/// Ask yt-dlp whether it can download a URL from a site other than YouTube, without
+/// downloading anything. YouTube URLs were already checked by `source::parse_source`.
+async fn probe_site(ytdlp_cmd: &str, url: &str, options: &DownloadOptions) -> Result<(), String> {
+    let ParsedSource::External { host, .. } = source::parse_source(url)? else {
+        return Ok(());
     };
+    let mut cmd = ytdlp_command(ytdlp_cmd, options);
+    cmd.arg("--simulate")
+        .arg("--no-warnings")
+        .arg("--flat-playlist")
+        .arg("--playlist-items")
+        .arg("1")
+        .arg("--print")
+        .arg("%(playlist_id|)s")
+        .arg(url);
+    let output = run_output(&mut cmd, &options.cancel)
+        .await
+        .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "yt-dlp can't download this {} URL: {}",
+            host,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    // Playlist downloads list their items the YouTube way, so albums and sets on other
+    // sites have to be added one track at a time for now
+    if !String::from_utf8_lossy(&output.stdout).trim().is_empty() {
+        return Err(format!(
+            "Playlists from {} aren't supported yet, add their tracks one at a time",
+            host
+        ));
+    }
+    Ok(())
+}
 
-    let mut downloaded_videos = Vec::new();
+/// Validate a URL before it is queued, probing it with yt-dlp when it is not a YouTube URL
+pub async fn check_url<R: Runtime>(
+    url: &str,
+    options: &DownloadOptions,
+    app_handle: &AppHandle<R>,
+) -> Result<ParsedSource, String> {
+    let parsed = source::parse_source(url)?;
+    if matches!(parsed, ParsedSource::External { .. }) {
+        let ytdlp_cmd = ensure_ytdlp(app_handle)
+            .await
+            .map_err(|e| format!("Failed to get bundled yt-dlp: {}", e))?;
+        probe_site(&ytdlp_cmd, url, options).await?;
+    }
+    Ok(parsed)
+}
+
+/// Look a single video up without downloading it, with the size its audio would have
+/// at `bitrate` when one is given
+pub async fn get_video_info<R: Runtime>(
+    url: &str,
+    bitrate: Option<u32>,
+    options: &DownloadOptions,
+    app_handle: &AppHandle<R>,
+) -> Result<VideoInfo, String> {
+    let parsed = source::parse_source(url)?;
+    let ytdlp_cmd = ensure_ytdlp(app_handle)
+        .await
+        .map_err(|e| format!("Failed to get bundled yt-dlp: {}", e))?;
+    let mut cmd = ytdlp_command(&ytdlp_cmd, options);
+    cmd.arg("--dump-json")
+        .arg("--no-playlist")
+        .arg("-f")
+        .arg(options.format_selector())
+        .arg(url);
+    let output = run_output(&mut cmd, &options.cancel)
+        .await
+        .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
+    if !output.status.success() {
+        return Err(with_workaround_hint(format!(
+            "Failed to get video info: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let video_info: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse video info JSON: {}", e))?;
+    let mut info = VideoInfo::from_video_info(&video_info, bitrate);
+    let long = info
+        .duration
+        .is_some_and(|d| d >= tracklist::LONG_VIDEO_SECONDS);
+    if info.tracklist.is_none() && long && !matches!(parsed, ParsedSource::External { .. }) {
+        if let Some(comment) = pinned_comment(&ytdlp_cmd, url, options).await {
+            info.tracklist =
+                tracklist::detect(&comment, TracklistSource::PinnedComment, info.duration);
+        }
+    }
+    Ok(info)
+}
 
-    // Download each video one by one with progress tracking
-    for (index, video_url) in video_urls.iter().enumerate() {
-        let current_song_num = index + 1;
+/// Text of the video's pinned comment, if it has one. Only the top few comments are
+/// fetched, a pinned comment always comes first.
+async fn pinned_comment(ytdlp_cmd: &str, url: &str, options: &DownloadOptions) -> Option<String> {
+    let mut cmd = ytdlp_command(ytdlp_cmd, options);
+    cmd.arg("--dump-json")
+        .arg("--no-playlist")
+        .arg("--skip-download")
+        .arg("--ignore-no-formats-error")
+        .arg("--write-comments")
+        .arg("--extractor-args")
+        .arg("youtube:max_comments=5,0,0,0;comment_sort=top")
+        .arg(url);
+    let output = run_output(&mut cmd, &options.cancel).await.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let video_info: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    video_info["comments"]
+        .as_array()?
+        .iter()
+        .find(|comment| comment["is_pinned"].as_bool().unwrap_or(false))
+        .and_then(|comment| comment["text"].as_str())
+        .map(|text| text.to_string())
+}
 
-        // Emit progress: starting new song
-        let start_progress = DownloadProgress {
-            overall_progress: (index as f64 / total_videos as f64) * 100.0,
-            current_song: Some(current_song_num),
-            total_songs: Some(total_videos),
-            song_progress: 0.0,
-            status: "Preparing download...".to_string(),
-            current_title: None,
-        };
-        app_handle
-            .emit_all("download-progress", start_progress)
-            .ok();
-
-        let info_output = Command::new(&ytdlp_cmd)
-            .arg("--dump-json")
-            .arg("--no-playlist")
-            .arg(video_url)
-            .output()
-            .await;
-
-        let mut current_title: Option<String> = None;
-        if let Ok(info) = info_output {
-            if info.status.success() && !info.stdout.is_empty() {
-                if let Ok(video_info) = serde_json::from_slice::<serde_json::Value>(&info.stdout) {
-                    if let Some(title) = video_info.get("title").and_then(|v| v.as_str()) {
-                        current_title = Some(sanitize_filename(title));
-
-                        // Emit progress with title
-                        let title_progress = DownloadProgress {
-                            overall_progress: (index as f64 / total_videos as f64) * 100.0,
-                            current_song: Some(current_song_num),
-                            total_songs: Some(total_videos),
-                            song_progress: 0.0,
-                            status: "Starting download...".to_string(),
-                            current_title: current_title.clone(),
-                        };
-                        app_handle
-                            .emit_all("download-progress", title_progress)
-                            .ok();
-                    }
+/// The videos of a playlist, for showing it and picking items before downloading
+pub async fn get_playlist_info<R: Runtime>(
+    url: &str,
+    options: &DownloadOptions,
+    app_handle: &AppHandle<R>,
+) -> Result<Vec<PlaylistEntry>, String> {
+    if !source::parse_source(url)?.is_playlist() {
+        return Err("URL does not appear to be a playlist URL.".to_string());
+    }
+    let ytdlp_cmd = ensure_ytdlp(app_handle)
+        .await
+        .map_err(|e| format!("Failed to get bundled yt-dlp: {}", e))?;
+    fetch_playlist_entries(&ytdlp_cmd, url, options).await
+}
+
+/// The first `max_results` YouTube search results for `query`, without downloading
+/// anything
+pub(crate) async fn search_youtube<R: Runtime>(
+    query: &str,
+    max_results: usize,
+    options: &DownloadOptions,
+    app_handle: &AppHandle<R>,
+) -> Result<Vec<PlaylistEntry>, String> {
+    let ytdlp_cmd = ensure_ytdlp(app_handle)
+        .await
+        .map_err(|e| format!("Failed to get bundled yt-dlp: {}", e))?;
+    // The saved playlist selection would pick among the results
+    let mut options = options.clone();
+    options.playlist_selection = PlaylistSelection::default();
+    let search = format!("ytsearch{}:{}", max_results, query);
+    fetch_playlist_entries(&ytdlp_cmd, &search, &options).await
+}
+
+/// Size of an MP3 of the given length at the target bitrate (kbps)
+pub(crate) fn size_for_duration(duration: f64, bitrate: u32) -> u64 {
+    (duration * bitrate as f64 * 1000.0 / 8.0) as u64
+}
+
+/// Projected size of the MP3 output for a video: duration × target bitrate, or the
+/// source size reported by yt-dlp when the duration is unknown
+pub fn estimate_output_size(video_info: &serde_json::Value, bitrate: u32) -> Option<u64> {
+    if let Some(duration) = video_info.get("duration").and_then(|v| v.as_f64()) {
+        return Some(size_for_duration(duration, bitrate));
+    }
+    video_info
+        .get("filesize")
+        .and_then(|v| v.as_u64())
+        .or_else(|| video_info.get("filesize_approx").and_then(|v| v.as_u64()))
+}
+
+/// Rough per-item overhead (extraction, conversion, tagging) used for time estimates
+pub(crate) const ESTIMATED_SECONDS_PER_ITEM: f64 = 8.0;
+
+/// Conservative download throughput used for time estimates
+pub(crate) const ESTIMATED_BYTES_PER_SECOND: f64 = 1024.0 * 1024.0;
+
+/// Returned instead of starting a playlist download that is larger than the
+/// confirmation threshold, so the user can back out of an accidental huge rip
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistConfirmation {
+    pub url: String,
+    pub item_count: usize,
+    pub threshold: usize,
+    /// Combined length of the items that report a duration
+    pub total_duration: Option<f64>,
+    pub estimated_size_bytes: Option<u64>,
+    pub estimated_time_seconds: f64,
+}
+
+/// Resolve the playlist and return a confirmation request if it has more than
+/// `threshold` items, or `None` if the download can start right away
+pub async fn check_playlist_size<R: Runtime>(
+    url: &str,
+    bitrate: u32,
+    threshold: usize,
+    options: &DownloadOptions,
+    app_handle: &AppHandle<R>,
+) -> Result<Option<PlaylistConfirmation>, String> {
+    let ytdlp_cmd = ensure_ytdlp(app_handle)
+        .await
+        .map_err(|e| format!("Failed to get bundled yt-dlp: {}", e))?;
+
+    let entries = fetch_playlist_entries(&ytdlp_cmd, url, options).await?;
+    if entries.len() <= threshold {
+        return Ok(None);
+    }
+
+    let total_duration = extrapolated_duration(&entries);
+    let estimated_size_bytes = total_duration.map(|seconds| size_for_duration(seconds, bitrate));
+    let estimated_time_seconds = entries.len() as f64 * ESTIMATED_SECONDS_PER_ITEM
+        + estimated_size_bytes.unwrap_or(0) as f64 / ESTIMATED_BYTES_PER_SECOND;
+
+    Ok(Some(PlaylistConfirmation {
+        url: url.to_string(),
+        item_count: entries.len(),
+        threshold,
+        total_duration,
+        estimated_size_bytes,
+        estimated_time_seconds,
+    }))
+}
+
+/// Combined length of the entries, extrapolated from the items that have a duration to
+/// the whole playlist
+fn extrapolated_duration(entries: &[PlaylistEntry]) -> Option<f64> {
+    let durations: Vec<f64> = entries.iter().filter_map(|e| e.duration).collect();
+    if durations.is_empty() {
+        return None;
+    }
+    let average = durations.iter().sum::<f64>() / durations.len() as f64;
+    Some(average * entries.len() as f64)
+}
+
+/// Bitrate assumed for the audio streams YouTube serves, in kbps
+pub(crate) const ESTIMATED_SOURCE_KBPS: u32 = 160;
+
+/// What a playlist download would cost, shown before the user commits to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistEstimate {
+    pub url: String,
+    pub item_count: usize,
+    /// Combined length of the items, extrapolated when some don't report one
+    pub total_duration: Option<f64>,
+    /// Source audio fetched from YouTube
+    pub estimated_download_bytes: Option<u64>,
+    /// Files written in the chosen format and bitrate
+    pub estimated_output_bytes: Option<u64>,
+    pub estimated_time_seconds: f64,
+    /// Download speed the time is based on, in bytes per second
+    pub throughput_bytes_per_second: f64,
+    /// Whether the speed was measured during this session's downloads rather than assumed
+    pub measured_throughput: bool,
+}
+
+/// Estimate the size and duration of downloading a playlist from its `--flat-playlist`
+/// listing. The time uses this session's measured download speeds and the encode speed
+/// of the last benchmark when there are any.
+pub async fn estimate_playlist<R: Runtime>(
+    url: &str,
+    bitrate: u32,
+    options: &DownloadOptions,
+    app_handle: &AppHandle<R>,
+) -> Result<PlaylistEstimate, String> {
+    if !source::parse_source(url)?.is_playlist() {
+        return Err("URL does not appear to be a playlist URL.".to_string());
+    }
+    let ytdlp_cmd = ensure_ytdlp(app_handle)
+        .await
+        .map_err(|e| format!("Failed to get bundled yt-dlp: {}", e))?;
+    let entries = fetch_playlist_entries(&ytdlp_cmd, url, options).await?;
+
+    let total_duration = extrapolated_duration(&entries);
+    let estimated_download_bytes =
+        total_duration.map(|seconds| size_for_duration(seconds, ESTIMATED_SOURCE_KBPS));
+    let estimated_output_bytes = total_duration
+        .map(|seconds| size_for_duration(seconds, options.audio_format.estimated_kbps(bitrate)));
+
+    let measured = speed_history::average_speed();
+    let throughput = measured.unwrap_or(ESTIMATED_BYTES_PER_SECOND);
+    let encode_seconds = benchmark::encode_speed(bitrate)
+        .zip(total_duration)
+        .map_or(0.0, |(speed, seconds)| seconds / speed);
+    // Items run side by side, the connection they share is the limit for the download
+    let concurrency = options.concurrency.max(1) as f64;
+    let estimated_time_seconds =
+        (entries.len() as f64 * ESTIMATED_SECONDS_PER_ITEM + encode_seconds) / concurrency
+            + estimated_download_bytes.unwrap_or(0) as f64 / throughput;
+
+    Ok(PlaylistEstimate {
+        url: url.to_string(),
+        item_count: entries.len(),
+        total_duration,
+        estimated_download_bytes,
+        estimated_output_bytes,
+        estimated_time_seconds,
+        throughput_bytes_per_second: throughput,
+        measured_throughput: measured.is_some(),
+    })
+}
+
+/// State shared by every item of a playlist download, whichever order they run in
+struct PlaylistRun<'a, R: Runtime> {
+    app_handle: &'a AppHandle<R>,
+    ytdlp_cmd: &'a str,
+    ffmpeg_cmd: &'a str,
+    ffmpeg_dir: &'a Path,
+    output_folder: &'a str,
+    bitrate: u32,
+    options: &'a DownloadOptions,
+    total_videos: usize,
+    unique_stems: &'a [Option<String>],
+    /// Audio files that were there before the download or already belong to an item
+    existing_files: Mutex<HashSet<String>>,
+    /// Progress of each item in percent, for the overall progress of the playlist
+    item_progress: Mutex<Vec<f64>>,
+}
+
+impl<R: Runtime> PlaylistRun<'_, R> {
+    /// Record the progress of one item and return the overall progress of the playlist
+    fn overall_progress(&self, index: usize, song_progress: f64) -> f64 {
+        let mut items = self.item_progress.lock().unwrap();
+        items[index] = song_progress;
+        items.iter().sum::<f64>() / self.total_videos as f64
+    }
+}
+
+/// How one attempt at a playlist item ended. A failed item doesn't stop the playlist.
+enum ItemOutcome {
+    Downloaded(DownloadResult),
+    Failed(String),
+    Cancelled,
+}
+
+/// Download one playlist item, once
+async fn download_playlist_item<R: Runtime>(
+    run: &PlaylistRun<'_, R>,
+    index: usize,
+    entry: &PlaylistEntry,
+) -> Result<ItemOutcome, String> {
+    let PlaylistRun {
+        app_handle,
+        ytdlp_cmd,
+        ffmpeg_cmd,
+        ffmpeg_dir,
+        output_folder,
+        bitrate,
+        options,
+        total_videos,
+        unique_stems,
+        ..
+    } = *run;
+
+    if options.cancel.is_cancelled() {
+        return Ok(ItemOutcome::Cancelled);
+    }
+    let video_url = &entry.url;
+    let current_song_num = index + 1;
+    let mut estimated_size = entry.duration.map(|d| size_for_duration(d, bitrate));
+
+    // Emit progress: starting new song
+    let start_progress = DownloadProgress {
+        overall_progress: run.overall_progress(index, 0.0),
+        current_song: Some(current_song_num),
+        total_songs: Some(total_videos),
+        song_progress: 0.0,
+        status: "Preparing download...".to_string(),
+        current_title: None,
+        estimated_size,
+        transfer: TransferStats::default(),
+    };
+    emit_progress(app_handle, start_progress);
+
+    let mut info_cmd = ytdlp_command(ytdlp_cmd, options);
+    info_cmd
+        .arg("--dump-json")
+        .arg("--no-playlist")
+        .arg("-f")
+        .arg(options.format_selector())
+        .arg(video_url);
+    let info_output = run_output(&mut info_cmd, &options.cancel).await;
+
+    let mut current_title: Option<String> = None;
+    let mut file_stem: Option<String> = None;
+    let mut video_info = serde_json::Value::Null;
+    if let Ok(info) = info_output {
+        if info.status.success() && !info.stdout.is_empty() {
+            if let Ok(parsed) = serde_json::from_slice::<serde_json::Value>(&info.stdout) {
+                video_info = parsed;
+                estimated_size = estimate_output_size(&video_info, bitrate).or(estimated_size);
+                if let Some(title) = video_info.get("title").and_then(|v| v.as_str()) {
+                    current_title = Some(sanitize_filename(title));
+                    file_stem = Some(file_stem_for(title, options));
+
+                    // Emit progress with title
+                    let title_progress = DownloadProgress {
+                        overall_progress: run.overall_progress(index, 0.0),
+                        current_song: Some(current_song_num),
+                        total_songs: Some(total_videos),
+                        song_progress: 0.0,
+                        status: "Starting download...".to_string(),
+                        current_title: current_title.clone(),
+                        estimated_size,
+                        transfer: TransferStats::default(),
+                    };
+                    emit_progress(app_handle, title_progress);
                 }
             }
         }
+    }
+
+    // Items sharing a title with another item get a unique name
+    if let Some(ref unique) = unique_stems[index] {
+        file_stem = Some(unique.clone());
+    }
 
-        // Check if file already exists
-        let expected_path = if let Some(ref title) = current_title {
-            Path::new(output_folder).join(format!("{}.mp3", title))
+    // Check if file already exists
+    let extension = options.audio_format.extension();
+    let expected_path = if let Some(ref stem) = file_stem {
+        Path::new(output_folder).join(format!("{}.{}", stem, extension))
+    } else {
+        // Fallback: use video ID
+        if let Some(id) = video_url
+            .split("v=")
+            .nth(1)
+            .and_then(|s| s.split('&').next())
+        {
+            Path::new(output_folder).join(format!("{}.{}", id, extension))
         } else {
-            // Fallback: use video ID
-            if let Some(id) = video_url
-                .split("v=")
-                .nth(1)
-                .and_then(|s| s.split('&').next())
-            {
-                Path::new(output_folder).join(format!("{}.mp3", id))
-            } else {
-                Path::new(output_folder).join(format!("video_{}.mp3", current_song_num))
-            }
+            Path::new(output_folder).join(format!("video_{}.{}", current_song_num, extension))
+        }
+    };
+
+    if expected_path.exists() {
+        // File already exists, skip
+        let file_size = std::fs::metadata(&expected_path).ok().map(|m| m.len());
+
+        let skipped = DownloadResult {
+            output_path: expected_path.to_string_lossy().to_string(),
+            title: current_title.clone(),
+            duration: None,
+            file_size,
+            format_id: None,
+            lossless_copy: false,
+            chapter_paths: Vec::new(),
+            lyrics_paths: Vec::new(),
         };
 
-        if expected_path.exists() {
-            // File already exists, skip
-            let file_size = std::fs::metadata(&expected_path).ok().map(|m| m.len());
+        // Emit progress: song skipped (already exists)
+        let skip_progress = DownloadProgress {
+            overall_progress: run.overall_progress(index, 100.0),
+            current_song: Some(current_song_num),
+            total_songs: Some(total_videos),
+            song_progress: 100.0,
+            status: "Already exists, skipping...".to_string(),
+            current_title: current_title.clone(),
+            estimated_size: None,
+            transfer: TransferStats::default(),
+        };
+        emit_progress(app_handle, skip_progress);
+        return Ok(ItemOutcome::Downloaded(skipped));
+    }
 
-            downloaded_videos.push(DownloadResult {
-                output_path: expected_path.to_string_lossy().to_string(),
-                title: current_title.clone(),
-                duration: None,
-                file_size,
-            });
+    let explicit_stem = file_stem
+        .as_deref()
+        .filter(|_| options.transliterate || unique_stems[index].is_some());
+    let output_template_str = output_template(Path::new(output_folder), explicit_stem);
+
+    let progress_ctx = ItemProgressContext {
+        run,
+        index,
+        current_title: current_title.clone(),
+        video_id: entry.id.clone(),
+        estimated_size,
+        cancel: options.cancel.clone(),
+    };
 
-            // Emit progress: song skipped (already exists)
-            let skip_progress = DownloadProgress {
-                overall_progress: ((index + 1) as f64 / total_videos as f64) * 100.0,
-                current_song: Some(current_song_num),
-                total_songs: Some(total_videos),
-                song_progress: 100.0,
-                status: "Already exists, skipping...".to_string(),
-                current_title: current_title.clone(),
-            };
-            app_handle.emit_all("download-progress", skip_progress).ok();
-            continue;
+    let cache_key = video_info
+        .get("id")
+        .and_then(|v| v.as_str())
+        .filter(|_| options.uses_media_cache());
+    if let Some(cached) = cache_key.and_then(media_cache::lookup) {
+        let transcoded = media_cache::transcode(
+            ffmpeg_cmd,
+            &cached,
+            &expected_path,
+            bitrate,
+            options,
+            &options.tags_for(&video_info),
+        )
+        .await;
+        return match transcoded {
+            Ok(lossless_copy) => {
+                let path_str = expected_path.to_string_lossy().to_string();
+                let converted = DownloadResult {
+                    output_path: path_str.clone(),
+                    title: current_title.clone(),
+                    duration: None,
+                    file_size: std::fs::metadata(&expected_path).ok().map(|m| m.len()),
+                    format_id: None,
+                    lossless_copy,
+                    chapter_paths: Vec::new(),
+                    lyrics_paths: Vec::new(),
+                };
+                let converted = normalized(ffmpeg_cmd, bitrate, options, converted).await;
+                run.existing_files.lock().unwrap().insert(path_str);
+
+                let cached_progress = DownloadProgress {
+                    overall_progress: run.overall_progress(index, 100.0),
+                    current_song: Some(current_song_num),
+                    total_songs: Some(total_videos),
+                    song_progress: 100.0,
+                    status: "Converted from cache".to_string(),
+                    current_title: current_title.clone(),
+                    estimated_size,
+                    transfer: TransferStats::default(),
+                };
+                emit_progress(app_handle, cached_progress);
+                Ok(ItemOutcome::Downloaded(converted))
+            }
+            Err(e) => {
+                eprintln!(
+                    "Warning: Failed to convert cached video {}: {}",
+                    current_song_num, e
+                );
+                Ok(ItemOutcome::Failed(e))
+            }
+        };
+    }
+
+    // Walk down the format ladder until one of them downloads successfully
+    let mut used_format: Option<Option<String>> = None;
+    let mut last_error = String::new();
+    let mut attempt_options = options.escalated(previously_failed(video_url));
+
+    for format_id in format_attempts(&video_info) {
+        let mut cmd = ytdlp_command(ytdlp_cmd, &attempt_options);
+        add_output_args(
+            &mut cmd,
+            cache_key.is_some(),
+            &output_template_str,
+            bitrate,
+            ffmpeg_dir,
+            options,
+        )?;
+        cmd.arg("--no-playlist")
+            .arg("--newline")
+            .arg("--progress-template")
+            .arg(PROGRESS_TEMPLATE);
+        if let Some(ref id) = format_id {
+            cmd.arg("-f").arg(id);
         }
+        cmd.arg(video_url);
+
+        let mut reconnect = Reconnect::new(app_handle, video_url, &options.cancel);
+        let (success, stderr_output) = loop {
+            let (success, stderr_output) = run_item_download(&mut cmd, &progress_ctx).await?;
+            if let Some(ref id) = entry.id {
+                download_log::append(
+                    id,
+                    &attempt_label(&format_id, &attempt_options),
+                    &stderr_output,
+                );
+            }
+            if success || !reconnect.after_failure(&stderr_output).await {
+                break (success, stderr_output);
+            }
+        };
 
-        let output_path_buf = Path::new(output_folder);
-        let output_template = output_path_buf.join("%(title)s.%(ext)s");
-        let output_template_str = output_template.to_string_lossy().to_string();
+        if success {
+            used_format = Some(format_id);
+            break;
+        }
 
-        let mut child = Command::new(&ytdlp_cmd)
-            .arg("-x")
-            .arg("--audio-format")
-            .arg("mp3")
-            .arg("--audio-quality")
-            .arg(format!("{}K", bitrate))
-            .arg("--ffmpeg-location")
-            .arg(ffmpeg_dir)
-            .arg("-o")
-            .arg(&output_template_str)
-            .arg("--no-playlist")
-            .arg("--newline")
-            .arg(video_url)
-            .stderr(std::process::Stdio::piped())
-            .stdout(std::process::Stdio::piped())
-            .spawn()
-            .map_err(|e| {
-                format!(
-                    "Failed to start download for video {}: {}",
-                    current_song_num, e
-                )
-            })?;
-
-        let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
-        let mut reader = BufReader::new(stderr);
-        let mut line = String::new();
-        let mut song_progress = 0.0;
-
-        // Parse progress for this single video
-        loop {
-            line.clear();
-            match reader.read_line(&mut line).await {
-                Ok(0) => break, // EOF
-                Ok(_) => {
-                    let line = line.trim();
-                    if !line.is_empty() {
-                        // Parse download progress: [download] XX.X%
-                        if line.contains("[download]") {
-                            if let Some(percent_pos) = line.find('%') {
-                                let mut num_start = percent_pos;
-                                let mut found_digit = false;
-
-                                while num_start > 0 {
-                                    let ch = line.chars().nth(num_start - 1).unwrap_or(' ');
-                                    if ch.is_ascii_digit() || ch == '.' {
-                                        found_digit = true;
-                                        num_start -= 1;
-                                    } else if found_digit {
-                                        break;
-                                    } else {
-                                        num_start -= 1;
-                                    }
-                                }
-
-                                if found_digit && num_start < percent_pos {
-                                    let percent_str = &line[num_start..percent_pos].trim();
-                                    if let Ok(percent) = percent_str.parse::<f64>() {
-                                        let new_progress = percent.min(100.0).max(0.0);
-
-                                        // Only update if progress changed significantly
-                                        if (new_progress - song_progress).abs() > 0.5
-                                            || song_progress == 0.0
-                                        {
-                                            song_progress = new_progress;
-
-                                            let overall_progress = if total_videos > 0 {
-                                                ((index as f64 + song_progress / 100.0)
-                                                    / total_videos as f64)
-                                                    * 100.0
-                                            } else {
-                                                song_progress
-                                            };
-
-                                            let progress = DownloadProgress {
-                                                overall_progress,
-                                                current_song: Some(current_song_num),
-                                                total_songs: Some(total_videos),
-                                                song_progress,
-                                                status: if song_progress >= 95.0 {
-                                                    "Converting to MP3...".to_string()
-                                                } else {
-                                                    "Downloading...".to_string()
-                                                },
-                                                current_title: current_title.clone(),
-                                            };
-                                            app_handle.emit_all("download-progress", progress).ok();
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        // Check for conversion status
-                        else if line.contains("[ExtractAudio]") || line.contains("[Merger]") {
-                            song_progress = 95.0;
-                            let overall_progress = if total_videos > 0 {
-                                ((index as f64 + 0.95) / total_videos as f64) * 100.0
-                            } else {
-                                95.0
-                            };
-
-                            let progress = DownloadProgress {
-                                overall_progress,
-                                current_song: Some(current_song_num),
-                                total_songs: Some(total_videos),
-                                song_progress: 95.0,
-                                status: "Converting to MP3...".to_string(),
-                                current_title: current_title.clone(),
-                            };
-                            app_handle.emit_all("download-progress", progress).ok();
-                        }
-                    }
-                }
-                Err(_) => break,
-            }
+        let format_error = is_format_error(&stderr_output);
+        last_error = stderr_output;
+        if options.cancel.is_cancelled() || !format_error {
+            break;
         }
 
-        // Wait for process to complete
-        let status_result = child
-            .wait()
-            .await
-            .map_err(|e| format!("Failed to wait for process: {}", e))?;
+        eprintln!(
+            "[download] Format {} failed for video {}, trying next format...",
+            format_id.as_deref().unwrap_or("default"),
+            current_song_num
+        );
+        attempt_options.verbosity = Verbosity::Verbose;
+    }
+    if options.cancel.is_cancelled() {
+        return Ok(ItemOutcome::Cancelled);
+    }
+    record_outcome(video_url, used_format.is_some());
 
-        if !status_result.success() {
+    let format_id = match used_format {
+        Some(format_id) => format_id,
+        None => {
             eprintln!(
                 "Warning: Download failed for video {}: {}",
                 current_song_num, video_url
             );
-            continue; // Skip this video and continue with next
+            return Ok(ItemOutcome::Failed(error_summary(&last_error)));
         }
+    };
 
-        // Emit 100% progress for this song
-        let complete_progress = DownloadProgress {
-            overall_progress: ((index + 1) as f64 / total_videos as f64) * 100.0,
-            current_song: Some(current_song_num),
-            total_songs: Some(total_videos),
-            song_progress: 100.0,
-            status: "Completed".to_string(),
-            current_title: current_title.clone(),
-        };
-        app_handle
-            .emit_all("download-progress", complete_progress)
-            .ok();
+    let lossless_copy = match cache_key {
+        Some(_) => {
+            match transcode_cached(ffmpeg_cmd, &video_info, &expected_path, bitrate, options).await
+            {
+                Ok(copied) => copied,
+                Err(e) => {
+                    eprintln!("Warning: {} for video {}", e, current_song_num);
+                    return Ok(ItemOutcome::Failed(e));
+                }
+            }
+        }
+        None => options.copies_audio(downloaded_acodec(&video_info, format_id.as_deref())),
+    };
+
+    // Emit 100% progress for this song
+    let complete_progress = DownloadProgress {
+        overall_progress: run.overall_progress(index, 100.0),
+        current_song: Some(current_song_num),
+        total_songs: Some(total_videos),
+        song_progress: 100.0,
+        status: "Completed".to_string(),
+        current_title: current_title.clone(),
+        estimated_size,
+        transfer: TransferStats::default(),
+    };
+    emit_progress(app_handle, complete_progress);
 
-        // Find the downloaded file - first try the expected path, then search for new files
+    // Find the downloaded file - first try the expected path, then search for new files.
+    // The search and the claim happen under one lock, so parallel items never take each
+    // other's file.
+    let (downloaded_path, path_str, metadata, file_name) = {
+        let mut existing_files = run.existing_files.lock().unwrap();
         let downloaded_file = if expected_path.exists()
             && !existing_files.contains(&expected_path.to_string_lossy().to_string())
         {
@@ -995,7 +3067,7 @@ pub async fn download_playlist_with_progress(
             if let Ok(entries) = std::fs::read_dir(output_folder) {
                 for entry in entries.filter_map(|e| e.ok()) {
                     let path = entry.path();
-                    if path.extension().and_then(|s| s.to_str()) == Some("mp3") {
+                    if path.extension().and_then(|s| s.to_str()) == Some(extension) {
                         let path_str = path.to_string_lossy().to_string();
                         if !existing_files.contains(&path_str) {
                             found_file = Some(path);
@@ -1007,26 +3079,280 @@ pub async fn download_playlist_with_progress(
             found_file
         };
 
-        if let Some(downloaded_path) = downloaded_file {
-            let path_str = downloaded_path.to_string_lossy().to_string();
-            if let Ok(metadata) = std::fs::metadata(&downloaded_path) {
-                let file_size = Some(metadata.len());
-                let file_name = downloaded_path
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .map(|s| s.to_string());
+        let Some(downloaded_path) = downloaded_file else {
+            return Ok(ItemOutcome::Failed(format!(
+                "yt-dlp finished but no {} file was found",
+                options.audio_format
+            )));
+        };
+        let path_str = downloaded_path.to_string_lossy().to_string();
+        let metadata = match std::fs::metadata(&downloaded_path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                return Ok(ItemOutcome::Failed(format!(
+                    "Failed to read {}: {}",
+                    path_str, e
+                )))
+            }
+        };
+        let file_name = downloaded_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_string());
+
+        // Claim the file so no other item finds it again
+        existing_files.insert(path_str.clone());
+        (downloaded_path, path_str, metadata, file_name)
+    };
+    if options.embeds_thumbnail() {
+        remove_thumbnail_files(&downloaded_path);
+    }
+    let downloaded = DownloadResult {
+        chapter_paths: chapter_files(&downloaded_path, options),
+        lyrics_paths: lyrics_files(&downloaded_path, options),
+        output_path: path_str,
+        title: file_name.or(current_title),
+        duration: None,
+        file_size: Some(metadata.len()),
+        format_id,
+        lossless_copy,
+    };
+    Ok(ItemOutcome::Downloaded(
+        normalized(ffmpeg_cmd, bitrate, options, downloaded).await,
+    ))
+}
 
-                downloaded_videos.push(DownloadResult {
-                    output_path: path_str.clone(),
-                    title: file_name.or(current_title.clone()),
-                    duration: None,
-                    file_size,
-                });
+/// Download one playlist item, retrying failures with exponential backoff as long as the
+/// error is one that may go away. Returns the failure once the retries are used up.
+async fn download_playlist_item_with_retry<R: Runtime>(
+    run: &PlaylistRun<'_, R>,
+    index: usize,
+    entry: &PlaylistEntry,
+) -> Result<Result<Option<DownloadResult>, FailedVideo>, String> {
+    let retry = &run.options.retry;
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let error = match download_playlist_item(run, index, entry).await? {
+            ItemOutcome::Downloaded(result) => return Ok(Ok(Some(result))),
+            ItemOutcome::Cancelled => return Ok(Ok(None)),
+            ItemOutcome::Failed(error) => error,
+        };
+
+        if attempts > retry.max_retries || is_permanent_error(&error) {
+            eprintln!(
+                "Warning: Download failed for video {} after {} attempt(s): {}",
+                index + 1,
+                attempts,
+                entry.url
+            );
+            return Ok(Err(FailedVideo {
+                url: entry.url.clone(),
+                title: entry.title.clone(),
+                error,
+                attempts,
+            }));
+        }
+
+        let delay = retry.delay_for(attempts);
+        eprintln!(
+            "[download] Video {} failed, retrying in {:.1}s: {}",
+            index + 1,
+            delay.as_secs_f64(),
+            error
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = run.options.cancel.cancelled() => return Ok(Ok(None)),
+        }
+    }
+}
+
+pub async fn download_playlist_with_progress<R: Runtime>(
+    url: &str,
+    output_folder: &str,
+    bitrate: u32,
+    audio_format: AudioFormat,
+    options: &DownloadOptions,
+    app_handle: AppHandle<R>,
+) -> Result<PlaylistDownloadResult, String> {
+    if !source::parse_source(url)?.is_playlist() {
+        return Err("URL does not appear to be a playlist URL.".to_string());
+    }
+    let started = std::time::SystemTime::now();
+
+    let ytdlp_cmd = match ensure_ytdlp(&app_handle).await {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            return Err(format!("Failed to get bundled yt-dlp: {}", e));
+        }
+    };
+
+    let ffmpeg_cmd = match ensure_ffmpeg(&app_handle).await {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            return Err(format!("Failed to get bundled ffmpeg: {}", e));
+        }
+    };
+
+    let ffmpeg_dir = Path::new(&ffmpeg_cmd)
+        .parent()
+        .ok_or("Failed to get ffmpeg directory")?;
+
+    let playlist_entries = match fetch_playlist_entries(&ytdlp_cmd, url, options).await {
+        Ok(entries) => entries,
+        Err(_) if options.cancel.is_cancelled() => {
+            // Nothing was downloaded yet
+            finish_cancelled(&app_handle, &DownloadFiles::default(), started);
+            return Err(CANCELLED_MESSAGE.to_string());
+        }
+        Err(e) => return Err(e),
+    };
+    let total_videos = playlist_entries.len();
+
+    if total_videos == 0 {
+        return Err("Playlist appears to be empty or could not be accessed.".to_string());
+    }
+
+    let mut options = options.for_batch(total_videos);
+    options.audio_format = audio_format;
+    let options = &options;
+    let unique_stems = disambiguated_stems(&playlist_entries, options);
+
+    // The whole playlist goes into one folder, named after its first item's fields
+    let first = &playlist_entries[0];
+    let output_folder = &options.folder_organization.resolve(
+        output_folder,
+        first.playlist_title.as_deref(),
+        first
+            .playlist_uploader
+            .as_deref()
+            .or(first.uploader.as_deref()),
+    )?;
+
+    // Capture existing files before download
+    let existing_files: HashSet<String> = if let Ok(entries) = std::fs::read_dir(output_folder) {
+        entries
+            .filter_map(|e| e.ok())
+            .filter(|e| {
+                e.path().extension().and_then(|s| s.to_str())
+                    == Some(options.audio_format.extension())
+            })
+            .filter_map(|e| e.path().to_string_lossy().to_string().into())
+            .collect()
+    } else {
+        HashSet::new()
+    };
+
+    let run = PlaylistRun {
+        app_handle: &app_handle,
+        ytdlp_cmd: &ytdlp_cmd,
+        ffmpeg_cmd: &ffmpeg_cmd,
+        ffmpeg_dir,
+        output_folder,
+        bitrate,
+        options,
+        total_videos,
+        unique_stems: &unique_stems,
+        existing_files: Mutex::new(existing_files),
+        item_progress: Mutex::new(vec![0.0; total_videos]),
+    };
 
-                // Add to existing_files to avoid finding it again in next iteration
-                existing_files.insert(path_str);
+    // Download up to `concurrency` videos at a time, each in its own yt-dlp process,
+    // fewer while the machine is busy
+    let concurrency = options.concurrency.clamp(1, MAX_CONCURRENT_DOWNLOADS);
+    let limit = AdaptiveLimit::new(concurrency, options.adaptive_concurrency);
+    let (run, limit) = (&run, &limit);
+    let mut pending = Vec::with_capacity(total_videos);
+    for (index, entry) in playlist_entries.iter().enumerate() {
+        pending.push(async move {
+            let _slot = limit.acquire().await;
+            (
+                index,
+                download_playlist_item_with_retry(run, index, entry).await,
+            )
+        });
+    }
+    let mut items = stream::iter(pending).buffer_unordered(concurrency);
+    let mut downloaded = Vec::new();
+    let mut failed = Vec::new();
+    let collect = async {
+        while let Some((index, result)) = items.next().await {
+            match result? {
+                Ok(Some(video)) => downloaded.push((index, video)),
+                Ok(None) => {}
+                Err(failure) => failed.push((index, failure)),
             }
         }
+        Ok::<(), String>(())
+    };
+    if options.adaptive_concurrency && concurrency > 1 {
+        tokio::select! {
+            collected = collect => collected?,
+            _ = limit.adapt() => unreachable!("adapting the limit never finishes"),
+        }
+    } else {
+        collect.await?;
+    }
+
+    // Keep the playlist order, whichever download finished first
+    downloaded.sort_by_key(|(index, _)| *index);
+    failed.sort_by_key(|(index, _)| *index);
+    let tracks: Vec<CollectionTrack> = downloaded
+        .iter()
+        .map(|(index, video)| CollectionTrack {
+            position: index + 1,
+            title: video.title.clone(),
+            url: playlist_entries[*index].url.clone(),
+            file: Path::new(&video.output_path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            duration: video.duration.or(playlist_entries[*index].duration),
+        })
+        .collect();
+    let downloaded_videos: Vec<DownloadResult> =
+        downloaded.into_iter().map(|(_, video)| video).collect();
+    let failed_videos: Vec<FailedVideo> = failed.into_iter().map(|(_, video)| video).collect();
+
+    if options.cancel.is_cancelled() {
+        let mut files = DownloadFiles {
+            folder: output_folder.to_string(),
+            names: Vec::new(),
+        };
+        for (entry, unique_stem) in playlist_entries.iter().zip(&unique_stems) {
+            let stem = unique_stem.clone().or_else(|| {
+                entry
+                    .title
+                    .as_deref()
+                    .map(|title| file_stem_for(title, options))
+            });
+            files.add_names(stem.as_deref(), entry.title.as_deref(), entry.id.as_deref());
+        }
+        finish_cancelled(&app_handle, &files, started);
+        return Ok(PlaylistDownloadResult {
+            output_folder: output_folder.to_string(),
+            total_videos,
+            downloaded_videos,
+            failed_videos,
+            cancelled: true,
+            liner_notes_path: None,
+        });
+    }
+
+    if options.folder_artwork && !tracks.is_empty() {
+        let written = folder_art::write_collection_files(
+            &ytdlp_cmd,
+            &ffmpeg_cmd,
+            url,
+            output_folder,
+            tracks,
+            options,
+        )
+        .await;
+        if let Err(e) = written {
+            eprintln!("[folder_art] Failed to write the folder metadata: {}", e);
+        }
     }
 
     // Emit final 100% progress
@@ -1037,47 +3363,440 @@ pub async fn download_playlist_with_progress(
         song_progress: 100.0,
         status: "Complete!".to_string(),
         current_title: None,
+        estimated_size: None,
+        transfer: TransferStats::default(),
     };
-    app_handle
-        .emit_all("download-progress", final_progress)
-        .ok();
+    emit_progress(&app_handle, final_progress);
 
     Ok(PlaylistDownloadResult {
         output_folder: output_folder.to_string(),
         total_videos,
         downloaded_videos,
+        failed_videos,
+        cancelled: false,
+        liner_notes_path: None,
     })
 }
 
-/// Validate if the URL is a valid YouTube URL
-/// Supports various YouTube URL formats across different platforms
-fn is_youtube_url(url: &str) -> bool {
-    let url_lower = url.to_lowercase();
-    url_lower.contains("youtube.com/watch")
-        || url_lower.contains("youtu.be/")
-        || url_lower.contains("youtube.com/embed/")
-        || url_lower.contains("youtube.com/v/")
-        || url_lower.contains("youtube.com/shorts/")
-        || url_lower.contains("m.youtube.com/watch")
-        || url_lower.contains("www.youtube.com/watch")
-        || url_lower.starts_with("https://youtube.com/")
-        || url_lower.starts_with("http://youtube.com/")
-        || url_lower.starts_with("https://youtu.be/")
-        || url_lower.starts_with("http://youtu.be/")
-        || url_lower.contains("youtube.com/playlist")
-}
-
-/// Check if the URL is a YouTube playlist URL
-pub fn is_playlist_url(url: &str) -> bool {
-    let url_lower = url.to_lowercase();
-    // Check for playlist parameter in URL
-    url_lower.contains("list=")
-        && (url_lower.contains("youtube.com/watch") || url_lower.contains("youtube.com/playlist"))
+/// Filename (without extension) for a video title, romanized if transliteration is on.
+/// Titles with nothing left after sanitizing are named "Untitled".
+pub(crate) fn file_stem_for(title: &str, options: &DownloadOptions) -> String {
+    let stem = if options.transliterate {
+        sanitize_filename(&transliterate(title))
+    } else {
+        sanitize_filename(title)
+    };
+    if stem.is_empty() {
+        "Untitled".to_string()
+    } else {
+        stem
+    }
+}
+
+/// yt-dlp output template for a download into `folder`, named after the title unless
+/// an explicit name is needed (romanized or disambiguated titles)
+pub(crate) fn output_template(folder: &Path, explicit_stem: Option<&str>) -> String {
+    let name = match explicit_stem {
+        Some(stem) => format!("{}.%(ext)s", stem.replace('%', "%%")),
+        None => "%(title)s.%(ext)s".to_string(),
+    };
+    folder.join(name).to_string_lossy().to_string()
+}
+
+/// Filenames for playlist items whose title collides with another item's, made unique
+/// with the video ID (or the playlist position when there is none). Items with a
+/// unique title get `None` and keep their normal name.
+fn disambiguated_stems(
+    entries: &[PlaylistEntry],
+    options: &DownloadOptions,
+) -> Vec<Option<String>> {
+    let stems: Vec<Option<String>> = entries
+        .iter()
+        .map(|e| e.title.as_deref().map(|t| file_stem_for(t, options)))
+        .collect();
+
+    // Compare case-insensitively, Windows and macOS filesystems usually are
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for stem in stems.iter().flatten() {
+        *counts.entry(stem.to_lowercase()).or_default() += 1;
+    }
+
+    stems
+        .iter()
+        .zip(entries)
+        .enumerate()
+        .map(|(index, (stem, entry))| {
+            let stem = stem.as_ref()?;
+            if counts[&stem.to_lowercase()] < 2 {
+                return None;
+            }
+            let suffix = entry.id.clone().unwrap_or_else(|| (index + 1).to_string());
+            Some(format!("{} [{}]", stem, sanitize_filename(&suffix)))
+        })
+        .collect()
+}
+
+/// Point a download either straight at the output folder, extracting the audio with
+/// yt-dlp, or at the media cache, in which case it is transcoded afterwards
+fn add_output_args(
+    cmd: &mut Command,
+    use_cache: bool,
+    output_template: &str,
+    bitrate: u32,
+    ffmpeg_dir: &Path,
+    options: &DownloadOptions,
+) -> Result<(), String> {
+    if use_cache {
+        cmd.arg("-o").arg(media_cache::output_template()?);
+    } else {
+        cmd.arg("-x")
+            .arg("--audio-format")
+            .arg(options.audio_format.ytdlp_name());
+        if !options.audio_format.is_lossless() {
+            cmd.arg("--audio-quality").arg(format!("{}K", bitrate));
+        }
+        cmd.arg("-o").arg(output_template);
+        if let Some(ref clip) = options.clip {
+            cmd.arg("--download-sections").arg(clip.section_arg());
+        }
+        if options.writes_tags() {
+            cmd.arg("--embed-metadata")
+                .args(options.tag_overrides.ytdlp_args());
+        }
+        if options.sponsorblock {
+            cmd.arg("--sponsorblock-remove")
+                .arg(SPONSORBLOCK_CATEGORIES);
+        }
+        if options.split_chapters {
+            cmd.arg("--split-chapters")
+                .arg("-o")
+                .arg(format!("chapter:{}", chapter_template(output_template)));
+        }
+        if let Some(ref lyrics) = options.lyrics {
+            lyrics.add_args(cmd);
+        }
+        // yt-dlp copies audio that already has the right codec, the encoder arguments
+        // that follow its own make sure the filter can run
+        if options.audio_filter().is_some() {
+            cmd.arg("--postprocessor-args").arg(format!(
+                "ExtractAudio:{}",
+                options.encoder_args(bitrate, false).join(" ")
+            ));
+        }
+        if options.embeds_thumbnail() {
+            cmd.arg("--embed-thumbnail")
+                .arg("--convert-thumbnails")
+                .arg("jpg");
+        }
+    }
+    cmd.arg("--ffmpeg-location").arg(ffmpeg_dir);
+    Ok(())
+}
+
+/// Output template for the chapters of a split video, numbered inside a folder named
+/// like the whole track
+fn chapter_template(output_template: &str) -> String {
+    let folder = output_template
+        .strip_suffix(".%(ext)s")
+        .unwrap_or(output_template);
+    format!(
+        "{}/%(section_number)02d - %(section_title)s.%(ext)s",
+        folder
+    )
+}
+
+/// Chapter files split from the track at `output_path`, in chapter order
+fn chapter_files(output_path: &Path, options: &DownloadOptions) -> Vec<String> {
+    if !options.split_chapters {
+        return Vec::new();
+    }
+    let extension = options.audio_format.extension();
+    let Ok(entries) = fs::read_dir(output_path.with_extension("")) else {
+        return Vec::new();
+    };
+    let mut files: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+        })
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    files.sort();
+    files
+}
+
+/// Run the loudness normalization pass over a freshly downloaded track when it is on.
+/// A track that can't be normalized is kept as it is.
+async fn normalized(
+    ffmpeg_cmd: &str,
+    bitrate: u32,
+    options: &DownloadOptions,
+    mut result: DownloadResult,
+) -> DownloadResult {
+    let Some(ref settings) = options.loudness else {
+        return result;
+    };
+    let path = Path::new(&result.output_path);
+    match conversion::normalize_loudness(ffmpeg_cmd, path, options.audio_format, bitrate, settings)
+        .await
+    {
+        Ok(()) => {
+            result.file_size = std::fs::metadata(path).ok().map(|m| m.len());
+            result.lossless_copy = false;
+        }
+        Err(e) => eprintln!("[download] {}, keeping {} as it is", e, path.display()),
+    }
+    result
+}
+
+/// Lyrics files saved next to the track at `output_path`
+fn lyrics_files(output_path: &Path, options: &DownloadOptions) -> Vec<String> {
+    if options.lyrics.is_none() {
+        return Vec::new();
+    }
+    lyrics::files_for(output_path)
+}
+
+/// Transcode a freshly cached download to the output path and trim the cache
+async fn transcode_cached(
+    ffmpeg_cmd: &str,
+    video_info: &serde_json::Value,
+    output_path: &Path,
+    bitrate: u32,
+    options: &DownloadOptions,
+) -> Result<bool, String> {
+    let cached = video_info["id"]
+        .as_str()
+        .and_then(media_cache::lookup)
+        .ok_or("Downloaded audio was not found in the media cache")?;
+    let copied = media_cache::transcode(
+        ffmpeg_cmd,
+        &cached,
+        output_path,
+        bitrate,
+        options,
+        &options.tags_for(video_info),
+    )
+    .await?;
+    if let Some(ref settings) = options.media_cache {
+        media_cache::evict(settings);
+    }
+    Ok(copied)
+}
+
+/// Audio codec of the format yt-dlp downloads, `None` being the format selector's pick
+fn downloaded_acodec<'a>(
+    video_info: &'a serde_json::Value,
+    format_id: Option<&str>,
+) -> Option<&'a str> {
+    match format_id {
+        Some(id) => video_info["formats"]
+            .as_array()?
+            .iter()
+            .find(|f| f["format_id"].as_str() == Some(id))?["acodec"]
+            .as_str(),
+        None => video_info["acodec"].as_str(),
+    }
+}
+
+/// Extract the percentage from a yt-dlp progress line such as `[download]  45.3% of 3MiB`.
+/// Tolerates comma decimal separators and (non-breaking) spaces before the `%` sign,
+/// as emitted under non-English locales.
+pub(crate) fn parse_progress_percent(line: &str) -> Option<f64> {
+    let percent_pos = line.find('%')?;
+    let before: Vec<char> = line[..percent_pos].chars().collect();
+
+    let mut end = before.len();
+    while end > 0 && before[end - 1].is_whitespace() {
+        end -= 1;
+    }
+
+    let mut start = end;
+    while start > 0
+        && (before[start - 1].is_ascii_digit() || matches!(before[start - 1], '.' | ','))
+    {
+        start -= 1;
+    }
+
+    let number: String = before[start..end]
+        .iter()
+        .map(|&c| if c == ',' { '.' } else { c })
+        .collect();
+    number
+        .trim_matches('.')
+        .parse::<f64>()
+        .ok()
+        .map(|percent| percent.clamp(0.0, 100.0))
+}
+
+/// Maximum number of formats tried before a download is reported as failed
+const MAX_FORMAT_ATTEMPTS: usize = 4;
+
+/// Build the ordered list of formats to try for a video.
+/// The first entry is the format yt-dlp selected for the format selector, followed by the
+/// remaining audio-only formats from best to worst bitrate. `None` lets yt-dlp pick.
+fn format_attempts(video_info: &serde_json::Value) -> Vec<Option<String>> {
+    let mut attempts: Vec<Option<String>> = Vec::new();
+
+    if let Some(selected) = video_info.get("format_id").and_then(|v| v.as_str()) {
+        attempts.push(Some(selected.to_string()));
+    }
+
+    for format_id in audio_format_ladder(video_info) {
+        if !attempts
+            .iter()
+            .any(|a| a.as_deref() == Some(format_id.as_str()))
+        {
+            attempts.push(Some(format_id));
+        }
+    }
+
+    if attempts.is_empty() {
+        attempts.push(None);
+    }
+
+    attempts.truncate(MAX_FORMAT_ATTEMPTS);
+    attempts
+}
+
+/// Collect audio-only format IDs from the `--dump-json` formats list, best bitrate first
+fn audio_format_ladder(video_info: &serde_json::Value) -> Vec<String> {
+    let mut formats: Vec<(String, f64)> = video_info
+        .get("formats")
+        .and_then(|v| v.as_array())
+        .map(|formats| {
+            formats
+                .iter()
+                .filter(|f| {
+                    f.get("vcodec").and_then(|v| v.as_str()) == Some("none")
+                        && f.get("acodec").and_then(|v| v.as_str()) != Some("none")
+                })
+                .filter_map(|f| {
+                    let id = f.get("format_id").and_then(|v| v.as_str())?;
+                    let bitrate = f
+                        .get("abr")
+                        .and_then(|v| v.as_f64())
+                        .or_else(|| f.get("tbr").and_then(|v| v.as_f64()))
+                        .unwrap_or(0.0);
+                    Some((id.to_string(), bitrate))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    formats.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    formats.into_iter().map(|(id, _)| id).collect()
+}
+
+/// Check whether a yt-dlp failure is specific to the chosen format, meaning another
+/// format of the same video may still succeed
+fn is_format_error(stderr: &str) -> bool {
+    let stderr_lower = stderr.to_lowercase();
+    stderr_lower.contains("http error 403")
+        || stderr_lower.contains("requested format is not available")
+        || stderr_lower.contains("unable to download video data")
+        || stderr_lower.contains("fragment")
+}
+
+/// Check whether a yt-dlp failure will fail the same way however often it is retried
+fn is_permanent_error(error: &str) -> bool {
+    let error_lower = error.to_lowercase();
+    error_lower.contains("video unavailable")
+        || error_lower.contains("private video")
+        || error_lower.contains("has been removed")
+        || error_lower.contains("copyright")
+        || error_lower.contains("members-only")
+        || error_lower.contains("confirm your age")
+}
+
+/// The `ERROR:` lines of yt-dlp output, or its last line when there are none
+fn error_summary(output: &str) -> String {
+    let errors: Vec<&str> = output
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| line.starts_with("ERROR:"))
+        .collect();
+    if !errors.is_empty() {
+        return errors.join("\n");
+    }
+    output
+        .lines()
+        .map(|line| line.trim())
+        .rfind(|line| !line.is_empty())
+        .unwrap_or("yt-dlp failed without an error message")
+        .to_string()
+}
+
+/// Check whether a yt-dlp failure is one that switching the YouTube player client
+/// usually fixes (bot checks, 403s, signature extraction breakage)
+pub fn needs_player_client_workaround(stderr: &str) -> bool {
+    let stderr_lower = stderr.to_lowercase();
+    stderr_lower.contains("http error 403")
+        || stderr_lower.contains("sign in to confirm you")
+        || stderr_lower.contains("nsig extraction failed")
+        || stderr_lower.contains("requested format is not available")
+        || stderr_lower.contains("this content isn't available")
+}
+
+/// Append a hint pointing at the player client workaround when it is likely to help
+pub(crate) fn with_workaround_hint(error: String) -> String {
+    if needs_player_client_workaround(&error) {
+        format!(
+            "{}\n\nTip: YouTube may be blocking the default player client. Try the recommended workaround to switch player clients and download again.",
+            error
+        )
+    } else {
+        error
+    }
+}
+
+fn attempt_label(format_id: &Option<String>, options: &DownloadOptions) -> String {
+    format!(
+        "format {} ({:?})",
+        format_id.as_deref().unwrap_or("default"),
+        options.verbosity
+    )
+}
+
+/// The last path printed by `--print after_move:filepath`
+fn printed_filepath(stdout: &[u8]) -> Option<PathBuf> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .map(|line| line.trim())
+        .rfind(|line| !line.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Whether a file name written by yt-dlp belongs to a title we sanitized ourselves.
+/// yt-dlp swaps reserved characters for full-width look-alikes (`:` -> `：`) where
+/// we use `_`, so both are folded the same way before comparing.
+pub(crate) fn matches_sanitized_title(actual_stem: &str, expected_stem: &str) -> bool {
+    let fold = |stem: &str| {
+        let ascii: String = stem
+            .chars()
+            .map(|c| match c {
+                '＜' => '<',
+                '＞' => '>',
+                '：' => ':',
+                '＂' => '"',
+                '⧸' | '／' => '/',
+                '⧹' | '＼' => '\\',
+                '｜' => '|',
+                '？' => '?',
+                '＊' => '*',
+                c => c,
+            })
+            .collect();
+        sanitize_filename(&ascii).to_lowercase()
+    };
+    fold(actual_stem) == fold(expected_stem)
 }
 
 /// Sanitize filename to be safe for all operating systems
 /// Removes or replaces characters that are invalid on Windows, macOS, and Linux
-fn sanitize_filename(filename: &str) -> String {
+pub(crate) fn sanitize_filename(filename: &str) -> String {
     filename
         .chars()
         .map(|c| match c {
@@ -1094,3 +3813,142 @@ fn sanitize_filename(filename: &str) -> String {
         .trim_end_matches(' ') // Windows doesn't allow trailing spaces
         .to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        file_stem_for, is_named_after, parse_progress_percent, parse_timestamp, Clip, ClipRange,
+        DownloadOptions, ProgressReport,
+    };
+    use std::path::Path;
+
+    #[test]
+    fn parses_c_locale_progress() {
+        let line = "[download]  45.3% of    3.52MiB at    1.21MiB/s ETA 00:02";
+        assert_eq!(parse_progress_percent(line), Some(45.3));
+        assert_eq!(
+            parse_progress_percent("[download] 100% of    3.52MiB in 00:00:03 at 1.02MiB/s"),
+            Some(100.0)
+        );
+    }
+
+    #[test]
+    fn parses_comma_decimal_progress() {
+        // yt-dlp doesn't localize its progress output and runs with LC_ALL=C, so this is
+        // a defensive input: a comma decimal as a German locale would format it
+        let line = "[download]  45,3% of    3,52MiB at    1,21MiB/s ETA 00:02";
+        assert_eq!(parse_progress_percent(line), Some(45.3));
+    }
+
+    #[test]
+    fn parses_progress_with_space_before_percent() {
+        // Defensive input as above, with the no-break space a French locale puts before `%`
+        let line = "[download]  12,0\u{a0}% of ~  10,00MiB at  512,00KiB/s ETA 00:18";
+        assert_eq!(parse_progress_percent(line), Some(12.0));
+        assert_eq!(
+            parse_progress_percent("[download]   7.5 % of 1.00GiB"),
+            Some(7.5)
+        );
+    }
+
+    #[test]
+    fn ignores_lines_without_a_percentage() {
+        assert_eq!(
+            parse_progress_percent("[download] Destination: /music/Song.webm"),
+            None
+        );
+        assert_eq!(parse_progress_percent("[download] % done"), None);
+    }
+
+    #[test]
+    fn parses_clip_times() {
+        assert_eq!(parse_timestamp("90"), Ok(90.0));
+        assert_eq!(parse_timestamp("1:30.5"), Ok(90.5));
+        assert_eq!(parse_timestamp("1:02:03"), Ok(3723.0));
+        assert!(parse_timestamp("1:75").is_err());
+        assert!(parse_timestamp("1:2:3:4").is_err());
+        assert!(parse_timestamp("-5").is_err());
+    }
+
+    #[test]
+    fn resolves_open_and_empty_clip_ranges() {
+        let range = ClipRange {
+            start_time: Some("1:00:00".to_string()),
+            end_time: None,
+        };
+        let clip = range.resolve().unwrap().unwrap();
+        assert_eq!(clip.section_arg(), "*3600-inf");
+        assert_eq!(clip.label(), "(1h00m00s-end)");
+        assert_eq!(ClipRange::default().resolve(), Ok(None));
+
+        let backwards = ClipRange {
+            start_time: Some("2:00".to_string()),
+            end_time: Some("1:00".to_string()),
+        };
+        assert!(backwards.resolve().is_err());
+    }
+
+    #[test]
+    fn clip_length_stops_at_the_video_end() {
+        let clip = Clip {
+            start: 60.0,
+            end: Some(300.0),
+        };
+        assert_eq!(clip.length(Some(200.0)), Some(140.0));
+        assert_eq!(clip.length(None), Some(240.0));
+        assert_eq!(clip.label(), "(1m00s-5m00s)");
+    }
+
+    #[test]
+    fn parses_structured_progress() {
+        let line = r#"[progress] {"status": "downloading", "downloaded_bytes": 1048576, "total_bytes": null, "total_bytes_estimate": 4194304.0, "speed": 524288.5, "eta": 6, "_percent_str": " 25.0%"}"#;
+        let report = ProgressReport::parse(line).unwrap();
+        assert_eq!(report.percent(), Some(25.0));
+        let transfer = report.transfer();
+        assert_eq!(transfer.downloaded_bytes, Some(1048576));
+        assert_eq!(transfer.total_bytes, Some(4194304));
+        assert_eq!(transfer.speed, Some(524288.5));
+        assert_eq!(transfer.eta, Some(6));
+
+        let finished = r#"[progress] {"status": "finished", "downloaded_bytes": 10}"#;
+        assert_eq!(
+            ProgressReport::parse(finished).unwrap().percent(),
+            Some(100.0)
+        );
+        assert!(ProgressReport::parse("[download]  45.3% of 3.52MiB").is_none());
+    }
+
+    #[test]
+    fn file_stems_are_sanitized_and_never_empty() {
+        let plain = DownloadOptions::default();
+        let romanized = DownloadOptions {
+            transliterate: true,
+            ..DownloadOptions::default()
+        };
+        assert_eq!(file_stem_for("AC/DC: Live?", &plain), "AC_DC_ Live_");
+        assert_eq!(
+            file_stem_for("Кино: Группа крови", &romanized),
+            "Kino_ Gruppa krovi"
+        );
+        assert_eq!(file_stem_for("Кино", &plain), "Кино");
+        assert_eq!(file_stem_for(" ... ", &plain), "Untitled");
+        assert_eq!(file_stem_for("ъь", &romanized), "Untitled");
+    }
+
+    #[test]
+    fn partial_files_belong_to_the_download_named_after_them() {
+        let names = vec!["Song".to_string(), "dQw4w9WgXcQ".to_string()];
+        for own in [
+            "Song.webm.part",
+            "Song.f251.webm.part-Frag3",
+            "Song.temp.mp3",
+            "dQw4w9WgXcQ.m4a.ytdl",
+        ] {
+            assert!(is_named_after(Path::new(own), &names), "{}", own);
+        }
+        for other in ["Song 2.webm.part", "Songs.webm.part", "Other.webm.part"] {
+            assert!(!is_named_after(Path::new(other), &names), "{}", other);
+        }
+        assert!(!is_named_after(Path::new(".webm.part"), &[String::new()]));
+    }
+}
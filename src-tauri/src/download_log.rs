@@ -0,0 +1,70 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use tauri::api::path::config_dir;
+
+/// Number of per-download logs kept, least recently written are removed first
+const MAX_DOWNLOAD_LOGS: usize = 100;
+
+pub(crate) fn get_logs_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("youtube-downloader").join("logs"))
+}
+
+fn get_log_path(video_id: &str) -> Option<PathBuf> {
+    let file_name: String = video_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+        .collect();
+    if file_name.is_empty() {
+        return None;
+    }
+    get_logs_dir().map(|dir| dir.join(format!("{}.log", file_name)))
+}
+
+/// Append the yt-dlp output of one download attempt to the video's log
+pub fn append(video_id: &str, attempt: &str, output: &str) {
+    let Some(path) = get_log_path(video_id) else {
+        return;
+    };
+
+    let written = (|| -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        writeln!(
+            file,
+            "=== {} {} ===\n{}",
+            chrono::Utc::now().to_rfc3339(),
+            attempt,
+            output.trim_end()
+        )
+    })();
+
+    match written {
+        Ok(()) => prune(),
+        Err(e) => eprintln!("[download] Failed to write log for {}: {}", video_id, e),
+    }
+}
+
+fn prune() {
+    let Some(entries) = get_logs_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return;
+    };
+    let mut logs: Vec<(PathBuf, std::time::SystemTime)> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| Some((e.path(), e.metadata().ok()?.modified().ok()?)))
+        .collect();
+    if logs.len() <= MAX_DOWNLOAD_LOGS {
+        return;
+    }
+    logs.sort_by_key(|(_, modified)| *modified);
+    for (path, _) in &logs[..logs.len() - MAX_DOWNLOAD_LOGS] {
+        fs::remove_file(path).ok();
+    }
+}
+
+/// Read the captured yt-dlp output for a video, if any download of it was logged
+pub fn load(video_id: &str) -> Option<String> {
+    fs::read_to_string(get_log_path(video_id)?).ok()
+}
@@ -0,0 +1,99 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Version of the event payload shapes, bumped along with the channel names whenever a
+/// payload changes in a way older UIs can't read
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Whether every event is also sent under its old name with the bare payload, for UIs
+/// from before the namespaced channels
+static LEGACY_EVENTS: AtomicBool = AtomicBool::new(true);
+
+/// Events the backend sends to the UI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    DownloadProgress,
+    QueueJobProgress,
+    QueueJobUpdated,
+    QueueStateChanged,
+    BatchProgress,
+    VideoDownloadProgress,
+    PreflightProgress,
+    RecommendedFixes,
+    ClipboardUrlDetected,
+    DownloadWaitingForNetwork,
+    UserDecisionRequired,
+    IncompleteDownloadsFound,
+    QueueForecastUpdated,
+}
+
+impl Event {
+    /// Namespaced channel carrying the versioned payload
+    pub fn channel(self) -> &'static str {
+        match self {
+            Event::DownloadProgress => "ytmp3://progress/v2",
+            Event::QueueJobProgress => "ytmp3://queue/job-progress/v2",
+            Event::QueueJobUpdated => "ytmp3://queue/job-updated/v2",
+            Event::QueueStateChanged => "ytmp3://queue/state-changed/v2",
+            Event::BatchProgress => "ytmp3://batch/progress/v2",
+            Event::VideoDownloadProgress => "ytmp3://video/progress/v2",
+            Event::PreflightProgress => "ytmp3://preflight/progress/v2",
+            Event::RecommendedFixes => "ytmp3://advisor/recommended-fixes/v2",
+            Event::ClipboardUrlDetected => "ytmp3://clipboard/url-detected/v2",
+            Event::DownloadWaitingForNetwork => "ytmp3://download/waiting-for-network/v2",
+            Event::UserDecisionRequired => "ytmp3://user-decision-required/v2",
+            Event::IncompleteDownloadsFound => "ytmp3://download/incomplete-found/v2",
+            Event::QueueForecastUpdated => "ytmp3://queue/forecast-updated/v2",
+        }
+    }
+
+    /// Name the event had before the channels were namespaced, none for events added
+    /// since
+    pub fn legacy_name(self) -> Option<&'static str> {
+        match self {
+            Event::DownloadProgress => Some("download-progress"),
+            Event::QueueJobProgress => Some("queue-job-progress"),
+            Event::QueueJobUpdated => Some("queue-job-updated"),
+            Event::QueueStateChanged => Some("queue-state-changed"),
+            Event::BatchProgress => None,
+            Event::VideoDownloadProgress => Some("video-download-progress"),
+            Event::PreflightProgress => Some("preflight-progress"),
+            Event::RecommendedFixes => Some("recommended-fixes"),
+            Event::ClipboardUrlDetected => None,
+            Event::DownloadWaitingForNetwork => Some("download-waiting-for-network"),
+            Event::UserDecisionRequired => None,
+            Event::IncompleteDownloadsFound => None,
+            Event::QueueForecastUpdated => None,
+        }
+    }
+}
+
+/// Send the legacy event names as well from now on, or stop sending them
+pub fn configure(legacy_events: bool) {
+    LEGACY_EVENTS.store(legacy_events, Ordering::SeqCst);
+}
+
+/// Send `payload` to every window on the event's channel, and under its legacy name
+/// when that is still wanted
+pub fn emit<R: Runtime>(app_handle: &AppHandle<R>, event: Event, payload: &impl Serialize) {
+    let payload = match serde_json::to_value(payload) {
+        Ok(payload) => payload,
+        Err(e) => {
+            eprintln!("[events] Failed to serialize {}: {}", event.channel(), e);
+            return;
+        }
+    };
+    let versioned = serde_json::json!({
+        "schema_version": SCHEMA_VERSION,
+        "payload": &payload,
+    });
+    if let Err(e) = app_handle.emit_all(event.channel(), versioned) {
+        eprintln!("[events] Failed to emit {}: {}", event.channel(), e);
+    }
+    if let Some(legacy_name) = event.legacy_name() {
+        if LEGACY_EVENTS.load(Ordering::SeqCst) {
+            app_handle.emit_all(legacy_name, payload).ok();
+        }
+    }
+}
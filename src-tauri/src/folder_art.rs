@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::download::{run_output, ytdlp_command, DownloadOptions};
+use crate::proxy;
+use crate::source;
+
+/// A downloaded playlist item, in playlist order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionTrack {
+    /// 1-based position in the playlist
+    pub position: usize,
+    pub title: Option<String>,
+    pub url: String,
+    /// File name inside the folder
+    pub file: String,
+    pub duration: Option<f64>,
+}
+
+/// What `metadata.json` holds about the playlist a folder was downloaded from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionMetadata {
+    pub source_url: String,
+    pub playlist_id: Option<String>,
+    pub title: Option<String>,
+    pub uploader: Option<String>,
+    pub description: Option<String>,
+    /// Year the playlist was last modified, the closest thing to a release year
+    pub year: Option<String>,
+    pub generated_at: String,
+    pub tracks: Vec<CollectionTrack>,
+}
+
+/// Playlist title, description and art URL from yt-dlp's playlist-level info
+async fn playlist_info(
+    ytdlp_cmd: &str,
+    url: &str,
+    options: &DownloadOptions,
+) -> Result<serde_json::Value, String> {
+    let mut cmd = ytdlp_command(ytdlp_cmd, options);
+    cmd.arg("--dump-single-json")
+        .arg("--flat-playlist")
+        .arg(source::listing_url(url));
+    let output = run_output(&mut cmd, &options.cancel)
+        .await
+        .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to get playlist info: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse playlist info JSON: {}", e))
+}
+
+/// The largest playlist thumbnail, or the first video's when the playlist has none
+fn artwork_url(info: &serde_json::Value, tracks: &[CollectionTrack]) -> Option<String> {
+    let largest = info["thumbnails"].as_array().and_then(|thumbnails| {
+        thumbnails
+            .iter()
+            .filter(|t| t["url"].is_string())
+            .max_by_key(|t| t["width"].as_u64().unwrap_or(0) * t["height"].as_u64().unwrap_or(0))
+            .and_then(|t| t["url"].as_str())
+    });
+    largest.map(|url| url.to_string()).or_else(|| {
+        tracks
+            .first()
+            .and_then(|track| track.url.split("v=").nth(1))
+            .and_then(|rest| rest.split('&').next())
+            .map(|id| format!("https://i.ytimg.com/vi/{}/hqdefault.jpg", id))
+    })
+}
+
+/// Download the artwork and convert it to `folder.jpg`, as thumbnails are often WebP
+async fn write_artwork(ffmpeg_cmd: &str, art_url: &str, folder: &Path) -> Result<(), String> {
+    let client = proxy::http_client(Duration::from_secs(20), Some(Duration::from_secs(60)))?;
+    let response = client
+        .get(art_url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to download the artwork: {}", e))?;
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to download the artwork: {}", e))?;
+
+    let source = folder.join(".folder-art.tmp");
+    fs::write(&source, &bytes).map_err(|e| format!("Failed to save the artwork: {}", e))?;
+    let result = tokio::process::Command::new(ffmpeg_cmd)
+        .arg("-y")
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-i")
+        .arg(&source)
+        .arg("-frames:v")
+        .arg("1")
+        .arg("-q:v")
+        .arg("2")
+        .arg(folder.join("folder.jpg"))
+        .output()
+        .await;
+    fs::remove_file(&source).ok();
+    let result = result.map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    if !result.status.success() {
+        return Err(format!(
+            "Failed to convert the artwork: {}",
+            String::from_utf8_lossy(&result.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn minutes_seconds(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Kodi album NFO, which Jellyfin reads as well
+fn album_nfo(metadata: &CollectionMetadata) -> String {
+    let mut nfo =
+        String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<album>\n");
+    let mut field = |tag: &str, value: Option<&str>| {
+        if let Some(value) = value.filter(|v| !v.trim().is_empty()) {
+            nfo.push_str(&format!("  <{0}>{1}</{0}>\n", tag, xml_escape(value)));
+        }
+    };
+    field("title", metadata.title.as_deref());
+    field("artist", metadata.uploader.as_deref());
+    field("albumartist", metadata.uploader.as_deref());
+    field("review", metadata.description.as_deref());
+    field("year", metadata.year.as_deref());
+    for track in &metadata.tracks {
+        nfo.push_str("  <track>\n");
+        nfo.push_str(&format!("    <position>{}</position>\n", track.position));
+        if let Some(ref title) = track.title {
+            nfo.push_str(&format!("    <title>{}</title>\n", xml_escape(title)));
+        }
+        if let Some(duration) = track.duration {
+            nfo.push_str(&format!(
+                "    <duration>{}</duration>\n",
+                minutes_seconds(duration)
+            ));
+        }
+        nfo.push_str("  </track>\n");
+    }
+    nfo.push_str("</album>\n");
+    nfo
+}
+
+/// Write `folder.jpg`, `album.nfo` and `metadata.json` describing the playlist into the
+/// folder it was downloaded to, for Kodi, Jellyfin and Plex. An existing folder.jpg is
+/// kept, it may be art the user picked.
+pub async fn write_collection_files(
+    ytdlp_cmd: &str,
+    ffmpeg_cmd: &str,
+    url: &str,
+    output_folder: &str,
+    tracks: Vec<CollectionTrack>,
+    options: &DownloadOptions,
+) -> Result<(), String> {
+    let folder = Path::new(output_folder);
+    let info = playlist_info(ytdlp_cmd, url, options).await?;
+    let text = |field: &str| info[field].as_str().map(|s| s.to_string());
+
+    if !folder.join("folder.jpg").exists() {
+        match artwork_url(&info, &tracks) {
+            Some(art_url) => {
+                if let Err(e) = write_artwork(ffmpeg_cmd, &art_url, folder).await {
+                    eprintln!("[folder_art] {}", e);
+                }
+            }
+            None => eprintln!("[folder_art] The playlist has no artwork"),
+        }
+    }
+
+    let metadata = CollectionMetadata {
+        source_url: url.to_string(),
+        playlist_id: text("id"),
+        title: text("title"),
+        uploader: text("uploader").or_else(|| text("channel")),
+        description: text("description"),
+        year: text("modified_date").map(|date| date.chars().take(4).collect()),
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        tracks,
+    };
+    fs::write(folder.join("album.nfo"), album_nfo(&metadata))
+        .map_err(|e| format!("Failed to write album.nfo: {}", e))?;
+    let json = serde_json::to_string_pretty(&metadata).map_err(|e| e.to_string())?;
+    fs::write(folder.join("metadata.json"), json)
+        .map_err(|e| format!("Failed to write metadata.json: {}", e))
+}
@@ -0,0 +1,144 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::download;
+use crate::queue::{JobStatus, QueueJob};
+use crate::source;
+use crate::speed_history;
+
+/// Length assumed for a track while no job has finished yet, in seconds
+const TYPICAL_TRACK_SECONDS: f64 = 240.0;
+
+/// Tracks assumed for a playlist job while no playlist job has finished yet
+const TYPICAL_PLAYLIST_TRACKS: f64 = 15.0;
+
+/// Expected time left for one job that hasn't finished
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobForecast {
+    pub job_id: String,
+    /// Seconds from now until the job starts, 0 for the running one
+    pub starts_in_seconds: f64,
+    pub remaining_seconds: f64,
+    /// The job is a playlist whose length isn't known yet, so its time is a guess
+    pub rough: bool,
+}
+
+/// When the queue is expected to run dry. Paused jobs are left out, they wait for
+/// `resume_all`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueueForecast {
+    pub remaining_jobs: usize,
+    pub remaining_seconds: f64,
+    /// RFC 3339 time the last job is expected to finish, none when the queue is empty
+    pub estimated_completion: Option<String>,
+    /// Average time a track took in the finished jobs, or the assumed time
+    pub seconds_per_track: f64,
+    /// Finished jobs the average comes from, 0 when it is assumed
+    pub based_on_jobs: usize,
+    pub jobs: Vec<JobForecast>,
+}
+
+/// How long a finished job ran, when it has both timestamps
+fn elapsed_seconds(job: &QueueJob) -> Option<f64> {
+    let started = DateTime::parse_from_rfc3339(job.started_at.as_deref()?).ok()?;
+    let finished = DateTime::parse_from_rfc3339(job.finished_at.as_deref()?).ok()?;
+    let seconds = (finished - started).num_milliseconds() as f64 / 1000.0;
+    (seconds > 0.0).then_some(seconds)
+}
+
+fn is_playlist(job: &QueueJob) -> bool {
+    source::parse_source(&job.url).is_ok_and(|s| s.is_playlist())
+}
+
+/// Time per track assumed from this session's download speed, or a conservative
+/// speed when nothing was measured
+fn assumed_seconds_per_track() -> f64 {
+    let throughput = speed_history::average_speed().unwrap_or(download::ESTIMATED_BYTES_PER_SECOND);
+    download::ESTIMATED_SECONDS_PER_ITEM
+        + download::size_for_duration(TYPICAL_TRACK_SECONDS, download::ESTIMATED_SOURCE_KBPS) as f64
+            / throughput
+}
+
+/// Forecast the jobs that haven't finished from how long the finished ones took,
+/// given the progress of the running ones from 0 to 100. The queue runs one job at a
+/// time, so each job starts when the one before it finishes.
+pub fn compute(
+    jobs: &[QueueJob],
+    progress: &HashMap<String, f64>,
+    now: DateTime<Utc>,
+) -> QueueForecast {
+    let finished: Vec<(&QueueJob, f64)> = jobs
+        .iter()
+        .filter(|j| j.status == JobStatus::Done && !j.output_paths.is_empty())
+        .filter_map(|j| Some((j, elapsed_seconds(j)?)))
+        .collect();
+    let tracks: usize = finished.iter().map(|(j, _)| j.output_paths.len()).sum();
+    let seconds_per_track = if tracks > 0 {
+        finished.iter().map(|(_, s)| s).sum::<f64>() / tracks as f64
+    } else {
+        assumed_seconds_per_track()
+    };
+    let playlist_tracks: Vec<usize> = finished
+        .iter()
+        .filter(|(j, _)| is_playlist(j))
+        .map(|(j, _)| j.output_paths.len())
+        .collect();
+    let tracks_per_playlist = if playlist_tracks.is_empty() {
+        TYPICAL_PLAYLIST_TRACKS
+    } else {
+        playlist_tracks.iter().sum::<usize>() as f64 / playlist_tracks.len() as f64
+    };
+
+    let mut forecasts = Vec::new();
+    let mut clock = 0.0;
+    // The running job first, then the queued ones in the order the worker picks them
+    let pending = jobs
+        .iter()
+        .filter(|j| j.status == JobStatus::Running)
+        .chain(jobs.iter().filter(|j| j.status == JobStatus::Queued));
+    for job in pending {
+        let rough = is_playlist(job);
+        let estimate = if rough {
+            seconds_per_track * tracks_per_playlist
+        } else {
+            seconds_per_track
+        };
+        let percent = progress
+            .get(&job.id)
+            .copied()
+            .unwrap_or(0.0)
+            .clamp(0.0, 100.0);
+        let started = job
+            .started_at
+            .as_deref()
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok());
+        let remaining = match started {
+            // Extrapolate from the running job's own pace once it has made some progress
+            Some(started) if percent >= 1.0 => {
+                let elapsed =
+                    (now - started.with_timezone(&Utc)).num_milliseconds() as f64 / 1000.0;
+                elapsed.max(0.0) / percent * (100.0 - percent)
+            }
+            _ => estimate * (1.0 - percent / 100.0),
+        };
+        forecasts.push(JobForecast {
+            job_id: job.id.clone(),
+            starts_in_seconds: clock,
+            remaining_seconds: remaining,
+            rough,
+        });
+        clock += remaining;
+    }
+
+    let estimated_completion = (!forecasts.is_empty())
+        .then(|| (now + chrono::Duration::milliseconds((clock * 1000.0) as i64)).to_rfc3339());
+    QueueForecast {
+        remaining_jobs: forecasts.len(),
+        remaining_seconds: clock,
+        estimated_completion,
+        seconds_per_track,
+        based_on_jobs: finished.len(),
+        jobs: forecasts,
+    }
+}
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::api::path::config_dir;
+use tauri::{AppHandle, Runtime};
+
+use crate::download::{self, DownloadOverrides};
+use crate::events::{self, Event};
+use crate::queue;
+
+/// A download that was started but never finished, because the app quit or crashed
+/// while it ran
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncompleteDownload {
+    pub id: String,
+    pub url: String,
+    pub output_folder: String,
+    pub bitrate: u32,
+    #[serde(flatten)]
+    pub overrides: DownloadOverrides,
+    pub started_at: String,
+}
+
+/// What the startup scan found: downloads to resume and the partial files yt-dlp left
+/// in their output folders
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IncompleteScan {
+    pub downloads: Vec<IncompleteDownload>,
+    pub partial_files: Vec<String>,
+}
+
+/// Serializes the read-modify-write of `incomplete.json`
+static JOURNAL_LOCK: Mutex<()> = Mutex::new(());
+
+fn get_journal_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("youtube-downloader").join("incomplete.json"))
+}
+
+fn load() -> Vec<IncompleteDownload> {
+    get_journal_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save(downloads: &[IncompleteDownload]) {
+    let saved = get_journal_path()
+        .ok_or("Failed to get config directory".to_string())
+        .and_then(|path| {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let content = serde_json::to_string_pretty(downloads).map_err(|e| e.to_string())?;
+            fs::write(path, content).map_err(|e| e.to_string())
+        });
+    if let Err(e) = saved {
+        eprintln!("[incomplete] Failed to save the journal: {}", e);
+    }
+}
+
+/// Note a download as started. Returns the id to pass to `finish` once it has run its
+/// course, whether it succeeded or not.
+pub fn begin(
+    url: &str,
+    output_folder: &str,
+    bitrate: u32,
+    overrides: &DownloadOverrides,
+) -> String {
+    let _guard = JOURNAL_LOCK.lock().unwrap();
+    let download = IncompleteDownload {
+        id: queue::new_id("incomplete"),
+        url: url.to_string(),
+        output_folder: output_folder.to_string(),
+        bitrate,
+        overrides: overrides.clone(),
+        started_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let id = download.id.clone();
+    let mut downloads = load();
+    downloads.push(download);
+    save(&downloads);
+    id
+}
+
+pub fn finish(id: &str) {
+    let _guard = JOURNAL_LOCK.lock().unwrap();
+    let mut downloads = load();
+    downloads.retain(|d| d.id != id);
+    save(&downloads);
+}
+
+/// Remove and return every incomplete download, for handing them to the queue
+pub fn take_all() -> Vec<IncompleteDownload> {
+    let _guard = JOURNAL_LOCK.lock().unwrap();
+    let downloads = load();
+    save(&[]);
+    downloads
+}
+
+/// Partial files in `folder` and the subfolders a folder organization sorts into
+fn partial_files(folder: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        if path.is_dir() {
+            if let Ok(inner) = fs::read_dir(&path) {
+                found.extend(
+                    inner
+                        .filter_map(|e| e.ok())
+                        .map(|e| e.path())
+                        .filter(|p| download::is_partial_file(p)),
+                );
+            }
+        } else if download::is_partial_file(&path) {
+            found.push(path);
+        }
+    }
+    found
+}
+
+/// Look for downloads that never finished and the partial files in their output
+/// folders and in `default_folder`
+pub fn scan(default_folder: Option<&str>) -> IncompleteScan {
+    let downloads = {
+        let _guard = JOURNAL_LOCK.lock().unwrap();
+        load()
+    };
+    let mut folders: Vec<&str> = downloads
+        .iter()
+        .map(|d| d.output_folder.as_str())
+        .chain(default_folder)
+        .collect();
+    folders.sort_unstable();
+    folders.dedup();
+    let partial_files = folders
+        .into_iter()
+        .flat_map(|folder| partial_files(Path::new(folder)))
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    IncompleteScan {
+        downloads,
+        partial_files,
+    }
+}
+
+/// Scan on startup and tell the frontend when there is something to resume
+pub fn start_scan<R: Runtime>(app_handle: AppHandle<R>, default_folder: Option<String>) {
+    tauri::async_runtime::spawn_blocking(move || {
+        let scan = scan(default_folder.as_deref());
+        if scan.downloads.is_empty() && scan.partial_files.is_empty() {
+            return;
+        }
+        eprintln!(
+            "[incomplete] Found {} unfinished downloads and {} partial files",
+            scan.downloads.len(),
+            scan.partial_files.len()
+        );
+        events::emit(&app_handle, Event::IncompleteDownloadsFound, &scan);
+    });
+}
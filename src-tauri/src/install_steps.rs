@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+use crate::deps;
+
+const YTDLP_RELEASES_URL: &str = "https://github.com/yt-dlp/yt-dlp/releases/latest";
+const FFMPEG_DOWNLOAD_URL: &str = "https://ffmpeg.org/download.html";
+
+/// One way to install a dependency: a command to copy into a terminal, a page to open,
+/// or both
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallStep {
+    /// `yt-dlp` or `ffmpeg`
+    pub binary: String,
+    /// `windows`, `macos` or `linux`
+    pub platform: String,
+    /// `winget`, `brew`, `apt`, `pip` and so on, `None` for a manual download
+    pub package_manager: Option<String>,
+    pub title: String,
+    pub command: Option<String>,
+    pub url: Option<String>,
+}
+
+/// `(binary, platform, package manager, command)` for every package manager install
+const PACKAGE_INSTALLS: [(&str, &str, &str, &str); 11] = [
+    ("yt-dlp", "windows", "winget", "winget install yt-dlp"),
+    ("yt-dlp", "windows", "scoop", "scoop install yt-dlp"),
+    ("yt-dlp", "macos", "brew", "brew install yt-dlp"),
+    ("yt-dlp", "linux", "apt", "sudo apt install yt-dlp"),
+    ("yt-dlp", "linux", "pip", "python3 -m pip install -U yt-dlp"),
+    ("ffmpeg", "windows", "winget", "winget install ffmpeg"),
+    ("ffmpeg", "windows", "scoop", "scoop install ffmpeg"),
+    ("ffmpeg", "macos", "brew", "brew install ffmpeg"),
+    ("ffmpeg", "linux", "apt", "sudo apt install ffmpeg"),
+    ("ffmpeg", "linux", "dnf", "sudo dnf install ffmpeg"),
+    ("ffmpeg", "linux", "pacman", "sudo pacman -S ffmpeg"),
+];
+
+fn download_url(binary: &str) -> &'static str {
+    match binary {
+        "ffmpeg" => FFMPEG_DOWNLOAD_URL,
+        _ => YTDLP_RELEASES_URL,
+    }
+}
+
+/// Install steps for `binary` (both when unset) on `platform` (this one when unset),
+/// package managers first and a manual download last
+pub fn for_platform(
+    binary: Option<&str>,
+    platform: Option<&str>,
+) -> Result<Vec<InstallStep>, String> {
+    let platform = platform.unwrap_or_else(|| deps::get_platform());
+    if !matches!(platform, "windows" | "macos" | "linux") {
+        return Err(format!(
+            "Unknown platform '{}', expected windows, macos or linux",
+            platform
+        ));
+    }
+    let binaries: Vec<&str> = match binary {
+        Some(binary @ ("yt-dlp" | "ffmpeg")) => vec![binary],
+        Some(other) => {
+            return Err(format!(
+                "Unknown dependency '{}', expected yt-dlp or ffmpeg",
+                other
+            ))
+        }
+        None => vec!["yt-dlp", "ffmpeg"],
+    };
+
+    let mut steps = Vec::new();
+    for binary in binaries {
+        steps.extend(
+            PACKAGE_INSTALLS
+                .iter()
+                .filter(|(b, p, _, _)| *b == binary && *p == platform)
+                .map(|&(_, _, manager, command)| InstallStep {
+                    binary: binary.to_string(),
+                    platform: platform.to_string(),
+                    package_manager: Some(manager.to_string()),
+                    title: format!("Install {} with {}", binary, manager),
+                    command: Some(command.to_string()),
+                    url: None,
+                }),
+        );
+        steps.push(InstallStep {
+            binary: binary.to_string(),
+            platform: platform.to_string(),
+            package_manager: None,
+            title: format!("Download {} and pick it with \"Install from file\"", binary),
+            command: None,
+            url: Some(download_url(binary).to_string()),
+        });
+    }
+    Ok(steps)
+}
@@ -0,0 +1,287 @@
+//! End-to-end tests of the download command layer against `tests/fixtures/stub-yt-dlp`,
+//! which prints canned yt-dlp output and writes fake files instead of downloading.
+//! Every test runs inside a scratch home directory, so no preferences, history or
+//! caches of the developer are touched and no network access is needed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::test::{mock_app, MockRuntime};
+use tauri::{App, AppHandle};
+
+use crate::advisor::{self, ErrorCode};
+use crate::backup;
+use crate::commands::{self, save_retry_policy, start_download, DownloadResponse, HistoryData};
+use crate::deps;
+use crate::download::{self, DownloadOverrides, PlaylistSelection, RetryPolicy, CANCELLED_MESSAGE};
+
+/// The history file and the download registries are shared, so tests run one at a time
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+static SANDBOX: OnceLock<PathBuf> = OnceLock::new();
+
+/// Point the home, config and cache directories at a scratch directory and swap the
+/// bundled binaries for the stub
+fn sandbox() -> &'static Path {
+    SANDBOX.get_or_init(|| {
+        let root =
+            std::env::temp_dir().join(format!("youtube-downloader-tests-{}", std::process::id()));
+        for (var, dir) in [
+            ("HOME", "home"),
+            ("XDG_CONFIG_HOME", "config"),
+            ("XDG_CACHE_HOME", "cache"),
+            ("XDG_DATA_HOME", "data"),
+        ] {
+            let dir = root.join(dir);
+            fs::create_dir_all(&dir).unwrap();
+            std::env::set_var(var, dir);
+        }
+        deps::use_test_binary(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/stub-yt-dlp"),
+        );
+        root
+    })
+}
+
+fn test_app() -> App<MockRuntime> {
+    sandbox();
+    mock_app()
+}
+
+fn output_dir(name: &str) -> PathBuf {
+    let dir = sandbox().join("downloads").join(name);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn video_url(id: &str) -> String {
+    format!("https://www.youtube.com/watch?v={}", id)
+}
+
+async fn download(
+    app_handle: AppHandle<MockRuntime>,
+    url: &str,
+    folder: &Path,
+    download_id: Option<&str>,
+) -> Result<DownloadResponse, String> {
+    start_download(
+        url.to_string(),
+        folder.to_string_lossy().to_string(),
+        192,
+        DownloadOverrides::default(),
+        true,
+        download_id.map(|id| id.to_string()),
+        app_handle,
+    )
+    .await
+}
+
+fn mp3_files(folder: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(folder)
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("mp3"))
+        .collect();
+    files.sort();
+    files
+}
+
+#[tokio::test]
+async fn single_download_writes_the_file_and_history() {
+    let _serial = SERIAL.lock().await;
+    let app = test_app();
+    let folder = output_dir("single");
+
+    let response = download(app.handle(), &video_url("stubOK00001"), &folder, None)
+        .await
+        .unwrap();
+    let DownloadResponse::Single(result) = response else {
+        panic!("expected a single download, got {:?}", response);
+    };
+
+    assert!(Path::new(&result.output_path).exists());
+    assert_eq!(result.title.as_deref(), Some("Stub Video stubOK00001"));
+    assert_eq!(result.format_id.as_deref(), Some("251"));
+
+    let history = HistoryData::load();
+    let entry = history
+        .downloads
+        .iter()
+        .find(|d| d.output_path == result.output_path)
+        .expect("the download is in the history");
+    assert_eq!(entry.ytdlp_version.as_deref(), Some("2099.01.01"));
+    assert_eq!(entry.ffmpeg_version.as_deref(), Some("stub"));
+}
+
+#[tokio::test]
+async fn playlist_download_retries_and_reports_failed_items() {
+    let _serial = SERIAL.lock().await;
+    let app = test_app();
+    let folder = output_dir("playlist");
+    save_retry_policy(Some(RetryPolicy {
+        max_retries: 1,
+        initial_delay_seconds: 0.0,
+        max_delay_seconds: 0.0,
+    }))
+    .await
+    .unwrap();
+
+    let response = download(
+        app.handle(),
+        "https://www.youtube.com/playlist?list=PLstubPlaylist",
+        &folder,
+        None,
+    )
+    .await
+    .unwrap();
+    let DownloadResponse::Playlist(result) = response else {
+        panic!("expected a playlist download, got {:?}", response);
+    };
+
+    assert_eq!(result.total_videos, 3);
+    assert!(!result.cancelled);
+    let titles: Vec<_> = result
+        .downloaded_videos
+        .iter()
+        .map(|v| v.title.as_deref().unwrap_or_default())
+        .collect();
+    assert_eq!(titles, ["Stub Video stubOK00001", "Stub Video stubOK00002"]);
+    assert_eq!(mp3_files(&folder).len(), 2);
+
+    assert_eq!(result.failed_videos.len(), 1);
+    let failed = &result.failed_videos[0];
+    assert!(failed.url.contains("stub403-001"));
+    assert!(failed.error.contains("HTTP Error 403"), "{}", failed.error);
+    assert_eq!(failed.attempts, 2);
+}
+
+#[tokio::test]
+async fn playlist_selection_skips_the_other_items() {
+    let _serial = SERIAL.lock().await;
+    let app = test_app();
+    let folder = output_dir("playlist-selection");
+
+    let overrides = DownloadOverrides {
+        selection: PlaylistSelection {
+            playlist_items: Some("1,2-".to_string()),
+            playlist_end: Some(2),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let response = start_download(
+        "https://www.youtube.com/playlist?list=PLstubPlaylist".to_string(),
+        folder.to_string_lossy().to_string(),
+        192,
+        overrides,
+        true,
+        None,
+        app.handle(),
+    )
+    .await
+    .unwrap();
+    let DownloadResponse::Playlist(result) = response else {
+        panic!("expected a playlist download, got {:?}", response);
+    };
+
+    assert_eq!(result.total_videos, 2);
+    assert!(result.failed_videos.is_empty());
+    assert_eq!(mp3_files(&folder).len(), 2);
+}
+
+#[tokio::test]
+async fn format_failure_retries_with_the_next_format() {
+    let _serial = SERIAL.lock().await;
+    let app = test_app();
+    let folder = output_dir("retry");
+
+    let response = download(app.handle(), &video_url("stubFLAKY01"), &folder, None)
+        .await
+        .unwrap();
+    let DownloadResponse::Single(result) = response else {
+        panic!("expected a single download, got {:?}", response);
+    };
+
+    assert_eq!(result.format_id.as_deref(), Some("140"));
+    assert!(Path::new(&result.output_path).exists());
+}
+
+#[tokio::test]
+async fn persistent_403_fails_with_a_classified_error() {
+    let _serial = SERIAL.lock().await;
+    let app = test_app();
+    let folder = output_dir("failure");
+
+    let error = download(app.handle(), &video_url("stub403-001"), &folder, None)
+        .await
+        .unwrap_err();
+
+    assert!(error.contains("HTTP Error 403"), "{}", error);
+    assert_eq!(advisor::classify(&error), ErrorCode::Forbidden);
+    assert!(mp3_files(&folder).is_empty());
+}
+
+#[tokio::test]
+async fn cancel_stops_a_running_download() {
+    let _serial = SERIAL.lock().await;
+    let app = test_app();
+    let folder = output_dir("cancel");
+
+    let handle = app.handle();
+    let url = video_url("stubSLOW001");
+    let task_folder = folder.clone();
+    let running =
+        tokio::spawn(
+            async move { download(handle, &url, &task_folder, Some("cancel-test")).await },
+        );
+
+    // The download registers for cancellation as soon as it starts
+    let mut cancelled = 0;
+    for _ in 0..100 {
+        cancelled = download::cancel_downloads(Some("cancel-test"));
+        if cancelled > 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(cancelled, 1);
+
+    let result = tokio::time::timeout(Duration::from_secs(10), running)
+        .await
+        .expect("the cancelled download stops promptly")
+        .unwrap();
+    assert_eq!(result.unwrap_err(), CANCELLED_MESSAGE);
+    assert!(mp3_files(&folder).is_empty());
+}
+
+#[tokio::test]
+async fn backup_restores_the_history() {
+    let _serial = SERIAL.lock().await;
+    let app = test_app();
+    let folder = output_dir("backup");
+
+    let response = download(app.handle(), &video_url("stubOKBACK1"), &folder, None)
+        .await
+        .unwrap();
+    let DownloadResponse::Single(result) = response else {
+        panic!("expected a single download, got {:?}", response);
+    };
+
+    let archive = sandbox().join("backup.zip");
+    let manifest = backup::create(&archive).unwrap();
+    assert!(manifest.files.contains(&"history.json".to_string()));
+
+    fs::remove_file(commands::get_history_path().unwrap()).unwrap();
+    assert!(HistoryData::load()
+        .downloads
+        .iter()
+        .all(|d| d.output_path != result.output_path));
+
+    backup::restore(&archive).unwrap();
+    assert!(HistoryData::load()
+        .downloads
+        .iter()
+        .any(|d| d.output_path == result.output_path));
+}
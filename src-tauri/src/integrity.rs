@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tauri::AppHandle;
+
+use crate::deps::{self, BinaryOrigin};
+use crate::deps_fetch;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityStatus {
+    Ok,
+    Missing,
+    /// Smaller than when it was installed, e.g. an interrupted copy
+    Truncated,
+    /// Same size or larger but different contents than when it was installed
+    Modified,
+    NotRunnable,
+    /// Runs, but reports a different version than the one installed
+    VersionMismatch,
+    /// Runs, but there is no manifest entry or bundled copy to compare it with
+    Unverified,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinaryIntegrity {
+    pub binary: String,
+    pub path: Option<String>,
+    pub origin: Option<BinaryOrigin>,
+    pub status: IntegrityStatus,
+    pub expected_sha256: Option<String>,
+    pub actual_sha256: Option<String>,
+    pub expected_version: Option<String>,
+    pub actual_version: Option<String>,
+    /// Whether a repair was attempted and succeeded
+    pub repaired: Option<bool>,
+    pub repair_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub checked_at: String,
+    pub binaries: Vec<BinaryIntegrity>,
+}
+
+impl IntegrityStatus {
+    fn needs_repair(self) -> bool {
+        !matches!(self, IntegrityStatus::Ok | IntegrityStatus::Unverified)
+    }
+}
+
+/// Compare the managed copy of `binary` with its manifest entry, or with the bundled
+/// copy when it predates the manifest
+fn check_binary(app_handle: &AppHandle, binary: &str) -> BinaryIntegrity {
+    let mut result = BinaryIntegrity {
+        binary: binary.to_string(),
+        path: None,
+        origin: None,
+        status: IntegrityStatus::Missing,
+        expected_sha256: None,
+        actual_sha256: None,
+        expected_version: None,
+        actual_version: None,
+        repaired: None,
+        repair_error: None,
+    };
+
+    let Ok(path) = deps::managed_binary_path(app_handle, binary) else {
+        return result;
+    };
+    result.path = Some(path.to_string_lossy().to_string());
+    let Ok(metadata) = fs::metadata(&path) else {
+        return result;
+    };
+
+    let entry = deps::load_manifest(app_handle).remove(binary);
+    result.actual_sha256 = deps::hash_file(&path).ok();
+    result.actual_version = deps::binary_version(&path, binary);
+
+    let (expected_size, expected_sha256) = match &entry {
+        Some(entry) => {
+            result.origin = Some(entry.origin);
+            result.expected_version = entry.version.clone();
+            (Some(entry.size), Some(entry.sha256.clone()))
+        }
+        None => match deps::get_bundled_binary_path(app_handle, binary)
+            .ok()
+            .filter(|_| deps::has_bundled_binary(app_handle, binary))
+        {
+            Some(bundled) => {
+                result.origin = Some(BinaryOrigin::Bundle);
+                (
+                    fs::metadata(&bundled).ok().map(|m| m.len()),
+                    deps::hash_file(&bundled).ok(),
+                )
+            }
+            None => (None, None),
+        },
+    };
+    result.expected_sha256 = expected_sha256;
+
+    result.status = if expected_size.is_some_and(|size| metadata.len() < size) {
+        IntegrityStatus::Truncated
+    } else if result.expected_sha256.is_some() && result.expected_sha256 != result.actual_sha256 {
+        IntegrityStatus::Modified
+    } else if result.actual_version.is_none() {
+        IntegrityStatus::NotRunnable
+    } else if result.expected_version.is_some() && result.expected_version != result.actual_version
+    {
+        IntegrityStatus::VersionMismatch
+    } else if result.expected_sha256.is_none() {
+        IntegrityStatus::Unverified
+    } else {
+        IntegrityStatus::Ok
+    };
+    result
+}
+
+/// Re-extract the bundled copy if there is one, download a fresh copy otherwise
+async fn repair_binary(
+    app_handle: &AppHandle,
+    binary: &str,
+    custom_mirror: Option<&str>,
+) -> Result<(), String> {
+    let handle = app_handle.clone();
+    let name = binary.to_string();
+    let bundled = tauri::async_runtime::spawn_blocking(move || {
+        deps::has_bundled_binary(&handle, &name)
+            .then(|| deps::reextract_binary(&handle, &name).map_err(|e| format!("{:#}", e)))
+    })
+    .await
+    .map_err(|e| format!("Repair task failed: {}", e))?;
+
+    match bundled {
+        Some(result) => result.map(|_| ()),
+        None => deps_fetch::fetch_binary(app_handle, binary, custom_mirror, |_, _, _| {})
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("{:#}", e)),
+    }
+}
+
+/// Check the managed yt-dlp and ffmpeg for tampering, truncation and version drift,
+/// optionally repairing whatever fails the check
+pub async fn verify(
+    app_handle: &AppHandle,
+    repair: bool,
+    custom_mirror: Option<&str>,
+) -> IntegrityReport {
+    let mut binaries = Vec::new();
+    for binary in ["yt-dlp", "ffmpeg"] {
+        let handle = app_handle.clone();
+        let mut result =
+            match tauri::async_runtime::spawn_blocking(move || check_binary(&handle, binary)).await
+            {
+                Ok(result) => result,
+                Err(e) => {
+                    eprintln!("[deps] Integrity check of {} failed: {}", binary, e);
+                    continue;
+                }
+            };
+
+        if repair && result.status.needs_repair() {
+            eprintln!(
+                "[deps] {} failed the integrity check ({:?}), repairing",
+                binary, result.status
+            );
+            let repaired = repair_binary(app_handle, binary, custom_mirror).await;
+            result.repaired = Some(repaired.is_ok());
+            result.repair_error = repaired.err();
+        }
+        binaries.push(result);
+    }
+
+    IntegrityReport {
+        checked_at: chrono::Utc::now().to_rfc3339(),
+        binaries,
+    }
+}
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Extra room kept free on the destination when relocating, in bytes
+const FREE_SPACE_MARGIN: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelocationFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelocationReport {
+    pub files_moved: usize,
+    pub bytes_moved: u64,
+    pub history_entries_updated: usize,
+    pub failed: Vec<RelocationFailure>,
+}
+
+/// All files below `dir`, recursively
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Remove the now empty directories below `dir`, keeping `dir` itself
+fn remove_empty_dirs(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        if path.is_dir() {
+            remove_empty_dirs(&path);
+            // Fails on purpose for directories that still have files
+            fs::remove_dir(&path).ok();
+        }
+    }
+}
+
+/// Closest existing ancestor, so free space can be checked before the folder exists
+fn existing_ancestor(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|p| p.exists())
+}
+
+#[cfg(unix)]
+fn same_volume(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), existing_ancestor(b).map(fs::metadata)) {
+        (Ok(a), Some(Ok(b))) => a.dev() == b.dev(),
+        _ => false,
+    }
+}
+
+#[cfg(windows)]
+fn same_volume(a: &Path, b: &Path) -> bool {
+    let prefix = |p: &Path| {
+        p.components()
+            .next()
+            .map(|c| c.as_os_str().to_ascii_lowercase())
+    };
+    prefix(a).is_some() && prefix(a) == prefix(b)
+}
+
+#[cfg(unix)]
+fn free_space(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let c_path = std::ffi::CString::new(existing_ancestor(path)?.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+fn free_space(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetDiskFreeSpaceExW(
+            directory: *const u16,
+            free_bytes_available: *mut u64,
+            total_bytes: *mut u64,
+            total_free_bytes: *mut u64,
+        ) -> i32;
+    }
+
+    let wide: Vec<u16> = existing_ancestor(path)?
+        .as_os_str()
+        .encode_wide()
+        .chain(Some(0))
+        .collect();
+    let mut available = 0u64;
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    (ok != 0).then_some(available)
+}
+
+/// Move a file, falling back to copy + delete across volumes
+pub(crate) fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+/// Move every file below `old_root` to the same relative location below `new_root`.
+/// Returns the old and new path of each moved file along with the report.
+pub fn relocate(
+    old_root: &Path,
+    new_root: &Path,
+) -> Result<(Vec<(PathBuf, PathBuf)>, RelocationReport), String> {
+    if !old_root.is_dir() {
+        return Err(format!("{} is not a folder", old_root.display()));
+    }
+    if new_root.starts_with(old_root) || old_root.starts_with(new_root) {
+        return Err(
+            "The new location cannot be inside the old one, or the other way round".to_string(),
+        );
+    }
+    if new_root.exists() && !new_root.is_dir() {
+        return Err(format!("{} is not a folder", new_root.display()));
+    }
+
+    let mut files = Vec::new();
+    collect_files(old_root, &mut files)
+        .map_err(|e| format!("Failed to read {}: {}", old_root.display(), e))?;
+    let total: u64 = files
+        .iter()
+        .filter_map(|f| fs::metadata(f).ok())
+        .map(|m| m.len())
+        .sum();
+
+    if !same_volume(old_root, new_root) {
+        if let Some(available) = free_space(new_root) {
+            if available < total + FREE_SPACE_MARGIN {
+                return Err(format!(
+                    "Not enough free space at {}: {} MB needed, {} MB available",
+                    new_root.display(),
+                    (total + FREE_SPACE_MARGIN) / (1024 * 1024),
+                    available / (1024 * 1024)
+                ));
+            }
+        }
+    }
+
+    let mut moved = Vec::new();
+    let mut report = RelocationReport {
+        files_moved: 0,
+        bytes_moved: 0,
+        history_entries_updated: 0,
+        failed: Vec::new(),
+    };
+
+    for file in files {
+        let Ok(relative) = file.strip_prefix(old_root) else {
+            continue;
+        };
+        let dest = new_root.join(relative);
+        if dest.exists() {
+            report.failed.push(RelocationFailure {
+                path: file.to_string_lossy().to_string(),
+                error: format!("{} already exists", dest.display()),
+            });
+            continue;
+        }
+
+        let size = fs::metadata(&file).map(|m| m.len()).unwrap_or(0);
+        match move_file(&file, &dest) {
+            Ok(()) => {
+                report.files_moved += 1;
+                report.bytes_moved += size;
+                moved.push((file, dest));
+            }
+            Err(e) => report.failed.push(RelocationFailure {
+                path: file.to_string_lossy().to_string(),
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    remove_empty_dirs(old_root);
+    Ok((moved, report))
+}
@@ -0,0 +1,75 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One track of a download session as listed in its liner notes
+#[derive(Debug, Clone)]
+pub struct LinerTrack {
+    pub title: String,
+    /// URL of the video, or of the playlist it came from
+    pub source: String,
+    pub duration: Option<f64>,
+}
+
+/// `3:07` or `1:02:45`
+fn track_length(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// Keep a title from breaking out of its table cell
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+/// Markdown liner notes: the note's first line as the heading, the rest of it below,
+/// then a table of the tracks
+pub fn render(note: &str, tracks: &[LinerTrack], downloaded_at: &str) -> String {
+    let note = note.trim();
+    let (heading, body) = note.split_once('\n').unwrap_or((note, ""));
+    let mut out = format!("# {}\n\n", heading.trim());
+    if !body.trim().is_empty() {
+        out.push_str(body.trim());
+        out.push_str("\n\n");
+    }
+
+    let total: f64 = tracks.iter().filter_map(|t| t.duration).sum();
+    out.push_str(&format!(
+        "Downloaded {} · {} track{} · {}\n\n",
+        downloaded_at,
+        tracks.len(),
+        if tracks.len() == 1 { "" } else { "s" },
+        track_length(total)
+    ));
+    out.push_str("| # | Title | Length | Source |\n|---|---|---|---|\n");
+    for (index, track) in tracks.iter().enumerate() {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            index + 1,
+            table_cell(&track.title),
+            track.duration.map(track_length).unwrap_or_default(),
+            track.source
+        ));
+    }
+    out
+}
+
+/// Write the liner notes into `folder` as `liner-notes.md`, or `liner-notes (2).md`
+/// and so on when an earlier session left its notes there
+pub fn write(folder: &Path, note: &str, tracks: &[LinerTrack]) -> Result<PathBuf, String> {
+    if note.trim().is_empty() {
+        return Err("The session note is empty".to_string());
+    }
+    let path = std::iter::once(folder.join("liner-notes.md"))
+        .chain((2..).map(|n| folder.join(format!("liner-notes ({}).md", n))))
+        .find(|path| !path.exists())
+        .expect("unbounded range always finds a free name");
+    let downloaded_at = chrono::Utc::now().format("%Y-%m-%d").to_string();
+    fs::write(&path, render(note, tracks, &downloaded_at))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
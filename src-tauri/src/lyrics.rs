@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tokio::process::Command;
+
+/// Subtitle extensions yt-dlp may leave next to a track, converted or not
+const SUBTITLE_EXTENSIONS: &[&str] = &["lrc", "vtt", "srt", "ass", "ttml", "srv3", "json3"];
+
+/// Subtitles or automatic captions saved next to each track, by default converted to
+/// `.lrc` so music players show them as synced lyrics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LyricsSettings {
+    pub enabled: bool,
+    /// Languages passed to `--sub-langs`, such as `en.*,ja` or `all`
+    pub languages: String,
+    /// Fall back to YouTube's automatic captions when there are no uploaded subtitles
+    pub auto_captions: bool,
+    /// Convert to `.lrc`, otherwise the subtitles are kept as yt-dlp downloads them
+    pub convert_to_lrc: bool,
+}
+
+impl Default for LyricsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            languages: "en.*".to_string(),
+            auto_captions: true,
+            convert_to_lrc: true,
+        }
+    }
+}
+
+impl LyricsSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        let languages = self.languages.trim();
+        if languages.is_empty() {
+            return Err("Choose at least one subtitle language".to_string());
+        }
+        if let Some(c) = languages
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || "-_.*,^$|()".contains(*c)))
+        {
+            return Err(format!(
+                "Invalid character '{}' in the subtitle languages, use codes like en.*,ja",
+                c
+            ));
+        }
+        Ok(())
+    }
+
+    /// Make yt-dlp write the subtitles next to the track, named `<track>.<lang>.lrc`
+    pub(crate) fn add_args(&self, cmd: &mut Command) {
+        cmd.arg("--write-subs");
+        if self.auto_captions {
+            cmd.arg("--write-auto-subs");
+        }
+        cmd.arg("--sub-langs").arg(self.languages.trim());
+        if self.convert_to_lrc {
+            cmd.arg("--convert-subs").arg("lrc");
+        }
+    }
+}
+
+/// Subtitle files saved next to the track at `output_path`, sorted by name
+pub(crate) fn files_for(output_path: &Path) -> Vec<String> {
+    let (Some(folder), Some(stem)) = (
+        output_path.parent(),
+        output_path.file_stem().and_then(|s| s.to_str()),
+    ) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}.", stem);
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    let mut files: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| {
+            let name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default();
+            // `<track>.<lang>.<ext>`, the language keeps the track itself out
+            name.starts_with(&prefix)
+                && name[prefix.len()..].contains('.')
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| SUBTITLE_EXTENSIONS.contains(&ext))
+        })
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    files.sort();
+    files
+}
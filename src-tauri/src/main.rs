@@ -1,24 +1,208 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod advisor;
+mod app_lock;
+mod audio_format;
+mod audio_preset;
+mod audit;
+mod backup;
+mod benchmark;
+mod channel_rules;
+mod clipboard;
 mod commands;
+mod compare;
+mod connectivity;
+mod conversion;
+mod cookies;
+mod crash;
+mod decisions;
 mod deps;
+mod deps_fetch;
+mod digest;
 mod download;
+mod download_log;
+mod events;
+mod folder_art;
+mod forecast;
+mod incomplete;
+mod install_steps;
+#[cfg(all(test, unix))]
+mod integration_tests;
+mod integrity;
+mod library;
+mod liner_notes;
+mod lyrics;
+mod maintenance;
+mod manifest;
+mod media_cache;
+mod media_server;
+mod mqtt;
+mod network_sim;
+mod preflight;
+mod proxy;
+mod queue;
+mod replacement;
+mod sections;
+mod silence;
+mod source;
+mod speed_history;
+mod storage;
+mod streaming;
+mod system_load;
+mod tags;
+mod tracklist;
+mod transliterate;
+mod trim;
+#[cfg(test)]
+mod ts_types;
+mod version_check;
+mod video;
+mod volume;
 
 use commands::*;
 
 fn main() {
+    crash::install_panic_hook();
+
     tauri::Builder::default()
+        .manage(queue::DownloadQueue::load())
+        .manage(clipboard::ClipboardWatcher::default())
+        .setup(|app| {
+            tauri::async_runtime::spawn_blocking(AppPreferences::bootstrap_environment);
+            AppPreferences::apply_runtime_settings();
+            AppPreferences::start_dependency_preflight(app.handle());
+            maintenance::start_scheduler(app.handle());
+            version_check::start_service(app.handle());
+            queue::start_worker(app.handle());
+            AppPreferences::start_clipboard_monitor(app.handle());
+            incomplete::start_scan(app.handle(), AppPreferences::load().output_folder);
+            Ok(())
+        })
+        .on_window_event(|event| decisions::handle_window_event(&event))
         .invoke_handler(tauri::generate_handler![
             download_from_youtube,
             get_download_history,
             clear_history,
+            verify_history_files,
+            export_history,
             check_deps,
+            check_ytdlp_environment,
             clear_extracted_binaries,
+            reextract_binaries,
             save_output_folder,
             get_output_folder,
             save_preferences,
-            get_preferences
+            get_preferences,
+            save_extractor_args,
+            apply_recommended_workaround,
+            save_http_client_options,
+            get_impersonate_targets,
+            save_politeness,
+            get_audit_trail,
+            get_last_crash_report,
+            clear_crash_reports,
+            save_path_augmentation,
+            save_playlist_confirm_threshold,
+            set_session_defaults,
+            get_session_defaults,
+            run_maintenance_now,
+            get_maintenance_status,
+            save_media_cache_settings,
+            clear_media_cache,
+            save_sectioned_download,
+            save_transliteration,
+            save_ytdlp_verbosity,
+            get_download_log,
+            get_speed_history,
+            relocate_library,
+            parse_pasted_urls,
+            get_app_lock_status,
+            set_app_lock_pin,
+            remove_app_lock_pin,
+            unlock_app,
+            lock_app,
+            save_dependency_preflight,
+            run_dependency_preflight,
+            get_preflight_status,
+            save_dependency_mirror,
+            install_dependency_from_file,
+            get_installation_instructions,
+            designate_binary,
+            verify_dependencies,
+            clear_ytdlp_cache,
+            cancel_download,
+            get_available_updates,
+            get_recommended_fixes,
+            apply_recommended_fix,
+            save_auto_apply_fixes,
+            enqueue_download,
+            get_queue,
+            get_queue_forecast,
+            export_queue,
+            import_queue,
+            remove_from_queue,
+            save_max_concurrent_downloads,
+            save_retry_policy,
+            run_benchmark,
+            get_benchmark_result,
+            save_adaptive_concurrency,
+            save_max_download_rate,
+            save_proxy_settings,
+            save_stream_conversion,
+            import_cookies,
+            remove_cookies,
+            get_cookies_status,
+            save_use_cookies,
+            save_audio_format,
+            download_video,
+            save_folder_artwork,
+            save_embed_metadata,
+            save_media_server,
+            refresh_media_library,
+            save_embed_thumbnail,
+            save_mqtt_settings,
+            test_mqtt_settings,
+            save_sponsorblock,
+            get_waveform,
+            trim_file,
+            parse_tracklist,
+            download_tracklist,
+            save_silence_trim,
+            save_split_chapters,
+            save_audio_preset,
+            estimate_playlist,
+            get_playlist_info,
+            pause_all,
+            resume_all,
+            cancel_all,
+            clear_completed,
+            save_legacy_events,
+            get_video_info,
+            save_allow_any_site,
+            download_multiple,
+            save_network_simulation,
+            save_clipboard_monitor,
+            save_weekly_digest,
+            save_reduced_updates,
+            get_channel_rules,
+            set_channel_rule,
+            save_storage_limits,
+            get_storage_usage,
+            create_backup,
+            restore_backup,
+            save_folder_organization,
+            save_lyrics,
+            find_replacement,
+            save_loudness_normalization,
+            export_library_manifest,
+            verify_manifest,
+            submit_decision,
+            start_quality_comparison,
+            keep_compared_quality,
+            discard_quality_comparison,
+            get_incomplete_downloads,
+            resume_incomplete_downloads
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::api::path::config_dir;
+use tauri::AppHandle;
+
+use crate::commands::{AppPreferences, HistoryData};
+use crate::deps;
+use crate::digest;
+use crate::download;
+
+/// How often the scheduler checks whether maintenance is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Minimum time between two scheduled maintenance runs
+const MIN_RUN_INTERVAL_HOURS: i64 = 24;
+
+/// Only one maintenance run at a time, scheduled or manual
+static MAINTENANCE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceTaskResult {
+    pub task: String,
+    pub success: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    pub started_at: String,
+    pub finished_at: String,
+    /// Whether the run was started by the idle scheduler rather than the user
+    pub scheduled: bool,
+    pub tasks: Vec<MaintenanceTaskResult>,
+}
+
+fn get_report_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("youtube-downloader").join("maintenance.json"))
+}
+
+/// Load the report of the last maintenance run, if any
+pub fn load_last_report() -> Option<MaintenanceReport> {
+    let content = fs::read_to_string(get_report_path()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn save_report(report: &MaintenanceReport) -> Result<(), String> {
+    let path = get_report_path().ok_or("Failed to get config directory")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let content = serde_json::to_string_pretty(report)
+        .map_err(|e| format!("Failed to serialize maintenance report: {}", e))?;
+    fs::write(&path, content).map_err(|e| e.to_string())
+}
+
+fn task_result(task: &str, outcome: Result<String, String>) -> MaintenanceTaskResult {
+    let (success, detail) = match outcome {
+        Ok(detail) => (true, detail),
+        Err(e) => (false, e),
+    };
+    MaintenanceTaskResult {
+        task: task.to_string(),
+        success,
+        detail,
+    }
+}
+
+fn prune_caches(app_handle: &AppHandle) -> Result<String, String> {
+    deps::prune_caches(app_handle)
+        .map(|removed| format!("Removed {} stale cache entries", removed))
+        .map_err(|e| e.to_string())
+}
+
+fn enforce_storage_limits() -> Result<String, String> {
+    let cleanup = AppPreferences::enforce_storage_limits();
+    Ok(format!(
+        "Removed {} files ({} bytes) over the storage limits",
+        cleanup.removed_files, cleanup.freed_bytes
+    ))
+}
+
+/// Check that the files recorded in the download history still exist, repairing
+/// entries recorded under a different name than yt-dlp used
+fn verify_history() -> Result<String, String> {
+    let mut history = HistoryData::load();
+    let repaired = history.repair_missing_paths();
+    if repaired > 0 {
+        history.save()?;
+    }
+    let missing = history
+        .downloads
+        .iter()
+        .filter(|d| !Path::new(&d.output_path).exists())
+        .count();
+    Ok(format!(
+        "Repaired {} history entries, {} of {} point to missing files",
+        repaired,
+        missing,
+        history.downloads.len()
+    ))
+}
+
+/// Run every maintenance task and store the report.
+/// Fails without doing anything while downloads are active or another run is in progress.
+pub fn run(app_handle: &AppHandle, scheduled: bool) -> Result<MaintenanceReport, String> {
+    let _lock = MAINTENANCE_LOCK
+        .try_lock()
+        .map_err(|_| "Maintenance is already running".to_string())?;
+
+    if download::active_downloads() > 0 {
+        return Err("Maintenance cannot run while downloads are active".to_string());
+    }
+
+    let started_at = chrono::Utc::now().to_rfc3339();
+    let tasks = vec![
+        task_result("prune_caches", prune_caches(app_handle)),
+        task_result("enforce_storage_limits", enforce_storage_limits()),
+        task_result("verify_history", verify_history()),
+    ];
+
+    let report = MaintenanceReport {
+        started_at,
+        finished_at: chrono::Utc::now().to_rfc3339(),
+        scheduled,
+        tasks,
+    };
+    if let Err(e) = save_report(&report) {
+        eprintln!("[maintenance] Failed to save report: {}", e);
+    }
+    Ok(report)
+}
+
+fn is_due() -> bool {
+    let Some(report) = load_last_report() else {
+        return true;
+    };
+    chrono::DateTime::parse_from_rfc3339(&report.finished_at)
+        .map(|finished| {
+            chrono::Utc::now().signed_duration_since(finished)
+                >= chrono::Duration::hours(MIN_RUN_INTERVAL_HOURS)
+        })
+        .unwrap_or(true)
+}
+
+/// Periodically run maintenance in the background whenever it is due and no
+/// downloads are active, and send the weekly digest when that is due
+pub fn start_scheduler(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            digest::send_if_due(&app_handle);
+
+            if download::active_downloads() > 0 || !is_due() {
+                continue;
+            }
+
+            let handle = app_handle.clone();
+            match tauri::async_runtime::spawn_blocking(move || run(&handle, true)).await {
+                Ok(Ok(_)) => eprintln!("[maintenance] Scheduled maintenance complete"),
+                Ok(Err(e)) => eprintln!("[maintenance] Skipped: {}", e),
+                Err(e) => eprintln!("[maintenance] Task panicked: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,437 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+use crate::audio_format::AudioFormat;
+use crate::deps;
+use crate::download::is_partial_file;
+
+/// Version of the manifest layout, bumped when a field changes meaning
+pub const MANIFEST_FORMAT_VERSION: u32 = 1;
+
+/// Tags written as their own CSV columns, the ones downloads are tagged with
+const TAG_COLUMNS: [&str; 7] = [
+    "title",
+    "artist",
+    "album",
+    "album_artist",
+    "genre",
+    "date",
+    "comment",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ManifestFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+impl ManifestFormat {
+    /// The format a manifest file is in, going by its extension
+    pub(crate) fn of(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => ManifestFormat::Csv,
+            _ => ManifestFormat::Json,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Relative to the library folder, with `/` separators on every platform
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+    /// Tags read from audio files, empty for everything else
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+    /// URL the file was downloaded from, when the history still has it
+    pub source_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryManifest {
+    pub format_version: u32,
+    pub generated_at: String,
+    pub entries: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestExport {
+    pub manifest_path: String,
+    pub files: usize,
+    pub total_bytes: u64,
+    /// Files that couldn't be read and are missing from the manifest
+    pub failed: Vec<ManifestFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestMismatch {
+    pub path: String,
+    pub expected_sha256: String,
+    pub actual_sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestVerification {
+    pub verified: usize,
+    /// Listed in the manifest but gone from the library
+    pub missing: Vec<String>,
+    /// Contents differ from the manifest: edited, re-downloaded or rotten
+    pub changed: Vec<ManifestMismatch>,
+    /// In the library but not in the manifest
+    pub unlisted: Vec<String>,
+    /// Listed files that couldn't be read
+    pub failed: Vec<ManifestFailure>,
+}
+
+/// Library files below `dir`, recursively, leaving out downloads still in progress
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if !is_partial_file(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// `path` relative to `root` in the portable form manifests store
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let parts: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+    Some(parts.join("/"))
+}
+
+fn is_audio_file(path: &Path) -> bool {
+    let Some(ext) = path.extension().and_then(|e| e.to_str()) else {
+        return false;
+    };
+    [
+        AudioFormat::Mp3,
+        AudioFormat::Opus,
+        AudioFormat::M4a,
+        AudioFormat::Flac,
+        AudioFormat::Wav,
+        AudioFormat::Ogg,
+    ]
+    .iter()
+    .any(|format| ext.eq_ignore_ascii_case(format.extension()))
+}
+
+/// Tags of an audio file, read with ffmpeg's `ffmetadata` output
+async fn read_tags(ffmpeg_cmd: &str, path: &Path) -> BTreeMap<String, String> {
+    let output = Command::new(ffmpeg_cmd)
+        .arg("-v")
+        .arg("error")
+        .arg("-i")
+        .arg(path)
+        .arg("-f")
+        .arg("ffmetadata")
+        .arg("-")
+        .output()
+        .await;
+    let Ok(output) = output else {
+        return BTreeMap::new();
+    };
+    if !output.status.success() {
+        return BTreeMap::new();
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.starts_with(';') && !line.starts_with('['))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.to_lowercase(), value.to_string()))
+        .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+        .collect()
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Rows of a CSV file, honouring quoted fields with separators and line breaks in them
+fn csv_rows(content: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+fn to_csv(manifest: &LibraryManifest) -> String {
+    let mut header = vec!["path", "size", "sha256", "source_url"];
+    header.extend(TAG_COLUMNS);
+    let mut csv = header.join(",");
+    csv.push('\n');
+    for entry in &manifest.entries {
+        let mut fields = vec![
+            csv_field(&entry.path),
+            entry.size.to_string(),
+            entry.sha256.clone(),
+            csv_field(entry.source_url.as_deref().unwrap_or_default()),
+        ];
+        fields.extend(
+            TAG_COLUMNS
+                .iter()
+                .map(|tag| csv_field(entry.tags.get(*tag).map_or("", String::as_str))),
+        );
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn from_csv(content: &str) -> Result<LibraryManifest, String> {
+    let mut rows = csv_rows(content).into_iter();
+    let header = rows.next().ok_or("The manifest is empty")?;
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| h.trim() == name)
+            .ok_or_else(|| format!("The manifest has no {} column", name))
+    };
+    let (path, size, sha256) = (column("path")?, column("size")?, column("sha256")?);
+    let source_url = column("source_url").ok();
+    let tags: Vec<(usize, &str)> = TAG_COLUMNS
+        .iter()
+        .filter_map(|tag| column(tag).ok().map(|index| (index, *tag)))
+        .collect();
+
+    let mut entries = Vec::new();
+    for (number, row) in rows.enumerate() {
+        if row.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        let field = |index: usize| row.get(index).map_or("", String::as_str);
+        entries.push(ManifestEntry {
+            path: field(path).to_string(),
+            size: field(size)
+                .parse()
+                .map_err(|_| format!("Row {} has an invalid size", number + 2))?,
+            sha256: field(sha256).to_lowercase(),
+            tags: tags
+                .iter()
+                .filter(|(index, _)| !field(*index).is_empty())
+                .map(|(index, tag)| (tag.to_string(), field(*index).to_string()))
+                .collect(),
+            source_url: source_url
+                .map(field)
+                .filter(|url| !url.is_empty())
+                .map(str::to_string),
+        });
+    }
+    Ok(LibraryManifest {
+        format_version: MANIFEST_FORMAT_VERSION,
+        generated_at: String::new(),
+        entries,
+    })
+}
+
+/// A file of the library with its size and SHA-256
+struct HashedFile {
+    path: PathBuf,
+    size: u64,
+    sha256: String,
+}
+
+/// Hash every file below `root`, skipping `exclude` (the manifest being written)
+fn hash_library(
+    root: &Path,
+    exclude: &Path,
+) -> Result<(Vec<HashedFile>, Vec<ManifestFailure>), String> {
+    let mut files = Vec::new();
+    collect_files(root, &mut files)
+        .map_err(|e| format!("Failed to read {}: {}", root.display(), e))?;
+    files.sort();
+
+    let mut hashed = Vec::new();
+    let mut failed = Vec::new();
+    for file in files.into_iter().filter(|f| f != exclude) {
+        let size = match fs::metadata(&file) {
+            Ok(metadata) => metadata.len(),
+            Err(e) => {
+                failed.push(ManifestFailure {
+                    path: file.to_string_lossy().to_string(),
+                    error: e.to_string(),
+                });
+                continue;
+            }
+        };
+        match deps::hash_file(&file) {
+            Ok(sha256) => hashed.push(HashedFile {
+                path: file,
+                size,
+                sha256,
+            }),
+            Err(e) => failed.push(ManifestFailure {
+                path: file.to_string_lossy().to_string(),
+                error: e.to_string(),
+            }),
+        }
+    }
+    Ok((hashed, failed))
+}
+
+/// Write a manifest of every file below `root` to `output`, with its size, SHA-256,
+/// tags and the URL from `sources` (output path to URL, from the download history)
+pub async fn export(
+    ffmpeg_cmd: &str,
+    root: &Path,
+    output: &Path,
+    format: ManifestFormat,
+    sources: HashMap<PathBuf, String>,
+) -> Result<ManifestExport, String> {
+    if !root.is_dir() {
+        return Err(format!("{} is not a folder", root.display()));
+    }
+    let (scan_root, exclude) = (root.to_path_buf(), output.to_path_buf());
+    let (hashed, failed) =
+        tauri::async_runtime::spawn_blocking(move || hash_library(&scan_root, &exclude))
+            .await
+            .map_err(|e| format!("Hashing task failed: {}", e))??;
+
+    let mut entries = Vec::new();
+    for file in hashed {
+        let Some(path) = relative_path(root, &file.path) else {
+            continue;
+        };
+        let tags = if is_audio_file(&file.path) {
+            read_tags(ffmpeg_cmd, &file.path).await
+        } else {
+            BTreeMap::new()
+        };
+        entries.push(ManifestEntry {
+            path,
+            size: file.size,
+            sha256: file.sha256,
+            tags,
+            source_url: sources.get(&file.path).cloned(),
+        });
+    }
+    let manifest = LibraryManifest {
+        format_version: MANIFEST_FORMAT_VERSION,
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        entries,
+    };
+
+    let content = match format {
+        ManifestFormat::Json => serde_json::to_string_pretty(&manifest)
+            .map_err(|e| format!("Failed to serialize manifest: {}", e))?,
+        ManifestFormat::Csv => to_csv(&manifest),
+    };
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(output, content)
+        .map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+
+    Ok(ManifestExport {
+        manifest_path: output.to_string_lossy().to_string(),
+        files: manifest.entries.len(),
+        total_bytes: manifest.entries.iter().map(|e| e.size).sum(),
+        failed,
+    })
+}
+
+/// Re-hash the library at `root` and compare it against a manifest written by `export`
+pub fn verify(manifest_path: &Path, root: &Path) -> Result<ManifestVerification, String> {
+    let content = fs::read_to_string(manifest_path)
+        .map_err(|e| format!("Failed to read {}: {}", manifest_path.display(), e))?;
+    let manifest = match ManifestFormat::of(manifest_path) {
+        ManifestFormat::Json => serde_json::from_str::<LibraryManifest>(&content)
+            .map_err(|e| format!("Not a library manifest: {}", e))?,
+        ManifestFormat::Csv => from_csv(&content)?,
+    };
+    if manifest.format_version > MANIFEST_FORMAT_VERSION {
+        return Err("The manifest was made by a newer version of the app".to_string());
+    }
+    if !root.is_dir() {
+        return Err(format!("{} is not a folder", root.display()));
+    }
+
+    let mut report = ManifestVerification {
+        verified: 0,
+        missing: Vec::new(),
+        changed: Vec::new(),
+        unlisted: Vec::new(),
+        failed: Vec::new(),
+    };
+    let mut listed = HashSet::new();
+    for entry in &manifest.entries {
+        listed.insert(entry.path.as_str());
+        let file = entry
+            .path
+            .split('/')
+            .fold(root.to_path_buf(), |path, part| path.join(part));
+        if !file.is_file() {
+            report.missing.push(entry.path.clone());
+            continue;
+        }
+        match deps::hash_file(&file) {
+            Ok(sha256) if sha256.eq_ignore_ascii_case(&entry.sha256) => report.verified += 1,
+            Ok(sha256) => report.changed.push(ManifestMismatch {
+                path: entry.path.clone(),
+                expected_sha256: entry.sha256.clone(),
+                actual_sha256: sha256,
+            }),
+            Err(e) => report.failed.push(ManifestFailure {
+                path: entry.path.clone(),
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    let mut files = Vec::new();
+    collect_files(root, &mut files)
+        .map_err(|e| format!("Failed to read {}: {}", root.display(), e))?;
+    files.sort();
+    report.unlisted = files
+        .iter()
+        .filter(|file| file.as_path() != manifest_path)
+        .filter_map(|file| relative_path(root, file))
+        .filter(|path| !listed.contains(path.as_str()))
+        .collect();
+    Ok(report)
+}
@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::api::path::cache_dir;
+use tokio::process::Command;
+
+use crate::download::DownloadOptions;
+use crate::tags;
+
+/// Extensions of files yt-dlp leaves behind while a download is still in progress
+const PARTIAL_EXTENSIONS: [&str; 3] = ["part", "ytdl", "tmp"];
+
+/// Optional cache of the raw audio downloaded from YouTube, keyed by video ID, so a
+/// video can be re-encoded at another bitrate without downloading it again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MediaCacheSettings {
+    pub enabled: bool,
+    /// Least recently used files are evicted once the cache grows past this size
+    pub max_size_mb: u64,
+}
+
+impl Default for MediaCacheSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_size_mb: 2048,
+        }
+    }
+}
+
+impl MediaCacheSettings {
+    fn max_size_bytes(&self) -> u64 {
+        self.max_size_mb * 1024 * 1024
+    }
+}
+
+pub(crate) fn get_cache_dir() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("youtube-downloader").join("media"))
+}
+
+/// yt-dlp output template that writes the raw download into the cache
+pub fn output_template() -> Result<String, String> {
+    let dir = get_cache_dir().ok_or("Failed to get cache directory")?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create media cache: {}", e))?;
+    Ok(dir.join("%(id)s.%(ext)s").to_string_lossy().to_string())
+}
+
+/// Completed cache files with their size and last use time
+fn cached_files() -> Vec<(PathBuf, u64, SystemTime)> {
+    let Some(entries) = get_cache_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let path = e.path();
+            let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+            if PARTIAL_EXTENSIONS.contains(&ext) {
+                return None;
+            }
+            let metadata = e.metadata().ok().filter(|m| m.is_file())?;
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((path, metadata.len(), modified))
+        })
+        .collect()
+}
+
+/// Find the cached source for a video, marking it as recently used
+pub fn lookup(video_id: &str) -> Option<PathBuf> {
+    let (path, _, _) = cached_files()
+        .into_iter()
+        .find(|(path, _, _)| path.file_stem().and_then(|s| s.to_str()) == Some(video_id))?;
+
+    // The modification time doubles as the last-used time for eviction
+    if let Ok(file) = fs::File::options().write(true).open(&path) {
+        file.set_modified(SystemTime::now()).ok();
+    }
+    Some(path)
+}
+
+/// Remove least recently used files until the cache fits within the configured size
+pub fn evict(settings: &MediaCacheSettings) {
+    let mut files = cached_files();
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    files.sort_by_key(|(_, _, modified)| *modified);
+
+    for (path, size, _) in files {
+        if total <= settings.max_size_bytes() {
+            break;
+        }
+        match fs::remove_file(&path) {
+            Ok(_) => total -= size,
+            Err(e) => eprintln!("[cache] Failed to evict {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Delete everything in the media cache, returning the number of bytes freed
+pub fn clear() -> Result<u64, String> {
+    let Some(dir) = get_cache_dir() else {
+        return Ok(0);
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(0);
+    };
+
+    let mut freed = 0;
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        fs::remove_file(&path)
+            .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        freed += size;
+    }
+    Ok(freed)
+}
+
+/// Encode a cached source file to the options' format at the given bitrate. A source
+/// already in that format is copied without re-encoding instead, unless the audio is
+/// filtered, which is what the returned flag says.
+pub async fn transcode(
+    ffmpeg_cmd: &str,
+    input: &Path,
+    output: &Path,
+    bitrate: u32,
+    options: &DownloadOptions,
+    tags: &[(&str, String)],
+) -> Result<bool, String> {
+    let copy = options.audio_filter().is_none()
+        && input
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(options.audio_format.extension()));
+    let mut cmd = Command::new(ffmpeg_cmd);
+    cmd.arg("-y")
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-i")
+        .arg(input)
+        .arg("-vn")
+        .args(options.encoder_args(bitrate, copy))
+        .args(tags::ffmpeg_args(tags));
+    let result = cmd
+        .arg(output)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+    if !result.status.success() {
+        fs::remove_file(output).ok();
+        return Err(format!(
+            "Failed to convert cached audio: {}",
+            String::from_utf8_lossy(&result.stderr)
+        ));
+    }
+    Ok(copy)
+}
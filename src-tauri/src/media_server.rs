@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaServerKind {
+    #[default]
+    Jellyfin,
+    Plex,
+}
+
+/// Media server whose library is rescanned after a batch of downloads.
+/// The token is stored with the other preferences, in plain text.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MediaServerSettings {
+    pub enabled: bool,
+    pub kind: MediaServerKind,
+    /// Server address such as `http://192.168.1.10:8096`
+    pub url: String,
+    /// Jellyfin API key or Plex token
+    pub token: String,
+    /// Plex library section to refresh, every section when unset. Jellyfin always
+    /// scans all of its libraries.
+    pub library_id: Option<String>,
+}
+
+impl MediaServerSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let url = url::Url::parse(self.url.trim())
+            .map_err(|e| format!("Invalid media server address: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err("The media server address must start with http:// or https://".to_string());
+        }
+        if self.token.trim().is_empty() {
+            return Err("The media server token is empty".to_string());
+        }
+        if let Some(ref id) = self.library_id {
+            if !id.trim().is_empty() && !id.trim().chars().all(|c| c.is_ascii_digit()) {
+                return Err(format!("Invalid Plex library ID '{}'", id.trim()));
+            }
+        }
+        Ok(())
+    }
+
+    /// The settings with the token replaced, for logs and the audit trail
+    pub fn redacted(&self) -> Self {
+        Self {
+            token: "********".to_string(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Ask the server to rescan its library so new downloads show up
+pub async fn refresh(settings: &MediaServerSettings) -> Result<(), String> {
+    settings.validate()?;
+    // Media servers usually live on the local network, so the download proxy is not used
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("youtube-downloader/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(30))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let base = settings.url.trim().trim_end_matches('/');
+    let token = settings.token.trim();
+
+    let request = match settings.kind {
+        MediaServerKind::Jellyfin => client
+            .post(format!("{}/Library/Refresh", base))
+            .header("X-Emby-Token", token),
+        MediaServerKind::Plex => {
+            let section = settings
+                .library_id
+                .as_deref()
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .unwrap_or("all");
+            client
+                .get(format!("{}/library/sections/{}/refresh", base, section))
+                .header("X-Plex-Token", token)
+        }
+    };
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach the media server: {}", e))?;
+    match response.status() {
+        status if status.is_success() => Ok(()),
+        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => {
+            Err("The media server rejected the token".to_string())
+        }
+        status => Err(format!("The media server answered {}", status)),
+    }
+}
@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// How long connecting and publishing may take before the message is dropped
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// MQTT broker the queue status and download events are published to, for Home
+/// Assistant and other dashboards. Plain TCP only, the password is stored with the
+/// other preferences in plain text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttSettings {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Retained topic holding the current queue status
+    pub status_topic: String,
+    /// Topic for job completions and the queue running dry
+    pub event_topic: String,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: String::new(),
+            port: 1883,
+            username: None,
+            password: None,
+            status_topic: "youtube-downloader/status".to_string(),
+            event_topic: "youtube-downloader/events".to_string(),
+        }
+    }
+}
+
+impl MqttSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.host.trim().is_empty() {
+            return Err("The MQTT broker host is empty".to_string());
+        }
+        if self.port == 0 {
+            return Err("The MQTT broker port must be between 1 and 65535".to_string());
+        }
+        for topic in [&self.status_topic, &self.event_topic] {
+            if topic.trim().is_empty() || topic.contains(['+', '#']) {
+                return Err(format!(
+                    "Invalid MQTT topic '{}', wildcards can't be published to",
+                    topic
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The settings with the password replaced, for logs and the audit trail
+    pub fn redacted(&self) -> Self {
+        Self {
+            password: self.password.as_ref().map(|_| "********".to_string()),
+            ..self.clone()
+        }
+    }
+}
+
+/// Broker the status is published to, set from the preferences
+static BROKER: Mutex<Option<MqttSettings>> = Mutex::new(None);
+
+/// Messages waiting to be published, sent one at a time so the retained status can't
+/// be overtaken by an older one
+static OUTBOX: OnceLock<UnboundedSender<Message>> = OnceLock::new();
+
+struct Message {
+    topic: fn(&MqttSettings) -> &str,
+    payload: Vec<u8>,
+    retain: bool,
+}
+
+/// Publish to `settings` from now on
+pub fn configure(settings: Option<MqttSettings>) {
+    *BROKER.lock().unwrap() = settings.filter(|s| s.enabled);
+}
+
+/// Replace the retained queue status, in the background
+pub fn publish_status(status: &impl Serialize) {
+    enqueue(|settings| &settings.status_topic, status, true);
+}
+
+/// Publish a download event, in the background
+pub fn publish_event(event: &impl Serialize) {
+    enqueue(|settings| &settings.event_topic, event, false);
+}
+
+fn enqueue(topic: fn(&MqttSettings) -> &str, message: &impl Serialize, retain: bool) {
+    if BROKER.lock().unwrap().is_none() {
+        return;
+    }
+    let Ok(payload) = serde_json::to_vec(message) else {
+        return;
+    };
+    let outbox = OUTBOX.get_or_init(|| {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Message>();
+        tauri::async_runtime::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                let Some(settings) = BROKER.lock().unwrap().clone() else {
+                    continue;
+                };
+                let topic = (message.topic)(&settings);
+                if let Err(e) = publish(&settings, topic, &message.payload, message.retain).await {
+                    eprintln!("[mqtt] Failed to publish to {}: {}", topic, e);
+                }
+            }
+        });
+        tx
+    });
+    outbox
+        .send(Message {
+            topic,
+            payload,
+            retain,
+        })
+        .ok();
+}
+
+/// Publish one message with QoS 0 over a short-lived connection
+pub async fn publish(
+    settings: &MqttSettings,
+    topic: &str,
+    payload: &[u8],
+    retain: bool,
+) -> Result<(), String> {
+    tokio::time::timeout(TIMEOUT, send(settings, topic, payload, retain))
+        .await
+        .map_err(|_| "The MQTT broker did not answer in time".to_string())?
+}
+
+async fn send(
+    settings: &MqttSettings,
+    topic: &str,
+    payload: &[u8],
+    retain: bool,
+) -> Result<(), String> {
+    let mut stream = TcpStream::connect((settings.host.trim(), settings.port))
+        .await
+        .map_err(|e| format!("Failed to connect to the MQTT broker: {}", e))?;
+
+    stream
+        .write_all(&connect_packet(settings))
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut connack = [0u8; 4];
+    stream
+        .read_exact(&mut connack)
+        .await
+        .map_err(|e| format!("The MQTT broker closed the connection: {}", e))?;
+    match connack {
+        [0x20, 0x02, _, 0] => {}
+        [0x20, 0x02, _, 4] | [0x20, 0x02, _, 5] => {
+            return Err("The MQTT broker rejected the user name or password".to_string())
+        }
+        [0x20, 0x02, _, code] => {
+            return Err(format!("The MQTT broker refused the connection ({})", code))
+        }
+        _ => return Err("The server did not answer as an MQTT broker".to_string()),
+    }
+
+    stream
+        .write_all(&publish_packet(topic, payload, retain))
+        .await
+        .map_err(|e| e.to_string())?;
+    // DISCONNECT, so the broker doesn't publish a will or log an error
+    stream.write_all(&[0xe0, 0x00]).await.ok();
+    stream.shutdown().await.ok();
+    Ok(())
+}
+
+/// MQTT's variable-length encoding of a packet's remaining length
+fn remaining_length(mut length: usize, packet: &mut Vec<u8>) {
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+}
+
+fn push_string(value: &[u8], body: &mut Vec<u8>) {
+    body.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    body.extend_from_slice(value);
+}
+
+fn packet(header: u8, body: Vec<u8>) -> Vec<u8> {
+    let mut packet = vec![header];
+    remaining_length(body.len(), &mut packet);
+    packet.extend(body);
+    packet
+}
+
+/// MQTT 3.1.1 CONNECT with a clean session
+fn connect_packet(settings: &MqttSettings) -> Vec<u8> {
+    let username = settings.username.as_deref().filter(|u| !u.is_empty());
+    let password = settings
+        .password
+        .as_deref()
+        .filter(|p| !p.is_empty() && username.is_some());
+    let mut flags = 0x02;
+    if username.is_some() {
+        flags |= 0x80;
+    }
+    if password.is_some() {
+        flags |= 0x40;
+    }
+
+    let mut body = Vec::new();
+    push_string(b"MQTT", &mut body);
+    body.push(4);
+    body.push(flags);
+    body.extend_from_slice(&30u16.to_be_bytes());
+    let client_id = format!("youtube-downloader-{}", std::process::id());
+    push_string(client_id.as_bytes(), &mut body);
+    if let Some(username) = username {
+        push_string(username.as_bytes(), &mut body);
+    }
+    if let Some(password) = password {
+        push_string(password.as_bytes(), &mut body);
+    }
+    packet(0x10, body)
+}
+
+fn publish_packet(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    push_string(topic.as_bytes(), &mut body);
+    body.extend_from_slice(payload);
+    packet(if retain { 0x31 } else { 0x30 }, body)
+}
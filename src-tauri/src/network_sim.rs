@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tokio::process::Command;
+
+use crate::download;
+
+/// Proxy yt-dlp is pointed at to fail a run, the discard port refuses connections
+const UNREACHABLE_PROXY: &str = "http://127.0.0.1:9";
+
+/// Debug setting making yt-dlp behave as if the network were slow and unreliable, to
+/// exercise retries, resuming and the progress UI. The same seed fails the same runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkSimulation {
+    pub enabled: bool,
+    /// Download speed cap such as `50K`, replacing the configured one
+    pub rate: Option<String>,
+    /// Pause before each request yt-dlp makes, in seconds
+    pub latency_secs: f64,
+    /// Share of yt-dlp runs, from 0 to 1, that fail with a refused connection
+    pub failure_rate: f64,
+    pub seed: u64,
+}
+
+impl Default for NetworkSimulation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rate: Some("100K".to_string()),
+            latency_secs: 0.0,
+            failure_rate: 0.0,
+            seed: 1,
+        }
+    }
+}
+
+impl NetworkSimulation {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(ref rate) = self.rate {
+            download::parse_download_rate(rate)?;
+        }
+        if !(0.0..=30.0).contains(&self.latency_secs) {
+            return Err(format!(
+                "The simulated latency must be between 0 and 30 seconds, got {}",
+                self.latency_secs
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.failure_rate) {
+            return Err(format!(
+                "The simulated failure rate must be between 0 and 1, got {}",
+                self.failure_rate
+            ));
+        }
+        Ok(())
+    }
+
+    /// Add the throttling to a yt-dlp command, pointing it at a dead proxy if this run
+    /// is one of those picked to fail. Goes after the real options so it overrides them.
+    pub(crate) fn apply(&self, cmd: &mut Command) {
+        if let Some(rate) = self
+            .rate
+            .as_deref()
+            .and_then(|rate| download::parse_download_rate(rate).ok())
+        {
+            cmd.arg("--limit-rate").arg(rate);
+        }
+        if self.latency_secs > 0.0 {
+            cmd.arg("--sleep-requests")
+                .arg(self.latency_secs.to_string());
+        }
+        if next_roll(self.seed) < self.failure_rate {
+            eprintln!("[network_sim] Failing this yt-dlp run on purpose");
+            cmd.arg("--proxy")
+                .arg(UNREACHABLE_PROXY)
+                .arg("--retries")
+                .arg("0");
+        }
+    }
+}
+
+/// Seed the failures were drawn with and the generator's state
+static RNG: Mutex<Option<(u64, u64)>> = Mutex::new(None);
+
+/// Start the failures over from the seed, so a run can be repeated
+pub fn configure(settings: Option<&NetworkSimulation>) {
+    *RNG.lock().unwrap() = settings.map(|s| (s.seed, s.seed));
+}
+
+/// Next number from 0 to 1 drawn from `seed`, restarting when the seed changes
+fn next_roll(seed: u64) -> f64 {
+    let mut rng = RNG.lock().unwrap();
+    let state = match *rng {
+        Some((current, state)) if current == seed => state,
+        _ => seed,
+    };
+    // splitmix64, nothing here needs more than evenly spread numbers
+    let state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    *rng = Some((seed, state));
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::AppHandle;
+
+use crate::deps;
+use crate::deps_fetch;
+use crate::events::{self, Event};
+
+/// Downloaded yt-dlp copies older than this are replaced, YouTube breaks old releases quickly
+const YTDLP_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Minimum time between two download progress events for the same binary
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreflightStage {
+    Checking,
+    Downloading,
+    Ready,
+    Failed,
+}
+
+/// Emitted as `preflight-progress` for each binary while the preflight runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreflightProgress {
+    pub binary: String,
+    pub stage: PreflightStage,
+    pub downloaded_bytes: Option<u64>,
+    pub total_bytes: Option<u64>,
+    pub message: Option<String>,
+}
+
+static PREFLIGHT_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Last progress of each binary, for a frontend that starts listening after the events
+static PREFLIGHT_STATUS: Mutex<Option<HashMap<String, PreflightProgress>>> = Mutex::new(None);
+
+fn report(app_handle: &AppHandle, progress: PreflightProgress) {
+    PREFLIGHT_STATUS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(progress.binary.clone(), progress.clone());
+    events::emit(app_handle, Event::PreflightProgress, &progress);
+}
+
+fn stage(binary: &str, stage: PreflightStage, message: Option<String>) -> PreflightProgress {
+    PreflightProgress {
+        binary: binary.to_string(),
+        stage,
+        downloaded_bytes: None,
+        total_bytes: None,
+        message,
+    }
+}
+
+/// Last reported progress of every binary, yt-dlp first
+pub fn status() -> Vec<PreflightProgress> {
+    let mut status: Vec<PreflightProgress> = PREFLIGHT_STATUS
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|status| status.values().cloned().collect())
+        .unwrap_or_default();
+    status.sort_by_key(|p| p.binary != "yt-dlp");
+    status
+}
+
+fn is_stale(binary: &str, path: &std::path::Path) -> bool {
+    binary == "yt-dlp"
+        && std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > YTDLP_MAX_AGE)
+}
+
+/// Why `binary` has to be downloaded, `None` when the bundled or managed copy will do
+fn needs_download(app_handle: &AppHandle, binary: &str) -> Option<String> {
+    if deps::has_bundled_binary(app_handle, binary) {
+        return None;
+    }
+    match deps::managed_binary_path(app_handle, binary) {
+        Ok(path) if !path.exists() => Some(format!("{} is not bundled for this platform", binary)),
+        Ok(path) if is_stale(binary, &path) => {
+            Some(format!("The downloaded {} is more than a week old", binary))
+        }
+        _ => None,
+    }
+}
+
+async fn ensure_binary(app_handle: &AppHandle, binary: &str, custom_mirror: Option<&str>) {
+    report(app_handle, stage(binary, PreflightStage::Checking, None));
+
+    let download_reason = {
+        let handle = app_handle.clone();
+        let name = binary.to_string();
+        tauri::async_runtime::spawn_blocking(move || needs_download(&handle, &name))
+            .await
+            .ok()
+            .flatten()
+    };
+
+    if let Some(reason) = download_reason {
+        eprintln!("[preflight] {}, downloading", reason);
+        let mut last_emit: Option<Instant> = None;
+        let fetched = deps_fetch::fetch_binary(
+            app_handle,
+            binary,
+            custom_mirror,
+            |url, downloaded, total| {
+                if last_emit.is_some_and(|at| at.elapsed() < PROGRESS_INTERVAL) {
+                    return;
+                }
+                last_emit = Some(Instant::now());
+                let message = format!("{}, downloading from {}", reason, url);
+                report(
+                    app_handle,
+                    PreflightProgress {
+                        downloaded_bytes: Some(downloaded),
+                        total_bytes: total,
+                        ..stage(binary, PreflightStage::Downloading, Some(message))
+                    },
+                );
+            },
+        )
+        .await;
+
+        if let Err(e) = fetched {
+            eprintln!("[preflight] Failed to download {}: {:#}", binary, e);
+            // A stale copy still works better than none, so only fail if there is nothing
+            let usable = deps::managed_binary_path(app_handle, binary).is_ok_and(|p| p.exists());
+            if !usable {
+                report(
+                    app_handle,
+                    stage(binary, PreflightStage::Failed, Some(format!("{:#}", e))),
+                );
+                return;
+            }
+        }
+    }
+
+    let handle = app_handle.clone();
+    let name = binary.to_string();
+    let verified =
+        tauri::async_runtime::spawn_blocking(move || deps::get_bundled_binary(&handle, &name))
+            .await;
+    let progress = match verified {
+        Ok(Ok(path)) => stage(
+            binary,
+            PreflightStage::Ready,
+            Some(path.to_string_lossy().to_string()),
+        ),
+        Ok(Err(e)) => stage(binary, PreflightStage::Failed, Some(e.to_string())),
+        Err(e) => stage(binary, PreflightStage::Failed, Some(e.to_string())),
+    };
+    report(app_handle, progress);
+}
+
+/// Make sure yt-dlp and ffmpeg are present and current before the first download,
+/// downloading them when the bundle has no usable copy. Returns `false` without doing
+/// anything if a preflight is already running. `custom_mirror` is tried before the
+/// official download locations.
+pub async fn run(app_handle: AppHandle, custom_mirror: Option<String>) -> bool {
+    if PREFLIGHT_RUNNING.swap(true, Ordering::SeqCst) {
+        return false;
+    }
+    for binary in ["yt-dlp", "ffmpeg"] {
+        ensure_binary(&app_handle, binary, custom_mirror.as_deref()).await;
+    }
+    PREFLIGHT_RUNNING.store(false, Ordering::SeqCst);
+    eprintln!("[preflight] Complete");
+    true
+}
+
+/// Run the preflight in the background after launch
+pub fn start(app_handle: AppHandle, custom_mirror: Option<String>) {
+    tauri::async_runtime::spawn(async move {
+        run(app_handle, custom_mirror).await;
+    });
+}
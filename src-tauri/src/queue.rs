@@ -0,0 +1,694 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::BuildHasher;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::api::path::config_dir;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+
+use crate::commands::{self, DownloadResponse};
+use crate::download::{self, DownloadOverrides, DownloadResult};
+use crate::events::{self, Event};
+use crate::forecast::{self, QueueForecast};
+use crate::mqtt;
+use crate::source::{self, ParsedSource, RejectedUrl};
+use crate::volume;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    /// Waits for `resume_all`, the worker skips it
+    Paused,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    /// Whether the job has run its course and will not run again
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            JobStatus::Done | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueJob {
+    pub id: String,
+    pub url: String,
+    pub output_folder: String,
+    pub bitrate: u32,
+    /// What is left unset follows the saved preferences when the job runs
+    #[serde(flatten)]
+    pub overrides: DownloadOverrides,
+    pub status: JobStatus,
+    pub added_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub error: Option<String>,
+    /// Files written by the job once it is done
+    pub output_paths: Vec<String>,
+}
+
+/// Version written into queue exports, raised when their layout changes
+const EXPORT_VERSION: u32 = 1;
+
+/// A job as saved by `export_queue`: what to download and how, without its progress
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedJob {
+    pub url: String,
+    pub output_folder: String,
+    pub bitrate: u32,
+    #[serde(flatten)]
+    pub overrides: DownloadOverrides,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct QueueExport {
+    version: u32,
+    exported_at: String,
+    jobs: Vec<ExportedJob>,
+}
+
+/// Outcome of `import_queue`
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueImport {
+    pub added: Vec<QueueJob>,
+    /// URLs already waiting in the queue with the same settings, or listed twice
+    pub duplicates: Vec<String>,
+    pub rejected: Vec<RejectedUrl>,
+}
+
+/// Number of jobs in each state and the one running, published as the MQTT status
+#[derive(Debug, Serialize)]
+struct QueueStatus<'a> {
+    queued: usize,
+    running: usize,
+    paused: usize,
+    done: usize,
+    failed: usize,
+    cancelled: usize,
+    current: Option<&'a QueueJob>,
+}
+
+impl<'a> QueueStatus<'a> {
+    fn of(jobs: &'a [QueueJob]) -> Self {
+        let count = |status| jobs.iter().filter(|j| j.status == status).count();
+        Self {
+            queued: count(JobStatus::Queued),
+            running: count(JobStatus::Running),
+            paused: count(JobStatus::Paused),
+            done: count(JobStatus::Done),
+            failed: count(JobStatus::Failed),
+            cancelled: count(JobStatus::Cancelled),
+            current: jobs.iter().find(|j| j.status == JobStatus::Running),
+        }
+    }
+}
+
+/// Sent as `queue-state-changed` after a queue-wide control
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueStateChange {
+    /// `pause_all`, `resume_all`, `cancel_all` or `clear_completed`
+    pub action: &'static str,
+    /// IDs of the jobs the control changed or removed
+    pub changed: Vec<String>,
+    /// The whole queue afterwards
+    pub jobs: Vec<QueueJob>,
+}
+
+/// Combined progress of a `download_multiple` batch, sent as `ytmp3://batch/progress/v2`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatchProgress {
+    pub batch_id: String,
+    pub total: usize,
+    /// URLs whose job is done, failed or cancelled, or that were rejected before queuing
+    pub finished: usize,
+    pub failed: usize,
+    /// Average progress of the URLs from 0 to 100, finished ones counting as complete
+    pub overall_progress: f64,
+}
+
+/// Outcome of one URL of a `download_multiple` batch
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchItemResult {
+    pub url: String,
+    /// Job that downloaded the URL, none when it was rejected before queuing
+    pub job_id: Option<String>,
+    pub status: JobStatus,
+    pub output_paths: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchResult {
+    pub batch_id: String,
+    /// One result per URL, in the order they were given
+    pub items: Vec<BatchItemResult>,
+    /// Liner notes written for the batch, when it was given a session note
+    pub liner_notes_path: Option<String>,
+}
+
+/// Downloads waiting to run, running and finished, kept in tauri State and saved to
+/// `queue.json` so a restart picks up where it left off
+pub struct DownloadQueue {
+    jobs: Mutex<Vec<QueueJob>>,
+    wake: Notify,
+    /// Latest progress of each running job, kept out of `queue.json`
+    progress: Mutex<HashMap<String, f64>>,
+    /// Woken whenever a job changes or reports progress, for batches waiting on their jobs
+    changed: Notify,
+}
+
+/// Check an imported job before it is queued, returning what its URL points at
+fn validate_import(job: &ExportedJob, output_folder: &str) -> Result<ParsedSource, String> {
+    let source = source::parse_source(&job.url)?;
+    if job.bitrate == 0 {
+        return Err("The bitrate is missing".to_string());
+    }
+    if job.overrides.clip.resolve()?.is_some() && source.is_playlist() {
+        return Err("A time range can only be downloaded from a single video".to_string());
+    }
+    volume::check_output_folder(output_folder)?;
+    Ok(source)
+}
+
+fn get_queue_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("youtube-downloader").join("queue.json"))
+}
+
+pub(crate) fn new_id(prefix: &str) -> String {
+    let nanos = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+    format!(
+        "{}-{:x}-{:04x}",
+        prefix,
+        nanos,
+        RandomState::new().hash_one(nanos) & 0xffff
+    )
+}
+
+impl DownloadQueue {
+    /// Load the saved queue. Jobs that were running when the app quit are queued again.
+    pub fn load() -> Self {
+        let mut jobs: Vec<QueueJob> = get_queue_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        for job in jobs.iter_mut().filter(|j| j.status == JobStatus::Running) {
+            job.status = JobStatus::Queued;
+            job.started_at = None;
+        }
+        Self {
+            jobs: Mutex::new(jobs),
+            wake: Notify::new(),
+            progress: Mutex::new(HashMap::new()),
+            changed: Notify::new(),
+        }
+    }
+
+    /// Save the queue and publish its new status
+    fn save(jobs: &[QueueJob]) {
+        mqtt::publish_status(&QueueStatus::of(jobs));
+        let saved = get_queue_path()
+            .ok_or("Failed to get config directory".to_string())
+            .and_then(|path| {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                let content = serde_json::to_string_pretty(jobs).map_err(|e| e.to_string())?;
+                fs::write(path, content).map_err(|e| e.to_string())
+            });
+        if let Err(e) = saved {
+            eprintln!("[queue] Failed to save the queue: {}", e);
+        }
+    }
+
+    pub fn jobs(&self) -> Vec<QueueJob> {
+        self.jobs.lock().unwrap().clone()
+    }
+
+    /// Add a download to the end of the queue and return its job
+    pub fn enqueue(
+        &self,
+        url: String,
+        output_folder: String,
+        bitrate: u32,
+        overrides: DownloadOverrides,
+    ) -> QueueJob {
+        let job = QueueJob {
+            id: new_id("job"),
+            url,
+            output_folder,
+            bitrate,
+            overrides,
+            status: JobStatus::Queued,
+            added_at: chrono::Utc::now().to_rfc3339(),
+            started_at: None,
+            finished_at: None,
+            error: None,
+            output_paths: Vec::new(),
+        };
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.push(job.clone());
+        Self::save(&jobs);
+        drop(jobs);
+        self.wake.notify_one();
+        self.changed.notify_waiters();
+        job
+    }
+
+    /// Remove a job, cancelling it first if it is running. Returns the removed job.
+    pub fn remove(&self, job_id: &str) -> Option<QueueJob> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let index = jobs.iter().position(|j| j.id == job_id)?;
+        let job = jobs.remove(index);
+        Self::save(&jobs);
+        drop(jobs);
+        self.changed.notify_waiters();
+        if job.status == JobStatus::Running {
+            download::cancel_downloads(Some(job_id));
+        }
+        Some(job)
+    }
+
+    /// Apply `change` to a job that is still in the queue and return its new state
+    fn update(&self, job_id: &str, change: impl FnOnce(&mut QueueJob)) -> Option<QueueJob> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.iter_mut().find(|j| j.id == job_id)?;
+        change(job);
+        let job = job.clone();
+        Self::save(&jobs);
+        drop(jobs);
+        self.changed.notify_waiters();
+        Some(job)
+    }
+
+    /// Note how far a running job's download has got, from 0 to 100
+    pub fn report_progress(&self, job_id: &str, percent: f64) {
+        self.progress
+            .lock()
+            .unwrap()
+            .insert(job_id.to_string(), percent);
+        self.changed.notify_waiters();
+    }
+
+    /// Move every job in one of the `from` states to `to` and return the IDs of the
+    /// running ones among them, whose downloads still have to be stopped
+    fn transition(&self, from: &[JobStatus], to: JobStatus) -> (Vec<String>, Vec<String>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let (mut changed, mut running) = (Vec::new(), Vec::new());
+        for job in jobs.iter_mut().filter(|j| from.contains(&j.status)) {
+            if job.status == JobStatus::Running {
+                running.push(job.id.clone());
+            }
+            job.status = to;
+            match to {
+                JobStatus::Queued | JobStatus::Paused => job.started_at = None,
+                _ => job.finished_at = Some(chrono::Utc::now().to_rfc3339()),
+            }
+            changed.push(job.id.clone());
+        }
+        if !changed.is_empty() {
+            Self::save(&jobs);
+            drop(jobs);
+            self.changed.notify_waiters();
+        }
+        (changed, running)
+    }
+
+    /// Hold every queued job and interrupt the running one, which starts over once
+    /// resumed. Jobs added afterwards run as usual.
+    pub fn pause_all(&self) -> QueueStateChange {
+        let (changed, running) =
+            self.transition(&[JobStatus::Queued, JobStatus::Running], JobStatus::Paused);
+        running.iter().for_each(|id| {
+            download::cancel_downloads(Some(id));
+        });
+        self.state_change("pause_all", changed)
+    }
+
+    /// Queue the paused jobs again
+    pub fn resume_all(&self) -> QueueStateChange {
+        let (changed, _) = self.transition(&[JobStatus::Paused], JobStatus::Queued);
+        self.wake.notify_one();
+        self.state_change("resume_all", changed)
+    }
+
+    /// Cancel every job that hasn't finished, stopping the running one
+    pub fn cancel_all(&self) -> QueueStateChange {
+        let (changed, running) = self.transition(
+            &[JobStatus::Queued, JobStatus::Running, JobStatus::Paused],
+            JobStatus::Cancelled,
+        );
+        running.iter().for_each(|id| {
+            download::cancel_downloads(Some(id));
+        });
+        self.state_change("cancel_all", changed)
+    }
+
+    /// Remove the jobs that are done, failed or cancelled
+    pub fn clear_completed(&self) -> QueueStateChange {
+        let mut jobs = self.jobs.lock().unwrap();
+        let changed: Vec<String> = jobs
+            .iter()
+            .filter(|j| j.status.is_finished())
+            .map(|j| j.id.clone())
+            .collect();
+        if !changed.is_empty() {
+            jobs.retain(|j| !j.status.is_finished());
+            Self::save(&jobs);
+        }
+        drop(jobs);
+        self.state_change("clear_completed", changed)
+    }
+
+    /// Wait for the jobs of a batch, queued or rejected per URL, to finish and return
+    /// the outcome of each URL. The combined progress is sent whenever it changes.
+    /// Paused jobs hold the batch until they are resumed.
+    pub async fn run_batch(
+        &self,
+        app_handle: &AppHandle,
+        entries: Vec<(String, Result<QueueJob, String>)>,
+    ) -> BatchResult {
+        let batch_id = new_id("batch");
+        // Last state seen of each job, so jobs cleared from the queue keep their outcome
+        let mut seen: HashMap<String, QueueJob> = entries
+            .iter()
+            .filter_map(|(_, job)| job.as_ref().ok())
+            .map(|job| (job.id.clone(), job.clone()))
+            .collect();
+        let rejected = entries.len() - seen.len();
+        let mut sent = None;
+        loop {
+            // Created before looking, so a change made while looking still wakes us
+            let changed = self.changed.notified();
+            let jobs = self.jobs();
+            for job in seen.values_mut() {
+                match jobs.iter().find(|j| j.id == job.id) {
+                    Some(current) => *job = current.clone(),
+                    None if !job.status.is_finished() => {
+                        job.status = JobStatus::Cancelled;
+                        job.error = Some("Removed from the queue".to_string());
+                    }
+                    None => {}
+                }
+            }
+
+            let finished: Vec<&QueueJob> =
+                seen.values().filter(|j| j.status.is_finished()).collect();
+            let running: f64 = {
+                let progress = self.progress.lock().unwrap();
+                seen.values()
+                    .filter(|j| !j.status.is_finished())
+                    .filter_map(|j| progress.get(&j.id))
+                    .sum()
+            };
+            let done = rejected + finished.len();
+            let update = BatchProgress {
+                batch_id: batch_id.clone(),
+                total: entries.len(),
+                finished: done,
+                failed: rejected
+                    + finished
+                        .iter()
+                        .filter(|j| j.status != JobStatus::Done)
+                        .count(),
+                overall_progress: if entries.is_empty() {
+                    100.0
+                } else {
+                    (done as f64 * 100.0 + running) / entries.len() as f64
+                },
+            };
+            if sent.as_ref() != Some(&update) {
+                events::emit(app_handle, Event::BatchProgress, &update);
+                sent = Some(update);
+            }
+            if done == entries.len() {
+                break;
+            }
+            changed.await;
+        }
+
+        let items = entries
+            .into_iter()
+            .map(|(url, job)| match job {
+                Ok(job) => {
+                    let job = seen.remove(&job.id).unwrap_or(job);
+                    BatchItemResult {
+                        url,
+                        job_id: Some(job.id),
+                        status: job.status,
+                        output_paths: job.output_paths,
+                        error: job.error,
+                    }
+                }
+                Err(error) => BatchItemResult {
+                    url,
+                    job_id: None,
+                    status: JobStatus::Failed,
+                    output_paths: Vec::new(),
+                    error: Some(error),
+                },
+            })
+            .collect();
+        BatchResult {
+            batch_id,
+            items,
+            liner_notes_path: None,
+        }
+    }
+
+    fn state_change(&self, action: &'static str, changed: Vec<String>) -> QueueStateChange {
+        QueueStateChange {
+            action,
+            changed,
+            jobs: self.jobs(),
+        }
+    }
+
+    /// Write the jobs still to run, and the failed ones, to `path` for importing on this
+    /// or another machine. Returns how many were written.
+    pub fn export(&self, path: &Path) -> Result<usize, String> {
+        let jobs: Vec<ExportedJob> = self
+            .jobs()
+            .into_iter()
+            .filter(|j| !j.status.is_finished() || j.status == JobStatus::Failed)
+            .map(|j| ExportedJob {
+                url: j.url,
+                output_folder: j.output_folder,
+                bitrate: j.bitrate,
+                overrides: j.overrides,
+            })
+            .collect();
+        let export = QueueExport {
+            version: EXPORT_VERSION,
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            jobs,
+        };
+        let content = serde_json::to_string_pretty(&export)
+            .map_err(|e| format!("Failed to serialize the queue: {}", e))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(export.jobs.len())
+    }
+
+    /// Queue the jobs of a file written by `export`, into `output_folder` instead of
+    /// the folders they were exported with when it is given. Jobs with an invalid URL,
+    /// time range or folder are rejected, and jobs for a video already waiting in the
+    /// queue with the same settings are skipped.
+    pub fn import(&self, path: &Path, output_folder: Option<&str>) -> Result<QueueImport, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let export: QueueExport = serde_json::from_str(&content)
+            .map_err(|e| format!("{} is not a queue export: {}", path.display(), e))?;
+        if export.version > EXPORT_VERSION {
+            return Err(format!(
+                "{} was exported by a newer version of the app",
+                path.display()
+            ));
+        }
+
+        // Jobs count as the same when they download the same video the same way
+        let key = |source: &ParsedSource, overrides: &DownloadOverrides| {
+            (
+                source.clone(),
+                serde_json::to_string(overrides).unwrap_or_default(),
+            )
+        };
+        let mut seen: HashSet<_> = self
+            .jobs()
+            .iter()
+            .filter(|j| !j.status.is_finished())
+            .filter_map(|j| Some(key(&source::parse_source(&j.url).ok()?, &j.overrides)))
+            .collect();
+        let mut import = QueueImport {
+            added: Vec::new(),
+            duplicates: Vec::new(),
+            rejected: Vec::new(),
+        };
+        for job in export.jobs {
+            let folder = output_folder.map_or_else(|| job.output_folder.clone(), str::to_string);
+            let source = match validate_import(&job, &folder) {
+                Ok(source) => source,
+                Err(reason) => {
+                    import.rejected.push(RejectedUrl {
+                        input: job.url,
+                        reason,
+                    });
+                    continue;
+                }
+            };
+            if !seen.insert(key(&source, &job.overrides)) {
+                import.duplicates.push(job.url);
+                continue;
+            }
+            import
+                .added
+                .push(self.enqueue(job.url, folder, job.bitrate, job.overrides));
+        }
+        Ok(import)
+    }
+
+    /// When the jobs that haven't finished are expected to be done
+    pub fn forecast(&self) -> QueueForecast {
+        let jobs = self.jobs();
+        let progress = self.progress.lock().unwrap().clone();
+        forecast::compute(&jobs, &progress, chrono::Utc::now())
+    }
+
+    /// Mark the oldest queued job as running and return it
+    fn next_job(&self) -> Option<QueueJob> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.iter_mut().find(|j| j.status == JobStatus::Queued)?;
+        job.status = JobStatus::Running;
+        job.started_at = Some(chrono::Utc::now().to_rfc3339());
+        let job = job.clone();
+        Self::save(&jobs);
+        Some(job)
+    }
+}
+
+/// The file of a video followed by its chapters, if it was split
+fn video_paths(video: &DownloadResult) -> impl Iterator<Item = String> + '_ {
+    std::iter::once(&video.output_path)
+        .chain(&video.chapter_paths)
+        .cloned()
+}
+
+fn output_paths(response: &DownloadResponse) -> Vec<String> {
+    match response {
+        DownloadResponse::Single(result) => video_paths(result).collect(),
+        DownloadResponse::Playlist(result) => result
+            .downloaded_videos
+            .iter()
+            .flat_map(video_paths)
+            .collect(),
+        DownloadResponse::ConfirmationRequired(_) => Vec::new(),
+    }
+}
+
+/// Run a job and return whether it wrote any files
+async fn run_job(app_handle: &AppHandle, job: QueueJob) -> bool {
+    let queue = app_handle.state::<DownloadQueue>();
+    events::emit(app_handle, Event::QueueJobUpdated, &job);
+
+    // Queuing a playlist is the confirmation, there is nobody to ask once it runs
+    let result = download::PROGRESS_JOB
+        .scope(
+            job.id.clone(),
+            commands::start_download(
+                job.url.clone(),
+                job.output_folder.clone(),
+                job.bitrate,
+                job.overrides.clone(),
+                true,
+                Some(job.id.clone()),
+                app_handle.clone(),
+            ),
+        )
+        .await;
+
+    queue.progress.lock().unwrap().remove(&job.id);
+    let updated = queue.update(&job.id, |job| {
+        // Paused and cancelled jobs were stopped on purpose and keep their new state
+        if result.is_err() && matches!(job.status, JobStatus::Paused | JobStatus::Cancelled) {
+            return;
+        }
+        job.finished_at = Some(chrono::Utc::now().to_rfc3339());
+        match &result {
+            Ok(response) => {
+                job.status = JobStatus::Done;
+                job.output_paths = output_paths(response);
+            }
+            Err(e) => {
+                job.status = JobStatus::Failed;
+                job.error = Some(e.clone());
+            }
+        }
+    });
+    if let Some(job) = updated {
+        if job.status.is_finished() {
+            mqtt::publish_event(&serde_json::json!({ "event": "job_finished", "job": &job }));
+        }
+        events::emit(app_handle, Event::QueueJobUpdated, &job);
+    }
+    result.is_ok_and(|response| !output_paths(&response).is_empty())
+}
+
+/// Send a new forecast whenever a job is added, removed or changes state. Progress
+/// alone doesn't count, the running job's share of the forecast follows from it.
+fn start_forecast_updates(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let queue = app_handle.state::<DownloadQueue>();
+        let mut sent: Option<Vec<(String, JobStatus)>> = None;
+        loop {
+            let changed = queue.changed.notified();
+            let state: Vec<(String, JobStatus)> =
+                queue.jobs().into_iter().map(|j| (j.id, j.status)).collect();
+            if sent.as_ref() != Some(&state) {
+                events::emit(&app_handle, Event::QueueForecastUpdated, &queue.forecast());
+                sent = Some(state);
+            }
+            changed.await;
+        }
+    });
+}
+
+/// Run queued jobs one after another in the background, waiting for new ones when the
+/// queue is empty. The media server is refreshed each time the queue runs dry.
+pub fn start_worker(app_handle: AppHandle) {
+    start_forecast_updates(app_handle.clone());
+    tauri::async_runtime::spawn(async move {
+        let queue = app_handle.state::<DownloadQueue>();
+        // Whether a job ran, and whether one downloaded something, since the queue
+        // last ran dry
+        let (mut ran, mut downloaded) = (false, false);
+        loop {
+            match queue.next_job() {
+                Some(job) => {
+                    ran = true;
+                    downloaded |= run_job(&app_handle, job).await;
+                }
+                None => {
+                    if std::mem::take(&mut ran) {
+                        mqtt::publish_event(&serde_json::json!({ "event": "queue_drained" }));
+                    }
+                    if std::mem::take(&mut downloaded) {
+                        commands::refresh_media_server().await;
+                    }
+                    queue.wake.notified().await
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::{AppHandle, Runtime};
+
+use crate::download::{self, DownloadOptions, PlaylistEntry};
+
+/// Search results looked at for a replacement
+const SEARCH_RESULTS: usize = 10;
+
+/// Candidates proposed at most, best match first
+const MAX_CANDIDATES: usize = 5;
+
+/// Results matching worse than this are not proposed
+const MIN_SCORE: f64 = 0.3;
+
+/// Difference in length, in seconds, at which a result no longer counts as the same
+/// recording
+const DURATION_TOLERANCE: f64 = 30.0;
+
+/// Words reuploads add or drop without changing what the video is
+const NOISE_WORDS: &[&str] = &[
+    "official",
+    "video",
+    "audio",
+    "lyrics",
+    "lyric",
+    "music",
+    "mv",
+    "hd",
+    "hq",
+    "4k",
+    "remastered",
+    "visualizer",
+    "full",
+];
+
+/// A video that might be the same recording as one that was removed from YouTube
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplacementCandidate {
+    pub url: String,
+    pub video_id: Option<String>,
+    pub title: Option<String>,
+    pub uploader: Option<String>,
+    pub duration: Option<f64>,
+    /// How closely the title and length match the original, from 0 to 1
+    pub score: f64,
+}
+
+fn title_words(title: &str) -> HashSet<String> {
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !NOISE_WORDS.contains(word))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Share of the words the two titles have in common
+fn title_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (title_words(a), title_words(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+/// 1 for the same length, falling to 0 at `DURATION_TOLERANCE` apart
+fn duration_similarity(a: f64, b: f64) -> f64 {
+    (1.0 - (a - b).abs() / DURATION_TOLERANCE).max(0.0)
+}
+
+fn score(entry: &PlaylistEntry, title: &str, duration: Option<f64>) -> f64 {
+    let title_score = entry
+        .title
+        .as_deref()
+        .map_or(0.0, |t| title_similarity(title, t));
+    match (duration, entry.duration) {
+        (Some(original), Some(found)) => {
+            0.7 * title_score + 0.3 * duration_similarity(original, found)
+        }
+        _ => title_score,
+    }
+}
+
+/// Search YouTube for videos with the title and length of the removed video
+/// `original_id`, best match first
+pub async fn find<R: Runtime>(
+    original_id: &str,
+    title: &str,
+    duration: Option<f64>,
+    options: &DownloadOptions,
+    app_handle: &AppHandle<R>,
+) -> Result<Vec<ReplacementCandidate>, String> {
+    let results = download::search_youtube(title, SEARCH_RESULTS, options, app_handle).await?;
+    let mut candidates: Vec<ReplacementCandidate> = results
+        .into_iter()
+        .filter(|entry| entry.id.as_deref() != Some(original_id))
+        .map(|entry| ReplacementCandidate {
+            score: score(&entry, title, duration),
+            url: entry.url,
+            video_id: entry.id,
+            title: entry.title,
+            uploader: entry.uploader,
+            duration: entry.duration,
+        })
+        .filter(|candidate| candidate.score >= MIN_SCORE)
+        .collect();
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    candidates.truncate(MAX_CANDIDATES);
+    Ok(candidates)
+}
@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::api::path::cache_dir;
+use tokio::task::JoinSet;
+
+use crate::download::{run_output, ytdlp_command, DownloadOptions};
+use crate::tags;
+
+/// Download very long videos as separate `--download-sections` ranges that are
+/// stitched together with ffmpeg. Finished sections are kept until the whole video
+/// is done, so an interrupted multi-hour download picks up where it left off.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SectionedDownload {
+    pub enabled: bool,
+    /// Only videos longer than this are split
+    pub min_duration_minutes: u32,
+    pub section_minutes: u32,
+    /// Number of sections fetched at the same time
+    pub parallel_sections: usize,
+}
+
+impl Default for SectionedDownload {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_duration_minutes: 120,
+            section_minutes: 30,
+            parallel_sections: 2,
+        }
+    }
+}
+
+impl SectionedDownload {
+    /// Whether a video of the given length should be downloaded in sections
+    pub fn applies_to(&self, duration: Option<f64>) -> bool {
+        self.enabled
+            && self.section_minutes > 0
+            && duration.is_some_and(|d| d > self.min_duration_minutes as f64 * 60.0)
+    }
+
+    /// `(start, end)` second ranges covering the whole video
+    fn ranges(&self, duration: f64) -> Vec<(u64, u64)> {
+        let length = self.section_minutes as u64 * 60;
+        let total = duration.ceil() as u64;
+        (0..total)
+            .step_by(length as usize)
+            .map(|start| (start, (start + length).min(total)))
+            .collect()
+    }
+}
+
+fn get_sections_dir(video_id: &str) -> Option<PathBuf> {
+    cache_dir().map(|dir| {
+        dir.join("youtube-downloader")
+            .join("sections")
+            .join(video_id)
+    })
+}
+
+/// Path of a finished section, if it has been downloaded already
+fn find_section(dir: &Path, index: usize) -> Option<PathBuf> {
+    let prefix = format!("section-{:04}.", index);
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|path| {
+            let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+            name.starts_with(&prefix) && !name.ends_with(".part") && !name.ends_with(".ytdl")
+        })
+}
+
+/// Download the video section by section and encode the joined audio to `output_path`
+pub async fn download_in_sections(
+    ytdlp_cmd: &str,
+    ffmpeg_cmd: &str,
+    url: &str,
+    video_info: &serde_json::Value,
+    output_path: &Path,
+    bitrate: u32,
+    options: &DownloadOptions,
+) -> Result<(), String> {
+    let settings = options.sections.clone().unwrap_or_default();
+    let video_id = video_info["id"]
+        .as_str()
+        .ok_or("Video has no ID to download in sections")?;
+    let duration = video_info["duration"]
+        .as_f64()
+        .ok_or("Video has no duration to download in sections")?;
+    let ffmpeg_dir = Path::new(ffmpeg_cmd)
+        .parent()
+        .ok_or("Failed to get ffmpeg directory")?;
+
+    let dir = get_sections_dir(video_id).ok_or("Failed to get cache directory")?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create sections folder: {}", e))?;
+
+    let ranges = settings.ranges(duration);
+    let pending: Vec<usize> = (0..ranges.len())
+        .filter(|&index| find_section(&dir, index).is_none())
+        .collect();
+    eprintln!(
+        "[sections] {} of {} sections left for {}",
+        pending.len(),
+        ranges.len(),
+        video_id
+    );
+
+    for batch in pending.chunks(settings.parallel_sections.max(1)) {
+        let mut tasks = JoinSet::new();
+        for &index in batch {
+            let (start, end) = ranges[index];
+            let template = dir.join(format!("section-{:04}.%(ext)s", index));
+            let mut cmd = ytdlp_command(ytdlp_cmd, options);
+            cmd.arg("-f")
+                .arg(options.format_selector())
+                .arg("--download-sections")
+                .arg(format!("*{}-{}", start, end))
+                .arg("--ffmpeg-location")
+                .arg(ffmpeg_dir)
+                .arg("-o")
+                .arg(template)
+                .arg("--no-playlist")
+                .arg(url);
+            let cancel = options.cancel.clone();
+            tasks.spawn(async move { (index, run_output(&mut cmd, &cancel).await) });
+        }
+
+        while let Some(joined) = tasks.join_next().await {
+            let (index, output) = joined.map_err(|e| format!("Section task failed: {}", e))?;
+            let output =
+                output.map_err(|e| format!("Failed to download section {}: {}", index, e))?;
+            if !output.status.success() {
+                return Err(format!(
+                    "Failed to download section {}: {}",
+                    index,
+                    String::from_utf8_lossy(&output.stderr)
+                ));
+            }
+        }
+    }
+
+    let sections: Vec<PathBuf> = (0..ranges.len())
+        .map(|index| {
+            find_section(&dir, index).ok_or(format!("Section {} is missing after download", index))
+        })
+        .collect::<Result<_, _>>()?;
+
+    stitch(
+        ffmpeg_cmd,
+        &dir,
+        &sections,
+        output_path,
+        bitrate,
+        options,
+        &options.tags_for(video_info),
+    )
+    .await?;
+    fs::remove_dir_all(&dir).ok();
+    Ok(())
+}
+
+/// Join the sections with ffmpeg's concat demuxer and encode them to the output format
+async fn stitch(
+    ffmpeg_cmd: &str,
+    dir: &Path,
+    sections: &[PathBuf],
+    output_path: &Path,
+    bitrate: u32,
+    options: &DownloadOptions,
+    tags: &[(&str, String)],
+) -> Result<(), String> {
+    let list: String = sections
+        .iter()
+        .map(|path| format!("file '{}'\n", path.to_string_lossy().replace('\'', "'\\''")))
+        .collect();
+    let list_path = dir.join("sections.txt");
+    fs::write(&list_path, list).map_err(|e| format!("Failed to write section list: {}", e))?;
+
+    let result = tokio::process::Command::new(ffmpeg_cmd)
+        .arg("-y")
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-f")
+        .arg("concat")
+        .arg("-safe")
+        .arg("0")
+        .arg("-i")
+        .arg(&list_path)
+        .arg("-vn")
+        .args(options.encoder_args(bitrate, false))
+        .args(tags::ffmpeg_args(tags))
+        .arg(output_path)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+    if !result.status.success() {
+        fs::remove_file(output_path).ok();
+        return Err(format!(
+            "Failed to join sections: {}",
+            String::from_utf8_lossy(&result.stderr)
+        ));
+    }
+    Ok(())
+}
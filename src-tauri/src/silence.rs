@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+/// Removal of the silence at the start and end of each track with ffmpeg's
+/// `silenceremove`, for music videos with long silent intros and outros. Silence in
+/// the middle of a track is kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SilenceTrim {
+    pub enabled: bool,
+    /// Audio quieter than this, in dBFS, counts as silence
+    pub threshold_db: f64,
+    /// Shortest stretch of silence that is removed, in seconds
+    pub min_duration: f64,
+}
+
+impl Default for SilenceTrim {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_db: -50.0,
+            min_duration: 0.5,
+        }
+    }
+}
+
+impl SilenceTrim {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(-90.0..=0.0).contains(&self.threshold_db) {
+            return Err(format!(
+                "The silence threshold must be between -90 and 0 dB, got {}",
+                self.threshold_db
+            ));
+        }
+        if !(0.0..=60.0).contains(&self.min_duration) {
+            return Err(format!(
+                "The shortest silence must be between 0 and 60 seconds, got {}",
+                self.min_duration
+            ));
+        }
+        Ok(())
+    }
+
+    /// ffmpeg audio filter trimming both ends. `silenceremove` only trims the start
+    /// reliably, so the end is trimmed as the start of the reversed track.
+    pub fn filter(&self) -> String {
+        let trim_start = format!(
+            "silenceremove=start_periods=1:start_duration={}:start_threshold={}dB",
+            self.min_duration, self.threshold_db
+        );
+        format!("{0},areverse,{0},areverse", trim_start)
+    }
+}
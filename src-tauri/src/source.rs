@@ -0,0 +1,411 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use url::Url;
+
+/// Hosts accepted as YouTube sources
+const YOUTUBE_HOSTS: [&str; 7] = [
+    "youtube.com",
+    "www.youtube.com",
+    "m.youtube.com",
+    "music.youtube.com",
+    "youtu.be",
+    "youtube-nocookie.com",
+    "www.youtube-nocookie.com",
+];
+
+/// Playlists that only exist for the signed-in account: Watch Later, Liked videos and
+/// YouTube Music's Liked music
+const ACCOUNT_PLAYLISTS: [(&str, &str); 3] = [
+    ("WL", "Watch Later"),
+    ("LL", "Liked videos"),
+    ("LM", "Liked music"),
+];
+
+/// Whether URLs from sites other than YouTube are accepted, for yt-dlp to check
+static ANY_SITE: AtomicBool = AtomicBool::new(false);
+
+/// What a validated URL points at
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ParsedSource {
+    Video {
+        video_id: String,
+    },
+    /// A playlist, optionally opened at a specific video (`watch?v=...&list=...`)
+    Playlist {
+        list_id: String,
+        video_id: Option<String>,
+    },
+    /// A channel by handle (`@name`), ID (`UC...`) or legacy `/c/` or `/user/` name
+    Channel {
+        channel: String,
+    },
+    /// A page on another site, such as SoundCloud, Bandcamp or Vimeo, that is only
+    /// known to be downloadable once yt-dlp has probed it
+    External {
+        host: String,
+        url: String,
+    },
+}
+
+impl ParsedSource {
+    /// Whether the URL lists several videos. Channels download like a playlist of
+    /// their uploads.
+    pub fn is_playlist(&self) -> bool {
+        matches!(
+            self,
+            ParsedSource::Playlist { .. } | ParsedSource::Channel { .. }
+        )
+    }
+}
+
+/// A YouTube URL found in pasted text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PastedUrl {
+    pub url: String,
+    pub source: ParsedSource,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectedUrl {
+    pub input: String,
+    pub reason: String,
+}
+
+/// Result of splitting a pasted blob into YouTube URLs ready for queuing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasteParseReport {
+    pub urls: Vec<PastedUrl>,
+    pub duplicates: Vec<String>,
+    pub rejected: Vec<RejectedUrl>,
+}
+
+fn is_youtube_host(url: &Url) -> bool {
+    url.host_str()
+        .map(|host| YOUTUBE_HOSTS.contains(&host.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Pull the video ID out of the forms YouTube uses: `watch?v=`, `youtu.be/<id>`,
+/// `/shorts/<id>`, `/embed/<id>`, `/v/<id>` and `/live/<id>`
+fn video_id(url: &Url) -> Option<String> {
+    if let Some((_, v)) = url.query_pairs().find(|(k, _)| k == "v") {
+        return Some(v.to_string()).filter(|v| !v.is_empty());
+    }
+
+    let mut segments = url.path_segments()?;
+    let first = segments.next()?;
+    let id = if url.host_str().map(|h| h.eq_ignore_ascii_case("youtu.be")) == Some(true) {
+        first
+    } else if matches!(first, "shorts" | "embed" | "v" | "live") {
+        segments.next()?
+    } else {
+        return None;
+    };
+    Some(id.to_string()).filter(|id| !id.is_empty())
+}
+
+/// The channel a `/@handle`, `/channel/UC...`, `/c/<name>` or `/user/<name>` URL points
+/// at, in the form it has in the path
+fn channel(url: &Url) -> Option<String> {
+    let mut segments = url.path_segments()?;
+    let first = segments.next()?;
+    let channel = match first {
+        "channel" | "c" | "user" => format!("{}/{}", first, segments.next()?),
+        handle if handle.len() > 1 && handle.starts_with('@') => handle.to_string(),
+        _ => return None,
+    };
+    Some(channel).filter(|c| !c.ends_with('/'))
+}
+
+/// Accept URLs from any site from now on, or only YouTube URLs
+pub fn configure(allow_any_site: bool) {
+    ANY_SITE.store(allow_any_site, Ordering::SeqCst);
+}
+
+/// Name of the account playlist a URL points at, such as Watch Later, when it needs
+/// cookies of a signed-in browser to be listed
+pub fn account_playlist(url: &str) -> Option<&'static str> {
+    match parse_source(url) {
+        Ok(ParsedSource::Playlist { list_id, .. }) => ACCOUNT_PLAYLISTS
+            .iter()
+            .find(|(id, _)| list_id.eq_ignore_ascii_case(id))
+            .map(|&(_, name)| name),
+        _ => None,
+    }
+}
+
+/// URL listing what a source downloads: the uploads tab for a channel, whose front
+/// page yt-dlp lists as separate tabs, the playlist page for account playlists opened
+/// at a video (`watch?v=...&list=WL`), and the URL itself otherwise
+pub fn listing_url(url: &str) -> String {
+    match parse_source(url) {
+        Ok(ParsedSource::Channel { channel }) => {
+            format!("https://www.youtube.com/{}/videos", channel)
+        }
+        Ok(ParsedSource::Playlist { list_id, .. }) if account_playlist(url).is_some() => {
+            format!(
+                "https://www.youtube.com/playlist?list={}",
+                list_id.to_uppercase()
+            )
+        }
+        _ => url.to_string(),
+    }
+}
+
+fn list_id(url: &Url) -> Option<String> {
+    url.query_pairs()
+        .find(|(k, _)| k == "list")
+        .map(|(_, v)| v.to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Validate an already parsed URL against the scheme and host allowlist and work out
+/// which video and/or playlist it refers to
+fn classify(url: &Url) -> Result<ParsedSource, String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("Not a YouTube URL".to_string());
+    }
+    if !is_youtube_host(url) {
+        return match url.host_str() {
+            Some(host) if ANY_SITE.load(Ordering::SeqCst) => Ok(ParsedSource::External {
+                host: host.to_lowercase(),
+                url: url.to_string(),
+            }),
+            _ => Err("Not a YouTube URL".to_string()),
+        };
+    }
+
+    match (video_id(url), list_id(url)) {
+        (video_id, Some(list_id)) => Ok(ParsedSource::Playlist { list_id, video_id }),
+        (Some(video_id), None) => Ok(ParsedSource::Video { video_id }),
+        (None, None) => channel(url)
+            .map(|channel| ParsedSource::Channel { channel })
+            .ok_or_else(|| "No video, playlist or channel found".to_string()),
+    }
+}
+
+/// Parse and validate a YouTube video, playlist or channel URL.
+/// A missing scheme is tolerated (`youtu.be/<id>`), anything off the host allowlist is not
+/// unless other sites are allowed.
+pub fn parse_source(input: &str) -> Result<ParsedSource, String> {
+    let input = input.trim();
+    let candidate = if input.contains("://") {
+        input.to_string()
+    } else {
+        format!("https://{}", input)
+    };
+
+    Url::parse(&candidate)
+        .map_err(|e| format!("Not a valid URL: {}", e))
+        .and_then(|url| classify(&url))
+        .map_err(|reason| {
+            format!(
+                "Invalid YouTube URL ({}). Please provide a valid YouTube video, playlist or channel URL.",
+                reason
+            )
+        })
+}
+
+/// Trim punctuation that commonly surrounds URLs in chat messages and markdown
+fn trim_token(token: &str) -> &str {
+    token
+        .trim_start_matches(['<', '(', '[', '{', '"', '\''])
+        .trim_end_matches(['>', ')', ']', '}', '"', '\'', ',', ';', '.', '!', '?'])
+}
+
+/// Find the URL-looking part of a whitespace separated token, if any
+fn url_candidate(token: &str) -> Option<String> {
+    let token = trim_token(token);
+    if let Some(pos) = token.find("https://").or_else(|| token.find("http://")) {
+        return Some(token[pos..].to_string());
+    }
+
+    let lower = token.to_lowercase();
+    if YOUTUBE_HOSTS
+        .iter()
+        .any(|host| lower.starts_with(&format!("{}/", host)))
+    {
+        return Some(format!("https://{}", token));
+    }
+    None
+}
+
+/// Extract every YouTube URL from a pasted blob of text, deduplicating by video and
+/// playlist ID and reporting URLs that were found but rejected
+pub fn parse_pasted_urls(text: &str) -> PasteParseReport {
+    let mut report = PasteParseReport {
+        urls: Vec::new(),
+        duplicates: Vec::new(),
+        rejected: Vec::new(),
+    };
+    let mut seen = HashSet::new();
+
+    for candidate in text.split_whitespace().filter_map(url_candidate) {
+        let url = match Url::parse(&candidate) {
+            Ok(url) => url,
+            Err(e) => {
+                report.rejected.push(RejectedUrl {
+                    input: candidate,
+                    reason: format!("Not a valid URL: {}", e),
+                });
+                continue;
+            }
+        };
+
+        let source = match classify(&url) {
+            Ok(source) => source,
+            Err(reason) => {
+                report.rejected.push(RejectedUrl {
+                    input: candidate,
+                    reason,
+                });
+                continue;
+            }
+        };
+
+        if !seen.insert(source.clone()) {
+            report.duplicates.push(candidate);
+            continue;
+        }
+
+        report.urls.push(PastedUrl {
+            url: url.to_string(),
+            source,
+        });
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{configure, parse_source, ParsedSource};
+    use std::sync::Mutex;
+
+    /// Tests that depend on whether other sites are allowed, a process-wide setting
+    static ANY_SITE_SETTING: Mutex<()> = Mutex::new(());
+
+    fn video(id: &str) -> ParsedSource {
+        ParsedSource::Video {
+            video_id: id.to_string(),
+        }
+    }
+
+    #[test]
+    fn rejects_youtube_in_the_query_of_another_host() {
+        let _setting = ANY_SITE_SETTING.lock().unwrap();
+        assert!(parse_source("https://evil.com/?youtube.com/watch").is_err());
+        assert!(parse_source("https://evil.com/watch?v=dQw4w9WgXcQ").is_err());
+        assert!(parse_source("https://youtube.com.evil.com/watch?v=dQw4w9WgXcQ").is_err());
+        assert!(parse_source("ftp://www.youtube.com/watch?v=dQw4w9WgXcQ").is_err());
+    }
+
+    #[test]
+    fn list_on_another_host_is_not_a_playlist() {
+        let _setting = ANY_SITE_SETTING.lock().unwrap();
+        assert!(parse_source("https://example.com/playlist?list=PLabc").is_err());
+
+        configure(true);
+        let parsed = parse_source("https://example.com/playlist?list=PLabc");
+        configure(false);
+        let parsed = parsed.unwrap();
+        assert!(matches!(parsed, ParsedSource::External { ref host, .. } if host == "example.com"));
+        assert!(!parsed.is_playlist());
+    }
+
+    #[test]
+    fn other_sites_follow_the_setting() {
+        let _setting = ANY_SITE_SETTING.lock().unwrap();
+        let url = "https://soundcloud.com/artist/track";
+        assert!(parse_source(url).is_err());
+
+        configure(true);
+        let allowed = parse_source(url);
+        configure(false);
+        assert_eq!(
+            allowed,
+            Ok(ParsedSource::External {
+                host: "soundcloud.com".to_string(),
+                url: url.to_string(),
+            })
+        );
+        assert!(parse_source(url).is_err());
+    }
+
+    #[test]
+    fn accepts_youtu_be_without_a_scheme() {
+        assert_eq!(
+            parse_source("youtu.be/dQw4w9WgXcQ"),
+            Ok(video("dQw4w9WgXcQ"))
+        );
+        assert_eq!(
+            parse_source("  https://youtu.be/dQw4w9WgXcQ?t=42  "),
+            Ok(video("dQw4w9WgXcQ"))
+        );
+    }
+
+    #[test]
+    fn finds_shorts_embed_and_live_ids() {
+        for url in [
+            "https://www.youtube.com/shorts/abc123",
+            "https://www.youtube.com/embed/abc123",
+            "https://www.youtube.com/live/abc123",
+            "https://www.youtube-nocookie.com/embed/abc123",
+        ] {
+            assert_eq!(parse_source(url), Ok(video("abc123")), "{}", url);
+        }
+        assert!(parse_source("https://www.youtube.com/shorts/").is_err());
+    }
+
+    #[test]
+    fn watch_with_list_is_a_playlist() {
+        let parsed = parse_source("https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PLabc");
+        assert_eq!(
+            parsed,
+            Ok(ParsedSource::Playlist {
+                list_id: "PLabc".to_string(),
+                video_id: Some("dQw4w9WgXcQ".to_string()),
+            })
+        );
+        assert!(parsed.unwrap().is_playlist());
+        assert_eq!(
+            parse_source("https://music.youtube.com/playlist?list=PLabc"),
+            Ok(ParsedSource::Playlist {
+                list_id: "PLabc".to_string(),
+                video_id: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_channels() {
+        let channel = |channel: &str| {
+            Ok(ParsedSource::Channel {
+                channel: channel.to_string(),
+            })
+        };
+        assert_eq!(
+            parse_source("https://www.youtube.com/@SomeArtist"),
+            channel("@SomeArtist")
+        );
+        assert_eq!(
+            parse_source("https://www.youtube.com/@SomeArtist/videos"),
+            channel("@SomeArtist")
+        );
+        assert_eq!(
+            parse_source("youtube.com/channel/UCabc123"),
+            channel("channel/UCabc123")
+        );
+        assert_eq!(
+            parse_source("https://www.youtube.com/user/name"),
+            channel("user/name")
+        );
+        assert!(parse_source("https://www.youtube.com/@").is_err());
+        assert!(parse_source("https://www.youtube.com/feed/trending").is_err());
+        assert!(parse_source("https://www.youtube.com/@SomeArtist")
+            .unwrap()
+            .is_playlist());
+    }
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Samples kept per download, one per second
+const MAX_SAMPLES: usize = 300;
+
+/// Downloads whose history is kept, the least recently sampled are dropped first
+const MAX_TRACKED_DOWNLOADS: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeedSample {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: i64,
+    pub bytes_per_second: f64,
+}
+
+static SPEED_HISTORY: Mutex<Option<HashMap<String, VecDeque<SpeedSample>>>> = Mutex::new(None);
+
+/// Record the current speed of a download, at most once a second
+pub fn record(id: &str, bytes_per_second: f64) {
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut history = SPEED_HISTORY.lock().unwrap();
+    let history = history.get_or_insert_with(HashMap::new);
+
+    if !history.contains_key(id) && history.len() >= MAX_TRACKED_DOWNLOADS {
+        let oldest = history
+            .iter()
+            .min_by_key(|(_, samples)| samples.back().map(|s| s.timestamp_ms).unwrap_or(0))
+            .map(|(key, _)| key.clone());
+        if let Some(oldest) = oldest {
+            history.remove(&oldest);
+        }
+    }
+
+    let samples = history.entry(id.to_string()).or_default();
+    if samples
+        .back()
+        .is_some_and(|last| now - last.timestamp_ms < 1000)
+    {
+        return;
+    }
+    if samples.len() >= MAX_SAMPLES {
+        samples.pop_front();
+    }
+    samples.push_back(SpeedSample {
+        timestamp_ms: now,
+        bytes_per_second,
+    });
+}
+
+/// Speed samples of a download, oldest first
+pub fn get(id: &str) -> Vec<SpeedSample> {
+    SPEED_HISTORY
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|history| history.get(id))
+        .map(|samples| samples.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Average speed of the downloads sampled this session, in bytes per second
+pub fn average_speed() -> Option<f64> {
+    let history = SPEED_HISTORY.lock().unwrap();
+    let speeds: Vec<f64> = history
+        .as_ref()?
+        .values()
+        .flatten()
+        .map(|sample| sample.bytes_per_second)
+        .filter(|&speed| speed > 0.0)
+        .collect();
+    (!speeds.is_empty()).then(|| speeds.iter().sum::<f64>() / speeds.len() as f64)
+}
+
+/// Extract the speed from a yt-dlp progress line such as
+/// `[download]  45.3% of 3.52MiB at 1.21MiB/s ETA 00:02`, in bytes per second
+pub fn parse_speed(line: &str) -> Option<f64> {
+    let after = &line[line.find(" at ")? + 4..];
+    let token = after.split_whitespace().next()?.strip_suffix("/s")?;
+    let unit_start = token.find(|c: char| c.is_ascii_alphabetic())?;
+    let value: f64 = token[..unit_start].parse().ok()?;
+    let multiplier = match &token[unit_start..] {
+        "B" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "KB" => 1000.0,
+        "MB" => 1000.0 * 1000.0,
+        "GB" => 1000.0 * 1000.0 * 1000.0,
+        _ => return None,
+    };
+    Some(value * multiplier)
+}
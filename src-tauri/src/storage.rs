@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::{AppHandle, Runtime};
+
+use crate::audit;
+use crate::crash;
+use crate::deps;
+use crate::download_log;
+use crate::media_cache::{self, MediaCacheSettings};
+
+/// Largest disk use allowed for the app-managed areas that grow without bound. Least
+/// recently modified files are removed first once an area grows past its limit. The
+/// media cache has its own limit in `MediaCacheSettings`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageLimits {
+    /// Per-download yt-dlp logs and crash reports
+    pub max_logs_mb: u64,
+    /// yt-dlp's cache of player code and extractor metadata
+    pub max_metadata_cache_mb: u64,
+}
+
+impl Default for StorageLimits {
+    fn default() -> Self {
+        Self {
+            max_logs_mb: 50,
+            max_metadata_cache_mb: 200,
+        }
+    }
+}
+
+impl StorageLimits {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_logs_mb == 0 || self.max_metadata_cache_mb == 0 {
+            return Err("Storage limits must be at least 1 MB".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageArea {
+    DownloadLogs,
+    CrashReports,
+    /// Rotated at a fixed size, never trimmed by the limits
+    AuditLog,
+    MetadataCache,
+    MediaCache,
+    /// yt-dlp and ffmpeg extracted or downloaded by the app
+    Binaries,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageAreaUsage {
+    pub area: StorageArea,
+    pub path: Option<String>,
+    pub bytes: u64,
+    pub files: usize,
+    /// Size the area is trimmed to, none when it isn't limited. Download logs and crash
+    /// reports share theirs.
+    pub limit_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageUsage {
+    pub areas: Vec<StorageAreaUsage>,
+    pub total_bytes: u64,
+}
+
+/// Files removed to bring the logs and the metadata cache back under their limits
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageCleanup {
+    pub removed_files: usize,
+    pub freed_bytes: u64,
+}
+
+fn megabytes(mb: u64) -> u64 {
+    mb * 1024 * 1024
+}
+
+/// Every file below `dir` with its size and modification time
+fn files_in(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            files.extend(files_in(&entry.path()));
+        } else if metadata.is_file() {
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            files.push((entry.path(), metadata.len(), modified));
+        }
+    }
+    files
+}
+
+/// Remove the least recently modified files of `dirs` until together they fit in
+/// `max_bytes`
+fn trim(dirs: &[PathBuf], max_bytes: u64, cleanup: &mut StorageCleanup) {
+    let mut files: Vec<_> = dirs.iter().flat_map(|dir| files_in(dir)).collect();
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    files.sort_by_key(|(_, _, modified)| *modified);
+    for (path, size, _) in files {
+        if total <= max_bytes {
+            break;
+        }
+        match fs::remove_file(&path) {
+            Ok(_) => {
+                total -= size;
+                cleanup.removed_files += 1;
+                cleanup.freed_bytes += size;
+            }
+            Err(e) => eprintln!("[storage] Failed to remove {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Bring every limited area back under its limit
+pub fn enforce(limits: &StorageLimits, media_cache: Option<&MediaCacheSettings>) -> StorageCleanup {
+    let mut cleanup = StorageCleanup::default();
+    let logs: Vec<PathBuf> = [download_log::get_logs_dir(), crash::get_crash_dir()]
+        .into_iter()
+        .flatten()
+        .collect();
+    trim(&logs, megabytes(limits.max_logs_mb), &mut cleanup);
+    if let Some(dir) = deps::ytdlp_cache_dir() {
+        trim(
+            &[dir],
+            megabytes(limits.max_metadata_cache_mb),
+            &mut cleanup,
+        );
+    }
+    // The media cache keeps downloads in progress out of its own eviction
+    if let Some(settings) = media_cache {
+        media_cache::evict(settings);
+    }
+    if cleanup.removed_files > 0 {
+        eprintln!(
+            "[storage] Removed {} files ({} bytes) over the storage limits",
+            cleanup.removed_files, cleanup.freed_bytes
+        );
+    }
+    cleanup
+}
+
+fn area_usage(
+    area: StorageArea,
+    paths: Vec<PathBuf>,
+    limit_bytes: Option<u64>,
+) -> StorageAreaUsage {
+    let files: Vec<_> = paths
+        .iter()
+        .flat_map(|path| {
+            if path.is_dir() {
+                files_in(path)
+            } else {
+                fs::metadata(path)
+                    .map(|m| vec![(path.clone(), m.len(), SystemTime::UNIX_EPOCH)])
+                    .unwrap_or_default()
+            }
+        })
+        .collect();
+    StorageAreaUsage {
+        area,
+        path: paths.first().map(|path| path.to_string_lossy().to_string()),
+        bytes: files.iter().map(|(_, size, _)| size).sum(),
+        files: files.len(),
+        limit_bytes,
+    }
+}
+
+/// How much disk each app-managed area uses
+pub fn usage<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    limits: &StorageLimits,
+    media_cache: Option<&MediaCacheSettings>,
+) -> StorageUsage {
+    let logs_limit = Some(megabytes(limits.max_logs_mb));
+    let areas = vec![
+        area_usage(
+            StorageArea::DownloadLogs,
+            download_log::get_logs_dir().into_iter().collect(),
+            logs_limit,
+        ),
+        area_usage(
+            StorageArea::CrashReports,
+            crash::get_crash_dir().into_iter().collect(),
+            logs_limit,
+        ),
+        area_usage(StorageArea::AuditLog, audit::log_files(), None),
+        area_usage(
+            StorageArea::MetadataCache,
+            deps::ytdlp_cache_dir().into_iter().collect(),
+            Some(megabytes(limits.max_metadata_cache_mb)),
+        ),
+        area_usage(
+            StorageArea::MediaCache,
+            media_cache::get_cache_dir().into_iter().collect(),
+            media_cache.map(|settings| megabytes(settings.max_size_mb)),
+        ),
+        area_usage(
+            StorageArea::Binaries,
+            deps::get_app_bin_dir(app_handle).into_iter().collect(),
+            None,
+        ),
+    ];
+    StorageUsage {
+        total_bytes: areas.iter().map(|a| a.bytes).sum(),
+        areas,
+    }
+}
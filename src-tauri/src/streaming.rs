@@ -0,0 +1,143 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
+
+use crate::download::{ytdlp_command, DownloadOptions, CANCELLED_MESSAGE};
+use crate::tags;
+
+/// Path the encoder writes to until the whole stream is converted
+fn partial_path(output_path: &Path) -> PathBuf {
+    let mut name = output_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    output_path.with_file_name(name)
+}
+
+/// Download the audio with yt-dlp writing to stdout and encode it with ffmpeg reading
+/// from stdin, so the downloaded .webm/.m4a never touches the disk. Nothing is left
+/// behind when it fails, and the caller can fall back to the usual download.
+/// Without a `bitrate` the audio, already in the output format, is copied into the file
+/// without re-encoding.
+pub async fn download_streamed(
+    ytdlp_cmd: &str,
+    ffmpeg_cmd: &str,
+    url: &str,
+    video_info: &serde_json::Value,
+    output_path: &Path,
+    bitrate: Option<u32>,
+    options: &DownloadOptions,
+) -> Result<(), String> {
+    let partial = partial_path(output_path);
+    let result = tokio::select! {
+        result = pipe(ytdlp_cmd, ffmpeg_cmd, url, video_info, &partial, bitrate, options) => result,
+        // Dropping the pipe kills both processes
+        _ = options.cancel.cancelled() => Err(CANCELLED_MESSAGE.to_string()),
+    };
+    match result {
+        Ok(()) => fs::rename(&partial, output_path)
+            .map_err(|e| format!("Failed to move the converted file into place: {}", e)),
+        Err(e) => {
+            fs::remove_file(&partial).ok();
+            Err(e)
+        }
+    }
+}
+
+async fn pipe(
+    ytdlp_cmd: &str,
+    ffmpeg_cmd: &str,
+    url: &str,
+    video_info: &serde_json::Value,
+    partial: &Path,
+    bitrate: Option<u32>,
+    options: &DownloadOptions,
+) -> Result<(), String> {
+    let mut download = ytdlp_command(ytdlp_cmd, options);
+    download
+        .arg("-f")
+        .arg(options.format_selector())
+        .arg("--no-playlist")
+        .arg("--no-part")
+        .arg("-o")
+        .arg("-")
+        .arg(url)
+        .kill_on_drop(true)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut download = download
+        .spawn()
+        .map_err(|e| format!("Failed to run yt-dlp: {}", e))?;
+
+    let mut encode = Command::new(ffmpeg_cmd);
+    encode
+        .arg("-y")
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-i")
+        .arg("pipe:0")
+        .arg("-vn")
+        .args(options.encoder_args(bitrate.unwrap_or_default(), bitrate.is_none()));
+    encode.args(tags::ffmpeg_args(&options.tags_for(video_info)));
+    encode
+        // The .part extension hides the format from ffmpeg
+        .arg("-f")
+        .arg(options.audio_format.ffmpeg_muxer())
+        .arg(partial)
+        .kill_on_drop(true)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    let mut encode = encode
+        .spawn()
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+    let mut download_out = download.stdout.take().ok_or("yt-dlp has no output pipe")?;
+    let mut encode_in = encode.stdin.take().ok_or("ffmpeg has no input pipe")?;
+    let mut download_err = download.stderr.take().ok_or("yt-dlp has no error pipe")?;
+    let mut encode_err = encode.stderr.take().ok_or("ffmpeg has no error pipe")?;
+
+    let copy = async move {
+        let copied = tokio::io::copy(&mut download_out, &mut encode_in).await;
+        // Closing stdin tells ffmpeg the stream is over, closing stdout stops yt-dlp
+        // from blocking on a full pipe if ffmpeg gave up
+        drop(encode_in);
+        drop(download_out);
+        copied
+    };
+    let read_download_err = async {
+        let mut text = String::new();
+        download_err.read_to_string(&mut text).await.ok();
+        text
+    };
+    let read_encode_err = async {
+        let mut text = String::new();
+        encode_err.read_to_string(&mut text).await.ok();
+        text
+    };
+    let (copied, download_errors, encode_errors) =
+        tokio::join!(copy, read_download_err, read_encode_err);
+
+    let download_status = download
+        .wait()
+        .await
+        .map_err(|e| format!("yt-dlp failed: {}", e))?;
+    let encode_status = encode
+        .wait()
+        .await
+        .map_err(|e| format!("ffmpeg failed: {}", e))?;
+
+    if !download_status.success() {
+        return Err(format!("Download failed: {}", download_errors.trim()));
+    }
+    if !encode_status.success() {
+        return Err(format!("Conversion failed: {}", encode_errors.trim()));
+    }
+    let copied = copied.map_err(|e| format!("Streaming to ffmpeg failed: {}", e))?;
+    if copied == 0 {
+        return Err("yt-dlp streamed no audio".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// How often the load is sampled while a batch runs
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(3);
+
+/// CPU busy fraction above which a worker is taken away
+const HIGH_CPU: f64 = 0.9;
+
+/// CPU busy fraction below which a worker is added
+const LOW_CPU: f64 = 0.7;
+
+/// Fraction of time the CPUs wait for the disk above which a worker is taken away
+const HIGH_IO_WAIT: f64 = 0.25;
+
+/// Fraction of time the CPUs wait for the disk below which a worker may be added
+const LOW_IO_WAIT: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy)]
+pub struct LoadSample {
+    /// Fraction of CPU time spent busy since the previous sample, 0 to 1
+    pub cpu: f64,
+    /// Fraction of CPU time spent waiting for I/O, where the platform reports it
+    pub io_wait: Option<f64>,
+}
+
+/// Samples the system-wide CPU and I/O load, each sample covering the time since the last
+pub struct LoadMonitor {
+    previous: Option<CpuTimes>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CpuTimes {
+    total: u64,
+    idle: u64,
+    io_wait: Option<u64>,
+}
+
+#[cfg(target_os = "linux")]
+fn cpu_times() -> Option<CpuTimes> {
+    let stat = std::fs::read_to_string("/proc/stat").ok()?;
+    let fields: Vec<u64> = stat
+        .lines()
+        .next()?
+        .strip_prefix("cpu ")?
+        .split_whitespace()
+        .map_while(|field| field.parse().ok())
+        .collect();
+    // user nice system idle iowait irq softirq steal, guest time is already in user
+    let total = fields.iter().take(8).sum();
+    let io_wait = *fields.get(4)?;
+    Some(CpuTimes {
+        total,
+        idle: fields[3] + io_wait,
+        io_wait: Some(io_wait),
+    })
+}
+
+#[cfg(windows)]
+fn cpu_times() -> Option<CpuTimes> {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetSystemTimes(idle_time: *mut u64, kernel_time: *mut u64, user_time: *mut u64) -> i32;
+    }
+
+    let (mut idle, mut kernel, mut user) = (0u64, 0u64, 0u64);
+    if unsafe { GetSystemTimes(&mut idle, &mut kernel, &mut user) } == 0 {
+        return None;
+    }
+    // Kernel time includes the idle time
+    Some(CpuTimes {
+        total: kernel + user,
+        idle,
+        io_wait: None,
+    })
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn cpu_times() -> Option<CpuTimes> {
+    None
+}
+
+/// Busy fraction from the one minute load average, for platforms without CPU counters
+#[cfg(all(unix, not(target_os = "linux")))]
+fn load_average() -> Option<f64> {
+    let mut load = [0f64; 1];
+    if unsafe { libc::getloadavg(load.as_mut_ptr(), 1) } != 1 {
+        return None;
+    }
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    Some((load[0] / cpus as f64).min(1.0))
+}
+
+#[cfg(not(all(unix, not(target_os = "linux"))))]
+fn load_average() -> Option<f64> {
+    None
+}
+
+impl LoadMonitor {
+    pub fn new() -> Self {
+        Self {
+            previous: cpu_times(),
+        }
+    }
+
+    /// Load since the previous call, `None` until two readings are available
+    pub fn sample(&mut self) -> Option<LoadSample> {
+        let Some(current) = cpu_times() else {
+            return load_average().map(|cpu| LoadSample { cpu, io_wait: None });
+        };
+        let previous = self.previous.replace(current)?;
+        let total = current.total.saturating_sub(previous.total);
+        if total == 0 {
+            return None;
+        }
+        let idle = current.idle.saturating_sub(previous.idle);
+        let io_wait = current
+            .io_wait
+            .zip(previous.io_wait)
+            .map(|(now, before)| now.saturating_sub(before) as f64 / total as f64);
+        Some(LoadSample {
+            cpu: total.saturating_sub(idle) as f64 / total as f64,
+            io_wait,
+        })
+    }
+}
+
+/// Limit on the number of batch items running at once that moves between 1 and a
+/// maximum with the system load
+pub struct AdaptiveLimit {
+    max: usize,
+    limit: AtomicUsize,
+    permits: Semaphore,
+}
+
+impl AdaptiveLimit {
+    /// Start at `max` when `adaptive` is off, otherwise at 1 and grow while the system
+    /// has room to spare
+    pub fn new(max: usize, adaptive: bool) -> Self {
+        let max = max.max(1);
+        let start = if adaptive { 1 } else { max };
+        Self {
+            max,
+            limit: AtomicUsize::new(start),
+            permits: Semaphore::new(start),
+        }
+    }
+
+    /// Wait for a free slot, held for as long as the permit lives
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        self.permits
+            .acquire()
+            .await
+            .expect("the limit semaphore is never closed")
+    }
+
+    /// Adjust the limit to the load until the returned future is dropped
+    pub async fn adapt(&self) {
+        let mut monitor = LoadMonitor::new();
+        loop {
+            tokio::time::sleep(SAMPLE_INTERVAL).await;
+            let Some(load) = monitor.sample() else {
+                continue;
+            };
+            let limit = self.limit.load(Ordering::SeqCst);
+            let io_wait = load.io_wait.unwrap_or(0.0);
+
+            let new_limit = if (load.cpu > HIGH_CPU || io_wait > HIGH_IO_WAIT) && limit > 1 {
+                // Wait for a running item to hand its slot back, then keep it
+                self.acquire().await.forget();
+                limit - 1
+            } else if load.cpu < LOW_CPU && io_wait < LOW_IO_WAIT && limit < self.max {
+                self.permits.add_permits(1);
+                limit + 1
+            } else {
+                continue;
+            };
+            self.limit.store(new_limit, Ordering::SeqCst);
+            eprintln!(
+                "[load] Concurrency {} -> {} (CPU {:.0}%, I/O wait {:.0}%)",
+                limit,
+                new_limit,
+                load.cpu * 100.0,
+                io_wait * 100.0
+            );
+        }
+    }
+}
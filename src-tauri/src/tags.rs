@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+
+/// Tags set by a channel rule in place of the ones found in the video info
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TagOverrides {
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+}
+
+impl TagOverrides {
+    fn entries(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        [
+            ("artist", &self.artist),
+            ("album", &self.album),
+            ("genre", &self.genre),
+        ]
+        .into_iter()
+        .filter_map(|(tag, value)| Some((tag, value.as_deref()?.trim())))
+        .filter(|(_, value)| !value.is_empty())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries().next().is_none()
+    }
+
+    /// Replace the overridden tags, adding the ones the video info didn't have
+    pub fn apply(&self, tags: &mut Vec<(&'static str, String)>) {
+        for (tag, value) in self.entries() {
+            tags.retain(|(existing, _)| *existing != tag);
+            tags.push((tag, value.to_string()));
+        }
+    }
+
+    /// yt-dlp arguments that make its metadata step write the overridden tags over the
+    /// ones it found. The values are quoted, yt-dlp splits these arguments like a shell.
+    pub fn ytdlp_args(&self) -> Vec<String> {
+        let metadata: Vec<String> = self
+            .entries()
+            .map(|(tag, value)| format!("-metadata '{}={}'", tag, value.replace('\'', "'\\''")))
+            .collect();
+        if metadata.is_empty() {
+            return Vec::new();
+        }
+        vec![
+            "--postprocessor-args".to_string(),
+            format!("Metadata+ffmpeg_o:{}", metadata.join(" ")),
+        ]
+    }
+}
+
+/// Tags for a downloaded video from its `--dump-json` info. Music videos carry the track,
+/// artist and album yt-dlp found, other videos fall back to their title and uploader.
+pub fn from_video_info(video_info: &serde_json::Value) -> Vec<(&'static str, String)> {
+    let first = |fields: &[&str]| {
+        fields
+            .iter()
+            .filter_map(|field| video_info[*field].as_str())
+            .map(str::trim)
+            .find(|value| !value.is_empty())
+            .map(|value| value.to_string())
+    };
+    let year = video_info["release_year"]
+        .as_u64()
+        .map(|year| year.to_string())
+        .or_else(|| first(&["upload_date"]).map(|date| date.chars().take(4).collect()));
+
+    [
+        ("title", first(&["track", "title"])),
+        (
+            "artist",
+            first(&["artist", "creator", "uploader", "channel"]),
+        ),
+        ("album", first(&["album"])),
+        ("album_artist", first(&["album_artist"])),
+        ("genre", first(&["genre"])),
+        ("date", year),
+        ("comment", first(&["webpage_url"])),
+    ]
+    .into_iter()
+    .filter_map(|(tag, value)| value.map(|value| (tag, value)))
+    .collect()
+}
+
+/// ffmpeg `-metadata` arguments writing the tags into the output file
+pub fn ffmpeg_args(tags: &[(&str, String)]) -> Vec<String> {
+    tags.iter()
+        .flat_map(|(tag, value)| ["-metadata".to_string(), format!("{}={}", tag, value)])
+        .collect()
+}
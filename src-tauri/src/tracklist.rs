@@ -0,0 +1,395 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::api::path::cache_dir;
+use tauri::{AppHandle, Runtime};
+
+use crate::audio_format::AudioFormat;
+use crate::download::{self, DownloadOptions};
+use crate::queue;
+use crate::trim;
+
+/// Characters around the title of a tracklist line, such as the dash in `00:00 - Intro`
+const SEPARATORS: &[char] = &['-', '–', '—', '|', ':', '•', '~'];
+
+/// Dashes between the title and the artist, as in `Track – Artist`
+const ARTIST_SEPARATORS: [&str; 3] = [" – ", " — ", " - "];
+
+/// Fewest tracks a description or comment needs before it counts as a tracklist, so a
+/// couple of "skip to" timestamps aren't offered as one
+const MIN_DETECTED_TRACKS: usize = 3;
+
+/// Videos at least this long also have their pinned comment searched for a tracklist
+pub const LONG_VIDEO_SECONDS: f64 = 20.0 * 60.0;
+
+/// Where a detected tracklist was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TracklistSource {
+    Description,
+    PinnedComment,
+}
+
+/// A tracklist found in the video's metadata, offered for splitting the video into
+/// tracks once the user has confirmed it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectedTracklist {
+    pub source: TracklistSource,
+    pub entries: Vec<TracklistEntry>,
+}
+
+/// One track of a tracklist, starting `start` seconds into the video
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TracklistEntry {
+    pub start: f64,
+    pub title: String,
+    pub artist: Option<String>,
+}
+
+/// A track cut out of the downloaded video
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TracklistTrack {
+    pub title: String,
+    pub artist: Option<String>,
+    pub start: f64,
+    pub end: f64,
+    pub output_path: String,
+    pub file_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TracklistResult {
+    /// Title of the video, used as the album of its tracks
+    pub title: Option<String>,
+    pub duration: f64,
+    pub tracks: Vec<TracklistTrack>,
+}
+
+/// `1:02:45`, `02:45` or `2:45` as seconds. Stricter than the clip timestamps of
+/// `download::parse_timestamp`: descriptions are full of other numbers, so only
+/// clock-style timestamps without fractions count here.
+fn parse_timestamp(text: &str) -> Option<f64> {
+    let text = text.trim_matches(|c: char| "[](){}".contains(c));
+    let parts: Vec<&str> = text.split(':').collect();
+    if !(2..=3).contains(&parts.len()) {
+        return None;
+    }
+    let mut seconds = 0u64;
+    for (index, part) in parts.iter().enumerate() {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let value: u64 = part.parse().ok()?;
+        // Everything after the first part is two digits below 60
+        if index > 0 && (part.len() != 2 || value >= 60) {
+            return None;
+        }
+        seconds = seconds * 60 + value;
+    }
+    Some(seconds as f64)
+}
+
+/// A track number in front of the timestamp, like `1.` or `01)`
+fn is_track_number(word: &str) -> bool {
+    let number = word.trim_end_matches(['.', ')']);
+    number.len() < word.len()
+        && (1..=3).contains(&number.len())
+        && number.chars().all(|c| c.is_ascii_digit())
+}
+
+/// A tracklist line with its timestamp at the start, after a track number, or at the
+/// end, such as `00:00 Track – Artist`, `1. [03:15] Track` or `Track - Artist (1:02:45)`.
+/// A range like `00:00 - 03:15` counts from its first timestamp.
+fn parse_line(line: &str) -> Option<(f64, String, Option<String>)> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (start, rest) = match words.as_slice() {
+        [first, rest @ ..] if parse_timestamp(first).is_some() => (parse_timestamp(first)?, rest),
+        [number, second, rest @ ..]
+            if is_track_number(number) && parse_timestamp(second).is_some() =>
+        {
+            (parse_timestamp(second)?, rest)
+        }
+        [rest @ .., last] if parse_timestamp(last).is_some() => (parse_timestamp(last)?, rest),
+        _ => return None,
+    };
+    let rest: Vec<&str> = rest
+        .iter()
+        .copied()
+        .skip_while(|word| word.chars().all(|c| SEPARATORS.contains(&c)))
+        .skip_while(|word| parse_timestamp(word).is_some())
+        .collect();
+    let text = rest.join(" ");
+    let text = text.trim_matches(|c: char| c.is_whitespace() || SEPARATORS.contains(&c));
+    let split = ARTIST_SEPARATORS
+        .iter()
+        .find_map(|separator| text.split_once(separator));
+    let (title, artist) = match split {
+        Some((title, artist)) if !title.trim().is_empty() && !artist.trim().is_empty() => {
+            (title.trim(), Some(artist.trim().to_string()))
+        }
+        _ => (text, None),
+    };
+    Some((start, title.to_string(), artist))
+}
+
+/// The tracklist in a video description or pasted by the user. Lines without a
+/// timestamp are skipped, and so are timestamps that don't come after the previous
+/// track, since descriptions mention other moments of the video too.
+pub fn parse(text: &str) -> Vec<TracklistEntry> {
+    let mut entries: Vec<TracklistEntry> = Vec::new();
+    for (start, title, artist) in text.lines().filter_map(parse_line) {
+        if entries.last().is_some_and(|last| start <= last.start) {
+            continue;
+        }
+        let title = if title.is_empty() {
+            format!("Track {}", entries.len() + 1)
+        } else {
+            title
+        };
+        entries.push(TracklistEntry {
+            start,
+            title,
+            artist,
+        });
+    }
+    entries
+}
+
+/// The tracklist in `text`, when it has enough tracks inside the video to be one.
+/// Timestamps past the end of the video are dropped.
+pub fn detect(
+    text: &str,
+    source: TracklistSource,
+    duration: Option<f64>,
+) -> Option<DetectedTracklist> {
+    let mut entries = parse(text);
+    if let Some(duration) = duration {
+        entries.retain(|e| e.start < duration);
+    }
+    (entries.len() >= MIN_DETECTED_TRACKS).then_some(DetectedTracklist { source, entries })
+}
+
+/// Check that the tracks are in order and all start before the end of the video
+fn validate(entries: &[TracklistEntry], duration: f64) -> Result<(), String> {
+    if entries.is_empty() {
+        return Err("The tracklist has no tracks".to_string());
+    }
+    for (index, pair) in entries.windows(2).enumerate() {
+        if pair[1].start <= pair[0].start {
+            return Err(format!(
+                "Track {} doesn't start after the track before it",
+                index + 2
+            ));
+        }
+    }
+    if let Some((index, entry)) = entries
+        .iter()
+        .enumerate()
+        .find(|(_, e)| e.start < 0.0 || e.start >= duration)
+    {
+        return Err(format!(
+            "Track {} starts at {}s, outside the {}s video",
+            index + 1,
+            entry.start,
+            duration.round()
+        ));
+    }
+    Ok(())
+}
+
+/// Download `url` once into the cache and cut it at the tracklist's timestamps into
+/// `NN - Title` files in `output_folder`, tagged with their title, artist, track number
+/// and the video title as the album
+pub async fn download<R: Runtime>(
+    url: &str,
+    entries: &[TracklistEntry],
+    output_folder: &str,
+    bitrate: u32,
+    audio_format: AudioFormat,
+    options: &DownloadOptions,
+    app_handle: &AppHandle<R>,
+) -> Result<TracklistResult, String> {
+    if entries.is_empty() {
+        return Err("The tracklist has no tracks".to_string());
+    }
+    let dir = cache_dir()
+        .ok_or("Failed to get cache directory")?
+        .join("youtube-downloader")
+        .join("tracklist")
+        .join(queue::new_id("tracklist"));
+    let mut options = options.clone();
+    options.split_chapters = false;
+    options.clip = None;
+    let result = download::download_youtube(
+        url,
+        &dir.to_string_lossy(),
+        bitrate,
+        audio_format,
+        &options,
+        app_handle,
+    )
+    .await;
+    let tracks = match result {
+        Ok(result) => split(result, entries, output_folder, app_handle).await,
+        Err(e) => Err(e),
+    };
+    fs::remove_dir_all(&dir).ok();
+    tracks
+}
+
+async fn split<R: Runtime>(
+    downloaded: download::DownloadResult,
+    entries: &[TracklistEntry],
+    output_folder: &str,
+    app_handle: &AppHandle<R>,
+) -> Result<TracklistResult, String> {
+    let duration = downloaded
+        .duration
+        .ok_or("The length of the video is unknown, so its last track can't be cut")?;
+    validate(entries, duration)?;
+    let ffmpeg_cmd = download::ensure_ffmpeg(app_handle).await?;
+    let input = Path::new(&downloaded.output_path);
+    let extension = input
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_default();
+    fs::create_dir_all(output_folder)
+        .map_err(|e| format!("Failed to create {}: {}", output_folder, e))?;
+
+    let mut tracks = Vec::with_capacity(entries.len());
+    for (index, entry) in entries.iter().enumerate() {
+        let end = entries.get(index + 1).map_or(duration, |next| next.start);
+        let output = Path::new(output_folder).join(format!(
+            "{:02} - {}.{}",
+            index + 1,
+            download::sanitize_filename(&entry.title),
+            extension
+        ));
+        if output.exists() {
+            return Err(format!("{} already exists", output.display()));
+        }
+        let mut tags = vec![
+            ("title", entry.title.clone()),
+            ("track", format!("{}/{}", index + 1, entries.len())),
+        ];
+        if let Some(ref artist) = entry.artist {
+            tags.push(("artist", artist.clone()));
+        }
+        if let Some(ref album) = downloaded.title {
+            tags.push(("album", album.clone()));
+        }
+        let cut = trim::trim(&ffmpeg_cmd, input, entry.start, end, &tags, &output).await?;
+        tracks.push(TracklistTrack {
+            title: entry.title.clone(),
+            artist: entry.artist.clone(),
+            start: entry.start,
+            end,
+            output_path: cut.output_path,
+            file_size: cut.file_size,
+        });
+    }
+    Ok(TracklistResult {
+        title: downloaded.title,
+        duration,
+        tracks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect, is_track_number, parse, parse_timestamp, TracklistEntry, TracklistSource};
+
+    fn entry(start: f64, title: &str, artist: Option<&str>) -> TracklistEntry {
+        TracklistEntry {
+            start,
+            title: title.to_string(),
+            artist: artist.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn parses_clock_timestamps_only() {
+        assert_eq!(parse_timestamp("2:45"), Some(165.0));
+        assert_eq!(parse_timestamp("02:45"), Some(165.0));
+        assert_eq!(parse_timestamp("1:02:45"), Some(3765.0));
+        assert_eq!(parse_timestamp("[03:15]"), Some(195.0));
+        assert_eq!(parse_timestamp("(1:02:45)"), Some(3765.0));
+        assert_eq!(parse_timestamp("90"), None);
+        assert_eq!(parse_timestamp("1:5"), None);
+        assert_eq!(parse_timestamp("1:60"), None);
+        assert_eq!(parse_timestamp("1:30.5"), None);
+        assert_eq!(parse_timestamp("1:02:03:04"), None);
+    }
+
+    #[test]
+    fn recognises_track_numbers() {
+        assert!(is_track_number("1."));
+        assert!(is_track_number("01)"));
+        assert!(!is_track_number("1"));
+        assert!(!is_track_number("2024."));
+    }
+
+    #[test]
+    fn timestamp_first_with_artist() {
+        assert_eq!(
+            parse("00:00 Track – Artist"),
+            vec![entry(0.0, "Track", Some("Artist"))]
+        );
+        assert_eq!(parse("00:00 - Intro"), vec![entry(0.0, "Intro", None)]);
+    }
+
+    #[test]
+    fn timestamp_after_track_number() {
+        assert_eq!(parse("1. [03:15] Track"), vec![entry(195.0, "Track", None)]);
+    }
+
+    #[test]
+    fn timestamp_at_the_end() {
+        assert_eq!(
+            parse("Track - Artist (1:02:45)"),
+            vec![entry(3765.0, "Track", Some("Artist"))]
+        );
+    }
+
+    #[test]
+    fn range_counts_from_its_first_timestamp() {
+        assert_eq!(
+            parse("00:00 - 03:15 Track\n03:15 - 06:00 Next"),
+            vec![entry(0.0, "Track", None), entry(195.0, "Next", None)]
+        );
+    }
+
+    #[test]
+    fn skips_timestamps_that_dont_move_forward() {
+        let text = "00:00 One\n03:00 Two\nBest part at 01:30\n03:00 Again\n05:00 Three";
+        assert_eq!(
+            parse(text),
+            vec![
+                entry(0.0, "One", None),
+                entry(180.0, "Two", None),
+                entry(300.0, "Three", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn names_untitled_tracks() {
+        assert_eq!(
+            parse("00:00\n01:00 Second"),
+            vec![entry(0.0, "Track 1", None), entry(60.0, "Second", None)]
+        );
+    }
+
+    #[test]
+    fn detects_only_real_tracklists() {
+        let text = "00:00 One\n03:00 Two\n05:00 Three\n09:00 Outro";
+        let detected = detect(text, TracklistSource::Description, Some(480.0)).unwrap();
+        assert_eq!(detected.entries.len(), 3);
+        assert!(detect(
+            "Skip to 03:00 for the drop",
+            TracklistSource::Description,
+            None
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,358 @@
+/// Romanize Cyrillic, Greek, Arabic, Japanese kana and Korean Hangul so titles make
+/// usable filenames on devices that cannot render those scripts. Characters without
+/// a simple reading, such as Chinese ideographs, are kept as they are.
+pub fn transliterate(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    // Set after a small `っ`/`ッ`, which doubles the next consonant
+    let mut geminate = false;
+
+    for c in text.chars() {
+        if let Some(romaji) = small_kana_y(c) {
+            // `きゃ` -> `kya`, `しゃ` -> `sha`
+            if out.ends_with("shi") || out.ends_with("chi") || out.ends_with("ji") {
+                out.pop();
+                out.push_str(&romaji[1..]);
+            } else if out.len() >= 2 && out.ends_with('i') {
+                out.pop();
+                out.push_str(romaji);
+            } else {
+                out.push_str(romaji);
+            }
+            continue;
+        }
+
+        if matches!(c, 'っ' | 'ッ') {
+            geminate = true;
+            continue;
+        }
+
+        let romanized = romanize(c);
+        if geminate {
+            geminate = false;
+            if let Some(first) = romanized.as_deref().and_then(|r| r.chars().next()) {
+                if first.is_ascii_alphabetic() && !"aeiou".contains(first) {
+                    out.push(first);
+                }
+            }
+        }
+
+        match romanized {
+            Some(r) => out.push_str(&r),
+            None => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Romanization of a single character, `None` when it should be kept as is
+fn romanize(c: char) -> Option<String> {
+    if c.is_ascii() {
+        return None;
+    }
+    let code = c as u32;
+
+    // Full-width ASCII forms and the ideographic space and punctuation
+    match code {
+        0xFF01..=0xFF5E => return char::from_u32(code - 0xFEE0).map(String::from),
+        0x3000 => return Some(" ".to_string()),
+        0x3001 => return Some(",".to_string()),
+        0x3002 => return Some(".".to_string()),
+        0x30FC => return Some(String::new()),
+        _ => {}
+    }
+
+    if (0xAC00..=0xD7A3).contains(&code) {
+        return Some(hangul(code - 0xAC00));
+    }
+
+    // Katakana share the hiragana layout, 0x60 code points higher
+    let kana = if (0x30A1..=0x30F6).contains(&code) {
+        char::from_u32(code - 0x60).unwrap_or(c)
+    } else {
+        c
+    };
+    if let Some(r) = hiragana(kana) {
+        return Some(r.to_string());
+    }
+
+    let lower = c.to_lowercase().next().unwrap_or(c);
+    let r = cyrillic(lower)
+        .or_else(|| greek(lower))
+        .or_else(|| arabic(c))?;
+
+    if lower != c {
+        // Keep the capital: `Щ` -> `Shch`
+        let mut chars = r.chars();
+        Some(match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        })
+    } else {
+        Some(r.to_string())
+    }
+}
+
+fn cyrillic(c: char) -> Option<&'static str> {
+    Some(match c {
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' => "g",
+        'д' => "d",
+        'е' => "e",
+        'ё' => "yo",
+        'ж' => "zh",
+        'з' => "z",
+        'и' => "i",
+        'й' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ы' => "y",
+        'э' => "e",
+        'ю' => "yu",
+        'я' => "ya",
+        'і' => "i",
+        'ї' => "yi",
+        'є' => "ye",
+        'ґ' => "g",
+        'ў' => "u",
+        'ђ' => "dj",
+        'ј' => "j",
+        'љ' => "lj",
+        'њ' => "nj",
+        'ћ' => "c",
+        'џ' => "dz",
+        'ѓ' => "gj",
+        'ќ' => "kj",
+        'ѕ' => "dz",
+        _ => return None,
+    })
+}
+
+fn greek(c: char) -> Option<&'static str> {
+    Some(match c {
+        'α' | 'ά' => "a",
+        'β' => "v",
+        'γ' => "g",
+        'δ' => "d",
+        'ε' | 'έ' => "e",
+        'ζ' => "z",
+        'η' | 'ή' => "i",
+        'θ' => "th",
+        'ι' | 'ί' | 'ϊ' | 'ΐ' => "i",
+        'κ' => "k",
+        'λ' => "l",
+        'μ' => "m",
+        'ν' => "n",
+        'ξ' => "x",
+        'ο' | 'ό' => "o",
+        'π' => "p",
+        'ρ' => "r",
+        'σ' | 'ς' => "s",
+        'τ' => "t",
+        'υ' | 'ύ' | 'ϋ' | 'ΰ' => "y",
+        'φ' => "f",
+        'χ' => "ch",
+        'ψ' => "ps",
+        'ω' | 'ώ' => "o",
+        _ => return None,
+    })
+}
+
+fn arabic(c: char) -> Option<&'static str> {
+    Some(match c {
+        'ا' | 'أ' | 'ى' | 'ة' => "a",
+        'إ' => "i",
+        'آ' => "aa",
+        'ب' => "b",
+        'ت' => "t",
+        'ث' => "th",
+        'ج' => "j",
+        'ح' | 'ه' => "h",
+        'خ' => "kh",
+        'د' => "d",
+        'ذ' => "dh",
+        'ر' => "r",
+        'ز' => "z",
+        'س' | 'ص' => "s",
+        'ش' => "sh",
+        'ض' => "d",
+        'ط' => "t",
+        'ظ' => "z",
+        'ع' | 'ء' | 'ئ' | 'ؤ' => "'",
+        'غ' => "gh",
+        'ف' => "f",
+        'ق' => "q",
+        'ك' | 'ک' => "k",
+        'ل' => "l",
+        'م' => "m",
+        'ن' => "n",
+        'و' => "w",
+        'ي' | 'ی' => "y",
+        'پ' => "p",
+        'چ' => "ch",
+        'ژ' => "zh",
+        'گ' => "g",
+        '،' => ",",
+        '؟' => "?",
+        // Short vowel and other diacritic marks
+        '\u{064B}'..='\u{0652}' => "",
+        _ => return None,
+    })
+}
+
+/// Small `ゃ`/`ゅ`/`ょ` (and their katakana forms) that combine with the kana before them
+fn small_kana_y(c: char) -> Option<&'static str> {
+    match c {
+        'ゃ' | 'ャ' => Some("ya"),
+        'ゅ' | 'ュ' => Some("yu"),
+        'ょ' | 'ョ' => Some("yo"),
+        _ => None,
+    }
+}
+
+fn hiragana(c: char) -> Option<&'static str> {
+    Some(match c {
+        'あ' | 'ぁ' => "a",
+        'い' | 'ぃ' => "i",
+        'う' | 'ぅ' => "u",
+        'え' | 'ぇ' => "e",
+        'お' | 'ぉ' => "o",
+        'か' => "ka",
+        'き' => "ki",
+        'く' => "ku",
+        'け' => "ke",
+        'こ' => "ko",
+        'が' => "ga",
+        'ぎ' => "gi",
+        'ぐ' => "gu",
+        'げ' => "ge",
+        'ご' => "go",
+        'さ' => "sa",
+        'し' => "shi",
+        'す' => "su",
+        'せ' => "se",
+        'そ' => "so",
+        'ざ' => "za",
+        'じ' | 'ぢ' => "ji",
+        'ず' | 'づ' => "zu",
+        'ぜ' => "ze",
+        'ぞ' => "zo",
+        'た' => "ta",
+        'ち' => "chi",
+        'つ' => "tsu",
+        'て' => "te",
+        'と' => "to",
+        'だ' => "da",
+        'で' => "de",
+        'ど' => "do",
+        'な' => "na",
+        'に' => "ni",
+        'ぬ' => "nu",
+        'ね' => "ne",
+        'の' => "no",
+        'は' => "ha",
+        'ひ' => "hi",
+        'ふ' => "fu",
+        'へ' => "he",
+        'ほ' => "ho",
+        'ば' => "ba",
+        'び' => "bi",
+        'ぶ' => "bu",
+        'べ' => "be",
+        'ぼ' => "bo",
+        'ぱ' => "pa",
+        'ぴ' => "pi",
+        'ぷ' => "pu",
+        'ぺ' => "pe",
+        'ぽ' => "po",
+        'ま' => "ma",
+        'み' => "mi",
+        'む' => "mu",
+        'め' => "me",
+        'も' => "mo",
+        'や' => "ya",
+        'ゆ' => "yu",
+        'よ' => "yo",
+        'ら' => "ra",
+        'り' => "ri",
+        'る' => "ru",
+        'れ' => "re",
+        'ろ' => "ro",
+        'わ' | 'ゎ' => "wa",
+        'を' => "wo",
+        'ん' => "n",
+        'ゔ' => "vu",
+        _ => return None,
+    })
+}
+
+/// Revised Romanization of a precomposed Hangul syllable, given its offset from U+AC00
+fn hangul(index: u32) -> String {
+    const INITIALS: [&str; 19] = [
+        "g", "kk", "n", "d", "tt", "r", "m", "b", "pp", "s", "ss", "", "j", "jj", "ch", "k", "t",
+        "p", "h",
+    ];
+    const VOWELS: [&str; 21] = [
+        "a", "ae", "ya", "yae", "eo", "e", "yeo", "ye", "o", "wa", "wae", "oe", "yo", "u", "wo",
+        "we", "wi", "yu", "eu", "ui", "i",
+    ];
+    const FINALS: [&str; 28] = [
+        "", "k", "k", "k", "n", "n", "n", "t", "l", "k", "m", "l", "l", "l", "p", "l", "m", "p",
+        "p", "t", "t", "ng", "t", "t", "k", "t", "p", "t",
+    ];
+
+    let initial = (index / 588) as usize;
+    let vowel = ((index % 588) / 28) as usize;
+    let last = (index % 28) as usize;
+    format!("{}{}{}", INITIALS[initial], VOWELS[vowel], FINALS[last])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::transliterate;
+
+    #[test]
+    fn romanizes_cyrillic_keeping_capitals() {
+        assert_eq!(transliterate("Привет, мир"), "Privet, mir");
+        assert_eq!(transliterate("Щука"), "Shchuka");
+        assert_eq!(transliterate("Лето 2024"), "Leto 2024");
+    }
+
+    #[test]
+    fn romanizes_greek() {
+        assert_eq!(transliterate("Αθήνα"), "Athina");
+        assert_eq!(transliterate("ψυχή"), "psychi");
+    }
+
+    #[test]
+    fn romanizes_kana_and_hangul() {
+        assert_eq!(transliterate("きょうと"), "kyouto");
+        assert_eq!(transliterate("ロック"), "rokku");
+        assert_eq!(transliterate("서울"), "seoul");
+    }
+
+    #[test]
+    fn keeps_ideographs_and_ascii() {
+        assert_eq!(transliterate("北京"), "北京");
+        assert_eq!(transliterate("東京 ロック"), "東京 rokku");
+        assert_eq!(transliterate("Plain title (Live)"), "Plain title (Live)");
+        assert_eq!(transliterate("ＡＢＣ１２３"), "ABC123");
+    }
+}
@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
+
+use crate::tags;
+
+/// Sample rate the audio is decoded at for the waveform, plenty for drawing peaks
+const WAVEFORM_RATE: usize = 8000;
+
+/// Decoded samples folded into each intermediate peak, 10ms at `WAVEFORM_RATE`
+const SAMPLES_PER_PEAK: usize = WAVEFORM_RATE / 100;
+
+/// Largest number of peaks a waveform can be asked for
+pub const MAX_WAVEFORM_SAMPLES: usize = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Waveform {
+    /// Length of the decoded audio in seconds
+    pub duration: f64,
+    /// Loudest sample of each equally long slice of the file, from 0.0 to 1.0
+    pub peaks: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrimResult {
+    pub output_path: String,
+    pub duration: f64,
+    pub file_size: Option<u64>,
+}
+
+/// Peaks of `path` for a trim editor, decoded to mono with ffmpeg. The audio is read as
+/// it decodes, so hour-long files don't have to fit in memory.
+pub async fn waveform(ffmpeg_cmd: &str, path: &Path, samples: usize) -> Result<Waveform, String> {
+    if !path.is_file() {
+        return Err(format!("File does not exist: {}", path.display()));
+    }
+    if samples == 0 || samples > MAX_WAVEFORM_SAMPLES {
+        return Err(format!(
+            "The waveform needs between 1 and {} samples",
+            MAX_WAVEFORM_SAMPLES
+        ));
+    }
+
+    let mut child = Command::new(ffmpeg_cmd)
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-i")
+        .arg(path)
+        .arg("-vn")
+        .arg("-ac")
+        .arg("1")
+        .arg("-ar")
+        .arg(WAVEFORM_RATE.to_string())
+        .arg("-f")
+        .arg("s16le")
+        .arg("-")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    let mut stdout = child.stdout.take().ok_or("Failed to read ffmpeg output")?;
+
+    let mut fine = Vec::new();
+    let (mut peak, mut in_peak, mut total) = (0u16, 0usize, 0usize);
+    // A read can end halfway through a sample, its first byte waits for the next read
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut carried = 0;
+    loop {
+        let read = stdout
+            .read(&mut buffer[carried..])
+            .await
+            .map_err(|e| format!("Failed to read ffmpeg output: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        let filled = carried + read;
+        for sample in buffer[..filled].chunks_exact(2) {
+            peak = peak.max(i16::from_le_bytes([sample[0], sample[1]]).unsigned_abs());
+            in_peak += 1;
+            total += 1;
+            if in_peak == SAMPLES_PER_PEAK {
+                fine.push(peak);
+                (peak, in_peak) = (0, 0);
+            }
+        }
+        carried = filled % 2;
+        if carried == 1 {
+            buffer[0] = buffer[filled - 1];
+        }
+    }
+    if in_peak > 0 {
+        fine.push(peak);
+    }
+
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to decode audio: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    // Short files have fewer peaks than asked for rather than repeated ones
+    let buckets = samples.min(fine.len());
+    let peaks = (0..buckets)
+        .map(|i| {
+            let slice = &fine[i * fine.len() / buckets..(i + 1) * fine.len() / buckets];
+            let loudest = slice.iter().copied().max().unwrap_or(0);
+            (loudest as f32 / i16::MAX as f32).min(1.0)
+        })
+        .collect();
+    Ok(Waveform {
+        duration: total as f64 / WAVEFORM_RATE as f64,
+        peaks,
+    })
+}
+
+/// Copy the audio between `start` and `end` seconds of `input` into `output`. The
+/// streams are copied without re-encoding, so the cut lands on the nearest audio frame
+/// and the tags and cover art are kept, with `tags` written over them.
+pub async fn trim(
+    ffmpeg_cmd: &str,
+    input: &Path,
+    start: f64,
+    end: f64,
+    tags: &[(&str, String)],
+    output: &Path,
+) -> Result<TrimResult, String> {
+    if !input.is_file() {
+        return Err(format!("File does not exist: {}", input.display()));
+    }
+    if !start.is_finite() || !end.is_finite() || start < 0.0 || end <= start {
+        return Err(format!(
+            "Invalid trim range {}s to {}s, the end must come after the start",
+            start, end
+        ));
+    }
+    let same_file = match (
+        fs::canonicalize(input),
+        output.parent().map(fs::canonicalize),
+    ) {
+        (Ok(input), Some(Ok(parent))) => output
+            .file_name()
+            .is_some_and(|name| parent.join(name) == input),
+        _ => false,
+    };
+    if same_file {
+        return Err("The trimmed file can't replace the file it is cut from".to_string());
+    }
+    let same_extension = input
+        .extension()
+        .zip(output.extension())
+        .is_some_and(|(a, b)| a.eq_ignore_ascii_case(b));
+    if !same_extension {
+        return Err("The trimmed file must keep the extension of the original".to_string());
+    }
+
+    let result = Command::new(ffmpeg_cmd)
+        .arg("-y")
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-ss")
+        .arg(format!("{:.3}", start))
+        .arg("-i")
+        .arg(input)
+        .arg("-t")
+        .arg(format!("{:.3}", end - start))
+        .arg("-map")
+        .arg("0")
+        .arg("-map_metadata")
+        .arg("0")
+        .args(tags::ffmpeg_args(tags))
+        .arg("-c")
+        .arg("copy")
+        .arg(output)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    if !result.status.success() {
+        fs::remove_file(output).ok();
+        return Err(format!(
+            "Failed to trim {}: {}",
+            input.display(),
+            String::from_utf8_lossy(&result.stderr).trim()
+        ));
+    }
+
+    Ok(TrimResult {
+        output_path: output.to_string_lossy().to_string(),
+        duration: end - start,
+        file_size: fs::metadata(output).ok().map(|m| m.len()),
+    })
+}
@@ -0,0 +1,514 @@
+//! Checks the hand-written TypeScript types in `src/store/download/types.ts` against the
+//! Rust structs and enums they mirror, so a field added, renamed or removed on one side
+//! only fails here instead of reaching the UI as `undefined`. Names are taken from
+//! serde itself: the fields or variants a type asks for when it is deserialized, or
+//! the keys it serializes to when it flattens other structs or can't be deserialized.
+
+use serde::de::value::MapDeserializer;
+use serde::de::{self, DeserializeOwned, Deserializer, Visitor};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::advisor::{ErrorCode, FixAdvice, RecommendedFix, Remedy};
+use crate::app_lock::AppLockStatus;
+use crate::audio_format::AudioFormat;
+use crate::audio_preset::AudioPreset;
+use crate::backup::BackupManifest;
+use crate::benchmark::{BenchmarkResult, ConcurrencyResult, EncodeResult};
+use crate::channel_rules::ChannelRule;
+use crate::clipboard::ClipboardUrls;
+use crate::commands::{
+    AppPreferences, DownloadHistory, DownloadResponse, HistoryPage, HistoryVerification,
+    SessionDefaults,
+};
+use crate::compare::{ComparisonCandidate, QualityComparison};
+use crate::connectivity::NetworkWait;
+use crate::conversion::LoudnessNormalization;
+use crate::cookies::CookiesStatus;
+use crate::decisions::{DecisionKind, DecisionRequest};
+use crate::download::{
+    ClipRange, DownloadOverrides, DownloadProgress, DownloadResult, ExtractorArgs, FailedVideo,
+    FolderOrganization, PlayerClient, PlaylistConfirmation, PlaylistDownloadResult, PlaylistEntry,
+    PlaylistEstimate, PlaylistSelection, Politeness, RetryPolicy, SteppedProgress, TransferStats,
+    Verbosity, VideoFormat, VideoInfo,
+};
+use crate::forecast::{JobForecast, QueueForecast};
+use crate::incomplete::{IncompleteDownload, IncompleteScan};
+use crate::install_steps::InstallStep;
+use crate::lyrics::LyricsSettings;
+use crate::manifest::{
+    ManifestExport, ManifestFailure, ManifestFormat, ManifestMismatch, ManifestVerification,
+};
+use crate::media_cache::MediaCacheSettings;
+use crate::media_server::{MediaServerKind, MediaServerSettings};
+use crate::mqtt::MqttSettings;
+use crate::network_sim::NetworkSimulation;
+use crate::preflight::{PreflightProgress, PreflightStage};
+use crate::proxy::{ProxyScheme, ProxySettings};
+use crate::queue::{
+    BatchItemResult, BatchProgress, BatchResult, JobStatus, QueueImport, QueueJob, QueueStateChange,
+};
+use crate::replacement::ReplacementCandidate;
+use crate::sections::SectionedDownload;
+use crate::silence::SilenceTrim;
+use crate::source::{ParsedSource, PastedUrl, RejectedUrl};
+use crate::speed_history::SpeedSample;
+use crate::storage::{StorageArea, StorageAreaUsage, StorageCleanup, StorageLimits, StorageUsage};
+use crate::tags::TagOverrides;
+use crate::tracklist::{
+    DetectedTracklist, TracklistEntry, TracklistResult, TracklistSource, TracklistTrack,
+};
+use crate::trim::{TrimResult, Waveform};
+use crate::version_check::{AvailableUpdates, ReleaseInfo, UpdateInfo};
+use crate::video::{VideoContainer, VideoProgress, VideoStage};
+
+const TYPES_TS: &str = include_str!("../../src/store/download/types.ts");
+
+/// Types with no Rust counterpart: the store state of the frontend, and the event
+/// wrappers built inline with `json!` where they are sent
+const FRONTEND_ONLY: &[&str] = &["DownloadState", "QueueJobProgress", "VersionedEvent"];
+
+/// What a type asked `Introspect` for, or why it couldn't be introspected
+#[derive(Debug)]
+enum Found {
+    Names(&'static [&'static str]),
+    Error(String),
+}
+
+impl fmt::Display for Found {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Found::Names(names) => write!(f, "{:?}", names),
+            Found::Error(e) => f.write_str(e),
+        }
+    }
+}
+
+impl std::error::Error for Found {}
+
+impl de::Error for Found {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Found::Error(msg.to_string())
+    }
+
+    fn unknown_variant(_: &str, expected: &'static [&'static str]) -> Self {
+        Found::Names(expected)
+    }
+}
+
+/// Deserializer with no data, which stops at the first thing a type asks for: the
+/// fields of a struct, the variants of an enum or, given its tag, the tags of an
+/// internally tagged enum
+struct Introspect(Option<&'static str>);
+
+impl<'de> Deserializer<'de> for Introspect {
+    type Error = Found;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Found> {
+        match self.0 {
+            // The empty tag is an unknown variant, reported with the known ones
+            Some(tag) => visitor.visit_map(MapDeserializer::new(std::iter::once((tag, "")))),
+            None => Err(Found::Error("not a plain struct or enum".to_string())),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Found> {
+        Err(Found::Names(fields))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Found> {
+        Err(Found::Names(variants))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+        byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map
+        identifier ignored_any
+    }
+}
+
+/// Field names of a struct or variant names of an enum as serde reads them
+fn names<T: DeserializeOwned>() -> BTreeSet<String> {
+    tagged_names::<T>(None)
+}
+
+fn tagged_names<T: DeserializeOwned>(tag: Option<&'static str>) -> BTreeSet<String> {
+    match T::deserialize(Introspect(tag)) {
+        Err(Found::Names(names)) => names.iter().map(|n| n.to_string()).collect(),
+        Err(Found::Error(e)) => panic!("{}: {}", std::any::type_name::<T>(), e),
+        Ok(_) => unreachable!(),
+    }
+}
+
+/// Keys of `value` serialized as a JSON object
+fn keys(value: &impl Serialize) -> BTreeSet<String> {
+    match serde_json::to_value(value).unwrap() {
+        serde_json::Value::Object(map) => map.keys().cloned().collect(),
+        other => panic!("{} doesn't serialize to an object", other),
+    }
+}
+
+/// Bodies of the `export type`s of types.ts by name
+fn ts_types() -> BTreeMap<&'static str, &'static str> {
+    TYPES_TS
+        .split("export type ")
+        .skip(1)
+        .map(|definition| {
+            let (name, body) = definition.split_once('=').unwrap();
+            let name = name.split('<').next().unwrap().trim();
+            (name, body.trim())
+        })
+        .collect()
+}
+
+/// Field names in an object type literal like `{ kind: "video"; video_id: string }`
+fn object_fields(text: &str) -> BTreeSet<String> {
+    text.split([';', '\n'])
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, _)| {
+            name.trim()
+                .trim_start_matches('{')
+                .trim()
+                .trim_end_matches('?')
+        })
+        .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// String literals of a type, the variants of a union like `"mp3" | "opus"`
+fn literals(body: &str) -> BTreeSet<String> {
+    body.split('"')
+        .skip(1)
+        .step_by(2)
+        .map(str::to_string)
+        .collect()
+}
+
+struct Checker {
+    types: BTreeMap<&'static str, &'static str>,
+    checked: BTreeSet<&'static str>,
+    mismatches: Vec<String>,
+}
+
+impl Checker {
+    fn body(&mut self, name: &'static str) -> &'static str {
+        self.checked.insert(name);
+        self.types
+            .get(name)
+            .copied()
+            .unwrap_or_else(|| panic!("types.ts has no type {}", name))
+    }
+
+    /// Fields of the object type `name`, with those of the types it is joined to by `&`
+    fn ts_fields(&mut self, name: &'static str) -> BTreeSet<String> {
+        let body = self.body(name);
+        let (joined, object) = body.split_once('{').unwrap_or((body, ""));
+        let mut fields = object_fields(object);
+        for other in joined.split('&').map(str::trim).filter(|n| !n.is_empty()) {
+            let other = *self.types.get_key_value(other).unwrap().0;
+            fields.extend(self.ts_fields(other));
+        }
+        fields
+    }
+
+    fn compare(&mut self, name: &str, what: &str, rust: BTreeSet<String>, ts: BTreeSet<String>) {
+        if rust != ts {
+            self.mismatches.push(format!(
+                "{}: {} only in Rust {:?}, only in TypeScript {:?}",
+                name,
+                what,
+                rust.difference(&ts).collect::<Vec<_>>(),
+                ts.difference(&rust).collect::<Vec<_>>()
+            ));
+        }
+    }
+
+    fn fields(&mut self, name: &'static str, rust: BTreeSet<String>) {
+        let ts = self.ts_fields(name);
+        self.compare(name, "fields", rust, ts);
+    }
+
+    fn variants(&mut self, name: &'static str, rust: BTreeSet<String>) {
+        let ts = literals(self.body(name));
+        self.compare(name, "variants", rust, ts);
+    }
+
+    /// An internally tagged enum of struct variants, whose TypeScript union has one
+    /// object literal per variant
+    fn tagged(&mut self, name: &'static str, tag: &str, values: &[impl Serialize]) {
+        let ts: BTreeMap<String, BTreeSet<String>> = self
+            .body(name)
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("| "))
+            .map(|member| {
+                let fields = object_fields(member);
+                let tag = member.split('"').nth(1).unwrap_or_default().to_string();
+                (tag, fields)
+            })
+            .collect();
+        for value in values {
+            let value = serde_json::to_value(value).unwrap();
+            let variant = value[tag].as_str().unwrap().to_string();
+            let fields = value.as_object().unwrap().keys().cloned().collect();
+            let expected = ts.get(&variant).cloned().unwrap_or_default();
+            self.compare(&format!("{} {}", name, variant), "fields", fields, expected);
+        }
+        let rust = values
+            .iter()
+            .map(|v| {
+                serde_json::to_value(v).unwrap()[tag]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        self.compare(name, "variants", rust, ts.into_keys().collect());
+    }
+
+    fn finish(self) {
+        let unchecked: Vec<_> = self
+            .types
+            .keys()
+            .filter(|name| !self.checked.contains(*name) && !FRONTEND_ONLY.contains(name))
+            .collect();
+        assert!(
+            unchecked.is_empty(),
+            "types.ts types not compared with Rust: {:?}",
+            unchecked
+        );
+        assert!(self.mismatches.is_empty(), "{}", self.mismatches.join("\n"));
+    }
+}
+
+fn queue_job() -> QueueJob {
+    QueueJob {
+        id: String::new(),
+        url: String::new(),
+        output_folder: String::new(),
+        bitrate: 0,
+        overrides: DownloadOverrides::default(),
+        status: JobStatus::Queued,
+        added_at: String::new(),
+        started_at: None,
+        finished_at: None,
+        error: None,
+        output_paths: Vec::new(),
+    }
+}
+
+#[test]
+fn typescript_types_match_the_rust_payloads() {
+    let mut check = Checker {
+        types: ts_types(),
+        checked: BTreeSet::new(),
+        mismatches: Vec::new(),
+    };
+
+    check.fields("DownloadHistory", names::<DownloadHistory>());
+    check.fields("HistoryVerification", names::<HistoryVerification>());
+    check.fields("HistoryPage", names::<HistoryPage>());
+    check.fields("DownloadResult", names::<DownloadResult>());
+    check.fields("PlaylistDownloadResult", names::<PlaylistDownloadResult>());
+    check.fields("FailedVideo", names::<FailedVideo>());
+    check.fields("RetryPolicy", names::<RetryPolicy>());
+    check.fields("PlaylistConfirmation", names::<PlaylistConfirmation>());
+    check.fields("VideoFormat", names::<VideoFormat>());
+    check.fields("VideoInfo", names::<VideoInfo>());
+    check.variants("TracklistSource", names::<TracklistSource>());
+    check.fields("DetectedTracklist", names::<DetectedTracklist>());
+    check.fields("PlaylistEntry", names::<PlaylistEntry>());
+    check.fields("ReplacementCandidate", names::<ReplacementCandidate>());
+    check.fields("PlaylistEstimate", names::<PlaylistEstimate>());
+    let response = literals(check.body("DownloadResponse"));
+    check.compare(
+        "DownloadResponse",
+        "variants",
+        tagged_names::<DownloadResponse>(Some("type")),
+        response,
+    );
+    check.variants("PlayerClient", names::<PlayerClient>());
+    check.fields("ExtractorArgs", names::<ExtractorArgs>());
+    check.fields("Politeness", names::<Politeness>());
+    check.fields("MediaCacheSettings", names::<MediaCacheSettings>());
+    check.fields("SectionedDownload", names::<SectionedDownload>());
+    check.fields("NetworkSimulation", names::<NetworkSimulation>());
+    check.fields("StorageLimits", names::<StorageLimits>());
+    check.variants("StorageArea", names::<StorageArea>());
+    check.fields("StorageAreaUsage", names::<StorageAreaUsage>());
+    check.fields("StorageUsage", names::<StorageUsage>());
+    check.fields("StorageCleanup", names::<StorageCleanup>());
+    check.fields("BackupManifest", names::<BackupManifest>());
+    check.variants("ManifestFormat", names::<ManifestFormat>());
+    check.fields("ManifestFailure", names::<ManifestFailure>());
+    check.fields("ManifestExport", names::<ManifestExport>());
+    check.fields("ManifestMismatch", names::<ManifestMismatch>());
+    check.fields("ManifestVerification", names::<ManifestVerification>());
+    check.fields("InstallStep", names::<InstallStep>());
+    check.variants("Verbosity", names::<Verbosity>());
+    check.variants("FolderOrganization", names::<FolderOrganization>());
+    check.fields("AppPreferences", names::<AppPreferences>());
+    check.fields("SessionDefaults", names::<SessionDefaults>());
+    let progress = DownloadProgress {
+        overall_progress: 0.0,
+        current_song: None,
+        total_songs: None,
+        song_progress: 0.0,
+        status: String::new(),
+        current_title: None,
+        estimated_size: None,
+        transfer: TransferStats::default(),
+    };
+    let progress = SteppedProgress {
+        progress: &progress,
+        step: 0,
+        total_steps: 0,
+    };
+    check.fields("DownloadProgressEvent", keys(&progress));
+    check.fields("SpeedSample", names::<SpeedSample>());
+    check.variants("PreflightStage", names::<PreflightStage>());
+    check.fields("PreflightProgress", names::<PreflightProgress>());
+    check.fields("AppLockStatus", names::<AppLockStatus>());
+    check.fields("ReleaseInfo", names::<ReleaseInfo>());
+    check.fields("UpdateInfo", names::<UpdateInfo>());
+    check.fields("AvailableUpdates", names::<AvailableUpdates>());
+    check.variants("ErrorCode", names::<ErrorCode>());
+    check.variants("Remedy", names::<Remedy>());
+    check.fields("RecommendedFix", names::<RecommendedFix>());
+    check.fields("FixAdvice", names::<FixAdvice>());
+    check.variants("JobStatus", names::<JobStatus>());
+    check.fields("QueueJob", keys(&queue_job()));
+    check.fields("RejectedUrl", names::<RejectedUrl>());
+    check.fields(
+        "QueueImport",
+        keys(&QueueImport {
+            added: Vec::new(),
+            duplicates: Vec::new(),
+            rejected: Vec::new(),
+        }),
+    );
+    check.fields("EncodeResult", names::<EncodeResult>());
+    check.fields("ConcurrencyResult", names::<ConcurrencyResult>());
+    check.fields("BenchmarkResult", names::<BenchmarkResult>());
+    check.variants("ProxyScheme", names::<ProxyScheme>());
+    check.fields("ProxySettings", names::<ProxySettings>());
+    check.fields("CookiesStatus", names::<CookiesStatus>());
+    check.variants("AudioFormat", names::<AudioFormat>());
+    check.variants("VideoContainer", names::<VideoContainer>());
+    check.variants("VideoStage", names::<VideoStage>());
+    check.fields("VideoProgress", names::<VideoProgress>());
+    check.variants("MediaServerKind", names::<MediaServerKind>());
+    check.fields("MediaServerSettings", names::<MediaServerSettings>());
+    check.fields("MqttSettings", names::<MqttSettings>());
+    check.fields("LyricsSettings", names::<LyricsSettings>());
+    check.fields("LoudnessNormalization", names::<LoudnessNormalization>());
+    check.fields("SilenceTrim", names::<SilenceTrim>());
+    check.variants("AudioPreset", names::<AudioPreset>());
+    check.fields("PlaylistSelection", names::<PlaylistSelection>());
+    check.fields("ClipRange", names::<ClipRange>());
+    check.fields("DownloadOverrides", keys(&DownloadOverrides::default()));
+    check.fields("TagOverrides", names::<TagOverrides>());
+    check.fields("ChannelRule", keys(&ChannelRule::default()));
+    check.fields("Waveform", names::<Waveform>());
+    check.fields("TracklistEntry", names::<TracklistEntry>());
+    check.fields("TracklistTrack", names::<TracklistTrack>());
+    check.fields("TracklistResult", names::<TracklistResult>());
+    check.fields("TrimResult", names::<TrimResult>());
+    check.fields(
+        "QueueStateChange",
+        keys(&QueueStateChange {
+            action: "pause_all",
+            changed: Vec::new(),
+            jobs: Vec::new(),
+        }),
+    );
+    check.fields(
+        "BatchProgress",
+        keys(&BatchProgress {
+            batch_id: String::new(),
+            total: 0,
+            finished: 0,
+            failed: 0,
+            overall_progress: 0.0,
+        }),
+    );
+    check.fields(
+        "BatchItemResult",
+        keys(&BatchItemResult {
+            url: String::new(),
+            job_id: None,
+            status: JobStatus::Queued,
+            output_paths: Vec::new(),
+            error: None,
+        }),
+    );
+    check.fields("JobForecast", names::<JobForecast>());
+    check.fields("QueueForecast", names::<QueueForecast>());
+    check.fields(
+        "BatchResult",
+        keys(&BatchResult {
+            batch_id: String::new(),
+            items: Vec::new(),
+            liner_notes_path: None,
+        }),
+    );
+    check.tagged(
+        "ParsedSource",
+        "kind",
+        &[
+            ParsedSource::Video {
+                video_id: String::new(),
+            },
+            ParsedSource::Playlist {
+                list_id: String::new(),
+                video_id: None,
+            },
+            ParsedSource::Channel {
+                channel: String::new(),
+            },
+            ParsedSource::External {
+                host: String::new(),
+                url: String::new(),
+            },
+        ],
+    );
+    check.fields("PastedUrl", names::<PastedUrl>());
+    check.fields("ClipboardUrls", keys(&ClipboardUrls { urls: Vec::new() }));
+    check.fields(
+        "NetworkWait",
+        keys(&NetworkWait {
+            url: String::new(),
+            waiting: false,
+            resumed: false,
+            attempt: 0,
+            max_attempts: 0,
+        }),
+    );
+    let incomplete = IncompleteDownload {
+        id: String::new(),
+        url: String::new(),
+        output_folder: String::new(),
+        bitrate: 0,
+        overrides: DownloadOverrides::default(),
+        started_at: String::new(),
+    };
+    check.fields("IncompleteDownload", keys(&incomplete));
+    check.fields("IncompleteScan", names::<IncompleteScan>());
+    check.fields("ComparisonCandidate", names::<ComparisonCandidate>());
+    check.fields("QualityComparison", names::<QualityComparison>());
+    check.variants("DecisionKind", names::<DecisionKind>());
+    check.fields("DecisionRequest", names::<DecisionRequest>());
+
+    check.finish();
+}
@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::BuildHasher;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::api::path::config_dir;
+use tauri::AppHandle;
+
+use crate::deps;
+use crate::proxy;
+
+/// Minimum time between two release checks of the same repository
+const CHECK_INTERVAL_HOURS: i64 = 24;
+
+/// Random delay added on top of the interval, so installs don't all hit GitHub at once
+const MAX_JITTER_MINUTES: u64 = 120;
+
+/// How often the background service looks at whether a check is due
+const SERVICE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+const YTDLP_REPO: &str = "yt-dlp/yt-dlp";
+const APP_REPO: &str = "datdt1935/tauri-youtube-mp3";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseInfo {
+    pub tag: String,
+    pub name: Option<String>,
+    pub url: String,
+    pub published_at: Option<String>,
+}
+
+/// Last answer GitHub gave for a repository, reused until the next check is due
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedRelease {
+    etag: Option<String>,
+    checked_at: String,
+    next_check_at: String,
+    release: Option<ReleaseInfo>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateInfo {
+    pub current_version: Option<String>,
+    pub latest: Option<ReleaseInfo>,
+    pub update_available: bool,
+    pub checked_at: Option<String>,
+    /// Why the last check failed, if it did
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvailableUpdates {
+    pub ytdlp: UpdateInfo,
+    pub app: UpdateInfo,
+}
+
+/// Only one check at a time, so the service and the settings screen don't race
+static CHECK_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+static CACHE_LOCK: Mutex<()> = Mutex::new(());
+
+fn get_cache_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("youtube-downloader").join("version_check.json"))
+}
+
+fn load_cache() -> HashMap<String, CachedRelease> {
+    get_cache_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn store_cached(repo: &str, cached: CachedRelease) {
+    let _lock = CACHE_LOCK.lock().unwrap();
+    let mut cache = load_cache();
+    cache.insert(repo.to_string(), cached);
+    let saved = get_cache_path()
+        .ok_or("Failed to get config directory".to_string())
+        .and_then(|path| {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let content = serde_json::to_string_pretty(&cache).map_err(|e| e.to_string())?;
+            fs::write(path, content).map_err(|e| e.to_string())
+        });
+    if let Err(e) = saved {
+        eprintln!("[updates] Failed to save version check cache: {}", e);
+    }
+}
+
+fn jitter() -> chrono::Duration {
+    let random = RandomState::new().hash_one(chrono::Utc::now().timestamp_nanos_opt());
+    chrono::Duration::minutes((random % (MAX_JITTER_MINUTES + 1)) as i64)
+}
+
+fn is_due(cached: Option<&CachedRelease>) -> bool {
+    cached
+        .and_then(|c| chrono::DateTime::parse_from_rfc3339(&c.next_check_at).ok())
+        .is_none_or(|next| chrono::Utc::now() >= next)
+}
+
+/// Ask GitHub for the latest release of `repo`, sending the cached ETag so an
+/// unchanged release costs no rate limit
+async fn fetch_latest(
+    repo: &str,
+    cached: Option<&CachedRelease>,
+) -> Result<(Option<String>, Option<ReleaseInfo>), String> {
+    let client = proxy::http_client(Duration::from_secs(20), Some(Duration::from_secs(20)))?;
+    let mut request = client
+        .get(format!(
+            "https://api.github.com/repos/{}/releases/latest",
+            repo
+        ))
+        .header("Accept", "application/vnd.github+json");
+    if let Some(etag) = cached.and_then(|c| c.etag.as_deref()) {
+        request = request.header("If-None-Match", etag);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach GitHub: {}", e))?;
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok((
+            cached.and_then(|c| c.etag.clone()),
+            cached.and_then(|c| c.release.clone()),
+        ));
+    }
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        // The repository has not published a release yet
+        return Ok((None, None));
+    }
+    let response = response
+        .error_for_status()
+        .map_err(|e| format!("GitHub returned an error: {}", e))?;
+
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read the GitHub response: {}", e))?;
+    let body: serde_json::Value = serde_json::from_str(&body)
+        .map_err(|e| format!("Failed to parse the GitHub response: {}", e))?;
+    let release = body["tag_name"].as_str().map(|tag| ReleaseInfo {
+        tag: tag.to_string(),
+        name: body["name"].as_str().map(|s| s.to_string()),
+        url: body["html_url"].as_str().unwrap_or_default().to_string(),
+        published_at: body["published_at"].as_str().map(|s| s.to_string()),
+    });
+    Ok((etag, release))
+}
+
+/// Check `repo` if its check is due (or `force` is set), returning the cached entry
+async fn check_repo(repo: &str, force: bool) -> Option<CachedRelease> {
+    let cached = load_cache().remove(repo);
+    if !force && !is_due(cached.as_ref()) {
+        return cached;
+    }
+
+    let now = chrono::Utc::now();
+    let next_check_at =
+        (now + chrono::Duration::hours(CHECK_INTERVAL_HOURS) + jitter()).to_rfc3339();
+    let updated = match fetch_latest(repo, cached.as_ref()).await {
+        Ok((etag, release)) => CachedRelease {
+            etag,
+            checked_at: now.to_rfc3339(),
+            next_check_at,
+            release,
+            error: None,
+        },
+        Err(e) => {
+            eprintln!("[updates] Release check for {} failed: {}", repo, e);
+            // Keep what we knew, and wait for the next interval instead of retrying hot
+            CachedRelease {
+                etag: cached.as_ref().and_then(|c| c.etag.clone()),
+                checked_at: now.to_rfc3339(),
+                next_check_at,
+                release: cached.and_then(|c| c.release),
+                error: Some(e),
+            }
+        }
+    };
+    store_cached(repo, updated.clone());
+    Some(updated)
+}
+
+/// Numeric parts of a version such as `v0.1.0` or `2024.08.06`
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .trim()
+        .trim_start_matches(['v', 'V'])
+        .split(['.', '-', '+'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+fn update_info(current_version: Option<String>, cached: Option<CachedRelease>) -> UpdateInfo {
+    let latest = cached.as_ref().and_then(|c| c.release.clone());
+    let update_available = match (&current_version, &latest) {
+        (Some(current), Some(latest)) => {
+            let (current, latest) = (version_parts(current), version_parts(&latest.tag));
+            !latest.is_empty() && latest > current
+        }
+        _ => false,
+    };
+    UpdateInfo {
+        current_version,
+        latest,
+        update_available,
+        checked_at: cached.as_ref().map(|c| c.checked_at.clone()),
+        error: cached.and_then(|c| c.error),
+    }
+}
+
+/// Latest yt-dlp and app releases compared with what is installed. GitHub is only
+/// asked when a repository's daily check is due, unless `force` is set.
+pub async fn available_updates(app_handle: &AppHandle, force: bool) -> AvailableUpdates {
+    let _lock = CHECK_LOCK.lock().await;
+    let ytdlp_release = check_repo(YTDLP_REPO, force).await;
+    let app_release = check_repo(APP_REPO, force).await;
+
+    let handle = app_handle.clone();
+    let ytdlp_version = tauri::async_runtime::spawn_blocking(move || {
+        let path = deps::get_bundled_binary(&handle, "yt-dlp").ok()?;
+        deps::binary_version(&path, "yt-dlp")
+    })
+    .await
+    .ok()
+    .flatten();
+
+    AvailableUpdates {
+        ytdlp: update_info(ytdlp_version, ytdlp_release),
+        app: update_info(
+            Some(app_handle.package_info().version.to_string()),
+            app_release,
+        ),
+    }
+}
+
+/// Keep the cached release information fresh in the background, checking each
+/// repository at most once a day
+pub fn start_service(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            available_updates(&app_handle, false).await;
+            tokio::time::sleep(SERVICE_INTERVAL).await;
+        }
+    });
+}
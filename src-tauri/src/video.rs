@@ -0,0 +1,335 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Runtime};
+
+use crate::download::{
+    ensure_ffmpeg, ensure_ytdlp, file_stem_for, forward_lines, output_template,
+    parse_progress_percent, remove_partial_files, run_output, sanitize_filename,
+    with_workaround_hint, ytdlp_command, DownloadOptions, DownloadResult, ProgressReport,
+    CANCELLED_MESSAGE, PROGRESS_TEMPLATE,
+};
+use crate::events::{self, Event};
+use crate::source;
+use crate::speed_history;
+
+/// Container the video and audio streams are merged into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VideoContainer {
+    #[default]
+    Mp4,
+    Mkv,
+}
+
+impl VideoContainer {
+    pub fn extension(self) -> &'static str {
+        match self {
+            VideoContainer::Mp4 => "mp4",
+            VideoContainer::Mkv => "mkv",
+        }
+    }
+
+    /// Format selector for the best video and audio, preferring streams MP4 players
+    /// can play when merging into MP4
+    fn format_selector(self) -> &'static str {
+        match self {
+            VideoContainer::Mp4 => "bestvideo[ext=mp4]+bestaudio[ext=m4a]/bestvideo+bestaudio/best",
+            VideoContainer::Mkv => "bestvideo+bestaudio/best",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VideoStage {
+    Video,
+    Audio,
+    Merging,
+    Completed,
+    Cancelled,
+}
+
+/// Emitted as `video-download-progress` while a video downloads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoProgress {
+    pub url: String,
+    pub title: Option<String>,
+    pub stage: VideoStage,
+    /// Progress of the stream being downloaded, 0 to 100
+    pub stream_progress: f64,
+    /// Progress of the whole download, 0 to 100
+    pub overall_progress: f64,
+}
+
+/// Share of the overall progress taken by the video stream, the audio stream and the merge
+const VIDEO_SHARE: f64 = 80.0;
+const AUDIO_SHARE: f64 = 15.0;
+
+impl VideoProgress {
+    fn new(url: &str, title: Option<&str>, stage: VideoStage, stream_progress: f64) -> Self {
+        let overall_progress = match stage {
+            VideoStage::Video => stream_progress * VIDEO_SHARE / 100.0,
+            VideoStage::Audio => VIDEO_SHARE + stream_progress * AUDIO_SHARE / 100.0,
+            VideoStage::Merging => VIDEO_SHARE + AUDIO_SHARE,
+            VideoStage::Completed => 100.0,
+            VideoStage::Cancelled => 0.0,
+        };
+        Self {
+            url: url.to_string(),
+            title: title.map(|t| t.to_string()),
+            stage,
+            stream_progress,
+            overall_progress,
+        }
+    }
+}
+
+fn emit_progress<R: Runtime>(app_handle: &AppHandle<R>, progress: VideoProgress) {
+    events::emit(app_handle, Event::VideoDownloadProgress, &progress);
+}
+
+/// Download the best video and audio of `url` and merge them into `container`
+pub async fn download_video<R: Runtime>(
+    url: &str,
+    output_folder: &str,
+    container: VideoContainer,
+    options: &DownloadOptions,
+    app_handle: &AppHandle<R>,
+) -> Result<DownloadResult, String> {
+    let started = std::time::SystemTime::now();
+    // Names the download's files start with, for removing its partial files on cancel
+    let mut names = Vec::new();
+    let result = download(
+        url,
+        output_folder,
+        container,
+        options,
+        app_handle,
+        &mut names,
+    )
+    .await;
+    if options.cancel.is_cancelled() {
+        remove_partial_files(Path::new(output_folder), &names, started);
+        emit_progress(
+            app_handle,
+            VideoProgress::new(url, None, VideoStage::Cancelled, 0.0),
+        );
+        return Err(CANCELLED_MESSAGE.to_string());
+    }
+    result
+}
+
+async fn download<R: Runtime>(
+    url: &str,
+    output_folder: &str,
+    container: VideoContainer,
+    options: &DownloadOptions,
+    app_handle: &AppHandle<R>,
+    names: &mut Vec<String>,
+) -> Result<DownloadResult, String> {
+    if source::parse_source(url)?.is_playlist() {
+        return Err("Video downloads take a single video URL, not a playlist".to_string());
+    }
+    let ytdlp_cmd = ensure_ytdlp(app_handle)
+        .await
+        .map_err(|e| format!("Failed to get bundled yt-dlp: {}", e))?;
+    let ffmpeg_cmd = ensure_ffmpeg(app_handle)
+        .await
+        .map_err(|e| format!("Failed to get bundled ffmpeg: {}", e))?;
+    let ffmpeg_dir = Path::new(&ffmpeg_cmd)
+        .parent()
+        .ok_or("Failed to get ffmpeg directory")?;
+
+    let mut info_cmd = ytdlp_command(&ytdlp_cmd, options);
+    info_cmd.arg("--dump-json").arg("--no-playlist").arg(url);
+    let info_output = run_output(&mut info_cmd, &options.cancel)
+        .await
+        .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
+    if !info_output.status.success() {
+        return Err(with_workaround_hint(format!(
+            "Failed to get video info: {}",
+            String::from_utf8_lossy(&info_output.stderr)
+        )));
+    }
+    let video_info: serde_json::Value = serde_json::from_slice(&info_output.stdout)
+        .map_err(|e| format!("Failed to parse video info JSON: {}", e))?;
+
+    let title = video_info["title"].as_str().map(sanitize_filename);
+    let duration = video_info["duration"].as_f64();
+    let file_stem = video_info["title"]
+        .as_str()
+        .map(|s| file_stem_for(s, options));
+    let expected_path = Path::new(output_folder).join(format!(
+        "{}.{}",
+        file_stem
+            .as_deref()
+            .or(video_info["id"].as_str())
+            .unwrap_or("video"),
+        container.extension()
+    ));
+    if expected_path.exists() {
+        return Ok(result(&expected_path, title, duration));
+    }
+    names.extend(
+        [
+            file_stem.as_deref(),
+            video_info["title"].as_str(),
+            video_info["id"].as_str(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(str::to_string),
+    );
+
+    let mut cmd = ytdlp_command(&ytdlp_cmd, options);
+    cmd.arg("-f")
+        .arg(container.format_selector())
+        .arg("--merge-output-format")
+        .arg(container.extension())
+        // A single-file fallback format still ends up in the chosen container
+        .arg("--remux-video")
+        .arg(container.extension())
+        .arg("--no-playlist")
+        .arg("--newline")
+        // Progress lines are still wanted next to the printed path
+        .arg("--progress")
+        .arg("--progress-template")
+        .arg(PROGRESS_TEMPLATE)
+        .arg("--print")
+        .arg("after_move:filepath")
+        .arg("--no-simulate")
+        .arg("-o")
+        .arg(output_template(
+            Path::new(output_folder),
+            file_stem.as_deref().filter(|_| options.transliterate),
+        ))
+        .arg("--ffmpeg-location")
+        .arg(ffmpeg_dir)
+        .arg(url);
+
+    let (success, output, reported_path) = run_with_progress(
+        &mut cmd,
+        url,
+        title.as_deref(),
+        video_info["id"].as_str(),
+        options,
+        app_handle,
+    )
+    .await?;
+    if !success {
+        return Err(with_workaround_hint(format!("Download failed: {}", output)));
+    }
+
+    let output_path = reported_path
+        .filter(|path| path.exists())
+        .unwrap_or(expected_path);
+    emit_progress(
+        app_handle,
+        VideoProgress::new(url, title.as_deref(), VideoStage::Completed, 100.0),
+    );
+    Ok(result(&output_path, title, duration))
+}
+
+fn result(path: &Path, title: Option<String>, duration: Option<f64>) -> DownloadResult {
+    DownloadResult {
+        output_path: path.to_string_lossy().to_string(),
+        title,
+        duration,
+        file_size: std::fs::metadata(path).ok().map(|m| m.len()),
+        format_id: None,
+        lossless_copy: false,
+        chapter_paths: Vec::new(),
+        lyrics_paths: Vec::new(),
+    }
+}
+
+/// Run the download, emitting progress for each stream it fetches. Returns whether it
+/// succeeded, its output and the path yt-dlp printed.
+async fn run_with_progress<R: Runtime>(
+    cmd: &mut tokio::process::Command,
+    url: &str,
+    title: Option<&str>,
+    video_id: Option<&str>,
+    options: &DownloadOptions,
+    app_handle: &AppHandle<R>,
+) -> Result<(bool, String, Option<PathBuf>), String> {
+    let mut child = cmd
+        .kill_on_drop(true)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start download: {}", e))?;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    forward_lines(
+        child.stdout.take().ok_or("Failed to capture stdout")?,
+        tx.clone(),
+    );
+    forward_lines(child.stderr.take().ok_or("Failed to capture stderr")?, tx);
+
+    let mut output = String::new();
+    let mut reported_path = None;
+    let mut stage = VideoStage::Video;
+    let mut stream_progress = 0.0;
+    loop {
+        let line = tokio::select! {
+            line = rx.recv() => line,
+            _ = options.cancel.cancelled() => {
+                child.kill().await.ok();
+                return Err(CANCELLED_MESSAGE.to_string());
+            }
+        };
+        let Some(line) = line else {
+            break;
+        };
+        let report = ProgressReport::parse(line.trim());
+        if report.is_none() {
+            output.push_str(&line);
+            output.push('\n');
+        }
+        let line = line.trim();
+
+        if report.is_some() || line.starts_with("[download]") {
+            let speed = report
+                .as_ref()
+                .and_then(|report| report.speed)
+                .or_else(|| speed_history::parse_speed(line));
+            if let (Some(id), Some(speed)) = (video_id, speed) {
+                speed_history::record(id, speed);
+            }
+            let percent = match report {
+                Some(ref report) => report.percent(),
+                None => parse_progress_percent(line),
+            };
+            let Some(percent) = percent else {
+                continue;
+            };
+            // `--print` silences the destination messages, so the audio stream shows
+            // as the progress starting over
+            if stage == VideoStage::Video && percent + 50.0 < stream_progress {
+                stage = VideoStage::Audio;
+            } else if (percent - stream_progress).abs() <= 0.5 {
+                continue;
+            }
+            stream_progress = percent;
+        } else if line.starts_with("[Merger]") || line.starts_with("[VideoRemuxer]") {
+            stage = VideoStage::Merging;
+        } else {
+            if Path::new(line).is_absolute() {
+                reported_path = Some(PathBuf::from(line));
+            }
+            continue;
+        }
+        emit_progress(
+            app_handle,
+            VideoProgress::new(url, title, stage, stream_progress),
+        );
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| format!("Download failed: {}", e))?;
+    Ok((status.success(), output, reported_path))
+}
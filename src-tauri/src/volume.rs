@@ -0,0 +1,290 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Kind of volume a folder lives on, as far as the platform can tell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VolumeKind {
+    Local,
+    Network,
+    /// Only Windows tells removable drives apart
+    #[cfg_attr(not(windows), allow(dead_code))]
+    Removable,
+    Optical,
+}
+
+#[derive(Debug, Clone)]
+struct Volume {
+    kind: VolumeKind,
+    /// File system type such as `nfs` or `smbfs`, when it is known
+    fs_type: Option<String>,
+    read_only: bool,
+}
+
+impl Volume {
+    /// "network volume (smbfs)", for error messages
+    fn describe(&self) -> String {
+        let kind = match self.kind {
+            VolumeKind::Local => "local volume",
+            VolumeKind::Network => "network volume",
+            VolumeKind::Removable => "removable drive",
+            VolumeKind::Optical => "CD/DVD drive",
+        };
+        match self.fs_type {
+            Some(ref fs_type) => format!("{} ({})", kind, fs_type),
+            None => kind.to_string(),
+        }
+    }
+}
+
+/// Errors a network file system reports once its server is gone
+fn is_disconnected(error: &io::Error) -> bool {
+    #[cfg(unix)]
+    {
+        matches!(
+            error.raw_os_error(),
+            Some(libc::ENOTCONN | libc::EHOSTDOWN | libc::EHOSTUNREACH | libc::ESTALE)
+        )
+    }
+    #[cfg(windows)]
+    {
+        // ERROR_BAD_NETPATH, ERROR_NETNAME_DELETED, ERROR_BAD_NET_NAME, ERROR_DEV_NOT_EXIST
+        // and ERROR_NOT_CONNECTED
+        matches!(error.raw_os_error(), Some(53 | 64 | 67 | 55 | 2250))
+    }
+}
+
+fn is_read_only_error(error: &io::Error) -> bool {
+    #[cfg(unix)]
+    {
+        error.raw_os_error() == Some(libc::EROFS)
+    }
+    #[cfg(windows)]
+    {
+        // ERROR_WRITE_PROTECT
+        error.raw_os_error() == Some(19)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn volume_of(path: &Path) -> Option<Volume> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    let mut vfs: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: both structs are written by the calls and the path is NUL-terminated
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0
+        || unsafe { libc::statvfs(c_path.as_ptr(), &mut vfs) } != 0
+    {
+        return None;
+    }
+    let (kind, fs_type) = match stat.f_type as u64 & 0xffff_ffff {
+        0x6969 => (VolumeKind::Network, Some("nfs")),
+        0x517b => (VolumeKind::Network, Some("smb")),
+        0xff53_4d42 => (VolumeKind::Network, Some("cifs")),
+        0xfe53_4d42 => (VolumeKind::Network, Some("smb2")),
+        0x0102_1997 => (VolumeKind::Network, Some("9p")),
+        0x5346_414f => (VolumeKind::Network, Some("afs")),
+        0x7375_7245 => (VolumeKind::Network, Some("coda")),
+        0x9660 => (VolumeKind::Optical, Some("iso9660")),
+        0x1501_3346 => (VolumeKind::Optical, Some("udf")),
+        _ => (VolumeKind::Local, None),
+    };
+    Some(Volume {
+        kind,
+        fs_type: fs_type.map(str::to_string),
+        read_only: vfs.f_flag & libc::ST_RDONLY != 0,
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn volume_of(path: &Path) -> Option<Volume> {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    // From <sys/mount.h>
+    const MNT_RDONLY: u32 = 0x0000_0001;
+    const MNT_LOCAL: u32 = 0x0000_1000;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    // SAFETY: the struct is written by the call and the path is NUL-terminated
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let fs_type = unsafe { CStr::from_ptr(stat.f_fstypename.as_ptr()) }
+        .to_string_lossy()
+        .to_string();
+    let kind = if stat.f_flags & MNT_LOCAL == 0 {
+        VolumeKind::Network
+    } else if fs_type == "cd9660" || fs_type == "udf" {
+        VolumeKind::Optical
+    } else {
+        VolumeKind::Local
+    };
+    Some(Volume {
+        kind,
+        fs_type: Some(fs_type).filter(|t| !t.is_empty()),
+        read_only: stat.f_flags & MNT_RDONLY != 0,
+    })
+}
+
+#[cfg(windows)]
+fn drive_type(path: &Path) -> Option<u32> {
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetDriveTypeW(root: *const u16) -> u32;
+    }
+
+    // `C:\`, or `\\server\share\` for UNC paths
+    let root = path.ancestors().last()?;
+    let mut wide: Vec<u16> = root.as_os_str().encode_wide().collect();
+    if wide.last() != Some(&(b'\\' as u16)) {
+        wide.push(b'\\' as u16);
+    }
+    wide.push(0);
+    // SAFETY: the root is a NUL-terminated UTF-16 string
+    Some(unsafe { GetDriveTypeW(wide.as_ptr()) })
+}
+
+#[cfg(windows)]
+fn volume_of(path: &Path) -> Option<Volume> {
+    let kind = match drive_type(path)? {
+        2 => VolumeKind::Removable,
+        4 => VolumeKind::Network,
+        5 => VolumeKind::Optical,
+        _ => VolumeKind::Local,
+    };
+    Some(Volume {
+        kind,
+        fs_type: None,
+        // Directories have no read-only flag worth trusting on Windows, the write
+        // probe finds write-protected drives
+        read_only: kind == VolumeKind::Optical,
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn volume_of(_path: &Path) -> Option<Volume> {
+    None
+}
+
+/// Whether a folder is a cloud sync placeholder whose contents only exist online, such
+/// as an online-only OneDrive or Dropbox folder
+#[cfg(windows)]
+fn is_cloud_placeholder(metadata: &fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_OFFLINE: u32 = 0x0000_1000;
+    const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x0004_0000;
+    const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x0040_0000;
+    metadata.file_attributes()
+        & (FILE_ATTRIBUTE_OFFLINE
+            | FILE_ATTRIBUTE_RECALL_ON_OPEN
+            | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS)
+        != 0
+}
+
+#[cfg(not(windows))]
+fn is_cloud_placeholder(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+fn disconnected_error(folder: &Path) -> String {
+    format!(
+        "The output folder {} is on a network drive that is not connected. Reconnect the drive or choose another folder.",
+        folder.display()
+    )
+}
+
+fn read_only_error(folder: &Path, volume: Option<&Volume>) -> String {
+    format!(
+        "The output folder {} is on a read-only {}. Choose a folder on a drive you can write to.",
+        folder.display(),
+        volume.map_or_else(|| "volume".to_string(), Volume::describe)
+    )
+}
+
+/// Make sure downloads can be written to `folder` before any of them starts, naming the
+/// kind of volume when it can't: read-only mounts, disconnected network drives and
+/// online-only cloud folders. A folder that doesn't exist yet is fine as long as it can
+/// be created.
+pub fn check_output_folder(folder: &str) -> Result<(), String> {
+    let folder = Path::new(folder.trim());
+    if folder.as_os_str().is_empty() {
+        return Err("Choose an output folder first".to_string());
+    }
+
+    // yt-dlp creates missing folders, so the closest existing one is what gets written to
+    let mut existing = None;
+    for ancestor in folder.ancestors() {
+        match fs::metadata(ancestor) {
+            Ok(metadata) => {
+                existing = Some((ancestor, metadata));
+                break;
+            }
+            Err(e) if is_disconnected(&e) => return Err(disconnected_error(folder)),
+            Err(_) => {}
+        }
+    }
+    let Some((existing, metadata)) = existing else {
+        #[cfg(windows)]
+        if matches!(drive_type(folder), Some(1 | 4)) {
+            return Err(disconnected_error(folder));
+        }
+        return Err(format!(
+            "The output folder {} doesn't exist and its drive can't be found",
+            folder.display()
+        ));
+    };
+    if !metadata.is_dir() {
+        return Err(format!(
+            "The output folder {} is not a folder",
+            existing.display()
+        ));
+    }
+    if is_cloud_placeholder(&metadata) {
+        return Err(format!(
+            "The output folder {} is an online-only cloud folder (OneDrive, Dropbox or similar). Make it available offline or choose another folder.",
+            folder.display()
+        ));
+    }
+
+    let volume = volume_of(existing);
+    if volume.as_ref().is_some_and(|v| v.read_only) {
+        return Err(read_only_error(folder, volume.as_ref()));
+    }
+
+    // Mount flags miss ACLs, write-protected media and full quotas, a real write doesn't
+    let probe = existing.join(format!(".youtube-downloader-{}.tmp", std::process::id()));
+    let written = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&probe);
+    match written {
+        Ok(_) => {
+            fs::remove_file(&probe).ok();
+            Ok(())
+        }
+        Err(e) if is_read_only_error(&e) => Err(read_only_error(folder, volume.as_ref())),
+        Err(e) if is_disconnected(&e) => Err(disconnected_error(folder)),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Err(format!(
+            "You don't have permission to write to the output folder {}{}",
+            folder.display(),
+            volume
+                .filter(|v| v.kind != VolumeKind::Local)
+                .map(|v| format!(" on a {}", v.describe()))
+                .unwrap_or_default()
+        )),
+        Err(e) => Err(format!(
+            "Can't write to the output folder {}: {}",
+            folder.display(),
+            e
+        )),
+    }
+}
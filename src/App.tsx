@@ -1,4 +1,5 @@
 import { useEffect } from "react";
+import { invoke } from "@tauri-apps/api/tauri";
 import { listen } from "@tauri-apps/api/event";
 import { UrlInput } from "./components/UrlInput/UrlInput";
 import { Progress } from "./components/Progress/Progress";
@@ -6,7 +7,11 @@ import { History } from "./components/History/History";
 import { ErrorDisplay } from "./components/ErrorDisplay/ErrorDisplay";
 import { useAppDispatch } from "./store/hooks";
 import { downloadActions } from "./store/download";
-import type { DownloadProgressEvent } from "./store/download/types";
+import type {
+  DecisionRequest,
+  DownloadProgressEvent,
+  VersionedEvent,
+} from "./store/download/types";
 import "./App.scss";
 
 export const App = () => {
@@ -16,12 +21,26 @@ export const App = () => {
     dispatch(downloadActions.loadHistory());
     dispatch(downloadActions.loadPreferences());
 
-    const progressUnlisten = listen<DownloadProgressEvent>("download-progress", (event) => {
-      dispatch(downloadActions.updateDownloadProgress(event.payload));
-    });
+    const progressUnlisten = listen<VersionedEvent<DownloadProgressEvent>>(
+      "ytmp3://progress/v2",
+      (event) => {
+        dispatch(downloadActions.updateDownloadProgress(event.payload.payload));
+      }
+    );
+
+    const decisionUnlisten = listen<VersionedEvent<DecisionRequest>>(
+      "ytmp3://user-decision-required/v2",
+      (event) => {
+        const { token, message, choices, default_choice } = event.payload.payload;
+        const declined = default_choice === choices[0] ? choices[1] : default_choice;
+        const choice = window.confirm(message) ? choices[0] : declined;
+        invoke("submit_decision", { token, choice }).catch(() => undefined);
+      }
+    );
 
     return () => {
       progressUnlisten.then(unlisten => unlisten());
+      decisionUnlisten.then(unlisten => unlisten());
     };
   }, [dispatch]);
 
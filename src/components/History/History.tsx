@@ -1,15 +1,20 @@
 import { useAppDispatch, useAppSelector } from "../../store/hooks";
-import { selectHistory } from "../../store/download/selectors";
+import { selectHistory, selectHistoryTotal } from "../../store/download/selectors";
 import { downloadActions } from "../../store/download";
 import "./History.scss";
 
 export const History = () => {
   const dispatch = useAppDispatch();
   const history = useAppSelector(selectHistory);
+  const historyTotal = useAppSelector(selectHistoryTotal);
 
   const handleClear = () => {
     dispatch(downloadActions.clearHistory());
   };
+
+  const handleLoadMore = () => {
+    dispatch(downloadActions.loadMoreHistory(history.length));
+  };
   const formatDate = (timestamp: string) => {
     try {
       const date = new Date(timestamp);
@@ -60,6 +65,11 @@ export const History = () => {
           </div>
         ))}
       </div>
+      {history.length < historyTotal && (
+        <button className="history__more-btn" onClick={handleLoadMore}>
+          Load more
+        </button>
+      )}
     </div>
   );
 };
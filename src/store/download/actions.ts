@@ -1,22 +1,43 @@
 import { invoke } from '@tauri-apps/api/tauri';
 import type { AppDispatch } from '../index';
-import type { DownloadHistory, AppPreferences, DownloadResponse } from './types';
+import type { HistoryPage, AppPreferences, DownloadResponse } from './types';
 import type { actions } from './index';
 
 type SliceActions = typeof actions;
 
+const HISTORY_PAGE_SIZE = 50;
+
 export const createExtendedActions = (sliceActions: SliceActions) => {
   const loadHistory = () => {
     return async (dispatch: AppDispatch) => {
       try {
-        const historyData = await invoke<DownloadHistory[]>("get_download_history");
-        dispatch(sliceActions.setHistory(historyData));
+        const page = await invoke<HistoryPage>("get_download_history", {
+          offset: 0,
+          limit: HISTORY_PAGE_SIZE,
+        });
+        dispatch(sliceActions.setHistory(page.downloads.slice().reverse()));
+        dispatch(sliceActions.setHistoryTotal(page.total));
       } catch (err) {
         console.error("Failed to load history:", err);
       }
     };
   };
 
+  const loadMoreHistory = (loaded: number) => {
+    return async (dispatch: AppDispatch) => {
+      try {
+        const page = await invoke<HistoryPage>("get_download_history", {
+          offset: loaded,
+          limit: HISTORY_PAGE_SIZE,
+        });
+        dispatch(sliceActions.prependHistory(page.downloads.slice().reverse()));
+        dispatch(sliceActions.setHistoryTotal(page.total));
+      } catch (err) {
+        console.error("Failed to load more history:", err);
+      }
+    };
+  };
+
   const loadPreferences = () => {
     return async (dispatch: AppDispatch) => {
       try {
@@ -80,20 +101,47 @@ export const createExtendedActions = (sliceActions: SliceActions) => {
           bitrate: bitrate,
         });
 
-        const result = await invoke<DownloadResponse>("download_from_youtube", {
+        let result = await invoke<DownloadResponse>("download_from_youtube", {
           url: url.trim(),
           outputFolder: outputFolder,
           bitrate: bitrate,
         });
 
+        if (result.type === "ConfirmationRequired") {
+          const sizeMb = result.estimated_size_bytes
+            ? ` (~${Math.round(result.estimated_size_bytes / (1024 * 1024))} MB`
+            : " (";
+          const minutes = Math.ceil(result.estimated_time_seconds / 60);
+          const proceed = window.confirm(
+            `This playlist has ${result.item_count} videos${sizeMb}, about ${minutes} min). Download all of them?`
+          );
+          if (!proceed) {
+            dispatch(sliceActions.resetDownloadState());
+            return;
+          }
+
+          result = await invoke<DownloadResponse>("download_from_youtube", {
+            url: url.trim(),
+            outputFolder: outputFolder,
+            bitrate: bitrate,
+            confirmed: true,
+          });
+        }
+
         console.log("Download result:", result);
 
         dispatch(sliceActions.setDownloadProgress(100));
         
-        if (result.type === "Playlist") {
+        if (result.type === "Playlist" && result.cancelled) {
+          dispatch(sliceActions.setDownloadStatus(`Download cancelled after ${result.downloaded_videos.length} of ${result.total_videos} videos.`));
+        } else if (result.type === "Playlist" && result.failed_videos.length > 0) {
+          const failed = result.failed_videos.map((v) => `${v.title ?? v.url}: ${v.error}`).join("\n");
+          dispatch(sliceActions.setDownloadStatus(`Downloaded ${result.downloaded_videos.length} videos from playlist, ${result.failed_videos.length} failed:\n${failed}`));
+          dispatch(sliceActions.setSongProgress(100));
+        } else if (result.type === "Playlist") {
           dispatch(sliceActions.setDownloadStatus(`Download complete! Downloaded ${result.downloaded_videos.length} videos from playlist.`));
           dispatch(sliceActions.setSongProgress(100));
-        } else {
+        } else if (result.type === "Single") {
           dispatch(sliceActions.setDownloadStatus("Download complete!"));
           dispatch(sliceActions.setIsPlaylist(false));
         }
@@ -117,11 +165,22 @@ export const createExtendedActions = (sliceActions: SliceActions) => {
     };
   };
 
+  const cancelDownload = () => {
+    return async () => {
+      try {
+        await invoke<number>("cancel_download");
+      } catch (err) {
+        console.error("Failed to cancel download:", err);
+      }
+    };
+  };
+
   const clearHistory = () => {
     return async (dispatch: AppDispatch) => {
       try {
         await invoke("clear_history");
         dispatch(sliceActions.setHistory([]));
+        dispatch(sliceActions.setHistoryTotal(0));
       } catch (err) {
         console.error("Failed to clear history:", err);
       }
@@ -130,9 +189,11 @@ export const createExtendedActions = (sliceActions: SliceActions) => {
 
   return {
     loadHistory,
+    loadMoreHistory,
     loadPreferences,
     savePreferences,
     downloadFromYoutube,
+    cancelDownload,
     clearHistory,
   };
 };
@@ -15,6 +15,7 @@ const initialState: DownloadState = {
   songProgress: 0,
   currentTitle: null,
   history: [],
+  historyTotal: 0,
   error: null,
 };
 
@@ -60,6 +61,12 @@ const slice = createSlice({
     setHistory: (state, action: PayloadAction<DownloadHistory[]>) => {
       state.history = action.payload;
     },
+    prependHistory: (state, action: PayloadAction<DownloadHistory[]>) => {
+      state.history = [...action.payload, ...state.history];
+    },
+    setHistoryTotal: (state, action: PayloadAction<number>) => {
+      state.historyTotal = action.payload;
+    },
     setError: (state, action: PayloadAction<string | null>) => {
       state.error = action.payload;
     },
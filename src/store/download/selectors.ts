@@ -64,6 +64,11 @@ export const selectHistory = createSelector(
   (download) => download.history
 );
 
+export const selectHistoryTotal = createSelector(
+  [selectDownloadState],
+  (download) => download.historyTotal
+);
+
 export const selectError = createSelector(
   [selectDownloadState],
   (download) => download.error
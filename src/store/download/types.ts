@@ -5,6 +5,24 @@ export type DownloadHistory = {
   bitrate: number;
   timestamp: string;
   duration?: number;
+  app_version?: string;
+  ytdlp_version?: string;
+  ffmpeg_version?: string;
+  audio_format: AudioFormat;
+  missing: boolean;
+};
+
+export type HistoryVerification = {
+  checked: number;
+  present: number;
+  missing: DownloadHistory[];
+  pruned: number;
+};
+
+export type HistoryPage = {
+  downloads: DownloadHistory[];
+  total: number;
+  offset: number;
 };
 
 export type DownloadResult = {
@@ -12,22 +30,274 @@ export type DownloadResult = {
   title?: string;
   duration?: number;
   file_size?: number;
+  format_id?: string;
+  lossless_copy: boolean;
+  chapter_paths: string[];
+  lyrics_paths: string[];
 };
 
 export type PlaylistDownloadResult = {
   output_folder: string;
   total_videos: number;
   downloaded_videos: DownloadResult[];
+  failed_videos: FailedVideo[];
+  cancelled: boolean;
+  liner_notes_path: string | null;
+};
+
+export type FailedVideo = {
+  url: string;
+  title: string | null;
+  error: string;
+  attempts: number;
+};
+
+export type RetryPolicy = {
+  max_retries: number;
+  initial_delay_seconds: number;
+  max_delay_seconds: number;
+};
+
+export type PlaylistConfirmation = {
+  url: string;
+  item_count: number;
+  threshold: number;
+  total_duration: number | null;
+  estimated_size_bytes: number | null;
+  estimated_time_seconds: number;
+};
+
+export type VideoFormat = {
+  format_id: string;
+  ext: string | null;
+  acodec: string | null;
+  vcodec: string | null;
+  abr: number | null;
+  filesize: number | null;
+  format_note: string | null;
+};
+
+export type VideoInfo = {
+  id: string | null;
+  title: string | null;
+  duration: number | null;
+  uploader: string | null;
+  thumbnail: string | null;
+  view_count: number | null;
+  format_id: string | null;
+  formats: VideoFormat[];
+  tracklist: DetectedTracklist | null;
+  estimated_size: number | null;
+};
+
+export type TracklistSource = "description" | "pinned_comment";
+
+export type DetectedTracklist = {
+  source: TracklistSource;
+  entries: TracklistEntry[];
+};
+
+export type PlaylistEntry = {
+  position: number;
+  url: string;
+  id: string | null;
+  title: string | null;
+  duration: number | null;
+  uploader: string | null;
+  upload_date: string | null;
+  playlist_title: string | null;
+  playlist_uploader: string | null;
+};
+
+export type ReplacementCandidate = {
+  url: string;
+  video_id: string | null;
+  title: string | null;
+  uploader: string | null;
+  duration: number | null;
+  score: number;
+};
+
+export type PlaylistEstimate = {
+  url: string;
+  item_count: number;
+  total_duration: number | null;
+  estimated_download_bytes: number | null;
+  estimated_output_bytes: number | null;
+  estimated_time_seconds: number;
+  throughput_bytes_per_second: number;
+  measured_throughput: boolean;
 };
 
 export type DownloadResponse = 
   | ({ type: "Single" } & DownloadResult)
-  | ({ type: "Playlist" } & PlaylistDownloadResult);
+  | ({ type: "Playlist" } & PlaylistDownloadResult)
+  | ({ type: "ConfirmationRequired" } & PlaylistConfirmation);
+
+export type PlayerClient = "default" | "web" | "web_safari" | "mweb" | "android" | "ios" | "tv";
+
+export type ExtractorArgs = {
+  player_clients: PlayerClient[];
+  formats: string | null;
+};
+
+export type Politeness = {
+  enabled: boolean;
+  min_batch_size: number;
+  sleep_requests: number;
+  sleep_interval: number;
+  max_sleep_interval: number;
+};
+
+export type MediaCacheSettings = {
+  enabled: boolean;
+  max_size_mb: number;
+};
+
+export type SectionedDownload = {
+  enabled: boolean;
+  min_duration_minutes: number;
+  section_minutes: number;
+  parallel_sections: number;
+};
+
+export type NetworkSimulation = {
+  enabled: boolean;
+  rate: string | null;
+  latency_secs: number;
+  failure_rate: number;
+  seed: number;
+};
+
+export type StorageLimits = {
+  max_logs_mb: number;
+  max_metadata_cache_mb: number;
+};
+
+export type StorageArea =
+  | "download_logs"
+  | "crash_reports"
+  | "audit_log"
+  | "metadata_cache"
+  | "media_cache"
+  | "binaries";
+
+export type StorageAreaUsage = {
+  area: StorageArea;
+  path: string | null;
+  bytes: number;
+  files: number;
+  limit_bytes: number | null;
+};
+
+export type StorageUsage = {
+  areas: StorageAreaUsage[];
+  total_bytes: number;
+};
+
+export type StorageCleanup = {
+  removed_files: number;
+  freed_bytes: number;
+};
+
+export type BackupManifest = {
+  format_version: number;
+  app_version: string;
+  created_at: string;
+  files: string[];
+};
+
+export type ManifestFormat = "json" | "csv";
+
+export type ManifestFailure = {
+  path: string;
+  error: string;
+};
+
+export type ManifestExport = {
+  manifest_path: string;
+  files: number;
+  total_bytes: number;
+  failed: ManifestFailure[];
+};
+
+export type ManifestMismatch = {
+  path: string;
+  expected_sha256: string;
+  actual_sha256: string;
+};
+
+export type ManifestVerification = {
+  verified: number;
+  missing: string[];
+  changed: ManifestMismatch[];
+  unlisted: string[];
+  failed: ManifestFailure[];
+};
+
+export type InstallStep = {
+  binary: "yt-dlp" | "ffmpeg";
+  platform: "windows" | "macos" | "linux";
+  package_manager: string | null;
+  title: string;
+  command: string | null;
+  url: string | null;
+};
+
+export type Verbosity = "quiet" | "normal" | "verbose";
+
+export type FolderOrganization = "none" | "playlist" | "uploader";
 
 export type AppPreferences = {
   output_folder: string | null;
   bitrate: number | null;
   last_url: string | null;
+  extractor_args: ExtractorArgs | null;
+  impersonate: string | null;
+  user_agent: string | null;
+  politeness: Politeness | null;
+  augment_path_from_shell: boolean | null;
+  playlist_confirm_threshold: number | null;
+  media_cache: MediaCacheSettings | null;
+  sectioned_download: SectionedDownload | null;
+  transliterate_titles: boolean | null;
+  ytdlp_verbosity: Verbosity | null;
+  preflight_dependencies: boolean | null;
+  dependency_mirror: string | null;
+  auto_apply_fixes: boolean | null;
+  max_concurrent_downloads: number | null;
+  playlist_retry: RetryPolicy | null;
+  adaptive_concurrency: boolean | null;
+  max_download_rate: string | null;
+  proxy: ProxySettings | null;
+  stream_conversion: boolean | null;
+  use_cookies: boolean | null;
+  audio_format: AudioFormat | null;
+  folder_artwork: boolean | null;
+  embed_metadata: boolean | null;
+  media_server: MediaServerSettings | null;
+  embed_thumbnail: boolean | null;
+  mqtt: MqttSettings | null;
+  sponsorblock: boolean | null;
+  silence_trim: SilenceTrim | null;
+  split_chapters: boolean | null;
+  audio_preset: AudioPreset | null;
+  legacy_events: boolean | null;
+  allow_any_site: boolean | null;
+  network_simulation: NetworkSimulation | null;
+  clipboard_monitor: boolean | null;
+  storage_limits: StorageLimits | null;
+  folder_organization: FolderOrganization | null;
+  lyrics: LyricsSettings | null;
+  loudness_normalization: LoudnessNormalization | null;
+  weekly_digest: boolean | null;
+  reduced_updates: boolean | null;
+};
+
+export type SessionDefaults = {
+  bitrate: number | null;
+  format: string | null;
+  audio_format: AudioFormat | null;
 };
 
 export type DownloadProgressEvent = {
@@ -37,6 +307,397 @@ export type DownloadProgressEvent = {
   song_progress: number;
   status: string;
   current_title: string | null;
+  estimated_size: number | null;
+  downloaded_bytes: number | null;
+  total_bytes: number | null;
+  speed: number | null;
+  eta: number | null;
+  step: number;
+  total_steps: number;
+};
+
+export type SpeedSample = {
+  timestamp_ms: number;
+  bytes_per_second: number;
+};
+
+export type PreflightStage = "checking" | "downloading" | "ready" | "failed";
+
+export type PreflightProgress = {
+  binary: string;
+  stage: PreflightStage;
+  downloaded_bytes: number | null;
+  total_bytes: number | null;
+  message: string | null;
+};
+
+export type AppLockStatus = {
+  enabled: boolean;
+  locked: boolean;
+  retry_after_seconds: number | null;
+};
+
+export type ReleaseInfo = {
+  tag: string;
+  name: string | null;
+  url: string;
+  published_at: string | null;
+};
+
+export type UpdateInfo = {
+  current_version: string | null;
+  latest: ReleaseInfo | null;
+  update_available: boolean;
+  checked_at: string | null;
+  error: string | null;
+};
+
+export type AvailableUpdates = {
+  ytdlp: UpdateInfo;
+  app: UpdateInfo;
+};
+
+export type ErrorCode =
+  | "forbidden"
+  | "bot_check"
+  | "signature_extraction"
+  | "format_unavailable"
+  | "rate_limited"
+  | "age_restricted"
+  | "network"
+  | "unknown";
+
+export type Remedy =
+  | "update_ytdlp"
+  | "switch_player_client"
+  | "enable_impersonation"
+  | "slow_down"
+  | "enable_cookies"
+  | "check_connection";
+
+export type RecommendedFix = {
+  remedy: Remedy;
+  description: string;
+  auto_applicable: boolean;
+  applied: boolean | null;
+};
+
+export type FixAdvice = {
+  error_code: ErrorCode;
+  consecutive_failures: number;
+  threshold: number;
+  fixes: RecommendedFix[];
+};
+
+export type JobStatus =
+  | "queued"
+  | "running"
+  | "paused"
+  | "done"
+  | "failed"
+  | "cancelled";
+
+export type QueueJob = DownloadOverrides & {
+  id: string;
+  url: string;
+  output_folder: string;
+  bitrate: number;
+  status: JobStatus;
+  added_at: string;
+  started_at: string | null;
+  finished_at: string | null;
+  error: string | null;
+  output_paths: string[];
+};
+
+export type RejectedUrl = {
+  input: string;
+  reason: string;
+};
+
+export type QueueImport = {
+  added: QueueJob[];
+  duplicates: string[];
+  rejected: RejectedUrl[];
+};
+
+export type QueueJobProgress = {
+  job_id: string;
+  progress: DownloadProgressEvent;
+};
+
+export type EncodeResult = {
+  bitrate: number;
+  seconds: number;
+  speed: number;
+};
+
+export type ConcurrencyResult = {
+  concurrency: number;
+  speed: number;
+};
+
+export type BenchmarkResult = {
+  ran_at: string;
+  sample_seconds: number;
+  cpu_threads: number;
+  encodes: EncodeResult[];
+  concurrency: ConcurrencyResult[];
+  disk_folder: string;
+  disk_write_mb_per_second: number;
+  recommended_concurrency: number;
+};
+
+export type ProxyScheme = "http" | "https" | "socks4" | "socks5";
+
+export type ProxySettings = {
+  enabled: boolean;
+  scheme: ProxyScheme;
+  host: string;
+  port: number;
+  username: string | null;
+  password: string | null;
+};
+
+export type CookiesStatus = {
+  imported: boolean;
+  updated_at: string | null;
+  cookie_count: number;
+  youtube_cookie_count: number;
+  enabled: boolean;
+};
+
+export type AudioFormat = "mp3" | "opus" | "m4a" | "flac" | "wav" | "ogg";
+
+export type VideoContainer = "mp4" | "mkv";
+
+export type VideoStage = "video" | "audio" | "merging" | "completed" | "cancelled";
+
+export type VideoProgress = {
+  url: string;
+  title: string | null;
+  stage: VideoStage;
+  stream_progress: number;
+  overall_progress: number;
+};
+
+export type MediaServerKind = "jellyfin" | "plex";
+
+export type MediaServerSettings = {
+  enabled: boolean;
+  kind: MediaServerKind;
+  url: string;
+  token: string;
+  library_id: string | null;
+};
+
+export type MqttSettings = {
+  enabled: boolean;
+  host: string;
+  port: number;
+  username: string | null;
+  password: string | null;
+  status_topic: string;
+  event_topic: string;
+};
+
+export type LyricsSettings = {
+  enabled: boolean;
+  languages: string;
+  auto_captions: boolean;
+  convert_to_lrc: boolean;
+};
+
+export type LoudnessNormalization = {
+  enabled: boolean;
+  target_lufs: number;
+  true_peak_db: number;
+  loudness_range: number;
+};
+
+export type SilenceTrim = {
+  enabled: boolean;
+  threshold_db: number;
+  min_duration: number;
+};
+
+export type AudioPreset = "original" | "noisy_environment";
+
+export type PlaylistSelection = {
+  playlist_items: string | null;
+  playlist_start: number | null;
+  playlist_end: number | null;
+  uploaded_after: string | null;
+};
+
+export type ClipRange = {
+  start_time: string | null;
+  end_time: string | null;
+};
+
+export type DownloadOverrides = PlaylistSelection & ClipRange & {
+  audio_format: AudioFormat | null;
+  sponsorblock: boolean | null;
+  split_chapters: boolean | null;
+  audio_preset: AudioPreset | null;
+  tags: TagOverrides;
+};
+
+export type TagOverrides = {
+  artist: string | null;
+  album: string | null;
+  genre: string | null;
+};
+
+export type ChannelRule = TagOverrides & {
+  channel: string;
+  audio_format: AudioFormat | null;
+  audio_preset: AudioPreset | null;
+  subfolder: string | null;
+};
+
+export type Waveform = {
+  duration: number;
+  peaks: number[];
+};
+
+export type TracklistEntry = {
+  start: number;
+  title: string;
+  artist: string | null;
+};
+
+export type TracklistTrack = {
+  title: string;
+  artist: string | null;
+  start: number;
+  end: number;
+  output_path: string;
+  file_size: number | null;
+};
+
+export type TracklistResult = {
+  title: string | null;
+  duration: number;
+  tracks: TracklistTrack[];
+};
+
+export type TrimResult = {
+  output_path: string;
+  duration: number;
+  file_size: number | null;
+};
+
+export type QueueStateChange = {
+  action: "pause_all" | "resume_all" | "cancel_all" | "clear_completed";
+  changed: string[];
+  jobs: QueueJob[];
+};
+
+export type BatchProgress = {
+  batch_id: string;
+  total: number;
+  finished: number;
+  failed: number;
+  overall_progress: number;
+};
+
+export type BatchItemResult = {
+  url: string;
+  job_id: string | null;
+  status: JobStatus;
+  output_paths: string[];
+  error: string | null;
+};
+
+export type JobForecast = {
+  job_id: string;
+  starts_in_seconds: number;
+  remaining_seconds: number;
+  rough: boolean;
+};
+
+export type QueueForecast = {
+  remaining_jobs: number;
+  remaining_seconds: number;
+  estimated_completion: string | null;
+  seconds_per_track: number;
+  based_on_jobs: number;
+  jobs: JobForecast[];
+};
+
+export type BatchResult = {
+  batch_id: string;
+  items: BatchItemResult[];
+  liner_notes_path: string | null;
+};
+
+export type ParsedSource =
+  | { kind: "video"; video_id: string }
+  | { kind: "playlist"; list_id: string; video_id: string | null }
+  | { kind: "channel"; channel: string }
+  | { kind: "external"; host: string; url: string };
+
+export type PastedUrl = {
+  url: string;
+  source: ParsedSource;
+};
+
+export type ClipboardUrls = {
+  urls: PastedUrl[];
+};
+
+export type NetworkWait = {
+  url: string;
+  waiting: boolean;
+  resumed: boolean;
+  attempt: number;
+  max_attempts: number;
+};
+
+export type IncompleteDownload = DownloadOverrides & {
+  id: string;
+  url: string;
+  output_folder: string;
+  bitrate: number;
+  started_at: string;
+};
+
+export type IncompleteScan = {
+  downloads: IncompleteDownload[];
+  partial_files: string[];
+};
+
+export type ComparisonCandidate = {
+  bitrate: number;
+  path: string;
+  file_size: number | null;
+};
+
+export type QualityComparison = {
+  id: string;
+  url: string;
+  title: string | null;
+  duration: number | null;
+  audio_format: AudioFormat;
+  candidates: ComparisonCandidate[];
+};
+
+export type DecisionKind = "overwrite" | "playlist_size" | "exit_with_active_downloads";
+
+export type DecisionRequest = {
+  token: string;
+  kind: DecisionKind;
+  message: string;
+  choices: string[];
+  default_choice: string;
+  timeout_seconds: number;
+  details: Record<string, unknown>;
+};
+
+export type VersionedEvent<T> = {
+  schema_version: number;
+  payload: T;
 };
 
 export type DownloadState = {
@@ -52,6 +713,7 @@ export type DownloadState = {
   songProgress: number;
   currentTitle: string | null;
   history: DownloadHistory[];
+  historyTotal: number;
   error: string | null;
 };
 